@@ -0,0 +1,102 @@
+//! 飛び駒の利きの表引きによる計算と減算による計算の速度比較
+//!
+//! `cargo bench --bench sliding_attacks`で減算による計算（デフォルト）の、
+//! `cargo bench --bench sliding_attacks --features sliding-attack-table`で表引きによる計算の速度を計測する。
+extern crate rand;
+extern crate rand_xorshift;
+extern crate usiagent;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use usiagent::bitboard::BitBoard;
+use usiagent::rule::{BANMEN_START_POS, Rule, State};
+use usiagent::shogi::{MochigomaCollections, Teban};
+
+const CANDIDATE_BITS_FUNCTIONS:[fn(BitBoard,BitBoard,u32) -> BitBoard; 4] = [
+	Rule::gen_candidate_bits_by_hisha_or_kyou_to_top,
+	Rule::gen_candidate_bits_by_hisha_to_right,
+	Rule::gen_candidate_bits_by_kaku_to_right_top,
+	Rule::gen_candidate_bits_by_kaku_to_right_bottom
+];
+const ITERATIONS:usize = 20000;
+
+fn bench_candidate_bits(occupancies:&[BitBoard]) {
+	let start = Instant::now();
+	let mut acc = BitBoard::default();
+
+	for i in 0..ITERATIONS {
+		let occupied = occupancies[i % occupancies.len()];
+
+		for f in CANDIDATE_BITS_FUNCTIONS.iter() {
+			for from in 0..81 {
+				acc |= f(BitBoard::default(),black_box(occupied),black_box(from));
+			}
+		}
+	}
+
+	black_box(acc);
+
+	let elapsed = start.elapsed();
+	let calls = ITERATIONS * CANDIDATE_BITS_FUNCTIONS.len() * 81;
+
+	println!("{:<16} {:>10.3} ms ({:.2} ns/call)",
+			 "candidate_bits",
+			 elapsed.as_secs_f64() * 1000.,
+			 elapsed.as_nanos() as f64 / calls as f64);
+}
+fn bench_legal_moves() {
+	let mut rnd = XorShiftRng::seed_from_u64(1234);
+	let mut positions = Vec::new();
+
+	let mut state = State::new(BANMEN_START_POS.clone());
+	let mut mc = MochigomaCollections::Empty;
+	let mut teban = Teban::Sente;
+
+	while positions.len() < 1000 {
+		let mvs = Rule::legal_moves_all(teban,&state,&mc);
+
+		if mvs.is_empty() || positions.len() % 200 == 199 {
+			state = State::new(BANMEN_START_POS.clone());
+			mc = MochigomaCollections::Empty;
+			teban = Teban::Sente;
+			positions.push((teban,state.clone(),mc.clone()));
+			continue;
+		}
+
+		let m = mvs[rnd.gen_range(0..mvs.len())];
+		let (next,nmc,_) = Rule::apply_move_none_check(&state,teban,&mc,m.to_applied_move());
+
+		state = next;
+		mc = nmc;
+		teban = teban.opposite();
+
+		positions.push((teban,state.clone(),mc.clone()));
+	}
+
+	let start = Instant::now();
+	let mut count = 0;
+
+	for _ in 0..20 {
+		for (teban,state,mc) in positions.iter() {
+			count += Rule::legal_moves_all(*teban,state,mc).len();
+		}
+	}
+
+	let elapsed = start.elapsed();
+
+	println!("{:<16} {:>10.3} ms ({} moves)","legal_moves_all",elapsed.as_secs_f64() * 1000.,black_box(count));
+}
+fn main() {
+	let mut rnd = XorShiftRng::seed_from_u64(1234);
+
+	let occupancies = (0..1024).map(|_| {
+		BitBoard::from((rnd.gen::<u128>() & rnd.gen::<u128>()) & (((1u128 << 81) - 1) << 1))
+	}).collect::<Vec<BitBoard>>();
+
+	bench_candidate_bits(&occupancies);
+	bench_legal_moves();
+}
@@ -0,0 +1,218 @@
+//! 探索速度の計測のためのベンチマーク
+use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use error::{BenchError, PlayerError, TypeConvertError};
+use player::USIPlayer;
+use protocol::PositionParser;
+use shogi::*;
+
+/// ベンチマークで探索する組み込みの局面のリスト（positionコマンドの`position`以降の形式）
+pub const BENCH_POSITIONS:[&str; 8] = [
+	"startpos",
+	"startpos moves 7g7f 3c3d 2g2f 4c4d 2f2e 2b3c 3i4h 3a3b 5i6h 5a6b",
+	"startpos moves 2g2f 8c8d 2f2e 8d8e 7g7f 4a3b 8h7g 3c3d 7i8h 2b7g+ 8h7g",
+	"startpos moves 7g7f 8c8d 2g2f 8d8e 8h7g 3c3d 7i8h 4a3b 6i7h 2b7g+ 8h7g 3a2b",
+	"sfen lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+	"sfen l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1",
+	"sfen 8l/1l+R2P3/p2pBG1pp/kps1p4/Nn1P2G2/P1P1P2PP/1PS6/1KSG3+r1/LN2+p3L w Sbgn2p 124",
+	"sfen 7nl/5Bk2/6pp1/8p/9/9/9/9/K8 b RGrb3g4s3n3l15p 1",
+];
+/// ベンチマークのデフォルトの探索の深さ
+pub const BENCH_DEFAULT_DEPTH:u32 = 6;
+
+/// ベンチマークの各局面の探索を打ち切る条件
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum BenchLimit {
+	/// 深さ
+	Depth(u32),
+	/// 探索ノード数
+	Nodes(u64),
+	/// 探索時間（ミリ秒）
+	Time(u64),
+}
+impl<'a> TryFrom<&'a [String]> for BenchLimit {
+	type Error = TypeConvertError<String>;
+
+	fn try_from(args:&'a [String]) -> Result<BenchLimit,TypeConvertError<String>> {
+		match args {
+			[kind,value] => {
+				let value = value.parse::<u64>()?;
+
+				match kind.as_str() {
+					"depth" if value <= u32::MAX as u64 => Ok(BenchLimit::Depth(value as u32)),
+					"nodes" => Ok(BenchLimit::Nodes(value)),
+					"movetime" => Ok(BenchLimit::Time(value)),
+					_ => Err(TypeConvertError::SyntaxError(format!("The limit of the benchmark is invalid. ({} {})",kind,value)))
+				}
+			},
+			_ => {
+				Err(TypeConvertError::SyntaxError(format!("The format of the limit of the benchmark is invalid. ({})",args.join(" "))))
+			}
+		}
+	}
+}
+/// ベンチマークを実行できるプレイヤー
+pub trait BenchPlayer<E>: USIPlayer<E> where E: PlayerError {
+	/// `USIPlayer::set_position`で設定された局面を指定された条件で探索し、探索したノード数を返す
+	///
+	/// 同じ条件で呼び出した時に毎回同じノード数を返すように実装すると、結果の合計ノード数を性能の比較に使える。
+	/// `stop`が`true`になった場合は`limit`に達していなくても探索を打ち切り、それまでに探索したノード数を返す
+	///
+	/// # Arguments
+	/// * `limit` - 探索を打ち切る条件
+	/// * `stop` - 探索の中断が要求されているか否か（`stop`や`quit`コマンドを受信すると`true`になる）
+	fn bench_search(&mut self,limit:&BenchLimit,stop:&AtomicBool) -> Result<u64,E>;
+}
+/// ベンチマークの結果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+	/// 局面ごとの探索ノード数
+	pub nodes_per_position:Vec<u64>,
+	/// 探索ノード数の合計
+	pub nodes:u64,
+	/// 探索にかかった時間の合計
+	pub elapsed:Duration,
+	/// 中断が要求されて全ての局面を探索せずに終了したか否か
+	pub stopped:bool,
+}
+impl BenchResult {
+	/// 1秒あたりの探索ノード数
+	pub fn nps(&self) -> u64 {
+		let ms = self.elapsed.as_millis().max(1) as u64;
+
+		self.nodes * 1000 / ms
+	}
+
+	/// 結果をUSIの`info string`などで出力するための行のリストに変換
+	pub fn report(&self) -> Vec<String> {
+		let mut lines = self.nodes_per_position.iter().enumerate().map(|(i,n)| {
+			format!("Position: {}/{} nodes {}",i + 1,self.nodes_per_position.len(),n)
+		}).collect::<Vec<String>>();
+
+		lines.push(String::from("==========================="));
+		lines.push(format!("Total time (ms) : {}",self.elapsed.as_millis()));
+		lines.push(format!("Nodes searched  : {}",self.nodes));
+		lines.push(format!("Nodes/second    : {}",self.nps()));
+
+		if self.stopped {
+			lines.push(String::from("The benchmark was stopped."));
+		}
+
+		lines
+	}
+}
+/// 固定の局面のリストを固定の条件で探索し、探索ノード数と時間を計測する
+///
+/// 探索ノード数の合計は実行環境によらず探索の実装のみで決まるため、探索の変更の確認に使える
+/// # Examples
+/// ```
+/// use usiagent::bench::{Bench, BenchLimit};
+///
+/// let bench = Bench::new().limit(BenchLimit::Nodes(10000));
+/// assert_eq!(bench.get_limit(),BenchLimit::Nodes(10000));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Bench {
+	positions:Vec<String>,
+	limit:BenchLimit,
+}
+impl Default for Bench {
+	fn default() -> Bench {
+		Bench::new()
+	}
+}
+impl Bench {
+	/// 組み込みの局面と深さ`BENCH_DEFAULT_DEPTH`の`Bench`の生成
+	pub fn new() -> Bench {
+		Bench {
+			positions:BENCH_POSITIONS.iter().map(|p| p.to_string()).collect(),
+			limit:BenchLimit::Depth(BENCH_DEFAULT_DEPTH),
+		}
+	}
+
+	/// 探索を打ち切る条件を設定
+	///
+	/// # Arguments
+	/// * `limit` - 探索を打ち切る条件
+	pub fn limit(mut self,limit:BenchLimit) -> Bench {
+		self.limit = limit;
+		self
+	}
+
+	/// 探索する局面のリストを設定
+	///
+	/// # Arguments
+	/// * `positions` - positionコマンドの`position`以降の形式の局面のリスト
+	pub fn positions(mut self,positions:Vec<String>) -> Bench {
+		self.positions = positions;
+		self
+	}
+
+	/// 探索を打ち切る条件を取得
+	pub fn get_limit(&self) -> BenchLimit {
+		self.limit
+	}
+
+	/// ベンチマークを実行する
+	///
+	/// 最初に`USIPlayer::newgame`を呼び、各局面について`USIPlayer::set_position`と`BenchPlayer::bench_search`を順に呼ぶ。
+	/// プレイヤーのロックはこれらの呼び出しの間だけ取得し、局面と局面の間では解放するため、
+	/// ベンチマークの実行中も`isready`などの他のコマンドの処理がプレイヤーを利用できる。
+	/// `stop`が`true`になると残りの局面は探索せずに終了し、それまでの結果を返す
+	///
+	/// # Arguments
+	/// * `player` - ベンチマークを実行するプレイヤー
+	/// * `stop` - 中断が要求されているか否か
+	pub fn run<T,E>(&self,player:&Mutex<T>,stop:&AtomicBool) -> Result<BenchResult,BenchError<E>>
+		where T: BenchPlayer<E>, E: PlayerError {
+		let position_parser = PositionParser::new();
+
+		let mut positions = Vec::with_capacity(self.positions.len());
+
+		for p in &self.positions {
+			positions.push(position_parser.parse(&p.split(' ').collect::<Vec<&str>>())?);
+		}
+
+		player.lock().or(Err(BenchError::MutexLockFailedError))?.newgame().map_err(BenchError::PlayerError)?;
+
+		let mut nodes_per_position = Vec::with_capacity(positions.len());
+		let mut elapsed = Duration::from_millis(0);
+		let mut stopped = false;
+
+		for p in positions {
+			if stop.load(Ordering::Acquire) {
+				stopped = true;
+				break;
+			}
+
+			let mut player = player.lock().or(Err(BenchError::MutexLockFailedError))?;
+
+			let (teban,banmen,mc,n,mvs) = p.extract();
+
+			let (ms,mg) = match mc {
+				MochigomaCollections::Pair(ms,mg) => (ms,mg),
+				MochigomaCollections::Empty => (Mochigoma::new(),Mochigoma::new())
+			};
+
+			player.set_position(teban,banmen,ms,mg,n,mvs).map_err(BenchError::PlayerError)?;
+
+			let start = Instant::now();
+
+			let nodes = player.bench_search(&self.limit,stop).map_err(BenchError::PlayerError)?;
+
+			elapsed += start.elapsed();
+
+			nodes_per_position.push(nodes);
+		}
+
+		Ok(BenchResult {
+			nodes:nodes_per_position.iter().sum(),
+			nodes_per_position,
+			elapsed,
+			stopped
+		})
+	}
+}
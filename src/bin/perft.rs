@@ -0,0 +1,144 @@
+//! 指定した局面からの合法手の数え上げ(perft)を行い、結果を出力する
+//!
+//! ```text
+//! perft [--divide] [--threads N] [--hash N] <depth> [startpos [moves ...] | sfen <sfen> [moves ...]]
+//! ```
+//!
+//! 局面を省略した場合は平手の初期局面から数え上げる
+extern crate usiagent;
+
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+
+use usiagent::hash::KyokumenHash;
+use usiagent::protocol::{PositionParser, ToSfen};
+use usiagent::rule::{Perft, PerftResult, Rule, State};
+
+const USAGE:&str = "usage: perft [--divide] [--threads N] [--hash N] <depth> [startpos [moves ...] | sfen <sfen> [moves ...]]";
+
+struct Options {
+	divide:bool,
+	threads:usize,
+	hash:usize,
+	depth:usize,
+	position:Vec<String>
+}
+fn parse_args(args:Vec<String>) -> Result<Options,String> {
+	let mut divide = false;
+	let mut threads = 1;
+	let mut hash = 0;
+	let mut depth = None;
+	let mut position = Vec::new();
+
+	let mut args = args.into_iter();
+
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"-d" | "--divide" if depth.is_none() => {
+				divide = true;
+			},
+			"-t" | "--threads" if depth.is_none() => {
+				threads = args.next().ok_or("--threads requires a value.")?
+							  .parse().map_err(|_| "The value of --threads is invalid.")?;
+			},
+			"--hash" if depth.is_none() => {
+				hash = args.next().ok_or("--hash requires a value.")?
+						   .parse().map_err(|_| "The value of --hash is invalid.")?;
+			},
+			_ if depth.is_none() => {
+				depth = Some(arg.parse().map_err(|_| format!("The depth is invalid. ({})",arg))?);
+			},
+			_ => {
+				position.push(arg);
+			}
+		}
+	}
+
+	let depth = depth.ok_or("The depth is not specified.")?;
+
+	if position.is_empty() {
+		position.push(String::from("startpos"));
+	}
+
+	Ok(Options {
+		divide,
+		threads,
+		hash,
+		depth,
+		position
+	})
+}
+fn print_result(r:&PerftResult) {
+	println!("nodes: {}, captures: {}, promotions: {}, checks: {}, mates: {}",
+			 r.nodes, r.captures, r.promotions, r.checks, r.mates);
+}
+fn run(options:Options) -> Result<(),String> {
+	let position = options.position.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+
+	let (teban, banmen, mc, _, mvs) = PositionParser::new().parse(&position).map_err(|e| e.to_string())?.extract();
+
+	let mut state = State::new(banmen);
+	let mut teban = teban;
+	let mut mc = mc;
+
+	for m in mvs {
+		let (next,nmc,_) = Rule::apply_valid_move(&state,teban,&mc,m.to_applied_move()).map_err(|e| e.to_string())?;
+
+		state = next;
+		mc = nmc;
+		teban = teban.opposite();
+	}
+
+	let mut perft = Perft::new().threads(options.threads);
+
+	if options.hash > 0 {
+		perft = perft.cache(Arc::new(KyokumenHash::new()),options.hash);
+	}
+
+	let start = Instant::now();
+
+	let result = if options.divide {
+		let mut divide = perft.divide(&state,teban,&mc,options.depth).into_iter().map(|(m,r)| {
+			m.to_move().to_sfen().map(|m| (m,r)).map_err(|e| e.to_string())
+		}).collect::<Result<Vec<(String,PerftResult)>,String>>()?;
+
+		divide.sort_by(|a,b| a.0.cmp(&b.0));
+
+		for &(ref m,ref r) in divide.iter() {
+			println!("{}: {}",m,r.nodes);
+		}
+
+		println!();
+
+		divide.into_iter().fold(PerftResult::default(),|acc,(_,r)| acc + r)
+	} else {
+		perft.perft(&state,teban,&mc,options.depth)
+	};
+
+	let elapsed = start.elapsed();
+
+	print_result(&result);
+
+	println!("time: {} ms, nps: {}",
+			 elapsed.as_millis(),
+			 (result.nodes as f64 / elapsed.as_secs_f64().max(1e-9)) as u64);
+
+	Ok(())
+}
+fn main() {
+	let options = match parse_args(env::args().skip(1).collect()) {
+		Ok(options) => options,
+		Err(e) => {
+			eprintln!("{}",e);
+			eprintln!("{}",USAGE);
+			process::exit(1);
+		}
+	};
+
+	if let Err(e) = run(options) {
+		eprintln!("{}",e);
+		process::exit(1);
+	}
+}
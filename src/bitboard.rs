@@ -3,6 +3,8 @@ use std::fmt;
 use std::fmt::Formatter;
 use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr, Sub};
 use bits::pop_lsb;
+#[cfg(all(any(test, feature = "sliding-attack-table"), target_feature = "bmi2"))]
+use bits::pext64;
 use rule::Square;
 
 /// 合法手を生成するために内部で利用するビットボード
@@ -286,3 +288,208 @@ impl<F> Iterator for PopLsbIterByCallback<F> where F: FnMut() -> Option<Square>
         (self.callback)()
     }
 }
+#[cfg(any(test, feature = "sliding-attack-table"))]
+impl BitBoard {
+    /// 飛び駒の一方向の利きを事前計算したテーブルを引いて返す
+    ///
+    /// # Arguments
+    /// * `direction` - 利きを求める方向
+    /// * `from` - 盤面の左上を0,0とし、x * 9 + yで表される駒の位置
+    /// * `occupied` - 盤面上の駒の配置を表すビットボード
+    ///
+    /// 利きの先にある最初の駒の位置も含めて返す。
+    /// 逆方向の利きは盤面を逆さまにしたビットボードと位置(80 - from)を渡すことで求める。
+    /// テーブルの添え字はBMI2が有効な場合はPEXT命令で、そうでない場合は乗算で求める。
+    /// feature `sliding-attack-table`が有効な場合のみ利用でき、`Rule`の合法手生成はこの関数で飛び駒の利きを求める。
+    #[inline]
+    pub fn sliding_attacks_by_table(direction:SlidingDirection,from:u32,occupied:BitBoard) -> BitBoard {
+        let entry = &SLIDING_ENTRIES[direction as usize][from as usize];
+        let occupied = unsafe { occupied.merged_bitboard };
+
+        BitBoard {
+            merged_bitboard: SLIDING_ATTACK_TABLE[entry.offset + sliding_index(entry,occupied)]
+        }
+    }
+}
+/// 飛び駒の利きを求める方向（盤面の左上を0,0とし、x * 9 + yで表される位置の増える方向のみ）
+#[cfg(any(test, feature = "sliding-attack-table"))]
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum SlidingDirection {
+    /// 下方向(y + 1)
+    Bottom = 0,
+    /// 右方向(x + 1)
+    Right,
+    /// 右上方向(x + 1,y - 1)
+    RightTop,
+    /// 右下方向(x + 1,y + 1)
+    RightBottom,
+}
+/// 各方向の(x,y)の増分
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const SLIDING_DELTAS:[(i32,i32); 4] = [(0,1),(1,0),(1,-1),(1,1)];
+/// 利きを遮る駒の配置を間隔を空けたビット列から連続したビット列に詰めるための乗数のシフト量
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const SLIDING_MAGIC_SHIFT:u32 = 54;
+/// 方向と位置ごとのテーブルの引き方
+#[cfg(any(test, feature = "sliding-attack-table"))]
+#[derive(Clone, Copy)]
+struct SlidingEntry {
+    /// 利きを遮る可能性のある位置のマスク（盤端の位置を除く）
+    #[cfg_attr(not(target_feature = "bmi2"), allow(dead_code))]
+    mask:u128,
+    /// maskの最下位ビットの位置
+    #[cfg_attr(target_feature = "bmi2", allow(dead_code))]
+    base:u32,
+    /// maskをbaseだけ右シフトした値
+    #[cfg_attr(target_feature = "bmi2", allow(dead_code))]
+    spread:u128,
+    /// 間隔を空けたビット列を連続したビット列に詰めるための乗数
+    #[cfg_attr(target_feature = "bmi2", allow(dead_code))]
+    magic:u128,
+    /// maskの立っているビットの数
+    bits:u32,
+    /// テーブル内での開始位置
+    offset:usize,
+}
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const fn sliding_ray_len(direction:usize,from:u32) -> u32 {
+    let (dx,dy) = SLIDING_DELTAS[direction];
+    let mut x = (from / 9) as i32 + dx;
+    let mut y = (from % 9) as i32 + dy;
+    let mut len = 0;
+
+    while x >= 0 && x < 9 && y >= 0 && y < 9 {
+        len += 1;
+        x += dx;
+        y += dy;
+    }
+
+    len
+}
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const fn build_sliding_entries() -> [[SlidingEntry; 81]; 4] {
+    let mut entries = [[SlidingEntry {
+        mask: 0, base: 0, spread: 0, magic: 0, bits: 0, offset: 0
+    }; 81]; 4];
+
+    let mut offset = 0;
+    let mut d = 0;
+
+    while d < 4 {
+        let (dx,dy) = SLIDING_DELTAS[d];
+        let stride = (dx * 9 + dy) as u32;
+        let mut from = 0;
+
+        while from < 81 {
+            let len = sliding_ray_len(d,from);
+            let bits = if len > 0 { len - 1 } else { 0 };
+            let base = from + stride + 1;
+
+            let mut mask = 0;
+            let mut spread = 0;
+            let mut magic = 0;
+            let mut i = 0;
+
+            while i < bits {
+                mask |= 1 << (base + i * stride);
+                spread |= 1 << (i * stride);
+                magic |= 1 << (SLIDING_MAGIC_SHIFT - i * (stride - 1));
+                i += 1;
+            }
+
+            if bits == 0 {
+                magic = 0;
+            } else if stride == 1 {
+                magic = 1 << SLIDING_MAGIC_SHIFT;
+            }
+
+            entries[d][from as usize] = SlidingEntry {
+                mask,
+                base: if bits > 0 { base } else { 0 },
+                spread,
+                magic,
+                bits,
+                offset
+            };
+
+            offset += 1 << bits;
+            from += 1;
+        }
+
+        d += 1;
+    }
+
+    entries
+}
+/// 各方向、各位置の`SlidingEntry`
+#[cfg(any(test, feature = "sliding-attack-table"))]
+static SLIDING_ENTRIES:[[SlidingEntry; 81]; 4] = build_sliding_entries();
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const fn sliding_attack_table_size() -> usize {
+    let last = SLIDING_ENTRIES[3][80];
+
+    last.offset + (1 << last.bits)
+}
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const SLIDING_ATTACK_TABLE_SIZE:usize = sliding_attack_table_size();
+#[cfg(any(test, feature = "sliding-attack-table"))]
+const fn build_sliding_attack_table() -> [u128; SLIDING_ATTACK_TABLE_SIZE] {
+    let mut table = [0; SLIDING_ATTACK_TABLE_SIZE];
+    let mut d = 0;
+
+    while d < 4 {
+        let (dx,dy) = SLIDING_DELTAS[d];
+        let mut from:u32 = 0;
+
+        while from < 81 {
+            let entry = SLIDING_ENTRIES[d][from as usize];
+            let mut index = 0;
+
+            while index < (1 << entry.bits) {
+                let mut x = (from / 9) as i32 + dx;
+                let mut y = (from % 9) as i32 + dy;
+                let mut i = 0;
+                let mut attacks = 0;
+
+                while x >= 0 && x < 9 && y >= 0 && y < 9 {
+                    attacks |= 1 << ((x * 9 + y) as u32 + 1);
+
+                    if i < entry.bits && index & (1 << i) != 0 {
+                        break;
+                    }
+
+                    i += 1;
+                    x += dx;
+                    y += dy;
+                }
+
+                table[entry.offset + index] = attacks;
+                index += 1;
+            }
+
+            from += 1;
+        }
+
+        d += 1;
+    }
+
+    table
+}
+/// 方向と位置と遮る駒の配置ごとの飛び駒の利き
+#[cfg(any(test, feature = "sliding-attack-table"))]
+static SLIDING_ATTACK_TABLE:[u128; SLIDING_ATTACK_TABLE_SIZE] = build_sliding_attack_table();
+#[cfg(all(any(test, feature = "sliding-attack-table"), target_feature = "bmi2"))]
+#[inline(always)]
+fn sliding_index(entry:&SlidingEntry,occupied:u128) -> usize {
+    let lo = pext64(occupied as u64,entry.mask as u64);
+    let hi = pext64((occupied >> 64) as u64,(entry.mask >> 64) as u64);
+
+    (lo | hi << (entry.mask as u64).count_ones()) as usize
+}
+#[cfg(all(any(test, feature = "sliding-attack-table"), not(target_feature = "bmi2")))]
+#[inline(always)]
+fn sliding_index(entry:&SlidingEntry,occupied:u128) -> usize {
+    let bits = (occupied >> entry.base) & entry.spread;
+
+    ((bits.wrapping_mul(entry.magic) >> SLIDING_MAGIC_SHIFT) & ((1 << entry.bits) - 1)) as usize
+}
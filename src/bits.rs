@@ -1,6 +1,8 @@
 //! ビットボード操作のためのユーティリティ関数群
 #[cfg(target_feature = "bmi1")]
 use std::arch::x86_64::_blsr_u64;
+#[cfg(target_feature = "bmi2")]
+use std::arch::x86_64::_pext_u64;
 #[cfg(target_feature = "bmi1")]
 #[inline(always)]
 pub fn blsr64(v:u64) -> u64 {
@@ -35,4 +37,32 @@ pub fn pop_lsb(bits:&mut u64) -> u64 {
 #[inline(always)]
 pub fn lsb64(bits:u64) -> u64 {
     bits.trailing_zeros() as u64
-}
\ No newline at end of file
+}
+#[cfg(target_feature = "bmi2")]
+#[inline(always)]
+pub fn pext64(v:u64,mask:u64) -> u64 {
+    unsafe { _pext_u64(v,mask) }
+}
+#[cfg(not(target_feature = "bmi2"))]
+#[inline(always)]
+/// マスクのビットが立っている位置のビットを取り出して下位ビットから詰めて返す
+///
+/// # Arguments
+/// * `v` - 操作対象のビット列
+/// * `mask` - 取り出すビット位置を表すマスク
+pub fn pext64(v:u64,mask:u64) -> u64 {
+    let mut mask = mask;
+    let mut result = 0;
+    let mut bit = 1;
+
+    while mask != 0 {
+        if v & mask & mask.wrapping_neg() != 0 {
+            result |= bit;
+        }
+
+        mask = blsr64(mask);
+        bit <<= 1;
+    }
+
+    result
+}
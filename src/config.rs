@@ -0,0 +1,160 @@
+//! INI形式の設定ファイルの読み込み
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use error::ConfigFileError;
+
+/// 設定ファイルの項目
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigEntry {
+	/// 行番号
+	pub line:usize,
+	/// 項目名
+	pub key:String,
+	/// 値
+	pub value:String,
+}
+/// `[セクション名]`の行で区切られ、各行に`項目名 = 値`を書くINI形式の設定ファイル
+///
+/// 空行と`#`または`;`で始まる行は無視される。
+/// 値の前後の空白は取り除かれ、値全体が`"`で囲まれている場合は外側の一組の`"`だけが取り除かれる（前後の空白や`"`を含む値はこの形式で書く）。
+/// 値を持たない項目（buttonのオプションなど）は項目名のみを書く。
+/// 最初のセクションより前に書かれた項目は名前が空文字列のセクションに属する
+///
+/// # Examples
+/// ```
+/// use usiagent::config::ConfigFile;
+///
+/// let config = ConfigFile::read(&b"[match]\ngames = 10\nkifu = \"logs/kifu.txt\"\n"[..]).unwrap();
+///
+/// assert_eq!(config.get("match","games"),Some("10"));
+/// assert_eq!(config.parse::<u32>("match","games").unwrap(),Some(10));
+/// assert_eq!(config.get("match","kifu"),Some("logs/kifu.txt"));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConfigFile {
+	sections:Vec<(String,Vec<ConfigEntry>)>,
+}
+impl ConfigFile {
+	/// 設定ファイルを読み込む
+	///
+	/// # Arguments
+	/// * `reader` - 読み込み元
+	pub fn read<R>(reader:R) -> Result<ConfigFile,ConfigFileError> where R: BufRead {
+		let mut sections = vec![(String::new(),Vec::new())];
+
+		for (i,line) in reader.lines().enumerate() {
+			let line = line?;
+			let l = line.trim();
+
+			if l.is_empty() || l.starts_with('#') || l.starts_with(';') {
+				continue;
+			}
+
+			if l.starts_with('[') {
+				if !l.ends_with(']') || l.len() < 3 {
+					return Err(ConfigFileError::InvalidFormat(i + 1,line.clone()));
+				}
+
+				sections.push((l[1..l.len()-1].trim().to_string(),Vec::new()));
+				continue;
+			}
+
+			let (key,value) = match l.find('=') {
+				Some(p) => (l[..p].trim(),l[p+1..].trim()),
+				None => (l,"")
+			};
+
+			if key.is_empty() {
+				return Err(ConfigFileError::InvalidFormat(i + 1,line.clone()));
+			}
+
+			let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+				&value[1..value.len()-1]
+			} else {
+				value
+			};
+
+			if let Some(&mut (_,ref mut entries)) = sections.last_mut() {
+				entries.push(ConfigEntry {
+					line:i + 1,
+					key:key.to_string(),
+					value:value.to_string(),
+				});
+			}
+		}
+
+		Ok(ConfigFile {
+			sections,
+		})
+	}
+
+	/// ファイルから設定ファイルを読み込む
+	///
+	/// # Arguments
+	/// * `file` - 読み込むファイルのパス
+	pub fn open(file:&str) -> Result<ConfigFile,ConfigFileError> {
+		ConfigFile::read(BufReader::new(File::open(file)?))
+	}
+
+	/// セクションの名前の一覧（同じ名前のセクションは一つにまとめられる）
+	pub fn section_names(&self) -> Vec<&str> {
+		let mut names = Vec::new();
+
+		for (name,entries) in &self.sections {
+			if !entries.is_empty() && !names.contains(&name.as_str()) {
+				names.push(name.as_str());
+			}
+		}
+
+		names
+	}
+
+	/// セクションの項目の一覧を書かれた順に返す
+	///
+	/// # Arguments
+	/// * `section` - セクションの名前
+	pub fn entries(&self,section:&str) -> Vec<&ConfigEntry> {
+		self.sections.iter()
+					 .filter(|(name,_)| name == section)
+					 .flat_map(|(_,entries)| entries.iter())
+					 .collect()
+	}
+
+	/// 項目の値を返す（同じ項目が複数ある場合は最後のもの）
+	///
+	/// # Arguments
+	/// * `section` - セクションの名前
+	/// * `key` - 項目名
+	pub fn get(&self,section:&str,key:&str) -> Option<&str> {
+		self.entries(section).into_iter().rev().find(|e| e.key == key).map(|e| e.value.as_str())
+	}
+
+	/// 項目の値を変換して返す
+	///
+	/// # Arguments
+	/// * `section` - セクションの名前
+	/// * `key` - 項目名
+	pub fn parse<T>(&self,section:&str,key:&str) -> Result<Option<T>,ConfigFileError> where T: FromStr {
+		match self.entries(section).into_iter().rev().find(|e| e.key == key) {
+			None => Ok(None),
+			Some(e) => {
+				e.value.parse::<T>().map(Some).map_err(|_| {
+					ConfigFileError::InvalidValue(e.line,e.key.clone(),e.value.clone())
+				})
+			}
+		}
+	}
+}
+/// 実行ファイルと同じディレクトリにあるファイルのパスを返す
+///
+/// # Arguments
+/// * `file_name` - ファイル名
+pub fn path_beside_executable(file_name:&str) -> io::Result<PathBuf> {
+	let exe = env::current_exe()?;
+
+	Ok(exe.parent().map(|p| p.join(file_name)).unwrap_or_else(|| PathBuf::from(file_name)))
+}
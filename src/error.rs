@@ -13,6 +13,7 @@ use crossbeam_channel::RecvError;
 use command::UsiCommand;
 use player::UsiInfoMessage;
 use selfmatch::SelfMatchMessage;
+use shogi::{KomaKind, MochigomaKind, Teban};
 
 /// イベント処理時のエラー
 #[derive(Debug)]
@@ -668,6 +669,118 @@ impl From<TypeConvertError<String>> for SfenStringConvertError {
 		SfenStringConvertError::TypeConvertError(err)
 	}
 }
+/// setoptionコマンドやオプションの値のパース、検証時のエラー
+#[derive(Debug,Clone,Eq,PartialEq)]
+pub enum SetOptionParseError {
+	/// 書式エラー（入力された文字列）
+	InvalidFormat(String),
+	/// 存在しないオプション（名前）
+	UnknownOption(String),
+	/// 値が指定されていない（名前）
+	MissingValue(String),
+	/// 値をオプションの種類に変換できない（名前,値）
+	InvalidValue(String,String),
+	/// spinの範囲外の値（名前,値,最小値,最大値）
+	OutOfRange(String,i64,i64,i64),
+	/// comboの項目に含まれない値（名前,値）
+	NotInCombo(String,String),
+}
+impl fmt::Display for SetOptionParseError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		SetOptionParseError::InvalidFormat(ref s) => {
+	 			write!(f,"The format of the setoption command is illegal. (input: {})",s)
+	 		},
+	 		SetOptionParseError::UnknownOption(ref name) => {
+	 			write!(f,"Could not get option type. (name: {})",name)
+	 		},
+	 		SetOptionParseError::MissingValue(ref name) => {
+	 			write!(f,"The value of the option is not specified. (name: {})",name)
+	 		},
+	 		SetOptionParseError::InvalidValue(ref name,ref value) => {
+	 			write!(f,"The value of the option is invalid. (name: {}, value: {})",name,value)
+	 		},
+	 		SetOptionParseError::OutOfRange(ref name,value,min,max) => {
+	 			write!(f,"The value of the option is out of range. (name: {}, value: {}, min: {}, max: {})",name,value,min,max)
+	 		},
+	 		SetOptionParseError::NotInCombo(ref name,ref value) => {
+	 			write!(f,"The value of the option is not one of the combo items. (name: {}, value: {})",name,value)
+	 		}
+	 	}
+	 }
+}
+impl error::Error for SetOptionParseError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		SetOptionParseError::InvalidFormat(_) => "The format of the setoption command is illegal.",
+	 		SetOptionParseError::UnknownOption(_) => "Could not get option type.",
+	 		SetOptionParseError::MissingValue(_) => "The value of the option is not specified.",
+	 		SetOptionParseError::InvalidValue(_,_) => "The value of the option is invalid.",
+	 		SetOptionParseError::OutOfRange(_,_,_,_) => "The value of the option is out of range.",
+	 		SetOptionParseError::NotInCombo(_,_) => "The value of the option is not one of the combo items.",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		None
+	}
+}
+/// 局面の検証で見つかった不正
+#[derive(Debug,Clone,Eq,PartialEq)]
+pub enum PositionValidationError {
+	/// 二歩（手番,筋）
+	Nifu(Teban,u32),
+	/// 行き所のない駒（駒の種類,筋,段）
+	DeadPiece(KomaKind,u32,u32),
+	/// 王の数が一枚ではない（手番,枚数）
+	InvalidOuCount(Teban,usize),
+	/// 駒の枚数が上限を超えている（駒の種類,盤上と持ち駒の合計枚数）
+	TooManyPieces(MochigomaKind,usize),
+	/// 駒の総数が40枚を超えている（総数）
+	TooManyPiecesTotal(usize),
+	/// 手番ではない側の王に王手がかかっている（王手をかけられている側の手番）
+	OpponentInCheck(Teban),
+}
+impl fmt::Display for PositionValidationError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		PositionValidationError::Nifu(t,file) => {
+	 			write!(f,"There are two unpromoted pawns of {:?} on file {}.",t,file)
+	 		},
+	 		PositionValidationError::DeadPiece(kind,file,rank) => {
+	 			write!(f,"{:?} on {}{} has no legal moves.",kind,file,rank)
+	 		},
+	 		PositionValidationError::InvalidOuCount(t,count) => {
+	 			write!(f,"{:?} has {} kings.",t,count)
+	 		},
+	 		PositionValidationError::TooManyPieces(kind,count) => {
+	 			write!(f,"There are too many pieces of {:?}. (count: {})",kind,count)
+	 		},
+	 		PositionValidationError::TooManyPiecesTotal(count) => {
+	 			write!(f,"There are more than 40 pieces. (count: {})",count)
+	 		},
+	 		PositionValidationError::OpponentInCheck(t) => {
+	 			write!(f,"The king of {:?} is in check though it is not its turn.",t)
+	 		}
+	 	}
+	 }
+}
+impl error::Error for PositionValidationError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		PositionValidationError::Nifu(_,_) => "There are two unpromoted pawns on the same file.",
+	 		PositionValidationError::DeadPiece(_,_,_) => "There is a piece that has no legal moves.",
+	 		PositionValidationError::InvalidOuCount(_,_) => "The number of kings is invalid.",
+	 		PositionValidationError::TooManyPieces(_,_) => "There are too many pieces of the same kind.",
+	 		PositionValidationError::TooManyPiecesTotal(_) => "There are more than 40 pieces.",
+	 		PositionValidationError::OpponentInCheck(_) => "The king of the side not to move is in check.",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		None
+	}
+}
 /// 棋譜書き込み時のエラー
 #[derive(Debug)]
 pub enum KifuWriteError {
@@ -719,6 +832,57 @@ impl From<io::Error> for KifuWriteError {
 		KifuWriteError::IOError(err)
 	}
 }
+/// 棋譜読み込み時のエラー
+#[derive(Debug)]
+pub enum KifuReadError {
+	/// 不正なフォーマット
+	InvalidFormat(String),
+	/// 存在しない対局の番号が指定された
+	OutOfRange(usize),
+	/// 型変換時のエラー
+	TypeConvertError(TypeConvertError<String>),
+	/// 入出力時のエラー
+	IOError(io::Error),
+}
+impl fmt::Display for KifuReadError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		KifuReadError::InvalidFormat(ref s) => write!(f,"The kifu format is invalid. ({})",s),
+	 		KifuReadError::OutOfRange(n) => write!(f,"Game number {} does not exist in the kifu.",n),
+	 		KifuReadError::TypeConvertError(_) => write!(f,"An error occurred during type conversion."),
+		 	KifuReadError::IOError(ref e) => write!(f,"IO Error. ({})",e),
+	 	}
+	 }
+}
+impl error::Error for KifuReadError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		KifuReadError::InvalidFormat(_) => "The kifu format is invalid.",
+	 		KifuReadError::OutOfRange(_) => "The specified game does not exist in the kifu.",
+	 		KifuReadError::TypeConvertError(_) => "An error occurred during type conversion.",
+		 	KifuReadError::IOError(_) => "There was an error reading kifu. (IO Error).",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	 	match *self {
+	 		KifuReadError::InvalidFormat(_) => None,
+	 		KifuReadError::OutOfRange(_) => None,
+	 		KifuReadError::TypeConvertError(ref e) => Some(e),
+	 		KifuReadError::IOError(ref e) => Some(e),
+	 	}
+	 }
+}
+impl From<TypeConvertError<String>> for KifuReadError {
+	fn from(err: TypeConvertError<String>) -> KifuReadError {
+		KifuReadError::TypeConvertError(err)
+	}
+}
+impl From<io::Error> for KifuReadError {
+	fn from(err:io::Error) -> KifuReadError {
+		KifuReadError::IOError(err)
+	}
+}
 /// `USIPlayer`の実装から投げられるエラーであることを示すマーカートレイト
 pub trait PlayerError: Error + fmt::Debug + Send + 'static {}
 /// サイズ超過のエラー
@@ -740,3 +904,276 @@ impl error::Error for LimitSizeError {
 		None
 	}
 }
+/// 拡張コマンドの実行時のエラー
+#[derive(Debug)]
+pub enum ExtensionCommandError {
+	/// 登録されていないコマンド（コマンド名）
+	UnknownCommand(String),
+	/// 引数が不正（入力された引数）
+	InvalidArgument(String),
+	/// positionコマンドで局面がまだ送られていない
+	PositionNotSet,
+	/// positionコマンドの指し手が合法手ではなく局面を再現できない
+	InvalidPosition(ShogiError),
+	/// sfen文字←→内部表現変換時のエラー
+	SfenStringConvertError(SfenStringConvertError),
+	/// 入出力時のエラー
+	IOError(io::Error),
+	/// その他
+	Fail(String),
+}
+impl fmt::Display for ExtensionCommandError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		ExtensionCommandError::UnknownCommand(ref name) => write!(f,"The extension command is not registered. (name: {})",name),
+	 		ExtensionCommandError::InvalidArgument(ref s) => write!(f,"The argument of the extension command is invalid. (input: {})",s),
+	 		ExtensionCommandError::PositionNotSet => write!(f,"The position has not been set yet."),
+	 		ExtensionCommandError::InvalidPosition(ref e) => write!(f,"The position could not be reproduced. ({})",e),
+	 		ExtensionCommandError::SfenStringConvertError(_) => write!(f,"An error occurred during conversion to sfen string."),
+		 	ExtensionCommandError::IOError(ref e) => write!(f,"IO Error. ({})",e),
+	 		ExtensionCommandError::Fail(ref s) => write!(f,"An error occurred while executing the extension command. ({})",s),
+	 	}
+	 }
+}
+impl error::Error for ExtensionCommandError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		ExtensionCommandError::UnknownCommand(_) => "The extension command is not registered.",
+	 		ExtensionCommandError::InvalidArgument(_) => "The argument of the extension command is invalid.",
+	 		ExtensionCommandError::PositionNotSet => "The position has not been set yet.",
+	 		ExtensionCommandError::InvalidPosition(_) => "The position could not be reproduced.",
+	 		ExtensionCommandError::SfenStringConvertError(_) => "An error occurred during conversion to sfen string.",
+		 	ExtensionCommandError::IOError(_) => "There was an error executing the extension command. (IO Error).",
+	 		ExtensionCommandError::Fail(_) => "An error occurred while executing the extension command.",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	 	match *self {
+	 		ExtensionCommandError::UnknownCommand(_) => None,
+	 		ExtensionCommandError::InvalidArgument(_) => None,
+	 		ExtensionCommandError::PositionNotSet => None,
+	 		ExtensionCommandError::InvalidPosition(ref e) => Some(e),
+	 		ExtensionCommandError::SfenStringConvertError(ref e) => Some(e),
+	 		ExtensionCommandError::IOError(ref e) => Some(e),
+	 		ExtensionCommandError::Fail(_) => None,
+	 	}
+	 }
+}
+impl From<SfenStringConvertError> for ExtensionCommandError {
+	fn from(err: SfenStringConvertError) -> ExtensionCommandError {
+		ExtensionCommandError::SfenStringConvertError(err)
+	}
+}
+impl From<ToMoveStringConvertError> for ExtensionCommandError {
+	fn from(err: ToMoveStringConvertError) -> ExtensionCommandError {
+		ExtensionCommandError::SfenStringConvertError(SfenStringConvertError::from(err))
+	}
+}
+impl From<TypeConvertError<String>> for ExtensionCommandError {
+	fn from(err: TypeConvertError<String>) -> ExtensionCommandError {
+		ExtensionCommandError::SfenStringConvertError(SfenStringConvertError::from(err))
+	}
+}
+impl From<ShogiError> for ExtensionCommandError {
+	fn from(err: ShogiError) -> ExtensionCommandError {
+		ExtensionCommandError::InvalidPosition(err)
+	}
+}
+impl From<io::Error> for ExtensionCommandError {
+	fn from(err:io::Error) -> ExtensionCommandError {
+		ExtensionCommandError::IOError(err)
+	}
+}
+/// ベンチマーク実行時のエラー
+#[derive(Debug)]
+pub enum BenchError<E> where E: PlayerError {
+	/// 局面の文字列のパースに失敗
+	InvalidPosition(TypeConvertError<String>),
+	/// `USIPlayer`の実装がエラーを投げた
+	PlayerError(E),
+	/// プレイヤーオブジェクトのロックの取得に失敗
+	MutexLockFailedError,
+}
+impl<E> fmt::Display for BenchError<E> where E: PlayerError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		BenchError::InvalidPosition(ref e) => write!(f,"The position of the benchmark is invalid. ({})",e),
+	 		BenchError::PlayerError(ref e) => write!(f,"An error occurred in the player while running the benchmark. ({})",e),
+	 		BenchError::MutexLockFailedError => write!(f,"Could not get exclusive lock on player object."),
+	 	}
+	 }
+}
+impl<E> error::Error for BenchError<E> where E: PlayerError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		BenchError::InvalidPosition(_) => "The position of the benchmark is invalid.",
+	 		BenchError::PlayerError(_) => "An error occurred in the player while running the benchmark.",
+	 		BenchError::MutexLockFailedError => "Could not get exclusive lock on player object.",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	 	match *self {
+	 		BenchError::InvalidPosition(ref e) => Some(e),
+	 		BenchError::PlayerError(ref e) => Some(e),
+	 		BenchError::MutexLockFailedError => None,
+	 	}
+	 }
+}
+impl<E> From<TypeConvertError<String>> for BenchError<E> where E: PlayerError {
+	fn from(err: TypeConvertError<String>) -> BenchError<E> {
+		BenchError::InvalidPosition(err)
+	}
+}
+/// 通信記録の読み込み時のエラー
+#[derive(Debug)]
+pub enum TranscriptReadError {
+	/// 不正なフォーマット（行番号,行の内容）
+	InvalidFormat(usize,String),
+	/// 入出力時のエラー
+	IOError(io::Error),
+}
+impl fmt::Display for TranscriptReadError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		TranscriptReadError::InvalidFormat(n,ref s) => write!(f,"The format of the transcript is invalid. (line {}: {})",n,s),
+		 	TranscriptReadError::IOError(ref e) => write!(f,"IO Error. ({})",e),
+	 	}
+	 }
+}
+impl error::Error for TranscriptReadError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		TranscriptReadError::InvalidFormat(_,_) => "The format of the transcript is invalid.",
+		 	TranscriptReadError::IOError(_) => "There was an error reading transcript. (IO Error).",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	 	match *self {
+	 		TranscriptReadError::InvalidFormat(_,_) => None,
+	 		TranscriptReadError::IOError(ref e) => Some(e),
+	 	}
+	 }
+}
+impl From<io::Error> for TranscriptReadError {
+	fn from(err:io::Error) -> TranscriptReadError {
+		TranscriptReadError::IOError(err)
+	}
+}
+/// オプションの定義に基づく値の検証、変換、設定ファイルの読み書きのエラー
+#[derive(Debug)]
+pub enum OptionRegistryError {
+	/// オプションの値が不正
+	OptionError(SetOptionParseError),
+	/// 設定ファイルの不正なフォーマット（行番号,行の内容）
+	InvalidFormat(usize,String),
+	/// 入出力時のエラー
+	IOError(io::Error),
+}
+impl fmt::Display for OptionRegistryError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		OptionRegistryError::OptionError(ref e) => write!(f,"{}",e),
+	 		OptionRegistryError::InvalidFormat(n,ref s) => {
+	 			write!(f,"The format of the option file is invalid. (line {}: {})",n,s)
+	 		},
+		 	OptionRegistryError::IOError(ref e) => write!(f,"IO Error. ({})",e),
+	 	}
+	 }
+}
+impl error::Error for OptionRegistryError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		OptionRegistryError::OptionError(_) => "The value of the option is invalid.",
+	 		OptionRegistryError::InvalidFormat(_,_) => "The format of the option file is invalid.",
+		 	OptionRegistryError::IOError(_) => "There was an error reading or writing the option file. (IO Error).",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	 	match *self {
+	 		OptionRegistryError::OptionError(ref e) => Some(e),
+	 		OptionRegistryError::IOError(ref e) => Some(e),
+	 		_ => None,
+	 	}
+	 }
+}
+impl From<SetOptionParseError> for OptionRegistryError {
+	fn from(err:SetOptionParseError) -> OptionRegistryError {
+		OptionRegistryError::OptionError(err)
+	}
+}
+impl From<io::Error> for OptionRegistryError {
+	fn from(err:io::Error) -> OptionRegistryError {
+		OptionRegistryError::IOError(err)
+	}
+}
+impl From<ConfigFileError> for OptionRegistryError {
+	fn from(err:ConfigFileError) -> OptionRegistryError {
+		match err {
+			ConfigFileError::InvalidFormat(n,s) => OptionRegistryError::InvalidFormat(n,s),
+			ConfigFileError::UnknownKey(n,key) => OptionRegistryError::InvalidFormat(n,key),
+			ConfigFileError::InvalidValue(n,key,value) => OptionRegistryError::InvalidFormat(n,format!("{} = {}",key,value)),
+			ConfigFileError::OptionError(e) => OptionRegistryError::OptionError(e),
+			ConfigFileError::IOError(e) => OptionRegistryError::IOError(e),
+		}
+	}
+}
+/// 設定ファイルの読み込みのエラー
+#[derive(Debug)]
+pub enum ConfigFileError {
+	/// 不正なフォーマット（行番号,行の内容）
+	InvalidFormat(usize,String),
+	/// 定義されていない項目（行番号,項目名）
+	UnknownKey(usize,String),
+	/// 項目の値が不正（行番号,項目名,値）
+	InvalidValue(usize,String,String),
+	/// オプションの値が不正
+	OptionError(SetOptionParseError),
+	/// 入出力時のエラー
+	IOError(io::Error),
+}
+impl fmt::Display for ConfigFileError {
+	 fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+	 	match *self {
+	 		ConfigFileError::InvalidFormat(n,ref s) => write!(f,"The format of the config file is invalid. (line {}: {})",n,s),
+	 		ConfigFileError::UnknownKey(n,ref key) => write!(f,"Unknown key in the config file. (line {}: {})",n,key),
+	 		ConfigFileError::InvalidValue(n,ref key,ref value) => {
+	 			write!(f,"The value in the config file is invalid. (line {}: {} = {})",n,key,value)
+	 		},
+	 		ConfigFileError::OptionError(ref e) => write!(f,"{}",e),
+		 	ConfigFileError::IOError(ref e) => write!(f,"IO Error. ({})",e),
+	 	}
+	 }
+}
+impl error::Error for ConfigFileError {
+	 fn description(&self) -> &str {
+	 	match *self {
+	 		ConfigFileError::InvalidFormat(_,_) => "The format of the config file is invalid.",
+	 		ConfigFileError::UnknownKey(_,_) => "Unknown key in the config file.",
+	 		ConfigFileError::InvalidValue(_,_,_) => "The value in the config file is invalid.",
+	 		ConfigFileError::OptionError(_) => "The value of the option in the config file is invalid.",
+		 	ConfigFileError::IOError(_) => "There was an error reading the config file. (IO Error).",
+	 	}
+	 }
+
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+	 	match *self {
+	 		ConfigFileError::OptionError(ref e) => Some(e),
+	 		ConfigFileError::IOError(ref e) => Some(e),
+	 		_ => None,
+	 	}
+	 }
+}
+impl From<SetOptionParseError> for ConfigFileError {
+	fn from(err:SetOptionParseError) -> ConfigFileError {
+		ConfigFileError::OptionError(err)
+	}
+}
+impl From<io::Error> for ConfigFileError {
+	fn from(err:io::Error) -> ConfigFileError {
+		ConfigFileError::IOError(err)
+	}
+}
@@ -51,6 +51,8 @@ pub enum SystemEvent {
 	GameOver(GameEndState),
 	/// USIコマンド送信要求
 	SendUsiCommand(UsiOutput),
+	/// 拡張コマンド受信イベント（コマンド名,引数）
+	Extension(String,Vec<String>),
 	/// 終了
 	QuitReady,
 }
@@ -79,6 +81,8 @@ pub enum SystemEventKind {
 	GameOver,
 	/// USIコマンド送信要求
 	SendUsiCommand,
+	/// 拡張コマンド受信イベント
+	Extension,
 	/// 終了
 	QuitReady,
 }
@@ -106,6 +110,7 @@ impl MapEventKind<SystemEventKind> for SystemEvent {
 			SystemEvent::Quit => SystemEventKind::Quit,
 			SystemEvent::GameOver(_) => SystemEventKind::GameOver,
 			SystemEvent::SendUsiCommand(_) => SystemEventKind::SendUsiCommand,
+			SystemEvent::Extension(_,_) => SystemEventKind::Extension,
 			SystemEvent::QuitReady => SystemEventKind::QuitReady,
 		}
 	}
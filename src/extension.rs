@@ -0,0 +1,243 @@
+//! USIプロトコルに含まれないエンジンのデバッグ用の拡張コマンド
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use error::ExtensionCommandError;
+use output::USIOutputWriter;
+use protocol::ToSfen;
+use rule::{PerftResult, Rule, State};
+use shogi::*;
+
+const KOMA_STR_MAP:[&str; 29] = [
+	"P","L","N","S","G","B","R","K","+P","+L","+N","+S","+B","+R",
+	"p","l","n","s","g","b","r","k","+p","+l","+n","+s","+b","+r",
+	"."
+];
+const DAN_STR_MAP:[&str; 9] = ["a","b","c","d","e","f","g","h","i"];
+
+/// 拡張コマンドのハンドラ
+///
+/// コマンド名を除いた引数のリスト、現在の局面（まだ局面が送られていない場合は`None`）、出力先が渡される
+pub type ExtensionCommandHandler = Arc<dyn Fn(&[String],Option<&ExtensionPosition>,&dyn USIOutputWriter)
+											-> Result<(),ExtensionCommandError> + Send + Sync + 'static>;
+/// 拡張コマンドに渡される現在の局面
+#[derive(Clone, Debug)]
+pub struct ExtensionPosition {
+	/// 手番
+	pub teban:Teban,
+	/// 盤面の状態
+	pub state:State,
+	/// 持ち駒
+	pub mc:MochigomaCollections,
+	/// 次の指し手の手数
+	pub n:u32,
+}
+impl ExtensionPosition {
+	/// positionコマンドの内容から`ExtensionPosition`を生成
+	///
+	/// # Arguments
+	/// * `teban` - 開始局面の手番
+	/// * `banmen` - 開始局面の盤面
+	/// * `mc` - 開始局面の持ち駒
+	/// * `n` - 開始局面の手数
+	/// * `mvs` - 開始局面からの指し手のリスト
+	/// # Errors
+	///
+	/// この関数は以下のエラーを返すケースがあります。
+	/// * [`InvalidPosition`] 指し手に合法手ではないものが含まれている
+	///
+	/// [`InvalidPosition`]: ../error/enum.ExtensionCommandError.html#variant.InvalidPosition
+	pub fn new(teban:Teban,banmen:Banmen,mc:MochigomaCollections,n:u32,mvs:&[Move])
+		-> Result<ExtensionPosition,ExtensionCommandError> {
+		let mut teban = teban;
+		let mut state = State::new(banmen);
+		let mut mc = mc;
+
+		for m in mvs {
+			let (next,nmc,_) = Rule::apply_valid_move(&state,teban,&mc,m.to_applied_move())?;
+
+			state = next;
+			mc = nmc;
+			teban = teban.opposite();
+		}
+
+		Ok(ExtensionPosition {
+			teban,
+			state,
+			mc,
+			n:n + mvs.len() as u32
+		})
+	}
+
+	/// 局面をsfen文字列に変換
+	pub fn to_sfen(&self) -> Result<String,ExtensionCommandError> {
+		Ok(format!("sfen {} {} {} {}",
+				   self.state.get_banmen().to_sfen()?,
+				   self.teban.to_sfen()?,
+				   self.mc.to_sfen()?,
+				   self.n))
+	}
+}
+/// 拡張コマンドのコマンド名とハンドラの対応を管理する
+#[derive(Clone, Default)]
+pub struct ExtensionCommands {
+	handlers:BTreeMap<String,ExtensionCommandHandler>
+}
+impl ExtensionCommands {
+	/// 空の`ExtensionCommands`の生成
+	pub fn new() -> ExtensionCommands {
+		ExtensionCommands {
+			handlers:BTreeMap::new()
+		}
+	}
+
+	/// 組み込みの拡張コマンド(`d`,`perft`,`moves`)を登録した`ExtensionCommands`の生成
+	pub fn with_builtin() -> ExtensionCommands {
+		let mut commands = ExtensionCommands::new();
+
+		commands.add("d",display);
+		commands.add("perft",perft);
+		commands.add("moves",moves);
+
+		commands
+	}
+
+	/// 拡張コマンドを登録する（同じ名前のコマンドが登録済みの場合は置き換える）
+	///
+	/// USIプロトコルのコマンドと同じ名前のコマンドは登録しても呼び出されない
+	///
+	/// # Arguments
+	/// * `name` - コマンド名
+	/// * `handler` - コマンドを受信した時に呼ばれるハンドラ
+	pub fn add<F>(&mut self,name:&str,handler:F)
+		where F: Fn(&[String],Option<&ExtensionPosition>,&dyn USIOutputWriter)
+						-> Result<(),ExtensionCommandError> + Send + Sync + 'static {
+		self.handlers.insert(name.to_string(),Arc::new(handler));
+	}
+
+	/// 登録されているコマンド名の一覧を取得
+	pub fn names(&self) -> BTreeSet<String> {
+		self.handlers.keys().cloned().collect()
+	}
+
+	/// 拡張コマンドを実行する
+	///
+	/// # Arguments
+	/// * `name` - コマンド名
+	/// * `args` - コマンド名を除いた引数のリスト
+	/// * `position` - 現在の局面
+	/// * `writer` - 出力先
+	pub fn execute(&self,name:&str,args:&[String],
+				   position:Option<&ExtensionPosition>,
+				   writer:&dyn USIOutputWriter) -> Result<(),ExtensionCommandError> {
+		match self.handlers.get(name) {
+			Some(handler) => handler(args,position,writer),
+			None => Err(ExtensionCommandError::UnknownCommand(name.to_string()))
+		}
+	}
+}
+impl fmt::Debug for ExtensionCommands {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_set().entries(self.handlers.keys()).finish()
+	}
+}
+/// 組み込みの`d`コマンド。現在の局面の盤面とsfen文字列を出力する
+///
+/// # Arguments
+/// * `_` - 引数（使用しない）
+/// * `position` - 現在の局面
+/// * `writer` - 出力先
+pub fn display(_:&[String],position:Option<&ExtensionPosition>,writer:&dyn USIOutputWriter)
+	-> Result<(),ExtensionCommandError> {
+	let position = position.ok_or(ExtensionCommandError::PositionNotSet)?;
+
+	let mut lines = Vec::new();
+
+	lines.push((1..10).rev().map(|x| format!("{:>3}",x)).collect::<String>());
+
+	let Banmen(kinds) = position.state.get_banmen();
+
+	for (y,row) in kinds.iter().enumerate() {
+		let mut line = row.iter().map(|&k| format!("{:>3}",KOMA_STR_MAP[k as usize])).collect::<String>();
+
+		line.push_str(&format!("  {}",DAN_STR_MAP[y]));
+
+		lines.push(line);
+	}
+
+	lines.push(format!("teban: {}",match position.teban {
+		Teban::Sente => "sente",
+		Teban::Gote => "gote"
+	}));
+	lines.push(position.to_sfen()?);
+
+	writer.write(&lines)?;
+
+	Ok(())
+}
+/// 組み込みの`perft`コマンド。`perft <depth>`の形式で現在の局面からperftを実行し、
+/// 初手ごとの末端の局面の数と集計結果を出力する
+///
+/// # Arguments
+/// * `args` - 引数（探索する深さ）
+/// * `position` - 現在の局面
+/// * `writer` - 出力先
+pub fn perft(args:&[String],position:Option<&ExtensionPosition>,writer:&dyn USIOutputWriter)
+	-> Result<(),ExtensionCommandError> {
+	let depth = match args {
+		[depth] => depth.parse::<usize>().map_err(|_| ExtensionCommandError::InvalidArgument(args.join(" ")))?,
+		_ => {
+			return Err(ExtensionCommandError::InvalidArgument(args.join(" ")));
+		}
+	};
+
+	let position = position.ok_or(ExtensionCommandError::PositionNotSet)?;
+
+	let divide = Rule::divide(&position.state,position.teban,&position.mc,depth);
+
+	let r = if depth == 0 {
+		Rule::perft(&position.state,position.teban,&position.mc,depth)
+	} else {
+		divide.iter().fold(PerftResult::default(),|acc,&(_,r)| acc + r)
+	};
+
+	let mut nodes = Vec::new();
+
+	for (m,r) in divide {
+		nodes.push((m.to_move().to_sfen()?,r.nodes));
+	}
+
+	nodes.sort();
+
+	let mut lines = nodes.into_iter().map(|(m,n)| format!("{}: {}",m,n)).collect::<Vec<String>>();
+
+	lines.push(format!("nodes: {}, captures: {}, promotions: {}, checks: {}, mates: {}",
+					   r.nodes, r.captures, r.promotions, r.checks, r.mates));
+
+	writer.write(&lines)?;
+
+	Ok(())
+}
+/// 組み込みの`moves`コマンド。現在の局面の合法手をUSI形式で空白区切りで出力する
+///
+/// # Arguments
+/// * `_` - 引数（使用しない）
+/// * `position` - 現在の局面
+/// * `writer` - 出力先
+pub fn moves(_:&[String],position:Option<&ExtensionPosition>,writer:&dyn USIOutputWriter)
+	-> Result<(),ExtensionCommandError> {
+	let position = position.ok_or(ExtensionCommandError::PositionNotSet)?;
+
+	let mut mvs = Vec::new();
+
+	for (m,_) in Rule::divide(&position.state,position.teban,&position.mc,1) {
+		mvs.push(m.to_move().to_sfen()?);
+	}
+
+	mvs.sort();
+
+	writer.write(&vec![mvs.join(" ")])?;
+
+	Ok(())
+}
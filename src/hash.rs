@@ -284,6 +284,7 @@ pub struct KyokumenHash<T>
 		    Standard: Distribution<T> {
 	kyokumen_hash_seeds:[[T; SUJI_MAX * DAN_MAX]; KOMA_KIND_MAX + 1],
 	mochigoma_hash_seeds:[[[T; MOCHIGOMA_KIND_MAX + 1]; MOCHIGOMA_MAX]; 2],
+	teban_hash_seed:T,
 }
 impl<T> KyokumenHash<T>
 	where T: Add + Sub + BitXor<Output = T> + Copy + InitialHash,
@@ -292,8 +293,21 @@ impl<T> KyokumenHash<T>
 	/// `KyokumenHash`の生成
 	pub fn new() -> KyokumenHash<T> {
 		let mut rnd = prelude::thread_rng();
-		let mut rnd = XorShiftRng::from_seed(rnd.gen::<[u8;16]>());
 
+		KyokumenHash::with_rng(XorShiftRng::from_seed(rnd.gen::<[u8;16]>()))
+	}
+
+	/// シードを指定して`KyokumenHash`を生成
+	///
+	/// 同じシードからは常に同じハッシュ値が計算されるため、プロセスをまたいで永続化するテーブルや定跡のキーに利用できる
+	///
+	/// # Arguments
+	/// * `seed` - 乱数のシード
+	pub fn with_seed(seed:u64) -> KyokumenHash<T> {
+		KyokumenHash::with_rng(XorShiftRng::seed_from_u64(seed))
+	}
+
+	fn with_rng(mut rnd:XorShiftRng) -> KyokumenHash<T> {
 		let mut kyokumen_hash_seeds:[[T; SUJI_MAX * DAN_MAX]; KOMA_KIND_MAX + 1] = [[T::INITIAL_HASH; SUJI_MAX * DAN_MAX]; KOMA_KIND_MAX + 1];
 		let mut mochigoma_hash_seeds:[[[T; MOCHIGOMA_KIND_MAX + 1]; MOCHIGOMA_MAX]; 2] = [[[T::INITIAL_HASH; MOCHIGOMA_KIND_MAX + 1]; MOCHIGOMA_MAX]; 2];
 
@@ -310,9 +324,12 @@ impl<T> KyokumenHash<T>
 			}
 		}
 
+		let teban_hash_seed = rnd.gen();
+
 		KyokumenHash {
 			kyokumen_hash_seeds:kyokumen_hash_seeds,
 			mochigoma_hash_seeds:mochigoma_hash_seeds,
+			teban_hash_seed,
 		}
 	}
 
@@ -501,6 +518,30 @@ impl<T> KyokumenHash<T>
 		})
 	}
 
+	/// メインハッシュに手番を反映した値を計算（後手番の場合のみ手番のシードが加わる）
+	///
+	/// # Arguments
+	/// * `h` - 盤面と持ち駒から計算したメインハッシュ
+	/// * `t` - 手番
+	pub fn calc_main_hash_with_teban(&self,h:T,t:Teban) -> T {
+		match t {
+			Teban::Sente => h,
+			Teban::Gote => h ^ self.teban_hash_seed,
+		}
+	}
+
+	/// サブハッシュに手番を反映した値を計算（後手番の場合のみ手番のシードが加わる）
+	///
+	/// # Arguments
+	/// * `h` - 盤面と持ち駒から計算したサブハッシュ
+	/// * `t` - 手番
+	pub fn calc_sub_hash_with_teban(&self,h:T,t:Teban) -> T {
+		match t {
+			Teban::Sente => h,
+			Teban::Gote => (Wrapping(h) + Wrapping(self.teban_hash_seed)).0,
+		}
+	}
+
 	/// ハッシュの初期値を計算
 	///
 	/// # Arguments
@@ -3,10 +3,11 @@ use std::thread;
 use std::sync::Mutex;
 use std::sync::Arc;
 use std::marker::Send;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use mpsc::Sender;
 
 use event::*;
+use command::UsiOptType;
 use protocol::*;
 use output::USIStdErrorWriter;
 use input::USIInputReader;
@@ -15,12 +16,25 @@ use OnErrorHandler;
 
 /// USIプロトコルのコマンド文字列を読み取りイベントを発火する構造体
 pub struct USIInterpreter {
+	extension_commands:BTreeSet<String>,
 }
 impl USIInterpreter {
 	/// `USIInterpreter`の生成
 	pub fn new() -> USIInterpreter {
 		USIInterpreter {
+			extension_commands:BTreeSet::new(),
+		}
+	}
 
+	/// 拡張コマンドの名前を指定して`USIInterpreter`を生成
+	///
+	/// 指定した名前で始まる行は`SystemEvent::Extension`として通知される
+	///
+	/// # Arguments
+	/// * `extension_commands` - 拡張コマンドの名前の集合
+	pub fn with_extension_commands(extension_commands:BTreeSet<String>) -> USIInterpreter {
+		USIInterpreter {
+			extension_commands,
 		}
 	}
 
@@ -31,19 +45,46 @@ impl USIInterpreter {
 	/// * `reader` - 入力を読み取るためのオブジェクト。実装によって標準入力以外から読み取るものを指定することも可能。
 	/// * `optmap` - プレイヤーオブジェクトに渡されるオプションの種類のマップ
 	/// * `logger` - ログを書き込むためのオブジェクト。実装によってファイル以外に書き込むものを指定することも可能。
+	///
+	/// 入力を読み取るスレッドの`JoinHandle`を返す。
+	/// スレッドは`quit`コマンドを受け取るか、入力の終端に達した時に`SystemEvent::Quit`を送って終了する。
+	/// 読み取りに失敗した場合はエラーを通知して読み取りを続ける
 	pub fn start<L,R>(&self,
 		event_sender:Sender<SystemEvent>,
-		mut reader:R,optmap:BTreeMap<String,SysEventOptionKind>, logger:&Arc<Mutex<L>>)
+		reader:R,optmap:BTreeMap<String,SysEventOptionKind>, logger:&Arc<Mutex<L>>) -> thread::JoinHandle<()>
+		where R: USIInputReader + Send + 'static, L: Logger,
+				Arc<Mutex<L>>: Send + 'static {
+		self.start_with_option_types(event_sender,reader,optmap,BTreeMap::new(),logger)
+	}
+
+	/// オプションの定義を指定して処理を開始
+	///
+	/// setoptionコマンドで送られてきた値がspinの範囲外、またはcomboの項目に含まれない場合はエラーをログに出力して無視する
+	///
+	/// # Arguments
+	/// * `event_sender` - システムイベントのSender
+	/// * `reader` - 入力を読み取るためのオブジェクト。実装によって標準入力以外から読み取るものを指定することも可能。
+	/// * `optmap` - プレイヤーオブジェクトに渡されるオプションの種類のマップ
+	/// * `opttypes` - プレイヤーオブジェクトがサポートしているオプションの定義のマップ
+	/// * `logger` - ログを書き込むためのオブジェクト。実装によってファイル以外に書き込むものを指定することも可能。
+	///
+	/// 入力を読み取るスレッドの`JoinHandle`を返す
+	pub fn start_with_option_types<L,R>(&self,
+		event_sender:Sender<SystemEvent>,
+		mut reader:R,optmap:BTreeMap<String,SysEventOptionKind>,
+		opttypes:BTreeMap<String,UsiOptType>, logger:&Arc<Mutex<L>>) -> thread::JoinHandle<()>
 		where R: USIInputReader + Send + 'static, L: Logger,
 				Arc<Mutex<L>>: Send + 'static {
 		let event_sender = event_sender.clone();
 		let logger = logger.clone();
 		let on_error_handler = Arc::new(Mutex::new(OnErrorHandler::new(logger.clone())));
 		let on_error_handler = on_error_handler.clone();
+		let extension_commands = self.extension_commands.clone();
 
 		thread::spawn(move || {
 			let position_parser = PositionParser::new();
 			let go_parser = GoParser::new();
+			let set_option_parser = SetOptionParser::new();
 
 			loop {
 				match reader.read() {
@@ -56,69 +97,14 @@ impl USIInterpreter {
 						let r = match f[0] {
 							"usi" => event_sender.send(SystemEvent::Usi),
 							"isready" => event_sender.send(SystemEvent::IsReady),
-							"setoption" if f.len() == 3 => {
-								match optmap.get(&f[2].to_string()) {
-									None => {
-										let _ = logger.lock().map(|mut logger| logger.logging(&String::from("Could not get option type."))).map_err(|_| {
-											USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
-											false
-										});
-										Ok(())
+							"setoption" => {
+								match set_option_parser.parse(&line["setoption".len()..],&optmap,&opttypes) {
+									Ok((name,value)) => {
+										event_sender.send(SystemEvent::SetOption(name,value))
 									},
-									Some(kind) => {
-										match (f[1],f[2],kind) {
-											("name", id, &SysEventOptionKind::Exist) => {
-												if let Err(ref e) = event_sender.send(SystemEvent::SetOption(id.to_string(),SysEventOption::Exist)) {
-													let _ = on_error_handler.lock().map(|h| h.call(e));
-												}
-											},
-											_ => {
-												let _ = logger.lock().map(|mut logger| logger.logging(&String::from("The format of the option command is illegal."))).map_err(|_| {
-													USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
-													false
-												});
-											}
-										}
-										Ok(())
-									}
-								}
-							},
-							"setoption" if f.len() >= 5 => {
-								match optmap.get(&f[2].to_string()) {
-									None => {
-										let _ = logger.lock().map(|mut logger| logger.logging(&String::from("Could not get option type."))).map_err(|_| {
-											USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
-											false
-										});
+									Err(ref e) => {
+										let _ = on_error_handler.lock().map(|h| h.call(e));
 										Ok(())
-									},
-									Some(kind) => {
-										match (f[1],f[2],f[3],f[4], kind) {
-											("name", id, "value", v, &SysEventOptionKind::Str) => {
-												event_sender.send(SystemEvent::SetOption(id.to_string(),SysEventOption::Str(v.to_string())))
-											},
-											("name", id, "value", v, &SysEventOptionKind::Num) => {
-												let _ = v.parse::<i64>().map(|n|{
-													event_sender.send(SystemEvent::SetOption(id.to_string(),SysEventOption::Num(n)))
-												}).map_err(|ref e| {
-													on_error_handler.lock().map(|h| h.call(e))
-												});
-												Ok(())
-											},
-											("name", id, "value", "true", &SysEventOptionKind::Bool) => {
-												event_sender.send(SystemEvent::SetOption(id.to_string(),SysEventOption::Bool(true)))
-											},
-											("name", id, "value", "false", &SysEventOptionKind::Bool) => {
-												event_sender.send(SystemEvent::SetOption(id.to_string(),SysEventOption::Bool(false)))
-											},
-											_ => {
-												let _ = logger.lock().map(|mut logger| logger.logging(&String::from("The format of the option command is illegal."))).map_err(|_| {
-													USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
-													false
-												});
-												Ok(())
-											}
-										}
 									}
 								}
 							},
@@ -163,7 +149,7 @@ impl USIInterpreter {
 									"lose" =>event_sender.send(SystemEvent::GameOver(GameEndState::Lose)),
 									"draw" => event_sender.send(SystemEvent::GameOver(GameEndState::Draw)),
 									_ => {
-										let _ = logger.lock().map(|mut logger| logger.logging(&String::from("The format of the gameover command is illegal."))).map_err(|_| {
+										let _ = logger.lock().map(|mut logger| logger.warn(module_path!(),"The format of the gameover command is illegal.")).map_err(|_| {
 											USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 											false
 										});
@@ -171,8 +157,15 @@ impl USIInterpreter {
 									}
 								}
 							},
+							name if extension_commands.contains(name) => {
+								let args = f[1..].iter()
+												 .filter(|s| !s.is_empty())
+												 .map(|s| s.to_string())
+												 .collect::<Vec<String>>();
+								event_sender.send(SystemEvent::Extension(name.to_string(),args))
+							},
 							_ => {
-								let _ = logger.lock().map(|mut logger| logger.logging(&format!("The format of the command is illegal. (input: {})",line))).map_err(|_| {
+								let _ = logger.lock().map(|mut logger| logger.warn(module_path!(),&format!("The format of the command is illegal. (input: {})",line))).map_err(|_| {
 									USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 									false
 								});
@@ -188,9 +181,10 @@ impl USIInterpreter {
 						if let Err(ref e) = event_sender.send(SystemEvent::Quit) {
 							let _ = on_error_handler.lock().map(|h| h.call(e));
 						}
+						break;
 					}
 				}
 			}
-		});
+		})
 	}
 }
\ No newline at end of file
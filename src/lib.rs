@@ -7,6 +7,8 @@ extern crate rand_xorshift;
 #[macro_use]
 extern crate crossbeam_channel;
 extern crate queuingtask;
+#[cfg(feature = "log-bridge")]
+extern crate log;
 
 pub mod event;
 pub mod error;
@@ -26,16 +28,29 @@ pub mod movepick;
 pub mod math;
 pub mod bits;
 pub mod bitboard;
-
+pub mod extension;
+pub mod bench;
+pub mod transcript;
+pub mod net;
+pub mod options;
+pub mod config;
+#[cfg(feature = "log-bridge")]
+pub mod logbridge;
+
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
 use std::convert::TryFrom;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::sync::{mpsc, Mutex};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::marker::Send;
+use std::thread;
 use std::marker::PhantomData;
 use std::sync::mpsc::{Receiver, Sender};
 
@@ -52,6 +67,12 @@ use player::*;
 use shogi::*;
 use protocol::*;
 use rule::*;
+use extension::*;
+use bench::*;
+use transcript::*;
+use net::*;
+use options::*;
+use config::*;
 
 /// enumの各項目にインデックスが対応する型の最大のインデックスを取得する
 pub trait MaxIndex {
@@ -81,7 +102,7 @@ impl<L> OnErrorHandler<L> where L: Logger {
 	/// # Arguments
 	/// * `e` - エラーオブジェクト
 	pub fn call<E>(&self,e:&E) -> bool where E: Error {
-		self.logger.lock().map(|mut logger| logger.logging_error(e)).map_err(|_| {
+		self.logger.lock().map(|mut logger| logger.log_error(LogLevel::Error,module_path!(),e)).map_err(|_| {
 			USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 				false
 		}).is_err()
@@ -109,6 +130,29 @@ impl SandBox {
 		r
 	}
 }
+/// positionコマンドの内容（手番、盤面、持ち駒、手数、指し手のリスト）
+type PositionCommand = (Teban,Banmen,MochigomaCollections,u32,Vec<Move>);
+/// 出力の度に排他ロックを取得して書き込む`USIOutputWriter`
+///
+/// 別スレッドで実行される拡張コマンドが出力先のロックを持ち続けないようにするために使う
+struct SharedOutputWriter<W> where W: USIOutputWriter {
+	writer:Arc<Mutex<W>>,
+}
+impl<W> SharedOutputWriter<W> where W: USIOutputWriter {
+	fn new(writer:Arc<Mutex<W>>) -> SharedOutputWriter<W> {
+		SharedOutputWriter {
+			writer
+		}
+	}
+}
+impl<W> USIOutputWriter for SharedOutputWriter<W> where W: USIOutputWriter {
+	fn write(&self,lines:&Vec<String>) -> io::Result<usize> {
+		match self.writer.lock() {
+			Ok(writer) => writer.write(lines),
+			Err(_) => Err(io::Error::other("Could not get exclusive lock on output writer object."))
+		}
+	}
+}
 /// go ponderによって生成された手を相手の指し手が決まるまで覚えておくための構造体
 pub enum OnAcceptMove  {
 	/// 指し手を格納済み
@@ -160,6 +204,12 @@ pub struct UsiAgent<T,E>
 	player:Arc<Mutex<T>>,
 	system_event_sender:Sender<SystemEvent>,
 	system_event_queue:Arc<Receiver<SystemEvent>>,
+	invalid_position_policy:InvalidPositionPolicy,
+	extension_commands:ExtensionCommands,
+	log_level:Option<LogLevel>,
+	default_options:Vec<(String,String)>,
+	info_interval:Duration,
+	bench_stop:Arc<AtomicBool>,
 }
 impl<T,E> UsiAgent<T,E>
 	where T: USIPlayer<E> + fmt::Debug + Send + 'static,
@@ -179,7 +229,120 @@ impl<T,E> UsiAgent<T,E>
 			player:Arc::new(Mutex::new(player)),
 			system_event_sender:s,
 			system_event_queue:Arc::new(r),
+			invalid_position_policy:InvalidPositionPolicy::Ignore,
+			extension_commands:ExtensionCommands::with_builtin(),
+			log_level:None,
+			default_options:Vec::new(),
+			info_interval:Duration::from_millis(0),
+			bench_stop:Arc::new(AtomicBool::new(false)),
+		}
+	}
+
+	/// `position`コマンドで不正な局面が送られてきた時の扱いを設定する（デフォルトは検証しない）
+	///
+	/// 検証は初期局面と、`moves`以降の指し手を適用した後の局面に対して行われる。
+	/// `InvalidPositionPolicy::Reject`を指定した場合、不正な局面はプレイヤーに渡されず、
+	/// 理由を`info string`で送信した上で保持している局面を破棄する。
+	/// 次に正しい局面が送られてくるまでの`go`には`bestmove resign`を返す
+	///
+	/// # Arguments
+	/// * `policy` - 不正な局面の扱い
+	pub fn set_invalid_position_policy(&mut self,policy:InvalidPositionPolicy) {
+		self.invalid_position_policy = policy;
+	}
+
+	/// infoコマンドを送信する最小の間隔を設定する（デフォルトは0で、送られたinfoコマンドをそのまま送信する）
+	///
+	/// プレイヤーの`think`などに渡される`InfoSender`は`ThrottledInfoSender`でラップされ、
+	/// 間隔内に送られたinfoコマンドはmultipvの番号ごとにまとめられる。
+	/// 保持されているinfoコマンドは`think`などから戻った後、`bestmove`や`checkmate`を送信する前に全て送信される
+	///
+	/// # Arguments
+	/// * `interval` - 送信の最小間隔
+	pub fn set_info_interval(&mut self,interval:Duration) {
+		self.info_interval = interval;
+	}
+
+	/// ログのレベルを変更する`LogLevel`オプションを有効にする
+	///
+	/// 有効にした場合、`usi`コマンドへの応答に`LogLevel`のcomboオプションが追加され、
+	/// `setoption name LogLevel value debug`のように送られてきた値で`Logger::set_level`が呼ばれる。
+	/// プレイヤーが同じ名前のオプションを定義している場合は値がプレイヤーにも渡される
+	///
+	/// # Arguments
+	/// * `default` - 開始時に設定するログのレベル
+	pub fn enable_log_level_option(&mut self,default:LogLevel) {
+		self.log_level = Some(default);
+	}
+
+	/// オプションのデフォルト値を設定する
+	///
+	/// 設定した値は開始時（`isready`より前）にプレイヤーに渡され、`usi`コマンドへの応答のデフォルト値にも反映される。
+	/// GUIから`setoption`で送られてきた値はこの値よりも優先される。
+	/// buttonのオプションの場合は`value`に空文字列を指定する
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `value` - 値の文字列
+	pub fn set_default_option(&mut self,name:&str,value:&str) {
+		self.default_options.retain(|(n,_)| n != name);
+		self.default_options.push((name.to_string(),value.to_string()));
+	}
+
+	/// 設定ファイルからオプションのデフォルト値を読み込む
+	///
+	/// 最初のセクションより前と`[options]`セクションに書かれた`オプション名 = 値`の行が対象となる。
+	/// 値の検証はプレイヤーのオプションの定義に基づいて開始時に行われ、不正な値はログに出力されて無視される
+	///
+	/// # Arguments
+	/// * `reader` - 読み込み元
+	pub fn read_default_options<R>(&mut self,reader:R) -> Result<(),ConfigFileError> where R: BufRead {
+		let config = ConfigFile::read(reader)?;
+
+		for entry in config.entries("").into_iter().chain(config.entries("options")) {
+			self.set_default_option(&entry.key,&entry.value);
+		}
+
+		Ok(())
+	}
+
+	/// ファイルからオプションのデフォルト値を読み込む
+	///
+	/// # Arguments
+	/// * `file` - 読み込むファイルのパス
+	pub fn load_default_options(&mut self,file:&str) -> Result<(),ConfigFileError> {
+		self.read_default_options(BufReader::new(File::open(file)?))
+	}
+
+	/// 実行ファイルと同じディレクトリにあるファイルからオプションのデフォルト値を読み込む
+	///
+	/// ファイルが存在しない場合は何もせずに`Ok(false)`を返す
+	///
+	/// # Arguments
+	/// * `file_name` - ファイル名
+	pub fn load_default_options_beside_executable(&mut self,file_name:&str) -> Result<bool,ConfigFileError> {
+		let path = path_beside_executable(file_name)?;
+
+		if !Path::new(&path).exists() {
+			return Ok(false);
 		}
+
+		self.load_default_options(&path.to_string_lossy()).map(|_| true)
+	}
+
+	/// USIプロトコルに含まれない拡張コマンドを登録する（`d`,`perft`,`moves`は組み込みで登録済み）
+	///
+	/// ハンドラにはコマンド名を除いた引数のリスト、最後に`position`コマンドで送られてきた局面、出力先が渡される。
+	/// ハンドラはコマンドを受信した順に、システムイベントを処理するスレッドとは別のスレッドで一つずつ実行される。
+	/// ハンドラがエラーを返した場合はログに出力される
+	///
+	/// # Arguments
+	/// * `name` - コマンド名
+	/// * `handler` - コマンドを受信した時に呼ばれるハンドラ
+	pub fn add_extension_command<F>(&mut self,name:&str,handler:F)
+		where F: Fn(&[String],Option<&ExtensionPosition>,&dyn USIOutputWriter)
+						-> Result<(),ExtensionCommandError> + Send + Sync + 'static {
+		self.extension_commands.add(name,handler);
 	}
 
 	/// デフォルト設定で開始（ログファイルのパスlogs/log.txt,ログをファイルに記録）
@@ -221,6 +384,49 @@ impl<T,E> UsiAgent<T,E>
 		self.start(input_reader,output_writer,logger,on_error)
 	}
 
+	/// ログファイルのパスと通信の記録先のパスを指定して開始
+	///
+	/// 標準入力から読み取った行と標準出力へ書き込んだ行が経過時間とともに記録される
+	///
+	/// # Arguments
+	/// * `path` - ログファイルのパス
+	/// * `transcript_path` - 通信の記録先のファイルのパス
+	/// * `on_error` - エラー発生時に呼ばれるコールバック関数。エラーオブジェクトへの参照とロガーが渡される。
+	pub fn start_with_transcript_path<F>(&self,path:String,transcript_path:String,mut on_error:F) ->
+		Result<(),USIAgentRunningError<'_,SystemEventQueue,E>>
+		where F: FnMut(Option<Arc<Mutex<OnErrorHandler<FileLogger>>>>,
+					&USIAgentRunningError<SystemEventQueue,E>) {
+
+		let logger = match FileLogger::new(path) {
+			Err(_) => {
+				let e = USIAgentStartupError::IOError(String::from(
+					"The log output destination file could not be opened."
+				));
+				let e = USIAgentRunningError::from(e);
+				on_error(None,&e);
+				return Err(e);
+			},
+			Ok(logger) => logger,
+		};
+
+		let recorder = match TranscriptRecorder::create(transcript_path) {
+			Err(_) => {
+				let e = USIAgentStartupError::IOError(String::from(
+					"The transcript output destination file could not be opened."
+				));
+				let e = USIAgentRunningError::from(e);
+				on_error(None,&e);
+				return Err(e);
+			},
+			Ok(recorder) => recorder,
+		};
+
+		let input_reader = TranscriptInputReader::new(USIStdInputReader::new(),recorder.clone());
+		let output_writer = TranscriptOutputWriter::new(USIStdOutputWriter::new(),recorder);
+
+		self.start(input_reader,output_writer,logger,on_error)
+	}
+
 	/// `Logger`,`USIInputReader`,`USIOutputWriter`を指定して開始
 	///
 	/// # Arguments
@@ -243,7 +449,7 @@ impl<T,E> UsiAgent<T,E>
 		let on_error_handler_arc = Arc::new(Mutex::new(OnErrorHandler::new(logger_arc.clone())));
 		let on_error_handler = on_error_handler_arc.clone();
 
-		let r = self.run(reader,writer,logger_arc,on_error_handler_arc);
+		let r = self.run(reader,writer,logger_arc,on_error_handler_arc).map(|_| ());
 
 		if let Err(ref e) = r {
 			on_error(Some(on_error_handler),e);
@@ -252,9 +458,104 @@ impl<T,E> UsiAgent<T,E>
 		r
 	}
 
+	/// 接続を待ち受け、接続ごとに一つのUSIのセッションを実行するサーバーとして開始
+	///
+	/// セッションは`quit`コマンドの受信または切断で終了し、接続を閉じた後に次の接続を待ち受ける。
+	/// 二つ目以降のセッションの開始前にはプレイヤーオブジェクトが`create_player`で生成したものに置き換えられる。
+	/// セッション中に発生したエラーは`on_error`に渡され、次の接続の待ち受けが続けられる
+	///
+	/// # Arguments
+	/// * `listener` - 接続を待ち受けるオブジェクト（`TcpListener`,`UnixListener`など）
+	/// * `max_sessions` - 実行するセッションの最大数（`None`の場合は無制限）
+	/// * `create_player` - セッションごとに新しいプレイヤーオブジェクトを生成する関数
+	/// * `logger` - ログを書き込むためのオブジェクト。全てのセッションで共有される。
+	/// * `on_error` - エラー発生時に呼ばれるコールバック関数。エラーオブジェクトへの参照とロガーが渡される。
+	pub fn serve<S,L,P,F>(&self,listener:&S,max_sessions:Option<usize>,mut create_player:P,logger:L,mut on_error:F) ->
+		Result<(),USIAgentRunningError<'_,SystemEventQueue,E>>
+		where S: USIListener, L: Logger + fmt::Debug, P: FnMut() -> T,
+			F: FnMut(Option<Arc<Mutex<OnErrorHandler<L>>>>,
+					&USIAgentRunningError<SystemEventQueue,E>),
+			L: Send + 'static,
+			OnAcceptMove: Send + 'static {
+
+		let logger_arc = Arc::new(Mutex::new(logger));
+		let on_error_handler_arc = Arc::new(Mutex::new(OnErrorHandler::new(logger_arc.clone())));
+
+		let mut sessions = 0;
+
+		while max_sessions.map(|m| sessions < m).unwrap_or(true) {
+			let connection = match listener.accept_connection() {
+				Ok(connection) => connection,
+				Err(e) => {
+					let e = USIAgentRunningError::from(USIAgentStartupError::IOError(
+						format!("The connection could not be accepted. ({})",e)
+					));
+					on_error(Some(on_error_handler_arc.clone()),&e);
+					return Err(e);
+				}
+			};
+
+			let (reader,writer) = match connection.try_clone_connection().and_then(|r| {
+				connection.try_clone_connection().map(|w| (r,w))
+			}) {
+				Ok((reader,writer)) => (reader,writer),
+				Err(e) => {
+					let e = USIAgentRunningError::from(USIAgentStartupError::IOError(
+						format!("The connection could not be duplicated. ({})",e)
+					));
+					on_error(Some(on_error_handler_arc.clone()),&e);
+					let _ = connection.shutdown_connection();
+					continue;
+				}
+			};
+
+			while self.system_event_queue.try_recv().is_ok() {}
+
+			if sessions > 0 {
+				match self.player.lock() {
+					Ok(mut player) => {
+						*player = create_player();
+					},
+					Err(_) => {
+						let e = USIAgentRunningError::from(USIAgentStartupError::MutexLockFailedOtherError(
+							String::from("Failed to acquire exclusive lock of player object.")
+						));
+						on_error(Some(on_error_handler_arc.clone()),&e);
+						let _ = connection.shutdown_connection();
+						return Err(e);
+					}
+				}
+			}
+
+			let r = self.run(USIStreamInputReader::new(reader),
+							 USIStreamOutputWriter::new(writer),
+							 logger_arc.clone(),on_error_handler_arc.clone());
+
+			let _ = connection.shutdown_connection();
+
+			match r {
+				Ok(Some(interpreter_thread)) => {
+					// 接続を閉じたことで入力の読み取りが終了した後に送られるQuitが
+					// 次のセッションに届かないよう、スレッドの終了を待ってからイベントを破棄する
+					let _ = interpreter_thread.join();
+
+					while self.system_event_queue.try_recv().is_ok() {}
+				},
+				Ok(None) => (),
+				Err(ref e) => {
+					on_error(Some(on_error_handler_arc.clone()),e);
+				}
+			}
+
+			sessions += 1;
+		}
+
+		Ok(())
+	}
+
 	fn run<R,W,L>(&self,reader:R,writer:W,logger_arc:Arc<Mutex<L>>,
 								on_error_handler_arc:Arc<Mutex<OnErrorHandler<L>>>) ->
-		Result<(),USIAgentRunningError<SystemEventQueue,E>>
+		Result<Option<thread::JoinHandle<()>>,USIAgentRunningError<SystemEventQueue,E>>
 		where R: USIInputReader, W: USIOutputWriter, L: Logger + fmt::Debug,
 			EventHandlerError<SystemEventKind, E>: From<E>,
 			R: Send + 'static,
@@ -263,6 +564,60 @@ impl<T,E> UsiAgent<T,E>
 			OnAcceptMove: Send + 'static {
 		let writer_arc = Arc::new(Mutex::new(writer));
 
+		let mut log_level = self.log_level;
+		let mut default_values:BTreeMap<String,SysEventOption> = BTreeMap::new();
+
+		if !self.default_options.is_empty() {
+			match self.player.lock() {
+				Ok(mut player) => {
+					let kinds = player.get_option_kinds().map_err(USIAgentStartupError::PlayerError)?;
+					let types = player.get_options().map_err(USIAgentStartupError::PlayerError)?;
+
+					for (name,value) in &self.default_options {
+						if log_level.is_some() && name == LogLevel::OPTION_NAME && !kinds.contains_key(name) {
+							match LogLevel::try_from(value.as_str()) {
+								Ok(level) => log_level = Some(level),
+								Err(ref e) => {
+									let _ = on_error_handler_arc.lock().map(|h| h.call(e));
+								}
+							}
+							continue;
+						}
+
+						match parse_option_value(name,value,&kinds,&types) {
+							Ok(v) => {
+								match player.set_option(name.clone(),v.clone()) {
+									Ok(()) => {
+										default_values.insert(name.clone(),v);
+									},
+									Err(ref e) => {
+										let _ = on_error_handler_arc.lock().map(|h| h.call(e));
+									}
+								}
+							},
+							Err(ref e) => {
+								let _ = on_error_handler_arc.lock().map(|h| h.call(e));
+							}
+						}
+					}
+				},
+				Err(_) => {
+					return Err(USIAgentRunningError::from(USIAgentStartupError::MutexLockFailedOtherError(
+						String::from("Could not get exclusive lock on player object")
+					)));
+				}
+			}
+		}
+
+		if let Some(level) = log_level {
+			match logger_arc.lock() {
+				Ok(mut logger) => logger.set_level(level),
+				Err(ref e) => {
+					let _ = on_error_handler_arc.lock().map(|h| h.call(e));
+				}
+			}
+		}
+
 		let system_event_queue_arc = Arc::clone(&self.system_event_queue);
 
 		let mut system_event_dispatcher:SystemEventDispatcher<UsiAgent<T,E>,E,L> = USIEventDispatcher::new(&on_error_handler_arc);
@@ -273,6 +628,9 @@ impl<T,E> UsiAgent<T,E>
 
 		let quit_ready_arc = Arc::new(AtomicBool::new(false));
 		let think_start_time_arc = Arc::new(Mutex::new(None));
+		// 拡張コマンドに渡す局面は、コマンドを受信した時に最後のpositionコマンドの内容から生成する
+		let current_position_arc:Arc<Mutex<Option<PositionCommand>>> = Arc::new(Mutex::new(None));
+		let position_rejected_arc = Arc::new(AtomicBool::new(false));
 
 		let writer = writer_arc.clone();
 
@@ -305,11 +663,23 @@ impl<T,E> UsiAgent<T,E>
 					match ctx.player.lock() {
 						Ok(mut player) => {
 							commands.push(UsiCommand::UsiId(T::ID.to_string(),T::AUTHOR.to_string()));
-							for cmd in player.get_options()?.iter()
-															.map(|(k,v)| UsiCommand::UsiOption(k.clone(),v.clone()))
+							let options = player.get_options()?;
+
+							for cmd in options.iter()
+															.map(|(k,v)| match default_values.get(k) {
+																Some(d) => UsiCommand::UsiOption(k.clone(),with_default_value(v,d)),
+																None => UsiCommand::UsiOption(k.clone(),v.clone())
+															})
 															.collect::<Vec<UsiCommand>>().into_iter() {
 								commands.push(cmd);
 							}
+
+							if let (Some(_),Some(level)) = (ctx.log_level,log_level) {
+								if !options.contains_key(LogLevel::OPTION_NAME) {
+									commands.push(UsiCommand::UsiOption(String::from(LogLevel::OPTION_NAME),
+																		LogLevel::usi_option(level)));
+								}
+							}
 						},
 						Err(_) => {
 							return Err(EventHandlerError::Fail(String::from(
@@ -394,12 +764,40 @@ impl<T,E> UsiAgent<T,E>
 			}
 		});
 
+		let logger = logger_arc.clone();
+
 		system_event_dispatcher.add_handler(SystemEventKind::SetOption, move |ctx,e| {
 			match e {
 				&SystemEvent::SetOption(ref name, ref value) => {
+					let is_log_level = ctx.log_level.is_some() && name == LogLevel::OPTION_NAME;
+
+					if is_log_level {
+						let level = match *value {
+							SysEventOption::Str(ref v) => {
+								LogLevel::try_from(v.as_str()).map_err(|e| EventHandlerError::Fail(format!("{}",e)))?
+							},
+							_ => {
+								return Err(EventHandlerError::Fail(String::from(
+									"The value of the LogLevel option is invalid."
+								)));
+							}
+						};
+
+						match logger.lock() {
+							Ok(mut logger) => logger.set_level(level),
+							Err(_) => {
+								return Err(EventHandlerError::Fail(String::from(
+									"Could not get exclusive lock on logger object"
+								)));
+							}
+						};
+					}
+
 					match ctx.player.lock() {
 						Ok(mut player) => {
-							player.set_option(name.clone(), value.clone())?;
+							if !is_log_level || player.get_option_kinds()?.contains_key(name) {
+								player.set_option(name.clone(), value.clone())?;
+							}
 						},
 						Err(_) => {
 							return Err(EventHandlerError::Fail(String::from(
@@ -446,6 +844,8 @@ impl<T,E> UsiAgent<T,E>
 
 		let on_error_handler = on_error_handler_arc.clone();
 		let thread_queue = thread_queue_arc.clone();
+		let current_position = current_position_arc.clone();
+		let position_rejected = position_rejected_arc.clone();
 
 		system_event_dispatcher.add_handler(SystemEventKind::Position, move |ctx,e| {
 			match e {
@@ -462,6 +862,87 @@ impl<T,E> UsiAgent<T,E>
 						}
 					};
 
+					if ctx.invalid_position_policy != InvalidPositionPolicy::Ignore {
+						let mut reasons = Vec::new();
+
+						if let Err(errors) = Rule::validate_position(&b,*t,&m) {
+							for e in &errors {
+								let _ = on_error_handler.lock().map(|h| h.call(e));
+								reasons.push(e.to_string());
+							}
+						}
+
+						if reasons.is_empty() {
+							let mut state = State::new(b.clone());
+							let mut teban = *t;
+							let mut mc = m.clone();
+
+							for mv in v {
+								match Rule::apply_valid_move(&state,teban,&mc,mv.to_applied_move()) {
+									Ok((next,nmc,_)) => {
+										state = next;
+										mc = nmc;
+										teban = teban.opposite();
+									},
+									Err(ref e) => {
+										let _ = on_error_handler.lock().map(|h| h.call(e));
+										reasons.push(format!("{} ({:?})",e,mv));
+										break;
+									}
+								}
+							}
+
+							if reasons.is_empty() {
+								if let Err(errors) = Rule::validate_position(state.get_banmen(),teban,&mc) {
+									for e in &errors {
+										let _ = on_error_handler.lock().map(|h| h.call(e));
+										reasons.push(e.to_string());
+									}
+								}
+							}
+						}
+
+						if !reasons.is_empty() && ctx.invalid_position_policy == InvalidPositionPolicy::Reject {
+							position_rejected.store(true,Ordering::Release);
+
+							match current_position.lock() {
+								Ok(mut current_position) => {
+									*current_position = None;
+								},
+								Err(_) => {
+									return Err(EventHandlerError::Fail(String::from(
+										"Could not get exclusive lock on current position object"
+									)));
+								}
+							};
+
+							for reason in reasons {
+								let cmd = UsiOutput::try_from(&UsiCommand::UsiInfo(vec![
+									UsiInfoSubCommand::Str(format!("position rejected: {}",reason))
+								]))?;
+
+								if let Err(ref e) = ctx.system_event_sender.send(SystemEvent::SendUsiCommand(cmd)) {
+									let _ = on_error_handler.lock().map(|h| h.call(e));
+								}
+							}
+
+							return Ok(());
+						}
+					}
+
+					position_rejected.store(false,Ordering::Release);
+
+					match current_position.lock() {
+						Ok(mut current_position) => {
+							*current_position = Some((*t,b.clone(),m.clone(),*n,v.clone()));
+						},
+						Err(_) => {
+							return Err(EventHandlerError::Fail(String::from(
+								"Could not get exclusive lock on current position object"
+							)));
+						}
+					};
+
 					let (ms,mg) = match m {
 						MochigomaCollections::Pair(ms, mg) => (ms, mg),
 						_ => (Mochigoma::new(),Mochigoma::new())
@@ -530,7 +1011,26 @@ impl<T,E> UsiAgent<T,E>
 
 		let in_ponder = in_ponder_arc.clone();
 
+		let position_rejected = position_rejected_arc.clone();
+
 		system_event_dispatcher.add_handler(SystemEventKind::Go, move |ctx,e| {
+			if position_rejected.load(Ordering::Acquire) {
+				let commands = vec![
+					UsiCommand::UsiInfo(vec![
+						UsiInfoSubCommand::Str(String::from("go refused: the last position was rejected."))
+					]),
+					UsiCommand::UsiBestMove(BestMove::Resign)
+				];
+
+				for cmd in &commands {
+					if let Err(ref e) = ctx.system_event_sender.send(SystemEvent::SendUsiCommand(UsiOutput::try_from(cmd)?)) {
+						let _ = on_error_handler.lock().map(|h| h.call(e));
+					}
+				}
+
+				return Ok(());
+			}
+
 			busy.store(true,Ordering::Release);
 
 			match user_event_queue.lock() {
@@ -563,6 +1063,8 @@ impl<T,E> UsiAgent<T,E>
 
 			in_ponder.store(is_ponder,Ordering::Release);
 
+			let info_interval = ctx.info_interval;
+
 			match *e {
 				SystemEvent::Go(UsiGo::Ponder(ref opt)) |
 					SystemEvent::Go(UsiGo::Go(ref opt @ UsiGoTimeLimit::Infinite)) => {
@@ -589,8 +1091,9 @@ impl<T,E> UsiAgent<T,E>
 
 							let info_sender = {
 								let info_send_worker = info_send_worker.clone();
-								USIInfoSender::new(info_send_worker)
+								ThrottledInfoSender::new(USIInfoSender::new(info_send_worker),info_interval)
 							};
+							let mut info_flusher = info_sender.clone();
 
 							let pinfo_sender = USIPeriodicallyInfo::new(
 																		writer.clone(),
@@ -634,6 +1137,16 @@ impl<T,E> UsiAgent<T,E>
 											_ => (),
 										}
 
+										// 保持しているinfoコマンドがbestmoveより後に出力されないよう、
+										// 全て書き込んで送信スレッドが終了するのを待ってから指し手を送る
+										if let Err(ref e) = info_flusher.flush() {
+											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
+										}
+
+										if let Err(ref e) = info_send_worker.quit(on_info_send_worker_quit_receiver) {
+											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
+										}
+
 										match UsiOutput::try_from(&UsiCommand::UsiBestMove(bm)) {
 											Ok(cmd) => {
 												if allow_immediate_move_inner.load(Ordering::Acquire) {
@@ -655,11 +1168,6 @@ impl<T,E> UsiAgent<T,E>
 												let _ = on_error_handler_inner.lock().map(|h| h.call(e));
 											}
 										}
-
-										if let Err(ref e) = info_send_worker.quit(on_info_send_worker_quit_receiver) {
-											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
-											return;
-										}
 									},
 									Err(ref e) => {
 										let _ = on_error_handler_inner.lock().map(|h| h.call(e));
@@ -697,8 +1205,9 @@ impl<T,E> UsiAgent<T,E>
 
 							let info_sender = {
 								let info_send_worker = info_send_worker.clone();
-								USIInfoSender::new(info_send_worker)
+								ThrottledInfoSender::new(USIInfoSender::new(info_send_worker),info_interval)
 							};
+							let mut info_flusher = info_sender.clone();
 
 							let pinfo_sender = USIPeriodicallyInfo::new(
 																		writer.clone(),
@@ -729,6 +1238,16 @@ impl<T,E> UsiAgent<T,E>
 											_ => (),
 										}
 
+										// 保持しているinfoコマンドがbestmoveより後に出力されないよう、
+										// 全て書き込んで送信スレッドが終了するのを待ってから指し手を送る
+										if let Err(ref e) = info_flusher.flush() {
+											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
+										}
+
+										if let Err(ref e) = info_send_worker.quit(on_info_send_worker_quit_receiver) {
+											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
+										}
+
 										match UsiOutput::try_from(&UsiCommand::UsiBestMove(m)) {
 											Ok(cmd) => {
 												if let Err(ref e) = system_event_sender.send(SystemEvent::SendUsiCommand(cmd)) {
@@ -739,11 +1258,6 @@ impl<T,E> UsiAgent<T,E>
 												let _ = on_error_handler_inner.lock().map(|h| h.call(e));
 											}
 										}
-
-										if let Err(ref e) = info_send_worker.quit(on_info_send_worker_quit_receiver) {
-											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
-											return;
-										}
 									},
 									Err(ref e) => {
 										let _ = on_error_handler_inner.lock().map(|h| h.call(e));
@@ -781,8 +1295,9 @@ impl<T,E> UsiAgent<T,E>
 
 							let info_sender = {
 								let info_send_worker = info_send_worker.clone();
-								USIInfoSender::new(info_send_worker)
+								ThrottledInfoSender::new(USIInfoSender::new(info_send_worker),info_interval)
 							};
+							let mut info_flusher = info_sender.clone();
 
 							let pinfo_sender = USIPeriodicallyInfo::new(writer.clone(),false);
 
@@ -810,6 +1325,16 @@ impl<T,E> UsiAgent<T,E>
 											_ => (),
 										}
 
+										// 保持しているinfoコマンドがbestmoveより後に出力されないよう、
+										// 全て書き込んで送信スレッドが終了するのを待ってから指し手を送る
+										if let Err(ref e) = info_flusher.flush() {
+											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
+										}
+
+										if let Err(ref e) = info_send_worker.quit(on_info_send_worker_quit_receiver) {
+											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
+										}
+
 										match UsiOutput::try_from(&UsiCommand::UsiCheckMate(m)) {
 											Ok(cmd) => {
 												if let Err(ref e) = system_event_sender.send(SystemEvent::SendUsiCommand(cmd)) {
@@ -820,11 +1345,6 @@ impl<T,E> UsiAgent<T,E>
 												let _ = on_error_handler_inner.lock().map(|h| h.call(e));
 											}
 										}
-
-										if let Err(ref e) = info_send_worker.quit(on_info_send_worker_quit_receiver) {
-											let _ = on_error_handler_inner.lock().map(|h| h.call(e));
-											return;
-										}
 									},
 									Err(ref e) => {
 										let _ = on_error_handler_inner.lock().map(|h| h.call(e));
@@ -858,6 +1378,8 @@ impl<T,E> UsiAgent<T,E>
 		system_event_dispatcher.add_handler(SystemEventKind::Stop, move |ctx,e| {
 			match e {
 				&SystemEvent::Stop => {
+					ctx.bench_stop.store(true,Ordering::Release);
+
 					if in_ponder.load(Ordering::Acquire) {
 						match think_start_time.lock() {
 							Ok(mut think_start_time) => {
@@ -960,6 +1482,8 @@ impl<T,E> UsiAgent<T,E>
 		system_event_dispatcher.add_handler(SystemEventKind::Quit, move |ctx,e| {
 			match e {
 				&SystemEvent::Quit => {
+					ctx.bench_stop.store(true,Ordering::Release);
+
 					let system_event_sender = ctx.system_event_sender.clone();
 					let on_error_handler_inner = on_error_handler.clone();
 					let player = ctx.player.clone();
@@ -1073,6 +1597,58 @@ impl<T,E> UsiAgent<T,E>
 			}
 		});
 
+		let on_error_handler = on_error_handler_arc.clone();
+		let writer = writer_arc.clone();
+		let current_position = current_position_arc.clone();
+		let extension_commands = Arc::new(self.extension_commands.clone());
+		let mut extension_thread_queue = ThreadQueue::new();
+
+		system_event_dispatcher.add_handler(SystemEventKind::Extension, move |_,e| {
+			match *e {
+				SystemEvent::Extension(ref name, ref args) => {
+					let position = match current_position.lock() {
+						Ok(current_position) => current_position.clone(),
+						Err(_) => {
+							return Err(EventHandlerError::Fail(String::from(
+								"Could not get exclusive lock on current position object"
+							)));
+						}
+					};
+
+					let on_error_handler = on_error_handler.clone();
+					let writer = SharedOutputWriter::new(writer.clone());
+					let extension_commands = extension_commands.clone();
+					let name = name.clone();
+					let args = args.clone();
+
+					// perftやbenchのように時間のかかるコマンドがstopやquit、isreadyの処理を妨げないよう、
+					// 局面の生成も含めてシステムイベントの処理とは別のスレッドで実行する
+					extension_thread_queue.submit(move || {
+						let position = match position {
+							Some((t,b,m,n,v)) => {
+								match ExtensionPosition::new(t,b,m,n,&v) {
+									Ok(position) => Some(position),
+									Err(ref e) => {
+										let _ = on_error_handler.lock().map(|h| h.call(e));
+										return;
+									}
+								}
+							},
+							None => None
+						};
+
+						if let Err(ref e) = extension_commands.execute(&name,&args,position.as_ref(),&writer) {
+							let _ = on_error_handler.lock().map(|h| h.call(e));
+						}
+					}).map(|_| ()).map_err(|_| {
+						EventHandlerError::Fail(
+							String::from("An error occurred while starting the extension command thread."))
+					})
+				},
+				ref e => Err(EventHandlerError::InvalidState(e.event_kind())),
+			}
+		});
+
 		let quit_ready = quit_ready_arc.clone();
 
 		system_event_dispatcher.add_handler(SystemEventKind::QuitReady, move |_,e| {
@@ -1085,7 +1661,7 @@ impl<T,E> UsiAgent<T,E>
 			}
 		});
 
-		let interpreter = USIInterpreter::new();
+		let interpreter = USIInterpreter::with_extension_commands(self.extension_commands.names());
 
 		let logger = logger_arc.clone();
 		let on_error_handler = on_error_handler_arc.clone();
@@ -1093,19 +1669,32 @@ impl<T,E> UsiAgent<T,E>
 		let player = self.player.clone();
 
 		let system_event_sender = self.system_event_sender.clone();
+		let log_level = self.log_level;
 
-		player.lock().map(|mut player| {
-			let option_kinds = match player.get_option_kinds() {
+		let interpreter_thread = player.lock().map(|mut player| {
+			let mut option_kinds = match player.get_option_kinds() {
 				Ok(option_kinds) => option_kinds,
 				Err(ref e) => {
 					let _ = on_error_handler.lock().map(|h| h.call(e));
-					return false;
+					return None;
 				}
 			};
-			interpreter.start(system_event_sender,reader,option_kinds,&logger);
-			true
+			let mut option_types = match player.get_options() {
+				Ok(option_types) => option_types,
+				Err(ref e) => {
+					let _ = on_error_handler.lock().map(|h| h.call(e));
+					return None;
+				}
+			};
+			if let Some(level) = log_level {
+				if !option_kinds.contains_key(LogLevel::OPTION_NAME) {
+					option_kinds.insert(String::from(LogLevel::OPTION_NAME),SysEventOptionKind::Str);
+					option_types.insert(String::from(LogLevel::OPTION_NAME),LogLevel::usi_option(level));
+				}
+			}
+			Some(interpreter.start_with_option_types(system_event_sender,reader,option_kinds,option_types,&logger))
 		}).or_else(|e| {
-			on_error_handler.lock().map(|h| h.call(&e))
+			on_error_handler.lock().map(|h| h.call(&e)).map(|_| None)
 		}).or(Err(USIAgentStartupError::MutexLockFailedOtherError(
 					String::from("Failed to acquire exclusive lock of player object."))))?;
 		let system_event_queue = Arc::clone(&system_event_queue_arc);
@@ -1123,6 +1712,41 @@ impl<T,E> UsiAgent<T,E>
 			};
 		}
 
-		Ok(())
+		Ok(interpreter_thread)
+	}
+}
+impl<T,E> UsiAgent<T,E>
+	where T: BenchPlayer<E> + fmt::Debug + Send + 'static,
+			E: PlayerError,
+			EventHandlerError<SystemEventKind, E>: From<E> {
+	/// `bench`コマンドを拡張コマンドとして登録する
+	///
+	/// `bench`のみの場合は`bench`に設定された条件で、`bench depth N`,`bench nodes N`,`bench movetime N`の場合は指定された条件で実行し、
+	/// 結果を`info string`として出力する。
+	/// 実行中に`stop`か`quit`を受信すると`BenchPlayer::bench_search`に中断を通知し、残りの局面は探索せずにそれまでの結果を出力する。
+	/// プレイヤーのロックは局面ごとに取得し直すため、実行中の`isready`などは現在の局面の探索が終わり次第処理される
+	///
+	/// # Arguments
+	/// * `bench` - 実行するベンチマーク
+	pub fn add_bench_command(&mut self,bench:Bench) {
+		let player = self.player.clone();
+		let stop = self.bench_stop.clone();
+
+		self.add_extension_command("bench", move |args,_,writer| {
+			let bench = if args.is_empty() {
+				bench.clone()
+			} else {
+				let limit = BenchLimit::try_from(args).map_err(|_| ExtensionCommandError::InvalidArgument(args.join(" ")))?;
+				bench.clone().limit(limit)
+			};
+
+			stop.store(false,Ordering::Release);
+
+			let r = bench.run(&*player,&stop).map_err(|e| ExtensionCommandError::Fail(e.to_string()))?;
+
+			writer.write(&r.report().into_iter().map(|l| format!("info string {}",l)).collect())?;
+
+			Ok(())
+		});
 	}
 }
\ No newline at end of file
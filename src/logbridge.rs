@@ -0,0 +1,170 @@
+//! `log`クレートとの間でログを受け渡すアダプタ（`log-bridge`フィーチャーが有効な場合のみ利用可能）
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use log;
+use log::{Log, Metadata, Record, LevelFilter, SetLoggerError};
+
+use logger::{Logger, LogLevel};
+
+impl From<LogLevel> for log::Level {
+	fn from(level:LogLevel) -> log::Level {
+		match level {
+			LogLevel::Error => log::Level::Error,
+			LogLevel::Warn => log::Level::Warn,
+			LogLevel::Info => log::Level::Info,
+			LogLevel::Debug => log::Level::Debug,
+			LogLevel::Trace => log::Level::Trace,
+		}
+	}
+}
+impl From<log::Level> for LogLevel {
+	fn from(level:log::Level) -> LogLevel {
+		match level {
+			log::Level::Error => LogLevel::Error,
+			log::Level::Warn => LogLevel::Warn,
+			log::Level::Info => LogLevel::Info,
+			log::Level::Debug => LogLevel::Debug,
+			log::Level::Trace => LogLevel::Trace,
+		}
+	}
+}
+/// `log`クレートのマクロへログを転送する`Logger`の実装
+///
+/// `logging`で出力されたログはエラーのレベル、ターゲット`usiagent`で転送される
+#[derive(Debug, Default)]
+pub struct LogFacadeLogger;
+impl LogFacadeLogger {
+	/// ターゲットが指定されていない場合に使われるターゲット
+	pub const DEFAULT_TARGET:&'static str = "usiagent";
+
+	/// `LogFacadeLogger`の生成
+	pub fn new() -> LogFacadeLogger {
+		LogFacadeLogger
+	}
+}
+impl Logger for LogFacadeLogger {
+	fn logging(&mut self, msg:&String) -> bool {
+		self.log(LogLevel::Error,"",msg)
+	}
+
+	fn log(&mut self, level:LogLevel, module:&str, msg:&str) -> bool {
+		let target = if module.is_empty() {
+			LogFacadeLogger::DEFAULT_TARGET
+		} else {
+			module
+		};
+
+		log::log!(target: target, level.into(), "{}", msg);
+		true
+	}
+}
+/// `log`クレートのマクロで出力されたログを`Logger`へ書き込む`log::Log`の実装
+///
+/// `UsiAgent`に渡す`Logger`と同じオブジェクトを共有することで、
+/// エンジンのコードとusiagentのログを一つの出力先にまとめることができる
+/// # Examples
+/// ```no_run
+/// extern crate log;
+/// extern crate usiagent;
+///
+/// use std::sync::{Arc, Mutex};
+/// use usiagent::logger::{FileLogger, Logger};
+/// use usiagent::logbridge::{SharedLogger, UsiLogBridge};
+///
+/// let logger = Arc::new(Mutex::new(FileLogger::new(String::from("logs/log.txt")).unwrap()));
+///
+/// UsiLogBridge::new(logger.clone()).init().unwrap();
+///
+/// log::info!("written to logs/log.txt");
+///
+/// // UsiAgent::startなどにはSharedLoggerで包んで渡す
+/// let mut shared = SharedLogger::new(logger);
+/// shared.logging(&String::from("also written to logs/log.txt"));
+/// ```
+pub struct UsiLogBridge<L> where L: Logger + Send + 'static {
+	logger:Arc<Mutex<L>>,
+	level:LevelFilter,
+}
+impl<L> UsiLogBridge<L> where L: Logger + Send + 'static {
+	/// `UsiLogBridge`の生成
+	///
+	/// # Arguments
+	/// * `logger` - 書き込み先の`Logger`
+	pub fn new(logger:Arc<Mutex<L>>) -> UsiLogBridge<L> {
+		UsiLogBridge {
+			logger,
+			level:LevelFilter::Trace,
+		}
+	}
+
+	/// 受け付けるログの最大のレベルを設定（デフォルトは`LevelFilter::Trace`）
+	///
+	/// # Arguments
+	/// * `level` - 受け付けるログの最大のレベル
+	pub fn level(mut self,level:LevelFilter) -> UsiLogBridge<L> {
+		self.level = level;
+		self
+	}
+
+	/// `log`クレートのグローバルなロガーとして登録する
+	pub fn init(self) -> Result<(),SetLoggerError> {
+		let level = self.level;
+
+		log::set_boxed_logger(Box::new(self))?;
+		log::set_max_level(level);
+
+		Ok(())
+	}
+}
+impl<L> fmt::Debug for UsiLogBridge<L> where L: Logger + Send + 'static {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f,"UsiLogBridge {{ level: {:?} }}",self.level)
+	}
+}
+impl<L> Log for UsiLogBridge<L> where L: Logger + Send + 'static {
+	fn enabled(&self, metadata:&Metadata) -> bool {
+		metadata.level() <= self.level
+	}
+
+	fn log(&self, record:&Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+
+		if let Ok(mut logger) = self.logger.lock() {
+			logger.log(record.level().into(),record.target(),&format!("{}",record.args()));
+		}
+	}
+
+	fn flush(&self) {}
+}
+/// `Arc<Mutex<L>>`で共有された`Logger`を`UsiAgent`などに渡すための`Logger`の実装
+#[derive(Debug)]
+pub struct SharedLogger<L> where L: Logger + fmt::Debug {
+	logger:Arc<Mutex<L>>,
+}
+impl<L> SharedLogger<L> where L: Logger + fmt::Debug {
+	/// `SharedLogger`の生成
+	///
+	/// # Arguments
+	/// * `logger` - 共有する`Logger`
+	pub fn new(logger:Arc<Mutex<L>>) -> SharedLogger<L> {
+		SharedLogger {
+			logger,
+		}
+	}
+}
+impl<L> Logger for SharedLogger<L> where L: Logger + fmt::Debug {
+	fn logging(&mut self, msg:&String) -> bool {
+		self.logger.lock().map(|mut logger| logger.logging(msg)).unwrap_or(false)
+	}
+
+	fn log(&mut self, level:LogLevel, module:&str, msg:&str) -> bool {
+		self.logger.lock().map(|mut logger| logger.log(level,module,msg)).unwrap_or(false)
+	}
+
+	fn set_level(&mut self, level:LogLevel) {
+		let _ = self.logger.lock().map(|mut logger| logger.set_level(level));
+	}
+}
@@ -1,13 +1,195 @@
 //! ログ出力
+use std::convert::TryFrom;
 use std::error::Error;
+use std::fmt;
 use std::io::{ self, BufWriter, Write  };
 use std::fs;
 use std::fs::OpenOptions;
 use chrono::prelude::*;
 
+use command::UsiOptType;
+use error::TypeConvertError;
 use output::USIStdErrorWriter;
 use string::AddIndent;
 
+/// ログのレベル
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum LogLevel {
+	/// エラー
+	Error = 1,
+	/// 警告
+	Warn,
+	/// 情報
+	Info,
+	/// デバッグ用の情報
+	Debug,
+	/// 詳細なトレース
+	Trace,
+}
+impl LogLevel {
+	/// USIオプションの名前
+	pub const OPTION_NAME:&'static str = "LogLevel";
+	/// 全てのレベル
+	pub const ALL:[LogLevel; 5] = [
+		LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace
+	];
+
+	/// USIオプションの値に変換
+	pub fn to_usi_option_value(&self) -> &'static str {
+		match *self {
+			LogLevel::Error => "error",
+			LogLevel::Warn => "warn",
+			LogLevel::Info => "info",
+			LogLevel::Debug => "debug",
+			LogLevel::Trace => "trace",
+		}
+	}
+
+	/// ログのレベルを選択するcomboのUSIオプションを生成
+	///
+	/// # Arguments
+	/// * `default` - デフォルト値
+	pub fn usi_option(default:LogLevel) -> UsiOptType {
+		UsiOptType::Combo(Some(default.to_usi_option_value().to_string()),
+						  LogLevel::ALL.iter().map(|l| l.to_usi_option_value().to_string()).collect())
+	}
+}
+impl fmt::Display for LogLevel {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			LogLevel::Error => write!(f,"ERROR"),
+			LogLevel::Warn => write!(f,"WARN"),
+			LogLevel::Info => write!(f,"INFO"),
+			LogLevel::Debug => write!(f,"DEBUG"),
+			LogLevel::Trace => write!(f,"TRACE"),
+		}
+	}
+}
+impl<'a> TryFrom<&'a str> for LogLevel {
+	type Error = TypeConvertError<String>;
+
+	fn try_from(s:&'a str) -> Result<LogLevel,TypeConvertError<String>> {
+		LogLevel::ALL.iter().find(|l| l.to_usi_option_value().eq_ignore_ascii_case(s)).cloned().ok_or(
+			TypeConvertError::SyntaxError(format!("The log level is invalid. ({})",s))
+		)
+	}
+}
+/// モジュールごとに出力するログのレベルを決めるフィルタ
+///
+/// `info,usiagent::selfmatch=debug,usiagent::rule=off`のように、
+/// デフォルトのレベルとモジュールのパスの前方一致ごとのレベルを`,`区切りで指定した文字列から生成できる
+/// # Examples
+/// ```
+/// use std::convert::TryFrom;
+/// use usiagent::logger::{LogFilter, LogLevel};
+///
+/// let filter = LogFilter::try_from("warn,usiagent::selfmatch=debug").unwrap();
+/// assert!(filter.enabled(LogLevel::Debug,"usiagent::selfmatch"));
+/// assert!(!filter.enabled(LogLevel::Info,"usiagent::rule"));
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LogFilter {
+	level:Option<LogLevel>,
+	modules:Vec<(String,Option<LogLevel>)>,
+}
+impl LogFilter {
+	/// `LogFilter`の生成
+	///
+	/// # Arguments
+	/// * `level` - 出力するログのレベル（`None`の場合は出力しない）
+	pub fn new(level:Option<LogLevel>) -> LogFilter {
+		LogFilter {
+			level,
+			modules:Vec::new(),
+		}
+	}
+
+	/// モジュールごとのレベルを追加する
+	///
+	/// # Arguments
+	/// * `module` - モジュールのパス（前方一致で比較し、最も長く一致したものが使われる）
+	/// * `level` - 出力するログのレベル（`None`の場合は出力しない）
+	pub fn module(mut self,module:&str,level:Option<LogLevel>) -> LogFilter {
+		self.modules.retain(|(m,_)| m != module);
+		self.modules.push((module.to_string(),level));
+		self
+	}
+
+	/// デフォルトのレベルを設定する
+	///
+	/// # Arguments
+	/// * `level` - 出力するログのレベル（`None`の場合は出力しない）
+	pub fn set_level(&mut self,level:Option<LogLevel>) {
+		self.level = level;
+	}
+
+	/// 指定したレベルとモジュールのログを出力するか
+	///
+	/// # Arguments
+	/// * `level` - ログのレベル
+	/// * `module` - ログの出力元のモジュールのパス
+	pub fn enabled(&self,level:LogLevel,module:&str) -> bool {
+		let limit = self.modules.iter()
+								.filter(|&(m,_)| module == m || module.starts_with(&format!("{}::",m)))
+								.max_by_key(|&(m,_)| m.len())
+								.map(|&(_,l)| l)
+								.unwrap_or(self.level);
+
+		limit.map(|limit| level <= limit).unwrap_or(false)
+	}
+}
+impl Default for LogFilter {
+	fn default() -> LogFilter {
+		LogFilter::new(Some(LogLevel::Info))
+	}
+}
+impl<'a> TryFrom<&'a str> for LogFilter {
+	type Error = TypeConvertError<String>;
+
+	fn try_from(s:&'a str) -> Result<LogFilter,TypeConvertError<String>> {
+		let parse_level = |l:&str| -> Result<Option<LogLevel>,TypeConvertError<String>> {
+			if l.eq_ignore_ascii_case("off") {
+				Ok(None)
+			} else {
+				LogLevel::try_from(l).map(Some)
+			}
+		};
+
+		let mut filter = LogFilter::default();
+
+		for spec in s.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+			match spec.find('=') {
+				Some(i) => {
+					filter = filter.module(spec[..i].trim(),parse_level(spec[i+1..].trim())?);
+				},
+				None => {
+					filter.set_level(parse_level(spec)?);
+				}
+			}
+		}
+
+		Ok(filter)
+	}
+}
+/// ログファイルのローテーションの条件
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum LogRotation {
+	/// ローテーションしない
+	Never,
+	/// ファイルのサイズが指定したバイト数を超える場合
+	Size(u64),
+	/// 日付が変わった場合
+	Daily,
+}
+/// ログの出力形式
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum LogFormat {
+	/// 日時とレベルの行に続けてインデントしたメッセージを出力するテキスト形式
+	Text,
+	/// 一行に一つのJSONオブジェクトを出力するJSON Lines形式
+	JsonLines,
+}
+
 /// ログの出力
 pub trait Logger {
 	/// ログの出力処理の本体の実装
@@ -20,26 +202,70 @@ pub trait Logger {
 	/// # Arguments
 	/// * `e` - ログに出力するエラー
 	fn logging_error<E: Error>(&mut self, e:&E) -> bool {
-		let mut messages:Vec<String> = vec![];
-		let mut indent:u32 = 0;
-
-		messages.push(format!("{}", e).add_indent(indent*2));
-
-		let mut e:&(dyn Error) = e;
-
-		while let Some(cause) = e.source() {
-			indent += 1;
-			messages.push(format!("{}", cause).add_indent(indent*2));
-			e = cause;
-		}
-
-		self.logging(&messages.join("\n"))
+		self.logging(&error_messages(e))
+	}
+	/// レベルと出力元のモジュールを指定して、エラーを発生元へたどりながら改行しつつインデントして出力する
+	///
+	/// # Arguments
+	/// * `level` - ログのレベル
+	/// * `module` - ログの出力元のモジュールのパス（通常は`module_path!()`）
+	/// * `e` - ログに出力するエラー
+	fn log_error<E: Error>(&mut self, level:LogLevel, module:&str, e:&E) -> bool {
+		self.log(level,module,&error_messages(e))
+	}
+	/// レベルと出力元のモジュールを指定してログを出力する
+	///
+	/// デフォルトの実装はレベルとモジュールを無視して`logging`を呼ぶ
+	///
+	/// # Arguments
+	/// * `level` - ログのレベル
+	/// * `module` - ログの出力元のモジュールのパス（通常は`module_path!()`）
+	/// * `msg` - 出力するログ
+	fn log(&mut self, _level:LogLevel, _module:&str, msg:&str) -> bool {
+		self.logging(&msg.to_string())
+	}
+	/// 出力するログのレベルを変更する（デフォルトの実装は何もしない）
+	///
+	/// # Arguments
+	/// * `level` - 出力するログのレベル
+	fn set_level(&mut self, _level:LogLevel) {}
+	/// エラーのログを出力する
+	fn error(&mut self, module:&str, msg:&str) -> bool {
+		self.log(LogLevel::Error,module,msg)
+	}
+	/// 警告のログを出力する
+	fn warn(&mut self, module:&str, msg:&str) -> bool {
+		self.log(LogLevel::Warn,module,msg)
+	}
+	/// 情報のログを出力する
+	fn info(&mut self, module:&str, msg:&str) -> bool {
+		self.log(LogLevel::Info,module,msg)
+	}
+	/// デバッグ用のログを出力する
+	fn debug(&mut self, module:&str, msg:&str) -> bool {
+		self.log(LogLevel::Debug,module,msg)
+	}
+	/// トレースのログを出力する
+	fn trace(&mut self, module:&str, msg:&str) -> bool {
+		self.log(LogLevel::Trace,module,msg)
 	}
 }
 /// ファイルへ出力する`Logger`の実装
+///
+/// `logging`で出力されたログはエラーのレベルとして扱われる。
+/// `filter`や`set_level`でレベルを設定するまでは、テキスト形式のログはレベルやモジュールを含まない従来の形式で出力される。
+/// ローテーションした古いログは`ファイル名.1`,`ファイル名.2`...の名前で保持される
 #[derive(Debug)]
 pub struct FileLogger {
 	writer:BufWriter<fs::File>,
+	file:String,
+	filter:LogFilter,
+	rotation:LogRotation,
+	retention:usize,
+	format:LogFormat,
+	detailed:bool,
+	size:u64,
+	date:NaiveDate,
 }
 impl FileLogger {
 	/// `FileLogger`の生成
@@ -47,18 +273,125 @@ impl FileLogger {
 	///
 	/// * `file` - 書き込み先のファイル
 	pub fn new(file:String) -> Result<FileLogger,io::Error> {
+		let writer = OpenOptions::new().append(true).create(true).open(&file)?;
+		let size = writer.metadata()?.len();
+
 		Ok(FileLogger {
-			writer:BufWriter::new(OpenOptions::new().append(true).create(true).open(file)?),
+			writer:BufWriter::new(writer),
+			file,
+			filter:LogFilter::default(),
+			rotation:LogRotation::Never,
+			retention:0,
+			format:LogFormat::Text,
+			detailed:false,
+			size,
+			date:Local::now().date_naive(),
 		})
 	}
+
+	/// 出力するログのフィルタを設定（デフォルトはinfo以上を出力）
+	///
+	/// # Arguments
+	/// * `filter` - ログのフィルタ
+	pub fn filter(mut self,filter:LogFilter) -> FileLogger {
+		self.filter = filter;
+		self.detailed = true;
+		self
+	}
+
+	/// ローテーションの条件と保持する古いログファイルの数を設定（デフォルトはローテーションしない）
+	///
+	/// # Arguments
+	/// * `rotation` - ローテーションの条件
+	/// * `retention` - 保持する古いログファイルの数
+	pub fn rotation(mut self,rotation:LogRotation,retention:usize) -> FileLogger {
+		self.rotation = rotation;
+		self.retention = retention;
+		self
+	}
+
+	/// 出力形式を設定（デフォルトは`LogFormat::Text`）
+	///
+	/// # Arguments
+	/// * `format` - 出力形式
+	pub fn format(mut self,format:LogFormat) -> FileLogger {
+		self.format = format;
+		self
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		self.writer.flush()?;
+
+		if self.retention > 0 {
+			let _ = fs::remove_file(format!("{}.{}",self.file,self.retention));
+
+			for i in (1..self.retention).rev() {
+				let from = format!("{}.{}",self.file,i);
+
+				if fs::metadata(&from).is_ok() {
+					fs::rename(&from,format!("{}.{}",self.file,i + 1))?;
+				}
+			}
+
+			fs::rename(&self.file,format!("{}.1",self.file))?;
+		}
+
+		self.writer = BufWriter::new(OpenOptions::new().write(true).truncate(true).create(true).open(&self.file)?);
+		self.size = 0;
+
+		Ok(())
+	}
+
+	fn format_message(&self,dt:&DateTime<Local>,level:LogLevel,module:&str,msg:&str) -> String {
+		match self.format {
+			LogFormat::Text if !self.detailed => {
+				format!("{}\n{}\n", dt.format("%Y-%m-%d %H:%M:%S"), msg.to_string().add_indent(2))
+			},
+			LogFormat::Text if module.is_empty() => {
+				format!("{} {}\n{}\n", dt.format("%Y-%m-%d %H:%M:%S%.3f"), level, msg.to_string().add_indent(2))
+			},
+			LogFormat::Text => {
+				format!("{} {} {}\n{}\n", dt.format("%Y-%m-%d %H:%M:%S%.3f"), level, module, msg.to_string().add_indent(2))
+			},
+			LogFormat::JsonLines => {
+				format!("{{\"time\":\"{}\",\"level\":\"{}\",\"module\":\"{}\",\"message\":\"{}\"}}\n",
+						dt.format("%Y-%m-%dT%H:%M:%S%.3f%:z"), level, escape_json(module), escape_json(msg))
+			}
+		}
+	}
 }
 impl Logger for FileLogger {
 	fn logging(&mut self, msg:&String) -> bool {
+		self.log(LogLevel::Error,"",msg)
+	}
+
+	fn log(&mut self, level:LogLevel, module:&str, msg:&str) -> bool {
+		if !self.filter.enabled(level,module) {
+			return true;
+		}
+
 		let dt = Local::now();
 
-		let msg = format!("{}\n{}\n", dt.format("%Y-%m-%d %H:%M:%S").to_string(), msg.add_indent(2));
-		match self.writer.write(msg.as_bytes()) {
-			Ok(_) => !self.writer.flush().is_err(),
+		let msg = self.format_message(&dt,level,module,msg);
+
+		let rotate = match self.rotation {
+			LogRotation::Never => false,
+			LogRotation::Size(max) => self.size > 0 && self.size + msg.len() as u64 > max,
+			LogRotation::Daily => self.date != dt.date_naive(),
+		};
+
+		if rotate {
+			if let Err(ref e) = self.rotate() {
+				let _ = USIStdErrorWriter::write(&format!("The log file could not be rotated. ({})",e));
+			}
+			self.date = dt.date_naive();
+		}
+
+		match self.writer.write_all(msg.as_bytes()) {
+			Ok(_) => {
+				self.size += msg.len() as u64;
+				self.writer.flush().is_ok()
+			},
 			Err(_)=> {
 				USIStdErrorWriter::write("The log could not be written to the file.").unwrap();
 				let _ = self.writer.flush();
@@ -66,4 +399,42 @@ impl Logger for FileLogger {
 			}
 		}
 	}
+
+	fn set_level(&mut self, level:LogLevel) {
+		self.filter.set_level(Some(level));
+		self.detailed = true;
+	}
+}
+fn error_messages<E: Error>(e:&E) -> String {
+	let mut messages:Vec<String> = vec![];
+	let mut indent:u32 = 0;
+
+	messages.push(format!("{}", e).add_indent(indent*2));
+
+	let mut e:&dyn Error = e;
+
+	while let Some(cause) = e.source() {
+		indent += 1;
+		messages.push(format!("{}", cause).add_indent(indent*2));
+		e = cause;
+	}
+
+	messages.join("\n")
+}
+fn escape_json(s:&str) -> String {
+	let mut escaped = String::with_capacity(s.len());
+
+	for c in s.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}",c as u32)),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
 }
\ No newline at end of file
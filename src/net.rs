@@ -0,0 +1,148 @@
+//! ネットワーク越しにUSIプロトコルで通信するための入出力の実装
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::Mutex;
+
+use input::USIInputReader;
+use output::USIOutputWriter;
+
+/// 読み取りと書き込みを行う接続
+pub trait USIConnection: Read + Write + Send + Sized + 'static {
+	/// 同じ接続を参照するオブジェクトを複製する
+	fn try_clone_connection(&self) -> io::Result<Self>;
+	/// 接続を閉じる
+	fn shutdown_connection(&self) -> io::Result<()>;
+}
+impl USIConnection for TcpStream {
+	fn try_clone_connection(&self) -> io::Result<TcpStream> {
+		self.try_clone()
+	}
+
+	fn shutdown_connection(&self) -> io::Result<()> {
+		self.shutdown(Shutdown::Both)
+	}
+}
+#[cfg(unix)]
+impl USIConnection for UnixStream {
+	fn try_clone_connection(&self) -> io::Result<UnixStream> {
+		self.try_clone()
+	}
+
+	fn shutdown_connection(&self) -> io::Result<()> {
+		self.shutdown(Shutdown::Both)
+	}
+}
+/// 接続を待ち受ける
+pub trait USIListener {
+	/// 受け付けた接続の型
+	type Connection: USIConnection;
+
+	/// 次の接続を受け付ける
+	fn accept_connection(&self) -> io::Result<Self::Connection>;
+}
+impl USIListener for TcpListener {
+	type Connection = TcpStream;
+
+	fn accept_connection(&self) -> io::Result<TcpStream> {
+		let (stream,_) = self.accept()?;
+
+		stream.set_nodelay(true)?;
+
+		Ok(stream)
+	}
+}
+#[cfg(unix)]
+impl USIListener for UnixListener {
+	type Connection = UnixStream;
+
+	fn accept_connection(&self) -> io::Result<UnixStream> {
+		let (stream,_) = self.accept()?;
+
+		Ok(stream)
+	}
+}
+/// ストリームから一行ずつ読み取る`USIInputReader`の実装
+#[derive(Debug)]
+pub struct USIStreamInputReader<S> where S: Read {
+	reader:BufReader<S>,
+}
+impl<S> USIStreamInputReader<S> where S: Read {
+	/// `USIStreamInputReader`の生成
+	///
+	/// # Arguments
+	/// * `stream` - 読み取り元のストリーム
+	pub fn new(stream:S) -> USIStreamInputReader<S> {
+		USIStreamInputReader {
+			reader:BufReader::new(stream),
+		}
+	}
+}
+impl<S> USIInputReader for USIStreamInputReader<S> where S: Read {
+	/// 入力を一行読み取る
+	///
+	/// 接続が切断されたことを示すエラー（`ConnectionReset`,`ConnectionAborted`,`BrokenPipe`,`UnexpectedEof`）は
+	/// 入力の終端と同様に`Ok(None)`として返す。それ以外のエラーはそのまま返す
+	fn read(&mut self) -> io::Result<Option<String>> {
+		let mut buf = String::new();
+
+		match self.reader.read_line(&mut buf) {
+			Ok(0) => Ok(None),
+			Ok(_) => {
+				let ptn:&[_] = &['\r','\n'];
+
+				Ok(Some(buf.as_str().trim_end_matches(ptn).to_string()))
+			},
+			Err(ref e) if is_disconnected(e) => Ok(None),
+			Err(e) => Err(e)
+		}
+	}
+}
+fn is_disconnected(e:&io::Error) -> bool {
+	matches!(e.kind(),io::ErrorKind::ConnectionReset |
+					  io::ErrorKind::ConnectionAborted |
+					  io::ErrorKind::BrokenPipe |
+					  io::ErrorKind::UnexpectedEof)
+}
+/// ストリームへ書き込む`USIOutputWriter`の実装
+#[derive(Debug)]
+pub struct USIStreamOutputWriter<S> where S: Write {
+	writer:Mutex<S>,
+}
+impl<S> USIStreamOutputWriter<S> where S: Write {
+	/// `USIStreamOutputWriter`の生成
+	///
+	/// # Arguments
+	/// * `stream` - 書き込み先のストリーム
+	pub fn new(stream:S) -> USIStreamOutputWriter<S> {
+		USIStreamOutputWriter {
+			writer:Mutex::new(stream),
+		}
+	}
+}
+impl<S> USIOutputWriter for USIStreamOutputWriter<S> where S: Write {
+	fn write(&self,lines:&Vec<String>) -> io::Result<usize> {
+		let mut s = lines.join("\n");
+		s.push('\n');
+
+		let mut writer = self.writer.lock().map_err(|_| {
+			io::Error::other("Could not get exclusive lock on stream object.")
+		})?;
+
+		writer.write_all(s.as_bytes())?;
+		writer.flush()?;
+
+		Ok(s.len())
+	}
+}
+/// TCPの接続から読み取る`USIInputReader`
+pub type USITcpInputReader = USIStreamInputReader<TcpStream>;
+/// TCPの接続へ書き込む`USIOutputWriter`
+pub type USITcpOutputWriter = USIStreamOutputWriter<TcpStream>;
+/// Unixドメインソケットの接続から読み取る`USIInputReader`
+#[cfg(unix)]
+pub type USIUnixInputReader = USIStreamInputReader<UnixStream>;
+/// Unixドメインソケットの接続へ書き込む`USIOutputWriter`
+#[cfg(unix)]
+pub type USIUnixOutputWriter = USIStreamOutputWriter<UnixStream>;
@@ -0,0 +1,401 @@
+//! USIオプションを一か所で定義するためのレジストリ
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use command::UsiOptType;
+use config::ConfigFile;
+use error::{OptionRegistryError, SetOptionParseError};
+use event::{SysEventOption, SysEventOptionKind};
+
+type Setter<T,V> = Box<dyn Fn(&mut T,V) + Send + Sync + 'static>;
+
+enum OptionSetter<T> {
+	Bool(Setter<T,bool>),
+	Num(Setter<T,i64>),
+	Str(Setter<T,String>),
+	Exist(Box<dyn Fn(&mut T) + Send + Sync + 'static>),
+}
+impl<T> OptionSetter<T> {
+	fn kind(&self) -> SysEventOptionKind {
+		match *self {
+			OptionSetter::Bool(_) => SysEventOptionKind::Bool,
+			OptionSetter::Num(_) => SysEventOptionKind::Num,
+			OptionSetter::Str(_) => SysEventOptionKind::Str,
+			OptionSetter::Exist(_) => SysEventOptionKind::Exist,
+		}
+	}
+}
+struct OptionEntry<T> {
+	opt_type:UsiOptType,
+	setter:OptionSetter<T>,
+}
+/// USIオプションの定義と値を設定する関数をまとめたレジストリ
+///
+/// オプションごとに名前、種類、デフォルト値、spinの範囲、comboの項目と値を設定する関数を一度だけ宣言し、
+/// `USIPlayer::get_option_kinds`,`USIPlayer::get_options`が返すマップの生成と、
+/// `USIPlayer::set_option`に渡された値の検証、変換、設定を行う。
+/// 自己対局の`player1_options`,`player2_options`に渡すオプションの一覧を設定ファイルへ保存、読み込みすることもできる
+///
+/// # Examples
+/// ```
+/// use usiagent::event::SysEventOption;
+/// use usiagent::options::OptionRegistry;
+///
+/// #[derive(Default)]
+/// struct Settings {
+///     threads:i64,
+///     use_book:bool,
+///     style:String,
+/// }
+///
+/// let registry = OptionRegistry::<Settings>::new()
+///     .spin("Threads",1,64,Some(4),|s,v| s.threads = v)
+///     .check("UseBook",Some(true),|s,v| s.use_book = v)
+///     .combo("Style",Some("normal"),&["normal","aggressive"],|s,v| s.style = v);
+///
+/// let mut settings = Settings::default();
+///
+/// registry.apply_defaults(&mut settings).unwrap();
+/// registry.apply(&mut settings,"Threads",SysEventOption::Num(8)).unwrap();
+///
+/// assert_eq!(settings.threads,8);
+/// assert!(settings.use_book);
+/// assert_eq!(&*settings.style,"normal");
+/// assert!(registry.apply(&mut settings,"Threads",SysEventOption::Num(128)).is_err());
+/// ```
+pub struct OptionRegistry<T> {
+	options:BTreeMap<String,OptionEntry<T>>,
+}
+impl<T> OptionRegistry<T> {
+	/// `OptionRegistry`の生成
+	pub fn new() -> OptionRegistry<T> {
+		OptionRegistry {
+			options:BTreeMap::new(),
+		}
+	}
+
+	fn add(mut self,name:&str,opt_type:UsiOptType,setter:OptionSetter<T>) -> OptionRegistry<T> {
+		self.options.insert(name.to_string(),OptionEntry {
+			opt_type,
+			setter,
+		});
+		self
+	}
+
+	/// checkのオプションを追加
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `default` - デフォルト値
+	/// * `setter` - 値を設定する関数
+	pub fn check<F>(self,name:&str,default:Option<bool>,setter:F) -> OptionRegistry<T>
+		where F: Fn(&mut T,bool) + Send + Sync + 'static {
+		self.add(name,UsiOptType::Check(default),OptionSetter::Bool(Box::new(setter)))
+	}
+
+	/// spinのオプションを追加
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `min` - 最小値
+	/// * `max` - 最大値
+	/// * `default` - デフォルト値
+	/// * `setter` - 値を設定する関数
+	pub fn spin<F>(self,name:&str,min:i64,max:i64,default:Option<i64>,setter:F) -> OptionRegistry<T>
+		where F: Fn(&mut T,i64) + Send + Sync + 'static {
+		self.add(name,UsiOptType::Spin(min,max,default),OptionSetter::Num(Box::new(setter)))
+	}
+
+	/// comboのオプションを追加
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `default` - デフォルト値
+	/// * `items` - 選択肢
+	/// * `setter` - 値を設定する関数
+	pub fn combo<F>(self,name:&str,default:Option<&str>,items:&[&str],setter:F) -> OptionRegistry<T>
+		where F: Fn(&mut T,String) + Send + Sync + 'static {
+		self.add(name,
+				 UsiOptType::Combo(default.map(|s| s.to_string()),items.iter().map(|s| s.to_string()).collect()),
+				 OptionSetter::Str(Box::new(setter)))
+	}
+
+	/// stringのオプションを追加
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `default` - デフォルト値
+	/// * `setter` - 値を設定する関数
+	pub fn string<F>(self,name:&str,default:Option<&str>,setter:F) -> OptionRegistry<T>
+		where F: Fn(&mut T,String) + Send + Sync + 'static {
+		self.add(name,UsiOptType::String(default.map(|s| s.to_string())),OptionSetter::Str(Box::new(setter)))
+	}
+
+	/// filenameのオプションを追加
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `default` - デフォルト値
+	/// * `setter` - 値を設定する関数
+	pub fn filename<F>(self,name:&str,default:Option<&str>,setter:F) -> OptionRegistry<T>
+		where F: Fn(&mut T,String) + Send + Sync + 'static {
+		self.add(name,UsiOptType::FileName(default.map(|s| s.to_string())),OptionSetter::Str(Box::new(setter)))
+	}
+
+	/// buttonのオプションを追加
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `setter` - ボタンが押された時に呼ばれる関数
+	pub fn button<F>(self,name:&str,setter:F) -> OptionRegistry<T>
+		where F: Fn(&mut T) + Send + Sync + 'static {
+		self.add(name,UsiOptType::Button,OptionSetter::Exist(Box::new(setter)))
+	}
+
+	/// `USIPlayer::get_option_kinds`が返すオプションの名前をキーとしたオプションの種類のマップを生成
+	pub fn option_kinds(&self) -> BTreeMap<String,SysEventOptionKind> {
+		self.options.iter().map(|(name,entry)| (name.clone(),entry.setter.kind())).collect()
+	}
+
+	/// `USIPlayer::get_options`が返すオプションの名前をキーとしたオプションの定義のマップを生成
+	pub fn options(&self) -> BTreeMap<String,UsiOptType> {
+		self.options.iter().map(|(name,entry)| (name.clone(),entry.opt_type.clone())).collect()
+	}
+
+	/// 定義されたオプションの名前の一覧
+	pub fn names(&self) -> Vec<String> {
+		self.options.keys().cloned().collect()
+	}
+
+	/// オプションの定義に基づいて値を検証する
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `value` - 値
+	pub fn validate(&self,name:&str,value:&SysEventOption) -> Result<(),SetOptionParseError> {
+		let entry = self.options.get(name).ok_or(SetOptionParseError::UnknownOption(name.to_string()))?;
+
+		validate_option_value(name,value,&entry.setter.kind(),Some(&entry.opt_type))
+	}
+
+	/// 値を検証してオプションに設定する
+	///
+	/// # Arguments
+	/// * `target` - 値を設定する対象
+	/// * `name` - オプションの名前
+	/// * `value` - 値
+	pub fn apply(&self,target:&mut T,name:&str,value:SysEventOption) -> Result<(),OptionRegistryError> {
+		self.validate(name,&value)?;
+
+		match (&self.options[name].setter,value) {
+			(OptionSetter::Bool(f),SysEventOption::Bool(b)) => f(target,b),
+			(OptionSetter::Num(f),SysEventOption::Num(n)) => f(target,n),
+			(OptionSetter::Str(f),SysEventOption::Str(s)) => f(target,s),
+			(OptionSetter::Exist(f),SysEventOption::Exist) => f(target),
+			_ => ()
+		}
+
+		Ok(())
+	}
+
+	/// デフォルト値を持つオプションの名前と値の一覧
+	pub fn defaults(&self) -> Vec<(String,SysEventOption)> {
+		self.options.iter().filter_map(|(name,entry)| {
+			let value = match entry.opt_type {
+				UsiOptType::Check(Some(b)) => SysEventOption::Bool(b),
+				UsiOptType::Spin(_,_,Some(n)) => SysEventOption::Num(n),
+				UsiOptType::Combo(Some(ref s),_) |
+				UsiOptType::String(Some(ref s)) |
+				UsiOptType::FileName(Some(ref s)) => SysEventOption::Str(s.clone()),
+				_ => {
+					return None;
+				}
+			};
+
+			Some((name.clone(),value))
+		}).collect()
+	}
+
+	/// デフォルト値を持つ全てのオプションにデフォルト値を設定する
+	///
+	/// # Arguments
+	/// * `target` - 値を設定する対象
+	pub fn apply_defaults(&self,target:&mut T) -> Result<(),OptionRegistryError> {
+		for (name,value) in self.defaults() {
+			self.apply(target,&name,value)?;
+		}
+
+		Ok(())
+	}
+
+	/// 文字列をオプションの種類に応じた値に変換して検証する
+	///
+	/// # Arguments
+	/// * `name` - オプションの名前
+	/// * `value` - 値の文字列（buttonの場合は空文字列）
+	pub fn parse_value(&self,name:&str,value:&str) -> Result<SysEventOption,SetOptionParseError> {
+		parse_option_value(name,value,&self.option_kinds(),&self.options())
+	}
+
+	/// `ConfigFile`の形式で`名前 = 値`と一行に一つずつ書かれたオプションの一覧を読み込む
+	///
+	/// 最初のセクションより前と`[options]`セクションに書かれた項目が対象となる。
+	/// 値の扱い（`"`による囲みなど）は`ConfigFile`と同じで、buttonのオプションは名前のみを書く
+	///
+	/// # Arguments
+	/// * `reader` - 読み込み元
+	pub fn read_options<R>(&self,reader:R) -> Result<Vec<(String,SysEventOption)>,OptionRegistryError> where R: BufRead {
+		let config = ConfigFile::read(reader)?;
+
+		let mut options = Vec::new();
+
+		for entry in config.entries("").into_iter().chain(config.entries("options")) {
+			options.push((entry.key.clone(),self.parse_value(&entry.key,&entry.value)?));
+		}
+
+		Ok(options)
+	}
+
+	/// オプションの一覧を`read_options`で読み込める形式で書き込む
+	///
+	/// 文字列の値は常に`"`で囲んで書き込むため、前後の空白や`"`を含む値も元の値として読み込まれる。
+	/// セクションの行は書き込まないので、書き込んだ内容はそのまま自己対局の設定ファイルの`[player1.options]`などの項目として使える
+	///
+	/// # Arguments
+	/// * `writer` - 書き込み先
+	/// * `options` - オプションの名前と値の一覧
+	pub fn write_options<W>(&self,mut writer:W,options:&[(String,SysEventOption)]) -> Result<(),OptionRegistryError> where W: Write {
+		for (name,value) in options {
+			self.validate(name,value)?;
+
+			match *value {
+				SysEventOption::Exist => writeln!(writer,"{}",name)?,
+				SysEventOption::Str(ref s) => writeln!(writer,"{} = \"{}\"",name,s)?,
+				ref v => writeln!(writer,"{} = {}",name,OptionValue(v))?,
+			}
+		}
+
+		writer.flush()?;
+
+		Ok(())
+	}
+
+	/// ファイルからオプションの一覧を読み込む
+	///
+	/// # Arguments
+	/// * `file` - 読み込むファイルのパス
+	pub fn load_options(&self,file:&str) -> Result<Vec<(String,SysEventOption)>,OptionRegistryError> {
+		self.read_options(BufReader::new(File::open(file)?))
+	}
+
+	/// オプションの一覧をファイルへ保存する
+	///
+	/// # Arguments
+	/// * `file` - 保存先のファイルのパス
+	/// * `options` - オプションの名前と値の一覧
+	pub fn save_options(&self,file:&str,options:&[(String,SysEventOption)]) -> Result<(),OptionRegistryError> {
+		self.write_options(BufWriter::new(File::create(file)?),options)
+	}
+}
+impl<T> Default for OptionRegistry<T> {
+	fn default() -> OptionRegistry<T> {
+		OptionRegistry::new()
+	}
+}
+impl<T> fmt::Debug for OptionRegistry<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f,"OptionRegistry {{ options: {:?} }}",self.options())
+	}
+}
+struct OptionValue<'a>(&'a SysEventOption);
+impl<'a> fmt::Display for OptionValue<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self.0 {
+			SysEventOption::Str(ref s) => write!(f,"{}",s),
+			SysEventOption::Num(n) => write!(f,"{}",n),
+			SysEventOption::Bool(b) => write!(f,"{}",b),
+			SysEventOption::Exist => Ok(()),
+		}
+	}
+}
+/// オプションの種類と定義のマップに基づいて文字列をオプションの値に変換して検証する
+///
+/// 値が空の場合、buttonのオプションであれば`SysEventOption::Exist`を返し、それ以外は`SetOptionParseError::MissingValue`を返す
+///
+/// # Arguments
+/// * `name` - オプションの名前
+/// * `value` - 値の文字列（buttonの場合は空文字列）
+/// * `kinds` - オプションの名前をキーとしたオプションの種類のマップ
+/// * `types` - オプションの名前をキーとしたオプションの定義のマップ
+pub fn parse_option_value(name:&str,value:&str,
+						  kinds:&BTreeMap<String,SysEventOptionKind>,
+						  types:&BTreeMap<String,UsiOptType>) -> Result<SysEventOption,SetOptionParseError> {
+	let kind = kinds.get(name).ok_or(SetOptionParseError::UnknownOption(name.to_string()))?;
+
+	let v = match *kind {
+		SysEventOptionKind::Exist if value.is_empty() => SysEventOption::Exist,
+		_ if value.is_empty() => {
+			return Err(SetOptionParseError::MissingValue(name.to_string()));
+		},
+		SysEventOptionKind::Bool if value == "true" => SysEventOption::Bool(true),
+		SysEventOptionKind::Bool if value == "false" => SysEventOption::Bool(false),
+		SysEventOptionKind::Num => {
+			SysEventOption::Num(value.parse::<i64>().map_err(|_| {
+				SetOptionParseError::InvalidValue(name.to_string(),value.to_string())
+			})?)
+		},
+		SysEventOptionKind::Str => SysEventOption::Str(value.to_string()),
+		_ => {
+			return Err(SetOptionParseError::InvalidValue(name.to_string(),value.to_string()));
+		}
+	};
+
+	validate_option_value(name,&v,kind,types.get(name))?;
+
+	Ok(v)
+}
+/// 値がオプションの種類に合っていて、spinであれば範囲内、comboであれば項目に含まれるかを検証する
+fn validate_option_value(name:&str,value:&SysEventOption,
+						 kind:&SysEventOptionKind,opt_type:Option<&UsiOptType>) -> Result<(),SetOptionParseError> {
+	match (kind,value) {
+		(&SysEventOptionKind::Bool,&SysEventOption::Bool(_)) | (&SysEventOptionKind::Exist,&SysEventOption::Exist) => {
+			Ok(())
+		},
+		(&SysEventOptionKind::Num,&SysEventOption::Num(n)) => {
+			match opt_type {
+				Some(&UsiOptType::Spin(min,max,_)) if n < min || n > max => {
+					Err(SetOptionParseError::OutOfRange(name.to_string(),n,min,max))
+				},
+				_ => Ok(())
+			}
+		},
+		(&SysEventOptionKind::Str,SysEventOption::Str(s)) => {
+			match opt_type {
+				Some(UsiOptType::Combo(_,items)) if !items.iter().any(|item| item == s) => {
+					Err(SetOptionParseError::NotInCombo(name.to_string(),s.clone()))
+				},
+				_ => Ok(())
+			}
+		},
+		(_,v) => {
+			Err(SetOptionParseError::InvalidValue(name.to_string(),OptionValue(v).to_string()))
+		}
+	}
+}
+/// オプションの定義のデフォルト値を置き換えたものを返す（buttonなどデフォルト値を持たない定義はそのまま返す）
+///
+/// # Arguments
+/// * `opt_type` - オプションの定義
+/// * `value` - 新しいデフォルト値
+pub fn with_default_value(opt_type:&UsiOptType,value:&SysEventOption) -> UsiOptType {
+	match (opt_type,value) {
+		(&UsiOptType::Check(_),&SysEventOption::Bool(b)) => UsiOptType::Check(Some(b)),
+		(&UsiOptType::Spin(min,max,_),&SysEventOption::Num(n)) => UsiOptType::Spin(min,max,Some(n)),
+		(UsiOptType::Combo(_,items),SysEventOption::Str(s)) => UsiOptType::Combo(Some(s.clone()),items.clone()),
+		(UsiOptType::String(_),SysEventOption::Str(s)) => UsiOptType::String(Some(s.clone())),
+		(UsiOptType::FileName(_),SysEventOption::Str(s)) => UsiOptType::FileName(Some(s.clone())),
+		(t,_) => t.clone(),
+	}
+}
@@ -348,6 +348,7 @@ impl<W> InfoSendWorker<W> where W: USIOutputWriter {
 										if let Err(ref e) = writer.write(&buffer) {
 											let _ = on_error_handler.lock().map(|h| h.call(e));
 										}
+										buffer.clear();
 									}
 								}
 							}
@@ -445,6 +446,169 @@ impl Clone for ConsoleInfoSender {
 		ConsoleInfoSender::new(self.silent)
 	}
 }
+/// 送信の間隔を制限し、間隔内に送られたinfoコマンドをまとめて送信する`InfoSender`のラッパー
+///
+/// 前回の送信から`interval`が経過するまでの間に送られたサブコマンドは、multipvの番号ごとに最新の値にまとめられて保持される。
+/// ただし`depth`か`pv`を含むinfoコマンドは新しい読み筋を表すため、保持されている同じmultipvの番号のものを置き換える。
+/// 詰みのスコアを含むもの、`send_immediate`で送られたもの、`flush`が呼ばれた時と最後のクローンがdropされた時は
+/// 保持している全てのinfoコマンドがすぐに送信される。
+/// `string`を含むinfoコマンドはまとめられず、送られた順に送信される。
+/// `UsiAgent`がプレイヤーに渡す`InfoSender`はこれでラップされており（間隔は`UsiAgent::set_info_interval`で設定する）、
+/// `think`から戻った後、`bestmove`を送信する前に`flush`が呼ばれる
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usiagent::command::UsiInfoSubCommand;
+/// use usiagent::player::{ConsoleInfoSender, InfoSender, ThrottledInfoSender};
+///
+/// let mut info_sender = ThrottledInfoSender::new(ConsoleInfoSender::new(true),Duration::from_millis(100));
+///
+/// info_sender.send(vec![UsiInfoSubCommand::Depth(1)]).unwrap();
+/// info_sender.send(vec![UsiInfoSubCommand::Depth(2)]).unwrap();
+/// info_sender.flush().unwrap();
+/// ```
+pub struct ThrottledInfoSender<S> where S: InfoSender {
+	info_sender:S,
+	interval:time::Duration,
+	state:Arc<Mutex<InfoThrottleState>>,
+}
+struct InfoThrottleState {
+	last_sent:Option<Instant>,
+	pending:BTreeMap<u32,Vec<UsiInfoSubCommand>>,
+	strings:Vec<Vec<UsiInfoSubCommand>>,
+}
+impl<S> ThrottledInfoSender<S> where S: InfoSender {
+	/// `ThrottledInfoSender`の生成
+	///
+	/// # Arguments
+	/// * `info_sender` - 実際にinfoコマンドを送信する`InfoSender`
+	/// * `interval` - 送信の最小間隔
+	pub fn new(info_sender:S,interval:time::Duration) -> ThrottledInfoSender<S> {
+		ThrottledInfoSender {
+			info_sender,
+			interval,
+			state:Arc::new(Mutex::new(InfoThrottleState {
+				last_sent:None,
+				pending:BTreeMap::new(),
+				strings:Vec::new(),
+			})),
+		}
+	}
+
+	fn merge(prev:Vec<UsiInfoSubCommand>,commands:Vec<UsiInfoSubCommand>) -> Vec<UsiInfoSubCommand> {
+		const ORDER:[UsiInfoSubCommandKind; 10] = [
+			UsiInfoSubCommandKind::Depth,
+			UsiInfoSubCommandKind::SelDepth,
+			UsiInfoSubCommandKind::Time,
+			UsiInfoSubCommandKind::Nodes,
+			UsiInfoSubCommandKind::Nps,
+			UsiInfoSubCommandKind::Hashfull,
+			UsiInfoSubCommandKind::MultiPv,
+			UsiInfoSubCommandKind::Score,
+			UsiInfoSubCommandKind::CurMove,
+			UsiInfoSubCommandKind::Pv,
+		];
+
+		let is_new_line = commands.iter().any(|c| {
+			c.get_kind() == UsiInfoSubCommandKind::Depth || c.get_kind() == UsiInfoSubCommandKind::Pv
+		});
+
+		if prev.is_empty() || is_new_line {
+			return commands;
+		}
+
+		ORDER.iter().filter_map(|&kind| {
+			commands.iter().find(|c| c.get_kind() == kind).or_else(|| {
+				prev.iter().find(|c| c.get_kind() == kind)
+			}).cloned()
+		}).collect()
+	}
+
+	fn send_pending(&mut self,state:&mut InfoThrottleState) -> Result<(), InfoSendError> {
+		let pending = std::mem::take(&mut state.pending);
+
+		for commands in std::mem::take(&mut state.strings).into_iter().chain(pending.into_values()) {
+			self.info_sender.send(commands)?;
+		}
+
+		state.last_sent = Some(Instant::now());
+
+		Ok(())
+	}
+
+	fn push(&mut self,commands:Vec<UsiInfoSubCommand>,immediate:bool) -> Result<(), InfoSendError> {
+		let state = self.state.clone();
+
+		let mut state = state.lock().map_err(|_| {
+			InfoSendError::Fail(String::from("Could not get exclusive lock on info throttle state object."))
+		})?;
+
+		let is_mate = commands.iter().any(|c| matches!(*c,
+			UsiInfoSubCommand::Score(UsiScore::Mate(_)) |
+			UsiInfoSubCommand::Score(UsiScore::MateUpper(_)) |
+			UsiInfoSubCommand::Score(UsiScore::MateLower(_))
+		));
+
+		if commands.iter().any(|c| c.get_kind() == UsiInfoSubCommandKind::Str) {
+			state.strings.push(commands);
+		} else {
+			let index = commands.iter().filter_map(|c| match *c {
+				UsiInfoSubCommand::MultiPv(n) => Some(n),
+				_ => None
+			}).next().unwrap_or(1);
+
+			let prev = state.pending.remove(&index).unwrap_or_default();
+
+			state.pending.insert(index,Self::merge(prev,commands));
+		}
+
+		let elapsed = state.last_sent.map(|t| t.elapsed() >= self.interval).unwrap_or(true);
+
+		if immediate || is_mate || elapsed {
+			self.send_pending(&mut state)?;
+		}
+
+		Ok(())
+	}
+}
+impl<S> InfoSender for ThrottledInfoSender<S> where S: InfoSender {
+	fn send(&mut self,commands:Vec<UsiInfoSubCommand>) -> Result<(), InfoSendError> {
+		self.push(commands,false)
+	}
+
+	fn send_immediate(&mut self, commands: Vec<UsiInfoSubCommand>) -> Result<(), InfoSendError> {
+		self.push(commands,true)?;
+		self.info_sender.flush()
+	}
+
+	fn flush(&mut self) -> Result<(), InfoSendError> {
+		let state = self.state.clone();
+
+		let mut state = state.lock().map_err(|_| {
+			InfoSendError::Fail(String::from("Could not get exclusive lock on info throttle state object."))
+		})?;
+
+		self.send_pending(&mut state)?;
+		self.info_sender.flush()
+	}
+}
+impl<S> Clone for ThrottledInfoSender<S> where S: InfoSender {
+	fn clone(&self) -> ThrottledInfoSender<S> {
+		ThrottledInfoSender {
+			info_sender:self.info_sender.clone(),
+			interval:self.interval,
+			state:self.state.clone(),
+		}
+	}
+}
+impl<S> Drop for ThrottledInfoSender<S> where S: InfoSender {
+	fn drop(&mut self) {
+		if Arc::strong_count(&self.state) == 1 {
+			let _ = self.flush();
+		}
+	}
+}
 /// 初期化処理時にKeepAliveとして空行を送信する
 pub trait KeepAliveSender {
 	/// 空行を送信する
@@ -2,6 +2,7 @@
 use std::fmt;
 use std::error::Error;
 use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
 use event::*;
@@ -9,6 +10,7 @@ use shogi::*;
 use rule::*;
 use command::*;
 use error::*;
+use options::parse_option_value;
 use Validate;
 
 /// USIプロトコル準拠のコマンド文字列
@@ -831,6 +833,80 @@ impl GoParser {
 		)
 	}
 }
+/// setoptionコマンドのパーサ
+#[derive(Default)]
+pub struct SetOptionParser {
+}
+impl SetOptionParser {
+	/// `SetOptionParser`の生成
+	pub fn new() -> SetOptionParser {
+		SetOptionParser{}
+	}
+
+	/// setoptionコマンドの先頭の'setoption'を除いた文字列をパースし、オプションの名前と値を返す
+	///
+	/// `name`と`value`の間をすべてオプションの名前、`value`以降の行の残りをすべて値として扱う。
+	/// 値は`kinds`の種類に変換した上で、`types`にオプションの定義があればspinの範囲とcomboの項目に含まれるかを検証する
+	///
+	/// # Arguments
+	/// * `params` - setoptionコマンドの'setoption'以降の文字列
+	/// * `kinds` - オプションの名前をキーとしたオプションの種類のマップ
+	/// * `types` - オプションの名前をキーとしたオプションの定義のマップ
+	pub fn parse(&self,params:&str,
+				 kinds:&BTreeMap<String,SysEventOptionKind>,
+				 types:&BTreeMap<String,UsiOptType>) -> Result<(String,SysEventOption),SetOptionParseError> {
+		let tokens = SetOptionParser::tokenize(params);
+
+		match tokens.first() {
+			Some(&(_,"name")) => (),
+			_ => {
+				return Err(SetOptionParseError::InvalidFormat(params.trim().to_string()));
+			}
+		}
+
+		let value_index = tokens.iter().skip(1).position(|&(_,t)| t == "value").map(|i| i + 1);
+
+		let name = tokens[1..value_index.unwrap_or(tokens.len())].iter()
+												.map(|&(_,t)| t)
+												.collect::<Vec<&str>>()
+												.join(" ");
+
+		if name.is_empty() {
+			return Err(SetOptionParseError::InvalidFormat(params.trim().to_string()));
+		}
+
+		let value = value_index.map(|i| params[tokens[i].0 + "value".len()..].trim());
+
+		let value = parse_option_value(&name,value.unwrap_or(""),kinds,types)?;
+
+		Ok((name,value))
+	}
+
+	/// 空白で区切られたトークンを元の文字列中の位置とともに返す
+	fn tokenize(s:&str) -> Vec<(usize,&str)> {
+		let mut tokens = Vec::new();
+		let mut start = None;
+
+		for (i,c) in s.char_indices() {
+			match (c.is_whitespace(),start) {
+				(true,Some(st)) => {
+					tokens.push((st,&s[st..i]));
+					start = None;
+				},
+				(false,None) => {
+					start = Some(i);
+				},
+				_ => ()
+			}
+		}
+
+		if let Some(st) = start {
+			tokens.push((st,&s[st..]));
+		}
+
+		tokens
+	}
+}
 impl ToSfen<TypeConvertError<String>> for Banmen {
 	fn to_sfen(&self) -> Result<String,TypeConvertError<String>> {
 		let mut s = String::new();
@@ -1023,6 +1099,11 @@ impl ToSfen<SfenStringConvertError> for (Teban,Banmen,MochigomaCollections,Vec<M
 		})
 	}
 }
+impl ToSfen<SfenStringConvertError> for Handicap {
+	fn to_sfen(&self) -> Result<String,SfenStringConvertError> {
+		(self.teban(),self.banmen(),MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()),Vec::new()).to_sfen()
+	}
+}
 impl ToSfen<UsiOutputCreateError> for CheckMate {
 	fn to_sfen(&self) -> Result<String, UsiOutputCreateError> {
 		Ok(match *self {
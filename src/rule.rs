@@ -2,13 +2,20 @@
 use std::collections::HashMap;
 use std::time::{Instant,Duration};
 use std::convert::TryFrom;
+use std::ops::{Add, AddAssign};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 use chrono::Local;
 use bitboard::BitBoard;
+#[cfg(feature = "sliding-attack-table")]
+use bitboard::SlidingDirection;
 
 use shogi::*;
 use hash::*;
 use error::*;
 use event::*;
+use command::UsiOptType;
 
 use shogi::KomaKind::{
 	SFu,
@@ -70,6 +77,43 @@ impl KomaKindFrom<u32> for ObtainKind {
 		}
 	}
 }
+impl KomaKindFrom<u32> for KomaKind {
+	#[inline]
+	fn kind_from(k:u32) -> KomaKind {
+		match k {
+			0 => SFu,
+			1 => SKyou,
+			2 => SKei,
+			3 => SGin,
+			4 => SKin,
+			5 => SKaku,
+			6 => SHisha,
+			7 => SOu,
+			8 => SFuN,
+			9 => SKyouN,
+			10 => SKeiN,
+			11 => SGinN,
+			12 => SKakuN,
+			13 => SHishaN,
+			14 => GFu,
+			15 => GKyou,
+			16 => GKei,
+			17 => GGin,
+			18 => GKin,
+			19 => GKaku,
+			20 => GHisha,
+			21 => GOu,
+			22 => GFuN,
+			23 => GKyouN,
+			24 => GKeiN,
+			25 => GGinN,
+			26 => GKakuN,
+			27 => GHishaN,
+			28 => Blank,
+			_ => unreachable!(),
+		}
+	}
+}
 impl KomaKindFrom<u32> for MochigomaKind {
 	#[inline]
 	fn kind_from(k:u32) -> MochigomaKind {
@@ -378,6 +422,338 @@ impl From<AppliedMove> for Move {
 		}
 	}
 }
+/// 16bitで表現される指し手
+///
+/// 置換表や定跡、学習データへの保存を想定した固定のフォーマットで、ビットの配置は以下の通り
+/// (位置はいずれも盤面左上を0,0とし、x * 9 + yで表されるインデックス)
+///
+/// * bit 0-6 - 移動先の位置
+/// * bit 7-13 - 移動元の位置（駒を置く手の場合は置く駒の`MochigomaKind`の値）
+/// * bit 14 - 持ち駒を置く手か否か
+/// * bit 15 - 成るか否か
+///
+/// 値が0となる指し手は存在しないため、0を指し手無しを表す値として使うことができる
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Move16(u16);
+impl Move16 {
+	/// 持ち駒を置く手を表すビット
+	const PUT:u16 = 1 << 14;
+	/// 成る手を表すビット
+	const NARI:u16 = 1 << 15;
+
+	/// 盤面上の駒を動かす手の`Move16`を生成
+	///
+	/// # Arguments
+	/// * `src` - 盤面左上を0,0とし、x * 9 + yで表される移動元の位置
+	/// * `dst` - 盤面左上を0,0とし、x * 9 + yで表される移動先の位置
+	/// * `nari` - 成るか否か
+	#[inline]
+	pub fn new_to(src:u32,dst:u32,nari:bool) -> Move16 {
+		let n = if nari {
+			Move16::NARI
+		} else {
+			0
+		};
+
+		Move16(n | (src as u16 & 0b1111111) << 7 | dst as u16 & 0b1111111)
+	}
+
+	/// 持ち駒を置く手の`Move16`を生成
+	///
+	/// # Arguments
+	/// * `kind` - 置く駒の種類
+	/// * `dst` - 盤面左上を0,0とし、x * 9 + yで表される駒を置く位置
+	#[inline]
+	pub fn new_put(kind:MochigomaKind,dst:u32) -> Move16 {
+		Move16(Move16::PUT | (kind as u16) << 7 | dst as u16 & 0b1111111)
+	}
+
+	/// 持ち駒を置く手か否か
+	#[inline]
+	pub fn is_put(&self) -> bool {
+		self.0 & Move16::PUT != 0
+	}
+	/// 成る手か否か
+	#[inline]
+	pub fn is_nari(&self) -> bool {
+		self.0 & Move16::NARI != 0
+	}
+	/// 移動元の左上からx * 9 + yで表されるインデックス（持ち駒を置く手の場合は`None`）
+	#[inline]
+	pub fn src(&self) -> Option<u32> {
+		if self.is_put() {
+			None
+		} else {
+			Some((self.0 as u32 >> 7) & 0b1111111)
+		}
+	}
+	/// 移動先の左上からx * 9 + yで表されるインデックス
+	#[inline]
+	pub fn dst(&self) -> u32 {
+		self.0 as u32 & 0b1111111
+	}
+	/// 置く駒の種類（盤面上の駒を動かす手の場合は`None`）
+	#[inline]
+	pub fn kind(&self) -> Option<MochigomaKind> {
+		if self.is_put() {
+			Some(MochigomaKind::kind_from((self.0 as u32 >> 7) & 0b111))
+		} else {
+			None
+		}
+	}
+
+	/// 局面を元に`LegalMove`へ変換
+	///
+	/// 獲った駒は移動先にある駒から求める。指し手が局面に対して合法か否かは検査しない
+	///
+	/// # Arguments
+	/// * `state` - 指し手を適用する前の盤面の状態
+	#[inline]
+	pub fn to_legal_move(self,state:&State) -> LegalMove {
+		match self.src() {
+			Some(src) => {
+				let (x,y) = self.dst().square_to_point();
+				let obtained = ObtainKind::try_from(state.banmen.0[y as usize][x as usize]).ok();
+
+				LegalMove::To(LegalMoveTo::new(src,self.dst(),self.is_nari(),obtained))
+			},
+			None => {
+				LegalMove::Put(LegalMovePut::new(MochigomaKind::kind_from((self.0 as u32 >> 7) & 0b111),self.dst()))
+			}
+		}
+	}
+	/// `AppliedMove`へ変換
+	#[inline]
+	pub fn to_applied_move(self) -> AppliedMove {
+		AppliedMove::from(self)
+	}
+	/// `Move`へ変換
+	#[inline]
+	pub fn to_move(self) -> Move {
+		Move::from(self)
+	}
+}
+/// 保存された値から`Move16`へ変換
+///
+/// 移動先、移動元が盤面の範囲外の値、置く駒の種類が不正な値、成る持ち駒を置く手はエラーとなる
+impl TryFrom<u16> for Move16 {
+	type Error = TypeConvertError<String>;
+
+	#[inline]
+	fn try_from(v:u16) -> Result<Move16,TypeConvertError<String>> {
+		let m = Move16(v);
+		let src = (v >> 7) & 0b1111111;
+
+		let valid = m.dst() < 81 && if m.is_put() {
+			!m.is_nari() && src as usize <= MOCHIGOMA_KIND_MAX
+		} else {
+			src < 81 && src as u32 != m.dst()
+		};
+
+		if valid {
+			Ok(m)
+		} else {
+			Err(TypeConvertError::LogicError(format!("Invalid value for Move16. (value = {:#06x})",v)))
+		}
+	}
+}
+impl From<Move16> for u16 {
+	#[inline]
+	fn from(m:Move16) -> u16 {
+		m.0
+	}
+}
+impl From<LegalMove> for Move16 {
+	#[inline]
+	fn from(m:LegalMove) -> Move16 {
+		match m {
+			LegalMove::To(m) => Move16::new_to(m.src(),m.dst(),m.is_nari()),
+			LegalMove::Put(m) => Move16::new_put(m.kind(),m.dst())
+		}
+	}
+}
+impl From<AppliedMove> for Move16 {
+	#[inline]
+	fn from(m:AppliedMove) -> Move16 {
+		match m {
+			AppliedMove::To(m) => Move16::new_to(m.src(),m.dst(),m.is_nari()),
+			AppliedMove::Put(m) => Move16::new_put(m.kind(),m.dst())
+		}
+	}
+}
+impl From<Move> for Move16 {
+	#[inline]
+	fn from(m:Move) -> Move16 {
+		Move16::from(AppliedMove::from(m))
+	}
+}
+impl From<Move16> for AppliedMove {
+	#[inline]
+	fn from(m:Move16) -> AppliedMove {
+		match m.src() {
+			Some(src) => {
+				let n = if m.is_nari() {
+					1
+				} else {
+					0
+				};
+
+				AppliedMove::To(AppliedMoveTo(n << 14 | m.dst() << 7 | src))
+			},
+			None => {
+				AppliedMove::Put(AppliedMovePut(m.dst() << 3 | (m.0 as u32 >> 7) & 0b111))
+			}
+		}
+	}
+}
+impl From<Move16> for Move {
+	#[inline]
+	fn from(m:Move16) -> Move {
+		Move::from(AppliedMove::from(m))
+	}
+}
+/// 動かした駒と獲った駒の情報を`Move16`に加えた32bitで表現される指し手
+///
+/// 局面が無くても`LegalMove`へ変換できる。ビットの配置は以下の通り
+///
+/// * bit 0-15 - `Move16`の値
+/// * bit 16-20 - 動かした駒（置いた駒）の`KomaKind`の値
+/// * bit 21-25 - 獲った駒の`KomaKind`の値（駒を獲らない手の場合は`KomaKind::Blank`の値）
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub struct Move32(u32);
+impl Move32 {
+	/// 局面と手番を元に`Move32`を生成
+	///
+	/// 指し手が局面に対して合法か否かは検査しない
+	///
+	/// # Arguments
+	/// * `state` - 指し手を適用する前の盤面の状態
+	/// * `teban` - 指し手を指す側の手番
+	/// * `m` - 指し手
+	pub fn new(state:&State,teban:Teban,m:Move16) -> Move32 {
+		let (dx,dy) = m.dst().square_to_point();
+
+		let (moved,captured) = match m.src() {
+			Some(src) => {
+				let (sx,sy) = src.square_to_point();
+
+				(state.banmen.0[sy as usize][sx as usize],state.banmen.0[dy as usize][dx as usize])
+			},
+			None => {
+				let kind = (m.0 as u32 >> 7) & 0b111;
+
+				let kind = match teban {
+					Teban::Sente => kind,
+					Teban::Gote => kind + GFu as u32
+				};
+
+				(KomaKind::kind_from(kind),Blank)
+			}
+		};
+
+		Move32((captured as u32) << 21 | (moved as u32) << 16 | m.0 as u32)
+	}
+
+	/// `Move16`を取得
+	#[inline]
+	pub fn move16(&self) -> Move16 {
+		Move16(self.0 as u16)
+	}
+	/// 動かした駒（置いた駒）
+	#[inline]
+	pub fn moved(&self) -> KomaKind {
+		KomaKind::kind_from((self.0 >> 16) & 0b11111)
+	}
+	/// 獲った駒
+	#[inline]
+	pub fn captured(&self) -> Option<KomaKind> {
+		match KomaKind::kind_from((self.0 >> 21) & 0b11111) {
+			Blank => None,
+			kind => Some(kind)
+		}
+	}
+	/// `LegalMove`へ変換
+	#[inline]
+	pub fn to_legal_move(self) -> LegalMove {
+		LegalMove::from(self)
+	}
+	/// `AppliedMove`へ変換
+	#[inline]
+	pub fn to_applied_move(self) -> AppliedMove {
+		AppliedMove::from(self)
+	}
+	/// `Move`へ変換
+	#[inline]
+	pub fn to_move(self) -> Move {
+		Move::from(self)
+	}
+}
+/// 保存された値から`Move32`へ変換
+///
+/// 下位16bitが`Move16`として不正な値、動かした駒や獲った駒の種類が不正な値はエラーとなる
+impl TryFrom<u32> for Move32 {
+	type Error = TypeConvertError<String>;
+
+	#[inline]
+	fn try_from(v:u32) -> Result<Move32,TypeConvertError<String>> {
+		let m16 = Move16::try_from(v as u16)?;
+		let moved = (v >> 16) & 0b11111;
+		let captured = (v >> 21) & 0b11111;
+
+		let valid = v >> 26 == 0 && moved < Blank as u32 && captured <= Blank as u32 && if m16.is_put() {
+			captured == Blank as u32 && moved % GFu as u32 == (v >> 7) & 0b111
+		} else {
+			true
+		};
+
+		if valid {
+			Ok(Move32(v))
+		} else {
+			Err(TypeConvertError::LogicError(format!("Invalid value for Move32. (value = {:#010x})",v)))
+		}
+	}
+}
+impl From<Move32> for u32 {
+	#[inline]
+	fn from(m:Move32) -> u32 {
+		m.0
+	}
+}
+impl From<Move32> for Move16 {
+	#[inline]
+	fn from(m:Move32) -> Move16 {
+		m.move16()
+	}
+}
+impl From<Move32> for LegalMove {
+	#[inline]
+	fn from(m:Move32) -> LegalMove {
+		let m16 = m.move16();
+
+		match m16.src() {
+			Some(src) => {
+				let obtained = m.captured().and_then(|k| ObtainKind::try_from(k).ok());
+
+				LegalMove::To(LegalMoveTo::new(src,m16.dst(),m16.is_nari(),obtained))
+			},
+			None => {
+				LegalMove::Put(LegalMovePut::new(MochigomaKind::kind_from((m.0 >> 7) & 0b111),m16.dst()))
+			}
+		}
+	}
+}
+impl From<Move32> for AppliedMove {
+	#[inline]
+	fn from(m:Move32) -> AppliedMove {
+		AppliedMove::from(m.move16())
+	}
+}
+impl From<Move32> for Move {
+	#[inline]
+	fn from(m:Move32) -> Move {
+		Move::from(m.move16())
+	}
+}
 impl Find<(KomaSrcPosition,KomaDstToPosition),Move> for Vec<LegalMove> {
 	#[inline]
 	fn find(&self,query:&(KomaSrcPosition,KomaDstToPosition)) -> Option<Move> {
@@ -632,79 +1008,326 @@ impl State {
 		&self.part
 	}
 }
-/// 合法手の生成に内部で利用するビットボードの集合
-#[derive(Clone, Eq, PartialEq, Debug)]
-pub struct PartialState {
-	/// 先手視点の先手側の駒のビットボード
-	pub sente_self_board:BitBoard,
-	/// 先手視点の後手側の駒のビットボード
-	pub sente_opponent_board:BitBoard,
-	/// 後手視点の後手側の駒のビットボード
-	pub gote_self_board:BitBoard,
-	/// 後手視点の先手側の駒のビットボード
-	pub gote_opponent_board:BitBoard,
-	/// 先手の成っている駒のビットボード
-	pub sente_nari_board:BitBoard,
-	/// 後手の成っている駒のビットボード
-	pub gote_nari_board:BitBoard,
-	/// 先手の歩の位置のビットボード
-	pub sente_fu_board:BitBoard,
-	/// 後手の歩の位置のビットボード
-	pub gote_fu_board:BitBoard,
-	/// 先手の香車の位置のビットボード
-	pub sente_kyou_board:BitBoard,
-	/// 後手の香車の位置のビットボード
-	pub gote_kyou_board:BitBoard,
-	/// 先手の桂馬の位置のビットボード
-	pub sente_kei_board:BitBoard,
-	/// 後手の桂馬の位置のビットボード
-	pub gote_kei_board:BitBoard,
-	/// 先手の銀の位置のビットボード
-	pub sente_gin_board:BitBoard,
-	/// 後手の銀の位置のビットボード
-	pub gote_gin_board:BitBoard,
-	/// 先手の金の位置のビットボード
-	pub sente_kin_board:BitBoard,
-	/// 後手の金の位置のビットボード
-	pub gote_kin_board:BitBoard,
-	/// 先手の角の位置のビットボード
-	pub sente_kaku_board:BitBoard,
-	/// 後手の角の位置のビットボード
-	pub gote_kaku_board:BitBoard,
-	/// 先手の飛車の位置のビットボード
-	pub sente_hisha_board:BitBoard,
-	/// 後手の飛車の位置のビットボード
-	pub gote_hisha_board:BitBoard,
-	/// 先手視点の後手の玉の位置のビットボード
-	pub sente_opponent_ou_position_board:BitBoard,
-	/// 後手視点の先手の王の位置のビットボード
-	pub gote_opponent_ou_position_board:BitBoard,
-	/// 先手側の飛車角香が効いていてその位置にある駒が動くことで素抜けが発生する可能性のある駒の位置のビットボード
-	pub sente_pin_board:BitBoard,
-	/// 後手側の飛車角香が効いていてその位置にある駒が動くことで素抜けが発生する可能性のある駒の位置のビットボード
-	pub gote_pin_board:BitBoard,
-	/// 先手側の駒のうち、後手側の玉に王手をかけている駒の位置のビットボード
-	pub sente_checked_board:BitBoard,
-	/// 後手側の駒のうち、先手側の王に王手をかけている駒の位置のビットボード
-	pub gote_checked_board:BitBoard,
+impl Symmetry for State {
+	fn flip(&self) -> State {
+		State::new(self.banmen.flip())
+	}
+
+	fn mirror(&self) -> State {
+		State::new(self.banmen.mirror())
+	}
 }
-impl PartialState {
-	/// 自身に対応する盤面を引数に受け取り`State`へと変換して返す。
-	///
-	/// 引数に渡した`Banmen`が自身の状態に対して正しくない場合は正しく動作しない。
+/// `State`に手番と持ち駒、局面のハッシュを組み合わせた局面
+///
+/// 手の適用と取り消しの際にハッシュが差分計算で自動的に更新されるため、
+/// 呼び出し側で`KyokumenHash::calc_main_hash`等を手の適用と同期して呼び出す必要がない
+#[derive(Clone)]
+pub struct Position {
+	state:State,
+	teban:Teban,
+	mc:MochigomaCollections,
+	mhash:u64,
+	shash:u64,
+	hasher:Arc<KyokumenHash<u64>>,
+	history:Vec<PositionHistory>,
+}
+/// `Position`に手を適用する前の状態
+#[derive(Clone)]
+struct PositionHistory {
+	state:State,
+	mc:MochigomaCollections,
+	mhash:u64,
+	shash:u64,
+	m:AppliedMove,
+	/// 適用した手が王手だったか否か
+	oute:bool,
+}
+/// 同一局面の出現（千日手）の判定結果
+///
+/// 勝ち負けや優劣は判定を行った局面の手番側から見たもの
+#[derive(Clone, Copy, Eq, PartialOrd, PartialEq, Debug)]
+pub enum RepetitionState {
+	/// 同一局面は現れていない
+	None,
+	/// 千日手（引き分け）
+	Draw,
+	/// 相手の連続王手の千日手（手番側の勝ち）
+	Win,
+	/// 自分の連続王手の千日手（手番側の負け）
+	Lose,
+	/// 盤面が同じで、手番側の持ち駒が以前の局面より多い（優等局面）
+	Superior,
+	/// 盤面が同じで、手番側の持ち駒が以前の局面より少ない（劣等局面）
+	Inferior,
+}
+impl Position {
+	/// `Position`の生成
 	///
 	/// # Arguments
-	/// * `banmen` - 自身に対応する盤面
-	#[inline]
-	pub fn to_full_state(&self,banmen:Banmen) -> State {
-		State {
-			banmen:banmen,
-			part:self.clone()
+	/// * `state` - 盤面の状態
+	/// * `teban` - 手番
+	/// * `mc` - 持ち駒
+	/// * `hasher` - 局面のハッシュを計算するためのオブジェクト
+	pub fn new(state:State,teban:Teban,mc:MochigomaCollections,hasher:Arc<KyokumenHash<u64>>) -> Position {
+		let (mhash,shash) = Position::calc_board_hash(&hasher,&state,&mc);
+
+		Position {
+			state,
+			teban,
+			mc,
+			mhash,
+			shash,
+			hasher,
+			history:Vec::new(),
 		}
 	}
 
-	/// pin駒（飛車、角、香から相手の王への利きの途中に効きを遮る駒が一つだけあるとき、その駒）のビットボードを更新する
-	#[inline]
+	fn calc_board_hash(hasher:&KyokumenHash<u64>,state:&State,mc:&MochigomaCollections) -> (u64,u64) {
+		match *mc {
+			MochigomaCollections::Pair(ref ms,ref mg) => {
+				hasher.calc_initial_hash(&state.banmen,ms,mg)
+			},
+			MochigomaCollections::Empty => {
+				hasher.calc_initial_hash(&state.banmen,&Mochigoma::new(),&Mochigoma::new())
+			}
+		}
+	}
+
+	/// 盤面の状態への不変な参照を返す
+	#[inline]
+	pub fn get_state(&self) -> &State {
+		&self.state
+	}
+
+	/// 手番を返す
+	#[inline]
+	pub fn teban(&self) -> Teban {
+		self.teban
+	}
+
+	/// 持ち駒への不変な参照を返す
+	#[inline]
+	pub fn get_mc(&self) -> &MochigomaCollections {
+		&self.mc
+	}
+
+	/// 盤面と持ち駒から計算したハッシュ（第一キー,第二キー）を返す（手番を含まないため、`KyokumenMap`のキーとして使える）
+	#[inline]
+	pub fn board_hash(&self) -> (u64,u64) {
+		(self.mhash,self.shash)
+	}
+
+	/// 手番、盤面、持ち駒を含めた局面のハッシュ（第一キー,第二キー）を返す
+	#[inline]
+	pub fn hash(&self) -> (u64,u64) {
+		(self.hasher.calc_main_hash_with_teban(self.mhash,self.teban),
+		 self.hasher.calc_sub_hash_with_teban(self.shash,self.teban))
+	}
+
+	/// 局面のハッシュを差分計算を使わずに全て計算しなおして返す
+	///
+	/// `hash`の返す値と一致しない場合は差分計算に不整合が発生している
+	pub fn calc_hash(&self) -> (u64,u64) {
+		let (mhash,shash) = Position::calc_board_hash(&self.hasher,&self.state,&self.mc);
+
+		(self.hasher.calc_main_hash_with_teban(mhash,self.teban),
+		 self.hasher.calc_sub_hash_with_teban(shash,self.teban))
+	}
+
+	/// これまでに適用した手の数（`undo_move`で取り消せる手の数）を返す
+	#[inline]
+	pub fn ply(&self) -> usize {
+		self.history.len()
+	}
+
+	/// 局面に手を適用して獲った駒を返す。合法手か否かのチェックは行わない。
+	///
+	/// # Arguments
+	/// * `m` - 適用する手
+	/// `AppliedMove`の状態が不正な時の動作は未定義
+	pub fn apply_move_none_check(&mut self,m:AppliedMove) -> Option<MochigomaKind> {
+		let (next,nmc,o) = Rule::apply_move_none_check(&self.state,self.teban,&self.mc,m);
+
+		let mhash = self.hasher.calc_main_hash(self.mhash,self.teban,&self.state.banmen,&self.mc,m,&o);
+		let shash = self.hasher.calc_sub_hash(self.shash,self.teban,&self.state.banmen,&self.mc,m,&o);
+
+		let oute = Rule::in_check(self.teban,&next);
+
+		let state = std::mem::replace(&mut self.state,next);
+		let mc = std::mem::replace(&mut self.mc,nmc);
+
+		self.history.push(PositionHistory {
+			state,
+			mc,
+			mhash:self.mhash,
+			shash:self.shash,
+			m,
+			oute,
+		});
+
+		self.mhash = mhash;
+		self.shash = shash;
+		self.teban = self.teban.opposite();
+
+		debug_assert_eq!(self.hash(),self.calc_hash(),"The incrementally updated hash is out of sync.");
+
+		o
+	}
+
+	/// 局面に手を適用して獲った駒を返す。
+	///
+	/// # Arguments
+	/// * `m` - 適用する手
+	///
+	/// # Errors
+	///
+	/// この関数は以下のエラーを返すケースがあります。
+	/// * [`InvalidState`] 手が合法手でない
+	///
+	/// [`InvalidState`]: ../error/enum.ShogiError.html#variant.InvalidState
+	pub fn apply_valid_move(&mut self,m:AppliedMove) -> Result<Option<MochigomaKind>,ShogiError> {
+		if !Rule::is_valid_move(&self.state,self.teban,&self.mc,m) {
+			Err(ShogiError::InvalidState(String::from(
+				"This is not legal move."
+			)))
+		} else {
+			Ok(self.apply_move_none_check(m))
+		}
+	}
+
+	/// 最後に適用した手を取り消して、取り消した手を返す（適用した手が無い場合はNone）
+	pub fn undo_move(&mut self) -> Option<AppliedMove> {
+		self.history.pop().map(|h| {
+			self.state = h.state;
+			self.mc = h.mc;
+			self.mhash = h.mhash;
+			self.shash = h.shash;
+			self.teban = self.teban.opposite();
+
+			debug_assert_eq!(self.hash(),self.calc_hash(),"The incrementally updated hash is out of sync.");
+
+			h.m
+		})
+	}
+
+	/// 直近の手を遡って現在の局面と同じ手番の局面を探し、同一局面の出現の種類を返す
+	///
+	/// 対局中の手と探索中に適用した手の区別なく、この`Position`に適用された手の履歴全体が対象となる。
+	/// 同一局面が一度でも現れていれば千日手として扱い、最も近い局面の結果を返す
+	///
+	/// # Arguments
+	/// * `max_ply` - 遡る手数の上限
+	pub fn repetition_state(&self,max_ply:usize) -> RepetitionState {
+		let n = self.history.len();
+		let limit = max_ply.min(n);
+
+		for i in (4..=limit).step_by(2) {
+			let h = &self.history[n - i];
+
+			if h.mhash == self.mhash && h.shash == self.shash {
+				if (n - i + 1..n).step_by(2).all(|j| self.history[j].oute) {
+					return RepetitionState::Win;
+				} else if (n - i..n).step_by(2).all(|j| self.history[j].oute) {
+					return RepetitionState::Lose;
+				} else {
+					return RepetitionState::Draw;
+				}
+			} else if h.state.banmen == self.state.banmen {
+				let current = Position::mochigoma_of(&self.mc,self.teban);
+				let prev = Position::mochigoma_of(&h.mc,self.teban);
+
+				if MOCHIGOMA_KINDS.iter().all(|&k| current.get(k) >= prev.get(k)) {
+					return RepetitionState::Superior;
+				} else if MOCHIGOMA_KINDS.iter().all(|&k| current.get(k) <= prev.get(k)) {
+					return RepetitionState::Inferior;
+				}
+			}
+		}
+
+		RepetitionState::None
+	}
+
+	fn mochigoma_of(mc:&MochigomaCollections,teban:Teban) -> Mochigoma {
+		match (mc,teban) {
+			(MochigomaCollections::Pair(ms,_),Teban::Sente) => ms.clone(),
+			(MochigomaCollections::Pair(_,mg),Teban::Gote) => mg.clone(),
+			(MochigomaCollections::Empty,_) => Mochigoma::new(),
+		}
+	}
+}
+/// 合法手の生成に内部で利用するビットボードの集合
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PartialState {
+	/// 先手視点の先手側の駒のビットボード
+	pub sente_self_board:BitBoard,
+	/// 先手視点の後手側の駒のビットボード
+	pub sente_opponent_board:BitBoard,
+	/// 後手視点の後手側の駒のビットボード
+	pub gote_self_board:BitBoard,
+	/// 後手視点の先手側の駒のビットボード
+	pub gote_opponent_board:BitBoard,
+	/// 先手の成っている駒のビットボード
+	pub sente_nari_board:BitBoard,
+	/// 後手の成っている駒のビットボード
+	pub gote_nari_board:BitBoard,
+	/// 先手の歩の位置のビットボード
+	pub sente_fu_board:BitBoard,
+	/// 後手の歩の位置のビットボード
+	pub gote_fu_board:BitBoard,
+	/// 先手の香車の位置のビットボード
+	pub sente_kyou_board:BitBoard,
+	/// 後手の香車の位置のビットボード
+	pub gote_kyou_board:BitBoard,
+	/// 先手の桂馬の位置のビットボード
+	pub sente_kei_board:BitBoard,
+	/// 後手の桂馬の位置のビットボード
+	pub gote_kei_board:BitBoard,
+	/// 先手の銀の位置のビットボード
+	pub sente_gin_board:BitBoard,
+	/// 後手の銀の位置のビットボード
+	pub gote_gin_board:BitBoard,
+	/// 先手の金の位置のビットボード
+	pub sente_kin_board:BitBoard,
+	/// 後手の金の位置のビットボード
+	pub gote_kin_board:BitBoard,
+	/// 先手の角の位置のビットボード
+	pub sente_kaku_board:BitBoard,
+	/// 後手の角の位置のビットボード
+	pub gote_kaku_board:BitBoard,
+	/// 先手の飛車の位置のビットボード
+	pub sente_hisha_board:BitBoard,
+	/// 後手の飛車の位置のビットボード
+	pub gote_hisha_board:BitBoard,
+	/// 先手視点の後手の玉の位置のビットボード
+	pub sente_opponent_ou_position_board:BitBoard,
+	/// 後手視点の先手の王の位置のビットボード
+	pub gote_opponent_ou_position_board:BitBoard,
+	/// 先手側の飛車角香が効いていてその位置にある駒が動くことで素抜けが発生する可能性のある駒の位置のビットボード
+	pub sente_pin_board:BitBoard,
+	/// 後手側の飛車角香が効いていてその位置にある駒が動くことで素抜けが発生する可能性のある駒の位置のビットボード
+	pub gote_pin_board:BitBoard,
+	/// 先手側の駒のうち、後手側の玉に王手をかけている駒の位置のビットボード
+	pub sente_checked_board:BitBoard,
+	/// 後手側の駒のうち、先手側の王に王手をかけている駒の位置のビットボード
+	pub gote_checked_board:BitBoard,
+}
+impl PartialState {
+	/// 自身に対応する盤面を引数に受け取り`State`へと変換して返す。
+	///
+	/// 引数に渡した`Banmen`が自身の状態に対して正しくない場合は正しく動作しない。
+	///
+	/// # Arguments
+	/// * `banmen` - 自身に対応する盤面
+	#[inline]
+	pub fn to_full_state(&self,banmen:Banmen) -> State {
+		State {
+			banmen:banmen,
+			part:self.clone()
+		}
+	}
+
+	/// pin駒（飛車、角、香から相手の王への利きの途中に効きを遮る駒が一つだけあるとき、その駒）のビットボードを更新する
+	#[inline]
 	fn update_pin(&mut self) {
 		let mut sente_pin_board = BitBoard::default();
 		let mut gote_pin_board = BitBoard::default();
@@ -1186,6 +1809,7 @@ const CANDIDATE_BITS:[u128; 14] = [
 	// 成飛(一マスだけ進める手だけここに定義)
 	0b000001010_000000000_000001010
 ];
+#[cfg(not(feature = "sliding-attack-table"))]
 const TO_RIGHT_TOP_MASK:[u128;17] = [
 	0b000000000_000000000_000000000_000000000_000000000_000000000_000000000_000000000_000000001,
 	0b000000000_000000000_000000000_000000000_000000000_000000000_000000000_000000001_000000010,
@@ -1205,6 +1829,7 @@ const TO_RIGHT_TOP_MASK:[u128;17] = [
 	0b010000000_000000001_000000000_000000000_000000000_000000000_000000000_000000000_000000000,
 	0b100000000_000000000_000000000_000000000_000000000_000000000_000000000_000000000_000000000,
 ];
+#[cfg(not(feature = "sliding-attack-table"))]
 const TO_RIGHT_BOTTOM_MASK:[u128;17] = [
 	0b100000000_010000000_001000000_000100000_000010000_000001000_000000100_000000010_000000001,
 	0b000000000_100000000_010000000_001000000_000100000_000010000_000001000_000000100_000000010,
@@ -1224,6 +1849,7 @@ const TO_RIGHT_BOTTOM_MASK:[u128;17] = [
 	0b000000010_000000001_000000000_000000000_000000000_000000000_000000000_000000000_000000000,
 	0b000000001_000000000_000000000_000000000_000000000_000000000_000000000_000000000_000000000,
 ];
+#[cfg(not(feature = "sliding-attack-table"))]
 const KAKU_TO_RIGHT_TOP_MASK_MAP:[u128;81] = [
 	TO_RIGHT_TOP_MASK[0],
 	TO_RIGHT_TOP_MASK[1],
@@ -1315,6 +1941,7 @@ const KAKU_TO_RIGHT_TOP_MASK_MAP:[u128;81] = [
 	TO_RIGHT_TOP_MASK[15],
 	TO_RIGHT_TOP_MASK[16],
 ];
+#[cfg(not(feature = "sliding-attack-table"))]
 const KAKU_TO_RIGHT_BOTTOM_MASK_MAP:[u128;81] = [
 	TO_RIGHT_BOTTOM_MASK[0],
 	TO_RIGHT_BOTTOM_MASK[1],
@@ -1406,7 +2033,9 @@ const KAKU_TO_RIGHT_BOTTOM_MASK_MAP:[u128;81] = [
 	TO_RIGHT_BOTTOM_MASK[9],
 	TO_RIGHT_BOTTOM_MASK[0],
 ];
+#[cfg(not(feature = "sliding-attack-table"))]
 const V_MASK: u128 = 0b111111111;
+#[cfg(not(feature = "sliding-attack-table"))]
 const H_MASK: u128 = 0b000000001_000000001_000000001_000000001_000000001_000000001_000000001_000000001_000000001;
 const TOP_MASK: u128 = 0b111111100_111111100_111111100;
 const BOTTOM_MASK: u128 = 0b000000111_000000111_000000111;
@@ -1445,6 +2074,98 @@ pub const BANMEN_START_POS:Banmen = Banmen([
 	[Blank,SKaku,Blank,Blank,Blank,Blank,Blank,SHisha,Blank],
 	[SKyou,SKei,SGin,SKin,SOu,SKin,SGin,SKei,SKyou],
 ]);
+/// 駒落ちの種類
+///
+/// 駒を落とすのは上手（後手）で、初期局面では上手である後手から指し始める
+#[derive(Clone, Copy, Eq, PartialOrd, PartialEq, Debug, Hash)]
+pub enum Handicap {
+	/// 香落ち（1一の香を落とす）
+	KyouOchi,
+	/// 右香落ち（9一の香を落とす）
+	MigiKyouOchi,
+	/// 角落ち
+	KakuOchi,
+	/// 飛車落ち
+	HishaOchi,
+	/// 飛香落ち
+	HiKyouOchi,
+	/// 二枚落ち
+	NimaiOchi,
+	/// 三枚落ち
+	SanmaiOchi,
+	/// 四枚落ち
+	YonmaiOchi,
+	/// 五枚落ち
+	GomaiOchi,
+	/// 六枚落ち
+	RokumaiOchi,
+	/// 七枚落ち
+	NanamaiOchi,
+	/// 八枚落ち
+	HachimaiOchi,
+	/// 十枚落ち
+	JumaiOchi,
+}
+/// 全ての駒落ちの種類
+pub const HANDICAPS:[Handicap; 13] = [
+	Handicap::KyouOchi,
+	Handicap::MigiKyouOchi,
+	Handicap::KakuOchi,
+	Handicap::HishaOchi,
+	Handicap::HiKyouOchi,
+	Handicap::NimaiOchi,
+	Handicap::SanmaiOchi,
+	Handicap::YonmaiOchi,
+	Handicap::GomaiOchi,
+	Handicap::RokumaiOchi,
+	Handicap::NanamaiOchi,
+	Handicap::HachimaiOchi,
+	Handicap::JumaiOchi,
+];
+impl Handicap {
+	/// 平手初期局面から取り除く後手の駒の位置（左上を(0,0)とした(x,y)）
+	fn removed_positions(&self) -> &'static [(usize,usize)] {
+		match *self {
+			Handicap::KyouOchi => &[(8,0)],
+			Handicap::MigiKyouOchi => &[(0,0)],
+			Handicap::KakuOchi => &[(7,1)],
+			Handicap::HishaOchi => &[(1,1)],
+			Handicap::HiKyouOchi => &[(1,1),(8,0)],
+			Handicap::NimaiOchi => &[(1,1),(7,1)],
+			Handicap::SanmaiOchi => &[(1,1),(7,1),(8,0)],
+			Handicap::YonmaiOchi => &[(1,1),(7,1),(0,0),(8,0)],
+			Handicap::GomaiOchi => &[(1,1),(7,1),(0,0),(8,0),(7,0)],
+			Handicap::RokumaiOchi => &[(1,1),(7,1),(0,0),(8,0),(7,0),(1,0)],
+			Handicap::NanamaiOchi => &[(1,1),(7,1),(0,0),(8,0),(7,0),(1,0),(6,0)],
+			Handicap::HachimaiOchi => &[(1,1),(7,1),(0,0),(8,0),(7,0),(1,0),(6,0),(2,0)],
+			Handicap::JumaiOchi => &[(1,1),(7,1),(0,0),(8,0),(7,0),(1,0),(6,0),(2,0),(3,0),(5,0)],
+		}
+	}
+
+	/// 駒落ちの初期局面の盤面を取得
+	pub fn banmen(&self) -> Banmen {
+		let mut banmen = BANMEN_START_POS.clone();
+
+		for &(x,y) in self.removed_positions() {
+			banmen.0[y][x] = Blank;
+		}
+
+		banmen
+	}
+
+	/// 駒落ちの初期局面で最初に指す側の手番（常に上手である後手）
+	pub fn teban(&self) -> Teban {
+		Teban::Gote
+	}
+
+	/// 盤面がいずれかの駒落ちの初期局面と一致すればその駒落ちの種類を返す
+	///
+	/// # Arguments
+	/// * `banmen` - 盤面
+	pub fn from_banmen(banmen:&Banmen) -> Option<Handicap> {
+		HANDICAPS.iter().find(|h| h.banmen() == *banmen).copied()
+	}
+}
 /// 指し手の列挙を定義するトレイト
 pub trait GenerateStrategy {
 	type Environment;
@@ -5874,6 +6595,80 @@ impl Validate for Moved {
 		}
 	}
 }
+/// 不正な局面が指定された時の扱い
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidPositionPolicy {
+	/// 局面を検証しない
+	#[default]
+	Ignore,
+	/// 不正をログに出力した上でそのまま受け付ける
+	Log,
+	/// 不正をログに出力して局面を受け付けない
+	Reject,
+}
+/// 入玉宣言の規則
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NyugyokuRule {
+	/// 入玉宣言を認めない
+	NoEnteringKing,
+	/// 24点法（31点以上で勝ち、24点以上30点以下で引き分け）
+	Csa24,
+	/// 27点法（先手は28点以上、後手は27点以上で勝ち）
+	#[default]
+	Csa27,
+	/// トライルール（玉が相手の玉の初期位置に移動できれば勝ち）
+	TryRule,
+}
+impl NyugyokuRule {
+	/// 入玉宣言の規則を指定する標準のUSIオプションの名前
+	pub const OPTION_NAME:&'static str = "EnteringKingRule";
+
+	/// USIオプションの値の文字列表現
+	pub fn to_usi_option_value(&self) -> &'static str {
+		match *self {
+			NyugyokuRule::NoEnteringKing => "NoEnteringKing",
+			NyugyokuRule::Csa24 => "CSARule24",
+			NyugyokuRule::Csa27 => "CSARule27",
+			NyugyokuRule::TryRule => "TryRule",
+		}
+	}
+
+	/// `EnteringKingRule`オプションの定義（combo）を返す
+	///
+	/// # Arguments
+	/// * `default` - オプションのデフォルト値
+	pub fn usi_option(default:NyugyokuRule) -> UsiOptType {
+		UsiOptType::Combo(Some(default.to_usi_option_value().to_string()),
+			[NyugyokuRule::NoEnteringKing,NyugyokuRule::Csa24,NyugyokuRule::Csa27,NyugyokuRule::TryRule]
+				.iter().map(|r| r.to_usi_option_value().to_string()).collect()
+		)
+	}
+}
+impl<'a> TryFrom<&'a str> for NyugyokuRule {
+	type Error = TypeConvertError<String>;
+
+	fn try_from(s:&'a str) -> Result<NyugyokuRule,TypeConvertError<String>> {
+		Ok(match s {
+			"NoEnteringKing" => NyugyokuRule::NoEnteringKing,
+			"CSARule24" => NyugyokuRule::Csa24,
+			"CSARule27" => NyugyokuRule::Csa27,
+			"TryRule" => NyugyokuRule::TryRule,
+			_ => {
+				return Err(TypeConvertError::SyntaxError(s.to_string()));
+			}
+		})
+	}
+}
+/// 入玉宣言の点数計算の結果
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NyugyokuPoint {
+	/// 玉が敵陣に入っているか否か
+	pub ou_entered:bool,
+	/// 敵陣にある玉以外の駒の枚数
+	pub count:usize,
+	/// 敵陣にある玉以外の駒と持ち駒の点数（大駒5点、小駒1点）
+	pub point:usize,
+}
 /// 合法手の列挙等を行う将棋のルールを管理
 pub struct Rule {
 
@@ -7409,6 +8204,7 @@ impl Rule {
 		mvs.into()
 	}
 
+	#[cfg(not(feature = "sliding-attack-table"))]
 	#[inline]
 	fn gen_candidate_bits_by_kaku_to_right_top_include(
 		self_occupied:BitBoard,
@@ -7427,6 +8223,16 @@ impl Rule {
 		BitBoard::from(candidate)
 	}
 
+	#[cfg(feature = "sliding-attack-table")]
+	#[inline]
+	fn gen_candidate_bits_by_kaku_to_right_top_include(
+		self_occupied:BitBoard,
+		opponent_occupied:BitBoard,
+		from:u32
+	) -> BitBoard {
+		BitBoard::sliding_attacks_by_table(SlidingDirection::RightTop,from,self_occupied | opponent_occupied)
+	}
+
 	#[inline]
 	fn gen_candidate_bits_by_kaku_to_right_top_with_exclude(
 		self_occupied:BitBoard,
@@ -7454,6 +8260,7 @@ impl Rule {
 		Rule::gen_candidate_bits_by_kaku_to_right_top_with_exclude(self_occupied,opponent_occupied,self_occupied,from)
 	}
 
+	#[cfg(not(feature = "sliding-attack-table"))]
 	#[inline]
 	fn gen_candidate_bits_by_kaku_to_right_bottom_include(
 		self_occupied:BitBoard,
@@ -7472,6 +8279,16 @@ impl Rule {
 		BitBoard::from(candidate)
 	}
 
+	#[cfg(feature = "sliding-attack-table")]
+	#[inline]
+	fn gen_candidate_bits_by_kaku_to_right_bottom_include(
+		self_occupied:BitBoard,
+		opponent_occupied:BitBoard,
+		from:u32
+	) -> BitBoard {
+		BitBoard::sliding_attacks_by_table(SlidingDirection::RightBottom,from,self_occupied | opponent_occupied)
+	}
+
 	#[inline]
 	fn gen_candidate_bits_by_kaku_to_right_bottom_with_exclude(
 		self_occupied:BitBoard,
@@ -7672,6 +8489,7 @@ impl Rule {
 						move_builder, mvs).unwrap();
 	}
 
+	#[cfg(not(feature = "sliding-attack-table"))]
 	#[inline]
 	fn gen_candidate_bits_by_hisha_or_kyou_to_top_include(
 		flip_self_occupied:BitBoard,
@@ -7690,6 +8508,16 @@ impl Rule {
 		BitBoard::from(candidate)
 	}
 
+	#[cfg(feature = "sliding-attack-table")]
+	#[inline]
+	fn gen_candidate_bits_by_hisha_or_kyou_to_top_include(
+		flip_self_occupied:BitBoard,
+		flip_opponent_occupied:BitBoard,
+		from:u32
+	) -> BitBoard {
+		BitBoard::sliding_attacks_by_table(SlidingDirection::Bottom,from,flip_self_occupied | flip_opponent_occupied)
+	}
+
 	#[inline]
 	fn gen_candidate_bits_by_hisha_or_kyou_to_top_with_exclude(
 		flip_self_occupied:BitBoard,
@@ -7718,6 +8546,7 @@ impl Rule {
 		Rule::gen_candidate_bits_by_hisha_or_kyou_to_top_with_exclude(flip_self_occupied,flip_opponent_occupied,flip_self_occupied,from)
 	}
 
+	#[cfg(not(feature = "sliding-attack-table"))]
 	#[inline]
 	fn gen_candidate_bits_by_hisha_to_right_include(
 		self_occupied:BitBoard,
@@ -7738,6 +8567,16 @@ impl Rule {
 		BitBoard::from(candidate)
 	}
 
+	#[cfg(feature = "sliding-attack-table")]
+	#[inline]
+	fn gen_candidate_bits_by_hisha_to_right_include(
+		self_occupied:BitBoard,
+		opponent_occupied:BitBoard,
+		from:u32
+	) -> BitBoard {
+		BitBoard::sliding_attacks_by_table(SlidingDirection::Right,from,self_occupied | opponent_occupied)
+	}
+
 	#[inline]
 	fn gen_candidate_bits_by_hisha_to_right_with_exclude(
 		self_occupied:BitBoard,
@@ -9491,52 +10330,443 @@ impl Rule {
 			}).collect::<Vec<LegalMove>>()
 	}
 
-	/// 盤面の状態を管理するビットボードを手を適用した状態に更新して返す
+	/// 手番と盤面の状態と持ち駒を元に一手詰めの手を探して返す
 	///
 	/// # Arguments
 	/// * `state` - 盤面の状態
-	/// * `t` - 手を列挙したい手番
-	/// * `m` - 適用する手
-	/// `State`もしくは`AppliedMove`の状態が不正な時の動作は未定義
+	/// * `t` - 詰ませる側の手番
+	/// * `mc` - 持ち駒
+	///
+	/// 相手の王の周囲と桂馬で王手をかけられる位置への駒打ちと駒の移動を候補とし、
+	/// 盤上の駒ごとの利きを一度だけ求めて使い回し、手を適用せずに王の逃げ場所、王手をかけている駒の取り、合駒の有無を判定する。
+	/// 開き王手や離れた位置からの王手は対象外。打ち歩詰めとなる手は除外される。詰みが見つからない場合や既に相手の王を取れる場合は`None`を返す。
+	/// `State`もしくは`MochigomaCollections`の状態が不正な時の動作は未定義
 	/// # Examples
 	/// ```
 	/// use usiagent::rule::*;
 	/// use usiagent::shogi::*;
-	/// let state = State::new(BANMEN_START_POS.clone());
-	/// let m = Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false)).to_applied_move();
-	/// let p = Rule::apply_move_to_partial_state_none_check(&state,Teban::Sente,&MochigomaCollections::Empty,m);
-	/// let mut banmen = BANMEN_START_POS.clone();
-	/// banmen.0[6][8] = KomaKind::Blank;
-	/// banmen.0[5][8] = KomaKind::SFu;
-	/// assert_eq!(State::new(banmen).get_part(),&p);
+	/// let mut banmen = Banmen([[KomaKind::Blank; 9]; 9]);
+	/// banmen.0[0][4] = KomaKind::GOu;
+	/// banmen.0[2][4] = KomaKind::SFu;
+	/// banmen.0[8][4] = KomaKind::SOu;
+	/// let mut ms = Mochigoma::new();
+	/// ms.insert(MochigomaKind::Kin,1);
+	/// let mc = MochigomaCollections::Pair(ms,Mochigoma::new());
+	/// let m = Rule::mate_1ply(&State::new(banmen),Teban::Sente,&mc);
+	/// assert_eq!(m.map(|m| m.to_move()),Some(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(5,2))));
 	/// ```
-	#[inline]
-	pub fn apply_move_to_partial_state_none_check(state:&State,t:Teban,_:&MochigomaCollections,m:AppliedMove)
-		-> PartialState {
-		let mut ps = state.part.clone();
-
-		match &state.banmen {
-			&Banmen(ref kinds) => {
-				match m {
-					AppliedMove::To(m) => {
-						let from = m.src();
-						let (sx,sy) = from.square_to_point();
-						let to = m.dst();
-						let nari = m.is_nari();
+	pub fn mate_1ply(state:&State,t:Teban,mc:&MochigomaCollections) -> Option<LegalMove> {
+		let ou = Rule::ou_square(t.opposite(),state);
 
-						let from_mask = 1 << (from + 1);
+		if ou == -1 {
+			return None;
+		}
 
-						let to_mask = 1 << (to + 1);
+		let occupied = Rule::occupied_board(state);
 
-						let inverse_from_mask = 1 << (80 - from + 1);
+		// 盤上の駒ごとの利きはここで一度だけ求め、各候補手の判定で使い回す
+		let attackers = Rule::piece_attacks(state,t,occupied);
+		let defenders = Rule::piece_attacks(state,t.opposite(),occupied);
 
-						let inverse_to_mask = 1 << (80 - to + 1);
+		if Rule::attackers_in(&attackers,BitBoard::default(),occupied,ou) != 0 {
+			return None;
+		}
 
-						let kind = kinds[sy as usize][sx as usize];
+		let (ou_kind,kei_kind) = if t == Teban::Sente {
+			(GOu,GKei)
+		} else {
+			(SOu,SKei)
+		};
 
-						let obtained = if kind < GFu {
-							ps.sente_self_board = ps.sente_self_board ^ (from_mask | to_mask);
-							ps.gote_opponent_board = ps.gote_opponent_board ^ (inverse_from_mask | inverse_to_mask);
+		// 王の周囲8マスと、王の位置から見て桂馬の利きとなる位置（王手をかけられる桂馬の位置）
+		let targets = (Rule::gen_attack_bits(ou_kind,ou as u32,occupied) |
+					   Rule::gen_attack_bits(kei_kind,ou as u32,occupied)) & BANMEN_MASK;
+
+		let hand = match *mc {
+			MochigomaCollections::Pair(ref ms,ref mg) => {
+				Some(if t == Teban::Sente { ms } else { mg })
+			},
+			MochigomaCollections::Empty => None
+		};
+
+		// 歩を打って詰ませる手は打ち歩詰めとなるため候補に含めない
+		if let Some(hand) = hand {
+			for &kind in &[MochigomaKind::Hisha,MochigomaKind::Kaku,MochigomaKind::Kin,MochigomaKind::Gin,
+						   MochigomaKind::Kei,MochigomaKind::Kyou] {
+				if hand.get(kind) == 0 {
+					continue;
+				}
+
+				let k = KomaKind::from((t,kind));
+
+				for to in (targets & !occupied).iter() {
+					if Rule::gen_attack_bits(k,to as u32,occupied) & (1 << (ou + 1)) == 0 {
+						continue;
+					}
+
+					let m = LegalMove::Put(LegalMovePut::new(kind,to as u32));
+
+					if Rule::is_mate_move(state,t,mc,m,ou,&attackers,&defenders) {
+						return Some(m);
+					}
+				}
+			}
+		}
+
+		let self_board = if t == Teban::Sente {
+			state.part.sente_self_board
+		} else {
+			state.part.sente_opponent_board
+		};
+
+		for &(from,kind,attack) in &attackers {
+			if kind == SOu || kind == GOu {
+				continue;
+			}
+
+			for to in (attack & !self_board & targets).iter() {
+				let (dx,dy) = to.square_to_point();
+				let obtained = ObtainKind::try_from(state.banmen.0[dy as usize][dx as usize]).ok();
+
+				let nari_options:&[bool] = if !Rule::is_possible_nari(kind,from,to) {
+					&[false]
+				} else if Rule::is_deny_move(kind,to) {
+					&[true]
+				} else {
+					&[true,false]
+				};
+
+				for &nari in nari_options {
+					let nk = if nari { kind.to_nari() } else { kind };
+
+					if Rule::gen_attack_bits(nk,to as u32,(occupied ^ (1 << (from + 1))) | (1 << (to + 1))) &
+						(1 << (ou + 1)) == 0 {
+						continue;
+					}
+
+					let m = LegalMove::To(LegalMoveTo::new(from as u32,to as u32,nari,obtained));
+
+					if Rule::is_mate_move(state,t,mc,m,ou,&attackers,&defenders) {
+						return Some(m);
+					}
+				}
+			}
+		}
+
+		None
+	}
+
+	/// 手番と盤面の状態と持ち駒を元に三手以内の詰みの初手を探して返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `t` - 詰ませる側の手番
+	/// * `mc` - 持ち駒
+	///
+	/// 王手に対する相手の全ての応手に対して`Rule::mate_1ply`で詰みが見つかる王手を返す。
+	/// 一手詰めがある場合はその手を優先して返す。
+	/// `State`もしくは`MochigomaCollections`の状態が不正な時の動作は未定義
+	pub fn mate_3ply(state:&State,t:Teban,mc:&MochigomaCollections) -> Option<LegalMove> {
+		if let Some(m) = Rule::mate_1ply(state,t,mc) {
+			return Some(m);
+		}
+
+		Rule::oute_only_moves_all(t,state,mc).into_iter().find(|&m| {
+			if let LegalMove::To(mv) = m {
+				if mv.obtained() == Some(ObtainKind::Ou) {
+					return false;
+				}
+			}
+
+			let am = m.to_applied_move();
+			let (next,nmc,_) = Rule::apply_move_none_check(state,t,mc,am);
+
+			if Rule::is_put_fu_and_mate(&next,t,&nmc,am) {
+				return false;
+			}
+
+			Rule::respond_oute_only_moves_all(t.opposite(),&next,&nmc).into_iter().all(|r| {
+				if let LegalMove::To(rm) = r {
+					if rm.obtained() == Some(ObtainKind::Ou) {
+						return false;
+					}
+				}
+
+				let (next,nmc,_) = Rule::apply_move_none_check(&next,t.opposite(),&nmc,r.to_applied_move());
+
+				Rule::mate_1ply(&next,t,&nmc).is_some()
+			})
+		})
+	}
+
+	/// 手番側の歩を指定した位置に打つと二歩になるかを返す
+	///
+	/// `removed`の位置の駒は盤上に無いものとして扱う
+	fn is_nifu(state:&State,t:Teban,to:Square,removed:Square) -> bool {
+		let (x,_) = to.square_to_point();
+		let fu = if t == Teban::Sente { SFu } else { GFu };
+
+		(0..9).any(|y| (x * 9 + y) as Square != removed && state.banmen.0[y as usize][x as usize] == fu)
+	}
+
+	/// 駒が指定した位置に不成で移動する（もしくは打たれる）と行き所のない駒になるかを返す
+	fn is_deny_move(kind:KomaKind,to:Square) -> bool {
+		let (_,y) = to.square_to_point();
+
+		match kind {
+			SFu | SKyou => y == 0,
+			SKei => y <= 1,
+			GFu | GKyou => y == 8,
+			GKei => y >= 7,
+			_ => false
+		}
+	}
+
+	/// 香車、角、飛車（成駒を含む）のように駒の配置で利きが遮られる駒かを返す
+	fn is_sliding(kind:KomaKind) -> bool {
+		matches!(kind,SKyou | SKaku | SKakuN | SHisha | SHishaN | GKyou | GKaku | GKakuN | GHisha | GHishaN)
+	}
+
+	/// 手番側の盤上の駒ごとに位置、種類、`occupied`の配置での利きを列挙する
+	fn piece_attacks(state:&State,t:Teban,occupied:BitBoard) -> Vec<(Square,KomaKind,BitBoard)> {
+		let board = if t == Teban::Sente {
+			state.part.sente_self_board
+		} else {
+			state.part.sente_opponent_board
+		};
+
+		board.iter().map(|p| {
+			let (x,y) = p.square_to_point();
+			let kind = state.banmen.0[y as usize][x as usize];
+
+			(p,kind,Rule::gen_attack_bits(kind,p as u32,occupied))
+		}).collect()
+	}
+
+	/// `piece_attacks`で求めた利きのうち、`excluded`の位置の駒を除いて`to`に利いている駒の位置を返す
+	///
+	/// 飛び駒の利きのみ`occupied`の配置で求め直す
+	fn attackers_in(pieces:&[(Square,KomaKind,BitBoard)],excluded:BitBoard,occupied:BitBoard,to:Square) -> BitBoard {
+		let to_bit:u128 = 1 << (to + 1);
+		let mut attackers = BitBoard::default();
+
+		for &(p,kind,attack) in pieces {
+			let p_bit:u128 = 1 << (p + 1);
+
+			if excluded & p_bit != 0 {
+				continue;
+			}
+
+			let attack = if Rule::is_sliding(kind) {
+				Rule::gen_attack_bits(kind,p as u32,occupied)
+			} else {
+				attack
+			};
+
+			if attack & to_bit != 0 {
+				attackers |= p_bit;
+			}
+		}
+
+		attackers
+	}
+
+	/// `piece_attacks`で求めた利きのうち、`excluded`の位置の駒を除いた利きの和を返す
+	///
+	/// 飛び駒の利きのみ`occupied`の配置で求め直す
+	fn control_in(pieces:&[(Square,KomaKind,BitBoard)],excluded:BitBoard,occupied:BitBoard) -> BitBoard {
+		let mut control = BitBoard::default();
+
+		for &(p,kind,attack) in pieces {
+			if excluded & (1 << (p + 1)) != 0 {
+				continue;
+			}
+
+			control |= if Rule::is_sliding(kind) {
+				Rule::gen_attack_bits(kind,p as u32,occupied)
+			} else {
+				attack
+			};
+		}
+
+		control
+	}
+
+	/// 王手の手を指した結果が詰みであるかを、`mate_1ply`で求めた駒ごとの利きから手を適用せずに判定する
+	///
+	/// 王の逃げ場所、王手をかけている駒を取る手、合駒を順に調べ、いずれも無い場合に詰みと判定する。
+	/// 歩を打つ手は渡されない前提で、打ち歩詰めの判定は行わない
+	fn is_mate_move(state:&State,t:Teban,mc:&MochigomaCollections,m:LegalMove,ou:Square,
+					attackers:&[(Square,KomaKind,BitBoard)],defenders:&[(Square,KomaKind,BitBoard)]) -> bool {
+		let (from_bit,to,kind):(u128,Square,KomaKind) = match m {
+			LegalMove::To(m) => {
+				let from = m.src() as Square;
+				let (x,y) = from.square_to_point();
+				let kind = state.banmen.0[y as usize][x as usize];
+
+				(1 << (from + 1),m.dst() as Square,if m.is_nari() { kind.to_nari() } else { kind })
+			},
+			LegalMove::Put(m) => {
+				(0,m.dst() as Square,KomaKind::from((t,m.kind())))
+			}
+		};
+
+		let to_bit:u128 = 1 << (to + 1);
+		let ou_bit:u128 = 1 << (ou + 1);
+		let moved = BitBoard::from(from_bit);
+		let captured = BitBoard::from(to_bit);
+		let occupied = (Rule::occupied_board(state) ^ from_bit) | to_bit;
+
+		let (defender_board,ou_kind) = if t == Teban::Sente {
+			(state.part.sente_opponent_board & !to_bit,GOu)
+		} else {
+			(state.part.sente_self_board & !to_bit,SOu)
+		};
+
+		// 自玉に利きが残る（もしくは生じる）手は指せない
+		let self_ou = Rule::ou_square(t,state);
+
+		if self_ou != -1 && Rule::attackers_in(defenders,captured,occupied,self_ou) != 0 {
+			return false;
+		}
+
+		// 王の逃げ場所。王が移動した後の配置で利きを調べるため、王を取り除いた配置を使う
+		let escape_occupied = occupied ^ ou_bit;
+		let control = Rule::control_in(attackers,moved,escape_occupied) |
+					  Rule::gen_attack_bits(kind,to as u32,escape_occupied);
+
+		if Rule::gen_attack_bits(ou_kind,ou as u32,occupied) & !defender_board & !control & BANMEN_MASK != 0 {
+			return false;
+		}
+
+		// 動かした駒以外の王手（開き王手）があれば両王手となり、王が逃げる以外の応手は無い
+		if Rule::attackers_in(attackers,moved,occupied,ou) != 0 {
+			return true;
+		}
+
+		let (ox,oy) = ou.square_to_point();
+		let (cx,cy) = to.square_to_point();
+		let (dx,dy) = (ox as i32 - cx as i32,oy as i32 - cy as i32);
+
+		// 王と王手をかけている駒の間のマス（合駒の位置）
+		let between = if dx == 0 || dy == 0 {
+			Rule::gen_attack_bits(SHisha,ou as u32,occupied) & Rule::gen_attack_bits(SHisha,to as u32,occupied)
+		} else if dx.abs() == dy.abs() {
+			Rule::gen_attack_bits(SKaku,ou as u32,occupied) & Rule::gen_attack_bits(SKaku,to as u32,occupied)
+		} else {
+			BitBoard::default()
+		} & BANMEN_MASK;
+
+		// 王以外の駒による王手駒の取りと合駒
+		for &(from,k,attack) in defenders {
+			let from_bit:u128 = 1 << (from + 1);
+
+			if k == SOu || k == GOu || from_bit == to_bit {
+				continue;
+			}
+
+			let attack = if Rule::is_sliding(k) {
+				Rule::gen_attack_bits(k,from as u32,occupied)
+			} else {
+				attack
+			};
+
+			for dst in (attack & (between | to_bit)).iter() {
+				let dst_bit:u128 = 1 << (dst + 1);
+				let occupied = (occupied ^ from_bit) | dst_bit;
+
+				// 応手の後に王に利きが残るか（pinされた駒を動かしていないか）
+				if Rule::attackers_in(attackers,moved,occupied,ou) != 0 {
+					continue;
+				}
+
+				if dst != to && Rule::gen_attack_bits(kind,to as u32,occupied) & ou_bit != 0 {
+					continue;
+				}
+
+				return false;
+			}
+		}
+
+		if between == 0 {
+			return true;
+		}
+
+		let hand = match *mc {
+			MochigomaCollections::Pair(ref ms,ref mg) => {
+				if t == Teban::Sente { mg } else { ms }
+			},
+			MochigomaCollections::Empty => {
+				return true;
+			}
+		};
+
+		let opponent = t.opposite();
+
+		for dst in between.iter() {
+			for &k in &[MochigomaKind::Kin,MochigomaKind::Gin,MochigomaKind::Kaku,MochigomaKind::Hisha,
+						MochigomaKind::Kyou,MochigomaKind::Kei,MochigomaKind::Fu] {
+				if hand.get(k) == 0 || Rule::is_deny_move(KomaKind::from((opponent,k)),dst) {
+					continue;
+				}
+
+				if k == MochigomaKind::Fu && Rule::is_nifu(state,opponent,dst,to) {
+					continue;
+				}
+
+				return false;
+			}
+		}
+
+		true
+	}
+
+	/// 盤面の状態を管理するビットボードを手を適用した状態に更新して返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `t` - 手を列挙したい手番
+	/// * `m` - 適用する手
+	/// `State`もしくは`AppliedMove`の状態が不正な時の動作は未定義
+	/// # Examples
+	/// ```
+	/// use usiagent::rule::*;
+	/// use usiagent::shogi::*;
+	/// let state = State::new(BANMEN_START_POS.clone());
+	/// let m = Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false)).to_applied_move();
+	/// let p = Rule::apply_move_to_partial_state_none_check(&state,Teban::Sente,&MochigomaCollections::Empty,m);
+	/// let mut banmen = BANMEN_START_POS.clone();
+	/// banmen.0[6][8] = KomaKind::Blank;
+	/// banmen.0[5][8] = KomaKind::SFu;
+	/// assert_eq!(State::new(banmen).get_part(),&p);
+	/// ```
+	#[inline]
+	pub fn apply_move_to_partial_state_none_check(state:&State,t:Teban,_:&MochigomaCollections,m:AppliedMove)
+		-> PartialState {
+		let mut ps = state.part.clone();
+
+		match &state.banmen {
+			&Banmen(ref kinds) => {
+				match m {
+					AppliedMove::To(m) => {
+						let from = m.src();
+						let (sx,sy) = from.square_to_point();
+						let to = m.dst();
+						let nari = m.is_nari();
+
+						let from_mask = 1 << (from + 1);
+
+						let to_mask = 1 << (to + 1);
+
+						let inverse_from_mask = 1 << (80 - from + 1);
+
+						let inverse_to_mask = 1 << (80 - to + 1);
+
+						let kind = kinds[sy as usize][sx as usize];
+
+						let obtained = if kind < GFu {
+							ps.sente_self_board = ps.sente_self_board ^ (from_mask | to_mask);
+							ps.gote_opponent_board = ps.gote_opponent_board ^ (inverse_from_mask | inverse_to_mask);
 
 							if nari {
 								ps.sente_nari_board = ps.sente_nari_board ^ to_mask;
@@ -10934,7 +12164,7 @@ impl Rule {
 		(teban,state,mc,r)
 	}
 
-	/// 入玉宣言勝ちが成立しているかどうかを返す
+	/// 入玉宣言勝ちが成立しているかどうかを返す（27点法）
 	///
 	/// # Arguments
 	/// * `state` - 盤面の状態
@@ -10944,153 +12174,163 @@ impl Rule {
 	/// `State`の状態が不正な場合の動作は未定義
 	#[inline]
 	pub fn is_nyugyoku_win(state:&State,t:Teban,mc:&MochigomaCollections,limit:&Option<Instant>) -> bool {
-		if Rule::in_check(t.opposite(), state) {
-			return false
-		}
+		Rule::judge_nyugyoku(state,t,mc,limit,NyugyokuRule::Csa27) == GameEndState::Win
+	}
 
+	/// 入玉宣言を行った時の勝敗を規則に従って判定する
+	///
+	/// 宣言の条件を満たさない場合は`GameEndState::Lose`、24点法で24点以上30点以下の場合は`GameEndState::Draw`を返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `t` - 宣言を行う手番
+	/// * `mc` - 持ち駒
+	/// * `limit` - 持ち時間を使い切った時点の時間
+	/// * `rule` - 入玉宣言の規則
+	///
+	/// `State`の状態が不正な場合の動作は未定義
+	pub fn judge_nyugyoku(state:&State,t:Teban,mc:&MochigomaCollections,limit:&Option<Instant>,rule:NyugyokuRule) -> GameEndState {
 		if let &Some(limit) = limit {
 			if limit > Instant::now() {
-				return false;
+				return GameEndState::Lose;
 			}
 		}
 
-		match t {
-			Teban::Sente => {
-				if state.part.gote_opponent_ou_position_board & NYUGYOKU_MASK << 1 == 0 {
-					return false;
+		match rule {
+			NyugyokuRule::NoEnteringKing => GameEndState::Lose,
+			NyugyokuRule::TryRule => {
+				if Rule::is_try_possible(state,t,mc) {
+					GameEndState::Win
+				} else {
+					GameEndState::Lose
 				}
 			},
-			Teban::Gote => {
-				if state.part.sente_opponent_ou_position_board & NYUGYOKU_MASK << 1 == 0 {
-					return false;
+			NyugyokuRule::Csa24 | NyugyokuRule::Csa27 => {
+				if Rule::in_check(t.opposite(), state) {
+					return GameEndState::Lose;
 				}
-			},
-		}
-
-		match t {
-			Teban::Sente => {
-				let ou_bitboard = state.part.gote_opponent_ou_position_board;
 
-				let ou_position = ou_bitboard.iter().next();
+				let p = Rule::nyugyoku_point(state,t,mc);
 
-				let ou_position = if let Some(ou_position) = ou_position {
-					ou_position
-				} else {
-					return false;
-				};
-
-				let ou_position = 80 - ou_position;
-				let ou_bitboard:u128 = 1 << (ou_position + 1);
-
-				let mut count = 0;
-				let mut point = 0;
-
-				let sente_occupied_board = state.part.sente_self_board;
-				let sente_hisha_board = state.part.sente_hisha_board;
-				let sente_kaku_board = state.part.sente_kaku_board;
-
-				let sente_occupied_board = sente_occupied_board &
-						!(sente_hisha_board | sente_kaku_board | ou_bitboard) & (SENTE_NARI_MASK << 1);
-
-				let sente_oogoma_board = (sente_hisha_board | sente_kaku_board) & (SENTE_NARI_MASK << 1);
-
-				{
-					let sum = sente_occupied_board.iter().count();
-					count += sum;
-					point += sum;
+				if !p.ou_entered || p.count < 10 {
+					return GameEndState::Lose;
 				}
 
-				{
-					let sum = sente_oogoma_board.iter().count();
-
-					count += sum;
-					point += sum * 5;
+				match (rule,t) {
+					(NyugyokuRule::Csa24,_) if p.point >= 31 => GameEndState::Win,
+					(NyugyokuRule::Csa24,_) if p.point >= 24 => GameEndState::Draw,
+					(NyugyokuRule::Csa27,Teban::Sente) if p.point >= 28 => GameEndState::Win,
+					(NyugyokuRule::Csa27,Teban::Gote) if p.point >= 27 => GameEndState::Win,
+					_ => GameEndState::Lose
 				}
+			}
+		}
+	}
 
-				if count < 10 {
-					return false;
-				} else if point >= 28 {
-					return true;
-				}
+	/// 入玉宣言の点数を計算する
+	///
+	/// 探索中に宣言の見込みを評価する用途を想定しているため、玉が敵陣に入っていない場合も点数を計算して返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `t` - 点数を計算する手番
+	/// * `mc` - 持ち駒
+	pub fn nyugyoku_point(state:&State,t:Teban,mc:&MochigomaCollections) -> NyugyokuPoint {
+		let (ou_entered,ou_board,self_board,hisha_board,kaku_board,camp_mask,hand) = match t {
+			Teban::Sente => (
+				state.part.gote_opponent_ou_position_board & NYUGYOKU_MASK << 1 != 0,
+				state.part.gote_opponent_ou_position_board.reverse(),
+				state.part.sente_self_board,
+				state.part.sente_hisha_board,
+				state.part.sente_kaku_board,
+				SENTE_NARI_MASK << 1,
+				match *mc {
+					MochigomaCollections::Pair(ref ms, _) => Some(ms),
+					MochigomaCollections::Empty => None
+				}
+			),
+			Teban::Gote => (
+				state.part.sente_opponent_ou_position_board & NYUGYOKU_MASK << 1 != 0,
+				state.part.sente_opponent_ou_position_board,
+				state.part.sente_opponent_board,
+				state.part.gote_hisha_board,
+				state.part.gote_kaku_board,
+				GOTE_NARI_MASK << 1,
+				match *mc {
+					MochigomaCollections::Pair(_, ref mg) => Some(mg),
+					MochigomaCollections::Empty => None
+				}
+			)
+		};
 
-				point += match mc {
-					&MochigomaCollections::Pair(ref mc, _) => {
-						mc.iter().map(|(k,count)| {
-							match k {
-								MochigomaKind::Hisha | MochigomaKind::Kaku => {
-									count * 5
-								},
-								_ => {
-									count
-								}
-							}
-						}).fold(0, |sum,s| sum + s)
-					},
-					&MochigomaCollections::Empty => {
-						0
-					}
-				};
+		let occupied_board = self_board & !(hisha_board | kaku_board | ou_board) & camp_mask;
+		let oogoma_board = (hisha_board | kaku_board) & camp_mask;
 
-				point >= 28
-			},
-			Teban::Gote => {
-				let ou_bitboard = state.part.sente_opponent_ou_position_board;
+		let count = occupied_board.bitcount() + oogoma_board.bitcount();
+		let mut point = occupied_board.bitcount() + oogoma_board.bitcount() * 5;
 
-				if ou_bitboard == 0 {
-					return false;
+		point += hand.map_or(0, |m| {
+			m.iter().map(|(k,count)| {
+				match k {
+					MochigomaKind::Hisha | MochigomaKind::Kaku => {
+						count * 5
+					},
+					_ => {
+						count
+					}
 				}
+			}).sum::<usize>()
+		});
 
-				let mut count = 0;
-				let mut point = 0;
+		NyugyokuPoint {
+			ou_entered,
+			count,
+			point
+		}
+	}
 
-				let gote_occupied_board = state.part.sente_opponent_board;
-				let gote_hisha_board = state.part.gote_hisha_board;
-				let gote_kaku_board = state.part.gote_kaku_board;
+	/// トライルールで玉が相手の玉の初期位置へ移動できるか否か
+	fn is_try_possible(state:&State,t:Teban,mc:&MochigomaCollections) -> bool {
+		let target = match t {
+			Teban::Sente => 4 * 9,
+			Teban::Gote => 4 * 9 + 8
+		};
 
-				let gote_occupied_board = gote_occupied_board &
-						!(gote_hisha_board | gote_kaku_board | ou_bitboard) & (GOTE_NARI_MASK << 1);
+		let ou = match t {
+			Teban::Sente => SOu,
+			Teban::Gote => GOu
+		};
 
-				let gote_oogoma_board = (gote_hisha_board | gote_kaku_board) & (GOTE_NARI_MASK << 1);
+		let mut buffer = RandomPicker::new(Prng::new(0));
 
-				{
-					let sum = gote_occupied_board.iter().count();
-					count += sum;
-					point += sum;
-				}
+		let r = if Rule::in_check(t.opposite(),state) {
+			Rule::generate_moves::<EvasionsAll>(t,state,mc,&mut buffer)
+		} else {
+			Rule::generate_moves::<NonEvasionsAll>(t,state,mc,&mut buffer)
+		};
 
-				{
-					let sum = gote_oogoma_board.iter().count();
-					count += sum;
-					point += sum * 5;
-				}
+		if r.is_err() {
+			return false;
+		}
 
-				if count < 10 {
-					return false;
-				} else if point >= 27 {
-					return true;
-				}
+		let mvs:Vec<LegalMove> = buffer.into();
 
-				point += match mc {
-					&MochigomaCollections::Pair(_, ref mc) => {
-						mc.iter().map(|(k,count)| {
-							match k {
-								MochigomaKind::Hisha | MochigomaKind::Kaku => {
-									count * 5
-								},
-								_ => {
-									count
-								}
-							}
-						}).fold(0, |sum,s| sum + s)
-					},
-					&MochigomaCollections::Empty => {
-						0
+		mvs.into_iter().any(|m| {
+			match m {
+				LegalMove::To(mt) if mt.dst() == target => {
+					let (x,y) = mt.src().square_to_point();
+
+					if state.banmen.0[y as usize][x as usize] != ou {
+						return false;
 					}
-				};
 
-				point >= 27
+					let (next,_,_) = Rule::apply_move_none_check(state,t,mc,m.to_applied_move());
+
+					!Rule::in_check(t.opposite(),&next)
+				},
+				_ => false
 			}
-		}
+		})
 	}
 
 	/// 王手に応じたか否か
@@ -12771,6 +14011,255 @@ impl Rule {
 		count
 	}
 
+	/// 盤面上の駒の配置を表すビットボードを返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	///
+	/// 返されるビットボードは先手側から見た配置になる
+	#[inline]
+	pub fn occupied_board(state:&State) -> BitBoard {
+		state.part.sente_self_board | state.part.sente_opponent_board
+	}
+
+	/// 任意の駒の配置のもとで、盤面上の駒が利いている位置をビットボードに列挙
+	///
+	/// # Arguments
+	/// * `kind` - 駒の種類
+	/// * `from` - 盤面の左上を0,0とし、x * 9 + yで表される駒の位置。常に先手側から見た位置になる（後手の場合も逆さまにならない）
+	/// * `occupied` - 先手側から見た盤面上の駒の配置を表すビットボード。香車、角、飛車の利きはこの配置で遮られる
+	///
+	/// 返されるビットボードは先手側から見た配置で、利いている位置に駒があるかどうかに関わらずビットが立つ。
+	/// 渡した引数の状態が不正な場合の動作は未定義
+	pub fn gen_attack_bits(kind:KomaKind,from:u32,occupied:BitBoard) -> BitBoard {
+		if kind >= Blank {
+			return BitBoard::default();
+		}
+
+		let flip_occupied = occupied.reverse();
+
+		let mut board = if kind < GFu {
+			Rule::gen_control_bits(from,kind)
+		} else {
+			Rule::gen_control_bits(80 - from,kind).reverse()
+		};
+
+		match kind {
+			SKyou => {
+				board |= Rule::gen_candidate_bits_by_hisha_or_kyou_to_top_include(
+					flip_occupied,BitBoard::default(),80 - from
+				).reverse();
+			},
+			GKyou => {
+				board |= Rule::gen_candidate_bits_by_hisha_or_kyou_to_top_include(
+					occupied,BitBoard::default(),from
+				);
+			},
+			SKaku | SKakuN | GKaku | GKakuN => {
+				board |= Rule::gen_candidate_bits_by_kaku_to_right_top_include(
+					occupied,BitBoard::default(),from
+				);
+				board |= Rule::gen_candidate_bits_by_kaku_to_right_bottom_include(
+					occupied,BitBoard::default(),from
+				);
+				board |= Rule::gen_candidate_bits_by_kaku_to_right_top_include(
+					flip_occupied,BitBoard::default(),80 - from
+				).reverse();
+				board |= Rule::gen_candidate_bits_by_kaku_to_right_bottom_include(
+					flip_occupied,BitBoard::default(),80 - from
+				).reverse();
+			},
+			SHisha | SHishaN | GHisha | GHishaN => {
+				board |= Rule::gen_candidate_bits_by_hisha_or_kyou_to_top_include(
+					occupied,BitBoard::default(),from
+				);
+				board |= Rule::gen_candidate_bits_by_hisha_to_right_include(
+					occupied,BitBoard::default(),from
+				);
+				board |= Rule::gen_candidate_bits_by_hisha_or_kyou_to_top_include(
+					flip_occupied,BitBoard::default(),80 - from
+				).reverse();
+				board |= Rule::gen_candidate_bits_by_hisha_to_right_include(
+					flip_occupied,BitBoard::default(),80 - from
+				).reverse();
+			},
+			_ => ()
+		}
+
+		board
+	}
+
+	/// 指定した位置に利いている駒の位置をビットボードに列挙
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `teban` - 利きを調べる側の手番
+	/// * `to` - 効きを調べたい位置
+	/// * `occupied` - 先手側から見た盤面上の駒の配置を表すビットボード
+	///
+	/// `occupied`に含まれない駒は取り除かれたものとして扱われるため、
+	/// 駒を取り除いた配置を渡すことで飛び駒の背後からの利き（X線）を列挙できる。
+	/// 返されるビットボードは先手側から見た配置になる
+	pub fn attackers_to(state:&State,teban:Teban,to:Square,occupied:BitBoard) -> BitBoard {
+		let pieces = if teban == Teban::Sente {
+			state.part.sente_self_board
+		} else {
+			state.part.sente_opponent_board
+		} & occupied;
+
+		let mut attackers = BitBoard::default();
+
+		for p in pieces.iter() {
+			let (x,y) = p.square_to_point();
+			let kind = state.banmen.0[y as usize][x as usize];
+
+			if Rule::gen_attack_bits(kind,p as u32,occupied) & (1 << (to + 1)) != 0 {
+				attackers |= 1 << (p + 1);
+			}
+		}
+
+		attackers
+	}
+
+	/// 盤面上の駒が移動できる位置をビットボードに列挙
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `from` - 盤面の左上を0,0とし、x * 9 + yで表される駒の位置
+	///
+	/// 自駒のある位置を除いた利きを返す。王手や行き所のない駒の制約は考慮しない。
+	/// 返されるビットボードは先手側から見た配置になる
+	pub fn mobility(state:&State,from:Square) -> BitBoard {
+		let (x,y) = from.square_to_point();
+		let kind = state.banmen.0[y as usize][x as usize];
+
+		let self_board = if kind < GFu {
+			state.part.sente_self_board
+		} else if kind < Blank {
+			state.part.sente_opponent_board
+		} else {
+			return BitBoard::default();
+		};
+
+		Rule::gen_attack_bits(kind,from as u32,Rule::occupied_board(state)) & !self_board
+	}
+
+	/// 局面が規則上あり得るものか検証し、見つかった不正をすべて返す
+	///
+	/// 二歩、行き所のない駒、王の数、駒の枚数、手番ではない側の王に王手がかかっていないかを検証する
+	///
+	/// # Arguments
+	/// * `banmen` - 盤面
+	/// * `teban` - 手番
+	/// * `mc` - 持ち駒
+	///
+	/// # Examples
+	/// ```
+	/// use usiagent::rule::{Rule, BANMEN_START_POS};
+	/// use usiagent::shogi::{Banmen, Mochigoma, MochigomaCollections, Teban};
+	///
+	/// let mc = MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new());
+	///
+	/// assert!(Rule::validate_position(&BANMEN_START_POS,Teban::Sente,&mc).is_ok());
+	///
+	/// let mut kinds = BANMEN_START_POS.0;
+	/// kinds[0][4] = kinds[8][4];
+	///
+	/// assert!(Rule::validate_position(&Banmen(kinds),Teban::Sente,&mc).is_err());
+	/// ```
+	pub fn validate_position(banmen:&Banmen,teban:Teban,mc:&MochigomaCollections)
+		-> Result<(),Vec<PositionValidationError>> {
+		let mut errors = Vec::new();
+
+		let mut ou_count = [0,0];
+		let mut counts = [0; MOCHIGOMA_KIND_MAX + 1];
+		let mut fu_files = [[false; 9]; 2];
+
+		for (y,row) in banmen.0.iter().enumerate() {
+			for (x,&kind) in row.iter().enumerate() {
+				if kind == Blank {
+					continue;
+				}
+
+				let (t,index) = if kind < GFu {
+					(Teban::Sente,0)
+				} else {
+					(Teban::Gote,1)
+				};
+
+				let file = 9 - x as u32;
+				let rank = y as u32 + 1;
+
+				match kind {
+					SOu | GOu => {
+						ou_count[index] += 1;
+					},
+					kind => {
+						if let Ok(k) = MochigomaKind::try_from(kind) {
+							counts[k as usize] += 1;
+						}
+					}
+				}
+
+				let dead = match kind {
+					SFu | SKyou => y == 0,
+					SKei => y <= 1,
+					GFu | GKyou => y == 8,
+					GKei => y >= 7,
+					_ => false,
+				};
+
+				if dead {
+					errors.push(PositionValidationError::DeadPiece(kind,file,rank));
+				}
+
+				if kind == SFu || kind == GFu {
+					if fu_files[index][x] {
+						errors.push(PositionValidationError::Nifu(t,file));
+					} else {
+						fu_files[index][x] = true;
+					}
+				}
+			}
+		}
+
+		if let MochigomaCollections::Pair(ref ms,ref mg) = *mc {
+			for m in &[ms,mg] {
+				for (k,c) in m.iter() {
+					counts[k as usize] += c;
+				}
+			}
+		}
+
+		for (&t,&count) in [Teban::Sente,Teban::Gote].iter().zip(ou_count.iter()) {
+			if count != 1 {
+				errors.push(PositionValidationError::InvalidOuCount(t,count));
+			}
+		}
+
+		for &k in MOCHIGOMA_KINDS.iter() {
+			if counts[k as usize] > MOCHIGOMA_MAX_COUNT[k as usize] as usize {
+				errors.push(PositionValidationError::TooManyPieces(k,counts[k as usize]));
+			}
+		}
+
+		let total = counts.iter().sum::<usize>() + ou_count[0] + ou_count[1];
+
+		if total > 40 {
+			errors.push(PositionValidationError::TooManyPiecesTotal(total));
+		}
+
+		if ou_count == [1,1] && Rule::in_check(teban,&State::new(banmen.clone())) {
+			errors.push(PositionValidationError::OpponentInCheck(teban.opposite()));
+		}
+
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(errors)
+		}
+	}
+
 	/// 駒が成れる手か判定する
 	///
 	/// # Arguments
@@ -12991,7 +14480,326 @@ impl Rule {
 
 		m
 	}
+
+	/// 指定した深さまでの合法手の数え上げ(perft)を行い、末端の局面の数などを返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `teban` - 手番
+	/// * `mc` - 持ち駒
+	/// * `depth` - 探索する深さ
+	///
+	/// スレッドの数やキャッシュを指定したい場合は`Perft`を利用する。
+	/// `State`もしくは`MochigomaCollections`の状態が不正な時の動作は未定義
+	/// # Examples
+	/// ```
+	/// use usiagent::rule::*;
+	/// use usiagent::shogi::*;
+	/// let state = State::new(BANMEN_START_POS.clone());
+	/// let mc = MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new());
+	/// assert_eq!(Rule::perft(&state,Teban::Sente,&mc,2).nodes,900);
+	/// ```
+	pub fn perft(state:&State,teban:Teban,mc:&MochigomaCollections,depth:usize) -> PerftResult {
+		Perft::new().perft(state,teban,mc,depth)
+	}
+
+	/// 初手ごとに指定した深さまでの合法手の数え上げ(perft)を行った結果を返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `teban` - 手番
+	/// * `mc` - 持ち駒
+	/// * `depth` - 探索する深さ(初手を含む)
+	///
+	/// `State`もしくは`MochigomaCollections`の状態が不正な時の動作は未定義
+	pub fn divide(state:&State,teban:Teban,mc:&MochigomaCollections,depth:usize) -> Vec<(LegalMove,PerftResult)> {
+		Perft::new().divide(state,teban,mc,depth)
+	}
+}
+/// perftの集計結果
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PerftResult {
+	/// 末端の局面の数
+	pub nodes:usize,
+	/// 末端の局面へ至る手のうち駒を取る手の数
+	pub captures:usize,
+	/// 末端の局面へ至る手のうち駒が成る手の数
+	pub promotions:usize,
+	/// 末端の局面のうち王手がかかっている局面の数
+	pub checks:usize,
+	/// 末端の局面のうち詰んでいる局面の数
+	pub mates:usize
+}
+impl Add for PerftResult {
+	type Output = PerftResult;
+
+	fn add(self, rhs: PerftResult) -> PerftResult {
+		PerftResult {
+			nodes: self.nodes + rhs.nodes,
+			captures: self.captures + rhs.captures,
+			promotions: self.promotions + rhs.promotions,
+			checks: self.checks + rhs.checks,
+			mates: self.mates + rhs.mates
+		}
+	}
+}
+impl AddAssign for PerftResult {
+	fn add_assign(&mut self, rhs: PerftResult) {
+		*self = *self + rhs;
+	}
+}
+/// perft実行中に探索済みの部分木の結果を保持するキャッシュ
+struct PerftCache {
+	map:KyokumenMap<u64,(usize,PerftResult)>,
+	len:usize,
+	capacity:usize
+}
+impl PerftCache {
+	fn new(capacity:usize) -> PerftCache {
+		PerftCache {
+			map:KyokumenMap::new(),
+			len:0,
+			capacity
+		}
+	}
+
+	fn get(&self,teban:Teban,mhash:u64,shash:u64,depth:usize) -> Option<PerftResult> {
+		match self.map.get(teban,&mhash,&shash) {
+			Some(&(d,r)) if d == depth => Some(r),
+			_ => None
+		}
+	}
+
+	fn insert(&mut self,teban:Teban,mhash:u64,shash:u64,depth:usize,r:PerftResult) {
+		if self.len < self.capacity && self.map.insert(teban,mhash,shash,(depth,r)).is_none() {
+			self.len += 1;
+		}
+	}
+}
+/// 合法手の数え上げ(perft)を行う
+///
+/// 初手で探索を分割して複数のスレッドで実行することと、
+/// `KyokumenHash`をキーとしたキャッシュを用いて同一局面の探索を省略することができる
+pub struct Perft {
+	threads:usize,
+	hasher:Option<Arc<KyokumenHash<u64>>>,
+	cache_size:usize
+}
+impl Perft {
+	/// シングルスレッドでキャッシュを利用しない`Perft`を生成する
+	pub fn new() -> Perft {
+		Perft {
+			threads:1,
+			hasher:None,
+			cache_size:0
+		}
+	}
+
+	/// 探索に利用するスレッドの数を設定する
+	///
+	/// # Arguments
+	/// * `threads` - スレッドの数(0の場合は1として扱う)
+	pub fn threads(self,threads:usize) -> Perft {
+		Perft {
+			threads:threads.max(1),
+			..self
+		}
+	}
+
+	/// 探索済みの部分木の結果をキャッシュするように設定する
+	///
+	/// # Arguments
+	/// * `hasher` - 局面のハッシュを計算するためのオブジェクト
+	/// * `cache_size` - スレッドごとにキャッシュする局面の最大数
+	pub fn cache(self,hasher:Arc<KyokumenHash<u64>>,cache_size:usize) -> Perft {
+		Perft {
+			hasher:Some(hasher),
+			cache_size,
+			..self
+		}
+	}
+
+	/// 指定した深さまでの合法手の数え上げを行い、末端の局面の数などを返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `teban` - 手番
+	/// * `mc` - 持ち駒
+	/// * `depth` - 探索する深さ
+	pub fn perft(&self,state:&State,teban:Teban,mc:&MochigomaCollections,depth:usize) -> PerftResult {
+		if depth == 0 {
+			return Perft::leaf(state,teban,mc,None);
+		}
+
+		self.divide(state,teban,mc,depth).into_iter().fold(PerftResult::default(),|acc,(_,r)| acc + r)
+	}
+
+	/// 初手ごとに指定した深さまでの合法手の数え上げを行った結果を返す
+	///
+	/// # Arguments
+	/// * `state` - 盤面の状態
+	/// * `teban` - 手番
+	/// * `mc` - 持ち駒
+	/// * `depth` - 探索する深さ(初手を含む)
+	///
+	/// 結果は初手の生成順に並ぶ。`depth`が0の場合は空の`Vec`を返す
+	pub fn divide(&self,state:&State,teban:Teban,mc:&MochigomaCollections,depth:usize) -> Vec<(LegalMove,PerftResult)> {
+		if depth == 0 {
+			return Vec::new();
+		}
+
+		let mvs = Perft::legal_moves(state,teban,mc);
+
+		let hash = self.hasher.as_ref().map(|hasher| {
+			match mc {
+				MochigomaCollections::Pair(ms,mg) => hasher.calc_initial_hash(&state.banmen,ms,mg),
+				MochigomaCollections::Empty => hasher.calc_initial_hash(&state.banmen,&Mochigoma::new(),&Mochigoma::new())
+			}
+		});
+
+		let next_index = AtomicUsize::new(0);
+		let results = Mutex::new(Vec::with_capacity(mvs.len()));
+
+		thread::scope(|s| {
+			for _ in 0..self.threads.min(mvs.len()).max(1) {
+				s.spawn(|| {
+					let mut searcher = PerftSearcher {
+						hasher:self.hasher.as_deref(),
+						cache:PerftCache::new(self.cache_size)
+					};
+
+					loop {
+						let index = next_index.fetch_add(1,Ordering::Relaxed);
+
+						let Some(&(m,ref next,ref nmc,o)) = mvs.get(index) else {
+							break;
+						};
+
+						let next_hash = searcher.child_hash(hash,state,teban,mc,m,&o);
+
+						let r = searcher.search(next,teban.opposite(),nmc,next_hash,m,depth - 1);
+
+						results.lock().unwrap().push((index,m,r));
+					}
+				});
+			}
+		});
+
+		let mut results = results.into_inner().unwrap();
+
+		results.sort_by_key(|&(index,_,_)| index);
+
+		results.into_iter().map(|(_,m,r)| (m,r)).collect()
+	}
+
+	fn leaf(state:&State,teban:Teban,mc:&MochigomaCollections,m:Option<LegalMove>) -> PerftResult {
+		let mut result = PerftResult {
+			nodes:1,
+			..PerftResult::default()
+		};
+
+		if let Some(LegalMove::To(m)) = m {
+			if m.obtained().is_some() {
+				result.captures += 1;
+			}
+
+			if m.is_nari() {
+				result.promotions += 1;
+			}
+		}
+
+		if Rule::in_check(teban.opposite(),state) {
+			result.checks += 1;
+
+			let mut mvs = RandomPicker::new(Prng::new(PERFT_SEED));
+
+			Rule::generate_moves::<EvasionsAll>(teban,state,mc,&mut mvs).unwrap();
+
+			if mvs.len() == 0 {
+				result.mates += 1;
+			}
+		}
+
+		result
+	}
+
+	fn legal_moves(state:&State,teban:Teban,mc:&MochigomaCollections)
+		-> Vec<(LegalMove,State,MochigomaCollections,Option<MochigomaKind>)> {
+		let mut mvs = RandomPicker::new(Prng::new(PERFT_SEED));
+
+		if Rule::in_check(teban.opposite(),state) {
+			Rule::generate_moves::<EvasionsAll>(teban,state,mc,&mut mvs).unwrap();
+		} else {
+			Rule::generate_moves::<NonEvasionsAll>(teban,state,mc,&mut mvs).unwrap();
+		}
+
+		let mvs:Vec<LegalMove> = mvs.into();
+
+		mvs.into_iter().filter_map(|m| {
+			let (next,nmc,o) = Rule::apply_move_none_check(state,teban,mc,m.to_applied_move());
+
+			if Rule::in_check(teban.opposite(),&next) {
+				None
+			} else {
+				Some((m,next,nmc,o))
+			}
+		}).collect()
+	}
 }
+impl Default for Perft {
+	fn default() -> Perft {
+		Perft::new()
+	}
+}
+/// スレッドごとにperftの部分木を探索する
+struct PerftSearcher<'a> {
+	hasher:Option<&'a KyokumenHash<u64>>,
+	cache:PerftCache
+}
+impl<'a> PerftSearcher<'a> {
+	fn child_hash(&self,hash:Option<(u64,u64)>,
+				  state:&State,teban:Teban,mc:&MochigomaCollections,
+				  m:LegalMove,o:&Option<MochigomaKind>) -> Option<(u64,u64)> {
+		match (self.hasher,hash) {
+			(Some(hasher),Some((mhash,shash))) => {
+				let am = m.to_applied_move();
+
+				Some((hasher.calc_main_hash(mhash,teban,&state.banmen,mc,am,o),
+					  hasher.calc_sub_hash(shash,teban,&state.banmen,mc,am,o)))
+			},
+			_ => None
+		}
+	}
+
+	fn search(&mut self,state:&State,teban:Teban,mc:&MochigomaCollections,
+			  hash:Option<(u64,u64)>,m:LegalMove,depth:usize) -> PerftResult {
+		if depth == 0 {
+			return Perft::leaf(state,teban,mc,Some(m));
+		}
+
+		if let Some((mhash,shash)) = hash {
+			if let Some(r) = self.cache.get(teban,mhash,shash,depth) {
+				return r;
+			}
+		}
+
+		let mut result = PerftResult::default();
+
+		for (m,next,nmc,o) in Perft::legal_moves(state,teban,mc) {
+			let next_hash = self.child_hash(hash,state,teban,mc,m,&o);
+
+			result += self.search(&next,teban.opposite(),&nmc,next_hash,m,depth - 1);
+		}
+
+		if let Some((mhash,shash)) = hash {
+			self.cache.insert(teban,mhash,shash,depth,result);
+		}
+
+		result
+	}
+}
+/// perftの手の生成に利用する乱数シード(数え上げの結果には影響しない)
+const PERFT_SEED:u64 = 0x2545F4914F6CDD1D;
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -13120,4 +14928,50 @@ mod tests {
 			}
 		}
 	}
+	#[cfg(not(feature = "sliding-attack-table"))]
+	#[test]
+	fn test_sliding_attacks_by_table_equal_to_mask() {
+		use bitboard::SlidingDirection;
+
+		let candidate_bits:[(SlidingDirection,(i32,i32),fn(BitBoard,BitBoard,u32) -> BitBoard); 4] = [
+			(SlidingDirection::Bottom,(0,1),Rule::gen_candidate_bits_by_hisha_or_kyou_to_top_include),
+			(SlidingDirection::Right,(1,0),Rule::gen_candidate_bits_by_hisha_to_right_include),
+			(SlidingDirection::RightTop,(1,-1),Rule::gen_candidate_bits_by_kaku_to_right_top_include),
+			(SlidingDirection::RightBottom,(1,1),Rule::gen_candidate_bits_by_kaku_to_right_bottom_include)
+		];
+
+		for &(direction,(dx,dy),candidate_bits) in candidate_bits.iter() {
+			for from in 0..81 {
+				let mut ray:u128 = 0;
+				let mut x = (from / 9) as i32 + dx;
+				let mut y = (from % 9) as i32 + dy;
+
+				while x >= 0 && x < 9 && y >= 0 && y < 9 {
+					ray |= 1 << (x * 9 + y + 1);
+					x += dx;
+					y += dy;
+				}
+
+				// 利きの方向にある駒の配置の全ての組み合わせについて、それ以外の位置が空の場合、
+				// 移動元にのみ駒がある場合、全て駒で埋まっている場合を調べる
+				let mut subset:u128 = 0;
+
+				loop {
+					for &others in &[0,1 << (from + 1),BANMEN_MASK & !ray] {
+						let occupied = BitBoard::from(subset | others);
+
+						assert_eq!(BitBoard::sliding_attacks_by_table(direction,from,occupied),
+								   candidate_bits(occupied,BitBoard::default(),from),
+								   "direction = {:?}, from = {}, occupied = {:b}",direction,from,subset | others);
+					}
+
+					subset = subset.wrapping_sub(ray) & ray;
+
+					if subset == 0 {
+						break;
+					}
+				}
+			}
+		}
+	}
 }
\ No newline at end of file
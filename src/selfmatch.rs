@@ -13,10 +13,14 @@ use std::marker::PhantomData;
 use std::time::{Instant,Duration};
 use std::io::Write;
 use std::io::BufWriter;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::fs;
 use std::fs::OpenOptions;
 use std::convert::TryFrom;
 
+use rand::{prelude, Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
 use crossbeam_channel::unbounded;
 use crossbeam_channel::Sender;
 use crossbeam_channel::Receiver;
@@ -38,7 +42,68 @@ use OnErrorHandler;
 use SandBox;
 use rule::*;
 use protocol::*;
-
+use config::ConfigFile;
+use options::parse_option_value;
+
+/// 自己対局の一手分の記録
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MoveRecord {
+	/// 指し手
+	pub m:Move,
+	/// 指し手を決めるまでに要した時間（初期局面の指定に含まれていた指し手の場合は0）
+	pub elapsed:Duration,
+	/// 指し手を返すまでにプレイヤーが最後に送信したinfoコマンドの評価値（指したプレイヤーから見た値）
+	pub score:Option<UsiScore>,
+	/// 指し手を返すまでにプレイヤーが最後に送信したinfoコマンドの読み筋
+	pub pv:Option<Vec<Move>>,
+}
+impl MoveRecord {
+	/// `MoveRecord`の生成
+	///
+	/// # Arguments
+	/// * `m` - 指し手
+	/// * `elapsed` - 指し手を決めるまでに要した時間
+	/// * `score` - 評価値
+	/// * `pv` - 読み筋
+	pub fn new(m:Move,elapsed:Duration,score:Option<UsiScore>,pv:Option<Vec<Move>>) -> MoveRecord {
+		MoveRecord {
+			m,
+			elapsed,
+			score,
+			pv
+		}
+	}
+}
+/// 自己対局の一局分の記録
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+	/// 開始時の局面のsfen文字列表現
+	pub initial_sfen:String,
+	/// 先手のプレイヤーの名前（自己対局では`USIPlayer::ID`にplayer1,player2の区別を付けたもの）
+	pub sente_name:String,
+	/// 後手のプレイヤーの名前（自己対局では`USIPlayer::ID`にplayer1,player2の区別を付けたもの）
+	pub gote_name:String,
+	/// 先手を持ったプレイヤーの番号（player1なら1、player2なら2）
+	pub sente_player:u32,
+	/// 対局の開始時間
+	pub start_dt:DateTime<Local>,
+	/// 対局の終了時間
+	pub end_dt:DateTime<Local>,
+	/// 対局終了時の状態
+	pub end_state:SelfMatchGameEndState,
+	/// 自己対局に用いたシード
+	pub seed:u64,
+	/// 対局の持ち時間
+	pub time_limit:UsiGoTimeLimit,
+	/// 開始局面からの指し手の記録
+	pub moves:Vec<MoveRecord>,
+}
+impl GameRecord {
+	/// 開始局面からの指し手のリストを取得
+	pub fn to_moves(&self) -> Vec<Move> {
+		self.moves.iter().map(|r| r.m).collect::<Vec<Move>>()
+	}
+}
 /// 棋譜を記録する
 pub trait SelfMatchKifuWriter {
 	/// 棋譜の書き込みを行う
@@ -47,6 +112,15 @@ pub trait SelfMatchKifuWriter {
 	/// * `initial_sfen` - 開始時の局面のsfen文字列表現
 	/// * `m` - 開始局面からの指し手のリスト
 	fn write(&mut self,initial_sfen:&String,m:&Vec<Move>) -> Result<(),KifuWriteError>;
+	/// 対局の記録を書き込むメソッドのデフォルト実装（開始局面と指し手のリストのみを`write`に渡す）
+	///
+	/// 対局者や勝敗、消費時間などの情報を記録できる形式の場合はこのメソッドを実装する
+	///
+	/// # Arguments
+	/// * `record` - 対局の記録
+	fn write_record(&mut self,record:&GameRecord) -> Result<(),KifuWriteError> {
+		self.write(&record.initial_sfen,&record.to_moves())
+	}
 	/// 開始時の局面のsfen文字列と`Vec<Move>`から棋譜のsfen文字列を生成するメソッドのデフォルト実装
 	///
 	/// # Arguments
@@ -90,6 +164,7 @@ pub trait SelfMatchKifuWriter {
 #[derive(Debug)]
 pub struct FileSfenKifuWriter {
 	writer:BufWriter<fs::File>,
+	comments:bool,
 }
 impl FileSfenKifuWriter {
 	/// FileSfenKifuWriterの生成
@@ -99,8 +174,23 @@ impl FileSfenKifuWriter {
 	pub fn new(file:String) -> Result<FileSfenKifuWriter,KifuWriteError> {
 		Ok(FileSfenKifuWriter {
 			writer:BufWriter::new(OpenOptions::new().append(true).create(true).open(file)?),
+			comments:false,
 		})
 	}
+
+	/// 各棋譜の前に対局者、開始終了時間、勝敗、シードをコメント行として書き込むFileSfenKifuWriterの生成
+	///
+	/// コメント行は`#`から始まる一行で、sfen形式では表現できない対局の情報を記録する
+	///
+	/// # Arguments
+	/// * `file` - 書き込み先ファイル
+	pub fn with_comments(file:String) -> Result<FileSfenKifuWriter,KifuWriteError> {
+		let mut writer = FileSfenKifuWriter::new(file)?;
+
+		writer.comments = true;
+
+		Ok(writer)
+	}
 }
 impl SelfMatchKifuWriter for FileSfenKifuWriter {
 	/// ファイルに棋譜を書き込む
@@ -111,57 +201,1170 @@ impl SelfMatchKifuWriter for FileSfenKifuWriter {
 	fn write(&mut self,initial_sfen:&String,m:&Vec<Move>) -> Result<(),KifuWriteError> {
 		let sfen = self.to_sfen(initial_sfen,m)?;
 
-		let _ = self.writer.write(format!("{}\n",sfen).as_bytes())?;
-		Ok(())
+		let _ = self.writer.write(format!("{}\n",sfen).as_bytes())?;
+		Ok(())
+	}
+
+	/// ファイルに対局の記録を書き込む（`with_comments`で生成した場合はコメント行も書き込む）
+	///
+	/// # Arguments
+	/// * `record` - 対局の記録
+	fn write_record(&mut self,record:&GameRecord) -> Result<(),KifuWriteError> {
+		if self.comments {
+			let _ = self.writer.write(format!("# sente={} gote={} sente_player={} start={} end={} result={:?} seed={}\n",
+				record.sente_name,record.gote_name,record.sente_player,
+				record.start_dt.format("%Y/%m/%d %H:%M:%S"),
+				record.end_dt.format("%Y/%m/%d %H:%M:%S"),
+				record.end_state,record.seed).as_bytes())?;
+		}
+
+		self.write(&record.initial_sfen,&record.to_moves())
+	}
+}
+/// CSA形式でファイルに記録する`SelfMatchKifuWriter`の実装
+///
+/// 対局者名、開始終了時間、一手ごとの消費時間、評価値と読み筋（`'**`で始まるコメント行）、終局理由を記録する。
+/// 複数の対局は`/`の行で区切って同じファイルに追記される
+#[derive(Debug)]
+pub struct FileCsaKifuWriter {
+	writer:BufWriter<fs::File>,
+	has_game:bool,
+}
+impl FileCsaKifuWriter {
+	/// FileCsaKifuWriterの生成
+	///
+	/// # Arguments
+	/// * `file` - 書き込み先ファイル
+	pub fn new(file:String) -> Result<FileCsaKifuWriter,KifuWriteError> {
+		let file = OpenOptions::new().append(true).create(true).open(file)?;
+		let has_game = file.metadata()?.len() > 0;
+
+		Ok(FileCsaKifuWriter {
+			writer:BufWriter::new(file),
+			has_game,
+		})
+	}
+
+	/// 対局の記録をCSA形式の文字列に変換
+	///
+	/// # Arguments
+	/// * `record` - 対局の記録
+	pub fn to_csa(record:&GameRecord) -> Result<String,KifuWriteError> {
+		FileCsaKifuWriter::to_csa_with_result(record,Some(&record.end_state))
+	}
+
+	fn to_csa_with_result(record:&GameRecord,end_state:Option<&SelfMatchGameEndState>) -> Result<String,KifuWriteError> {
+		let (teban, banmen, mc, _, _) = PositionParser::new().parse(
+			&record.initial_sfen.split(" ").collect::<Vec<&str>>()
+		).map_err(|e| KifuWriteError::SfenStringConvertError(SfenStringConvertError::TypeConvertError(e)))?.extract();
+
+		let mut lines:Vec<String> = Vec::new();
+
+		lines.push(String::from("V2.2"));
+		lines.push(format!("N+{}",record.sente_name));
+		lines.push(format!("N-{}",record.gote_name));
+		lines.push(format!("$START_TIME:{}",record.start_dt.format("%Y/%m/%d %H:%M:%S")));
+		lines.push(format!("$END_TIME:{}",record.end_dt.format("%Y/%m/%d %H:%M:%S")));
+		lines.push(format!("'seed={}",record.seed));
+
+		let (ms, mg) = match mc {
+			MochigomaCollections::Pair(ref ms, ref mg) => (ms.clone(),mg.clone()),
+			MochigomaCollections::Empty => (Mochigoma::new(),Mochigoma::new()),
+		};
+
+		if banmen == BANMEN_START_POS && ms.is_empty() && mg.is_empty() {
+			lines.push(String::from("PI"));
+		} else {
+			let Banmen(ref kinds) = banmen;
+
+			for (y,row) in kinds.iter().enumerate() {
+				let mut line = format!("P{}",y+1);
+
+				for &k in row.iter() {
+					line.push_str(&match csa_koma(k) {
+						Some((t,name)) => format!("{}{}",csa_teban(t),name),
+						None => String::from(" * "),
+					});
+				}
+
+				lines.push(line);
+			}
+
+			for &(t,m) in &[(Teban::Sente,&ms),(Teban::Gote,&mg)] {
+				if !m.is_empty() {
+					let mut line = format!("P{}",csa_teban(t));
+
+					for (k,c) in m.iter() {
+						for _ in 0..c {
+							line.push_str(&format!("00{}",csa_mochigoma(k)));
+						}
+					}
+
+					lines.push(line);
+				}
+			}
+		}
+
+		lines.push(String::from(csa_teban(teban)));
+
+		let mut banmen = banmen;
+		let mut teban = teban;
+
+		for r in &record.moves {
+			let mut pv_banmen = banmen.clone();
+
+			let m = csa_move(&mut banmen,teban,r.m).ok_or(KifuWriteError::InvalidState(String::from(
+				"The move could not be converted to csa format."
+			)))?;
+
+			lines.push(m);
+			lines.push(format!("T{}",r.elapsed.as_secs()));
+
+			if r.score.is_some() || r.pv.is_some() {
+				let mut line = String::from("'**");
+
+				if let Some(ref s) = r.score {
+					let s = match *s {
+						UsiScore::Cp(s) | UsiScore::CpUpper(s) | UsiScore::CpLower(s) => s,
+						UsiScore::Mate(UsiScoreMate::Num(n)) |
+							UsiScore::MateUpper(n) | UsiScore::MateLower(n) if n < 0 => -CSA_MATE_SCORE,
+						UsiScore::Mate(UsiScoreMate::Minus) => -CSA_MATE_SCORE,
+						UsiScore::Mate(_) | UsiScore::MateUpper(_) | UsiScore::MateLower(_) => CSA_MATE_SCORE,
+					};
+
+					line.push_str(&format!(" {}",match teban {
+						Teban::Sente => s,
+						Teban::Gote => -s,
+					}));
+				}
+
+				if let Some(ref pv) = r.pv {
+					let mut t = teban;
+
+					for &m in pv {
+						match csa_move(&mut pv_banmen,t,m) {
+							Some(m) => {
+								line.push_str(&format!(" {}",m));
+							},
+							None => {
+								break;
+							}
+						}
+						t = t.opposite();
+					}
+				}
+
+				lines.push(line);
+			}
+
+			teban = teban.opposite();
+		}
+
+		let end_state = match end_state {
+			Some(end_state) => end_state,
+			None => {
+				return Ok(lines.join("\n"));
+			}
+		};
+
+		lines.push(String::from(match *end_state {
+			SelfMatchGameEndState::Win(_) => "%TSUMI",
+			SelfMatchGameEndState::Resign(_) => "%TORYO",
+			SelfMatchGameEndState::NyuGyokuWin(_) => "%KACHI",
+			SelfMatchGameEndState::Draw => "%HIKIWAKE",
+			SelfMatchGameEndState::Timeover(_) => "%TIME_UP",
+			SelfMatchGameEndState::Foul(_,FoulKind::Sennichite) => "%SENNICHITE",
+			SelfMatchGameEndState::Foul(_,FoulKind::SennichiteOu) => "%OUTE_SENNICHITE",
+			SelfMatchGameEndState::Foul(Teban::Sente,_) | SelfMatchGameEndState::NyuGyokuLose(Teban::Sente) => "%+ILLEGAL_ACTION",
+			SelfMatchGameEndState::Foul(Teban::Gote,_) | SelfMatchGameEndState::NyuGyokuLose(Teban::Gote) => "%-ILLEGAL_ACTION",
+		}));
+
+		Ok(lines.join("\n"))
+	}
+
+	fn append(&mut self,csa:String) -> Result<(),KifuWriteError> {
+		if self.has_game {
+			let _ = self.writer.write(b"/\n")?;
+		}
+
+		let _ = self.writer.write(format!("{}\n",csa).as_bytes())?;
+		self.writer.flush()?;
+
+		self.has_game = true;
+
+		Ok(())
+	}
+}
+impl SelfMatchKifuWriter for FileCsaKifuWriter {
+	/// ファイルに棋譜を書き込む（対局の情報が無いため、対局者名などは空で書き込まれ、終局の行は書き込まれない）
+	///
+	/// # Arguments
+	/// * `initial_sfen` - 開始時の局面のsfen文字列表現
+	/// * `m` - 開始局面からの指し手のリスト
+	fn write(&mut self,initial_sfen:&String,m:&Vec<Move>) -> Result<(),KifuWriteError> {
+		let now = Local::now();
+
+		let csa = FileCsaKifuWriter::to_csa_with_result(&GameRecord {
+			initial_sfen:initial_sfen.clone(),
+			sente_name:String::new(),
+			gote_name:String::new(),
+			sente_player:1,
+			start_dt:now,
+			end_dt:now,
+			end_state:SelfMatchGameEndState::Draw,
+			seed:0,
+			time_limit:UsiGoTimeLimit::None,
+			moves:m.iter().map(|&m| MoveRecord::new(m,Duration::from_secs(0),None,None)).collect::<Vec<MoveRecord>>()
+		},None)?;
+
+		self.append(csa)
+	}
+
+	/// ファイルに対局の記録をCSA形式で書き込む
+	///
+	/// # Arguments
+	/// * `record` - 対局の記録
+	fn write_record(&mut self,record:&GameRecord) -> Result<(),KifuWriteError> {
+		let csa = FileCsaKifuWriter::to_csa(record)?;
+
+		self.append(csa)
+	}
+}
+/// CSA形式の評価値のコメントで詰みを表す値
+const CSA_MATE_SCORE:i64 = 100000;
+
+fn csa_teban(t:Teban) -> &'static str {
+	match t {
+		Teban::Sente => "+",
+		Teban::Gote => "-",
+	}
+}
+fn csa_mochigoma(k:MochigomaKind) -> &'static str {
+	match k {
+		MochigomaKind::Fu => "FU",
+		MochigomaKind::Kyou => "KY",
+		MochigomaKind::Kei => "KE",
+		MochigomaKind::Gin => "GI",
+		MochigomaKind::Kin => "KI",
+		MochigomaKind::Kaku => "KA",
+		MochigomaKind::Hisha => "HI",
+	}
+}
+fn csa_koma(k:KomaKind) -> Option<(Teban,&'static str)> {
+	Some(match k {
+		KomaKind::SFu => (Teban::Sente,"FU"),
+		KomaKind::SKyou => (Teban::Sente,"KY"),
+		KomaKind::SKei => (Teban::Sente,"KE"),
+		KomaKind::SGin => (Teban::Sente,"GI"),
+		KomaKind::SKin => (Teban::Sente,"KI"),
+		KomaKind::SKaku => (Teban::Sente,"KA"),
+		KomaKind::SHisha => (Teban::Sente,"HI"),
+		KomaKind::SOu => (Teban::Sente,"OU"),
+		KomaKind::SFuN => (Teban::Sente,"TO"),
+		KomaKind::SKyouN => (Teban::Sente,"NY"),
+		KomaKind::SKeiN => (Teban::Sente,"NK"),
+		KomaKind::SGinN => (Teban::Sente,"NG"),
+		KomaKind::SKakuN => (Teban::Sente,"UM"),
+		KomaKind::SHishaN => (Teban::Sente,"RY"),
+		KomaKind::GFu => (Teban::Gote,"FU"),
+		KomaKind::GKyou => (Teban::Gote,"KY"),
+		KomaKind::GKei => (Teban::Gote,"KE"),
+		KomaKind::GGin => (Teban::Gote,"GI"),
+		KomaKind::GKin => (Teban::Gote,"KI"),
+		KomaKind::GKaku => (Teban::Gote,"KA"),
+		KomaKind::GHisha => (Teban::Gote,"HI"),
+		KomaKind::GOu => (Teban::Gote,"OU"),
+		KomaKind::GFuN => (Teban::Gote,"TO"),
+		KomaKind::GKyouN => (Teban::Gote,"NY"),
+		KomaKind::GKeiN => (Teban::Gote,"NK"),
+		KomaKind::GGinN => (Teban::Gote,"NG"),
+		KomaKind::GKakuN => (Teban::Gote,"UM"),
+		KomaKind::GHishaN => (Teban::Gote,"RY"),
+		KomaKind::Blank => {
+			return None;
+		}
+	})
+}
+/// 指し手をCSA形式の文字列に変換して盤面に適用する（変換できない手の場合は盤面を変更せずにNoneを返す）
+fn csa_move(banmen:&mut Banmen,t:Teban,m:Move) -> Option<String> {
+	let Banmen(ref mut kinds) = *banmen;
+
+	match m {
+		Move::To(KomaSrcPosition(sx,sy),KomaDstToPosition(dx,dy,n)) => {
+			if !(1..=9).contains(&sx) || !(1..=9).contains(&sy) || !(1..=9).contains(&dx) || !(1..=9).contains(&dy) {
+				return None;
+			}
+
+			let k = kinds[sy as usize - 1][9 - sx as usize];
+
+			match csa_koma(k) {
+				Some((kt,_)) if kt == t => (),
+				_ => {
+					return None;
+				}
+			}
+
+			let k = if n {
+				k.to_nari()
+			} else {
+				k
+			};
+
+			kinds[sy as usize - 1][9 - sx as usize] = KomaKind::Blank;
+			kinds[dy as usize - 1][9 - dx as usize] = k;
+
+			csa_koma(k).map(|(_,name)| format!("{}{}{}{}{}{}",csa_teban(t),sx,sy,dx,dy,name))
+		},
+		Move::Put(k,KomaDstPutPosition(dx,dy)) => {
+			if !(1..=9).contains(&dx) || !(1..=9).contains(&dy) {
+				return None;
+			}
+
+			kinds[dy as usize - 1][9 - dx as usize] = KomaKind::from((t,k));
+
+			Some(format!("{}00{}{}{}",csa_teban(t),dx,dy,csa_mochigoma(k)))
+		}
+	}
+}
+/// 複数の対局をヘッダ付きで一つのファイルに記録する`SelfMatchKifuWriter`の実装
+///
+/// 各対局は`[名前 "値"]`形式のヘッダ行（Sente, Gote, SentePlayer, Result, Reason, Date, EndDate,
+/// TimeControl, Opening, Seed, Position）、空行、指し手と勝敗を空白区切りで並べた行、空行の順で書き込まれる。
+/// 各対局の開始位置のバイトオフセットは`<書き込み先ファイル>.idx`に一行ずつ記録され、
+/// `KifuArchiveReader`による任意の対局の読み込みに使用される
+#[derive(Debug)]
+pub struct FileArchiveKifuWriter {
+	writer:BufWriter<fs::File>,
+	index_writer:BufWriter<fs::File>,
+	offset:u64,
+}
+impl FileArchiveKifuWriter {
+	/// FileArchiveKifuWriterの生成
+	///
+	/// # Arguments
+	/// * `file` - 書き込み先ファイル
+	pub fn new(file:String) -> Result<FileArchiveKifuWriter,KifuWriteError> {
+		let index_file = OpenOptions::new().append(true).create(true).open(format!("{}.idx",file))?;
+		let file = OpenOptions::new().append(true).create(true).open(file)?;
+		let offset = file.metadata()?.len();
+
+		Ok(FileArchiveKifuWriter {
+			writer:BufWriter::new(file),
+			index_writer:BufWriter::new(index_file),
+			offset,
+		})
+	}
+
+	/// 対局の記録をアーカイブ形式の文字列（末尾の空行を含む）に変換
+	///
+	/// 開始局面のsfen文字列が`moves`を含む場合、`Position`ヘッダには`moves`より前の局面を記録する。
+	/// `record.moves`は`moves`より前の局面からの指し手として扱われ、`moves`に続く指し手は記録されない
+	///
+	/// # Arguments
+	/// * `record` - 対局の記録
+	pub fn to_archive(record:&GameRecord) -> Result<String,KifuWriteError> {
+		FileArchiveKifuWriter::to_archive_with_result(record,Some(&record.end_state))
+	}
+
+	fn to_archive_with_result(record:&GameRecord,end_state:Option<&SelfMatchGameEndState>) -> Result<String,KifuWriteError> {
+		let result = end_state.map(archive_result).unwrap_or("*");
+
+		let tokens = record.initial_sfen.split_whitespace().collect::<Vec<&str>>();
+
+		let position = match tokens.iter().position(|&t| t == "moves") {
+			Some(i) => tokens[..i].join(" "),
+			None => tokens.join(" "),
+		};
+
+		let sente_player = if record.sente_player == 2 {
+			2
+		} else {
+			1
+		};
+
+		let headers = vec![
+			("Sente",record.sente_name.clone()),
+			("Gote",record.gote_name.clone()),
+			("SentePlayer",format!("{}",sente_player)),
+			("Result",String::from(result)),
+			("Reason",end_state.map(archive_reason).unwrap_or_else(|| String::from("Unknown"))),
+			("Date",format!("{}",record.start_dt.format("%Y/%m/%d %H:%M:%S"))),
+			("EndDate",format!("{}",record.end_dt.format("%Y/%m/%d %H:%M:%S"))),
+			("TimeControl",archive_time_control(&record.time_limit)),
+			("Opening",archive_opening_id(&record.initial_sfen)),
+			("Seed",format!("{}",record.seed)),
+			("Position",position),
+		];
+
+		let mut s = String::new();
+
+		for (k,v) in headers {
+			s.push_str(&format!("[{} \"{}\"]\n",k,v.replace("\\","\\\\").replace("\"","\\\"")));
+		}
+
+		s.push('\n');
+
+		for m in &record.moves {
+			s.push_str(&m.m.to_sfen().map_err(SfenStringConvertError::ToMoveString)?);
+			s.push(' ');
+		}
+
+		s.push_str(result);
+		s.push_str("\n\n");
+
+		Ok(s)
+	}
+
+	fn append(&mut self,s:String) -> Result<(),KifuWriteError> {
+		self.writer.write_all(s.as_bytes())?;
+		self.writer.flush()?;
+
+		self.index_writer.write_all(format!("{}\n",self.offset).as_bytes())?;
+		self.index_writer.flush()?;
+
+		self.offset += s.len() as u64;
+
+		Ok(())
+	}
+}
+impl SelfMatchKifuWriter for FileArchiveKifuWriter {
+	/// ファイルに棋譜を書き込む（対局の情報が無いため、対局者名などは空で、結果は不明（`*`）として書き込まれる）
+	///
+	/// # Arguments
+	/// * `initial_sfen` - 開始時の局面のsfen文字列表現
+	/// * `m` - 開始局面からの指し手のリスト
+	fn write(&mut self,initial_sfen:&String,m:&Vec<Move>) -> Result<(),KifuWriteError> {
+		let now = Local::now();
+
+		let s = FileArchiveKifuWriter::to_archive_with_result(&GameRecord {
+			initial_sfen:initial_sfen.clone(),
+			sente_name:String::new(),
+			gote_name:String::new(),
+			sente_player:1,
+			start_dt:now,
+			end_dt:now,
+			end_state:SelfMatchGameEndState::Draw,
+			seed:0,
+			time_limit:UsiGoTimeLimit::None,
+			moves:m.iter().map(|&m| MoveRecord::new(m,Duration::from_secs(0),None,None)).collect::<Vec<MoveRecord>>()
+		},None)?;
+
+		self.append(s)
+	}
+
+	/// ファイルに対局の記録を書き込み、インデックスに対局の開始位置を追記する
+	///
+	/// # Arguments
+	/// * `record` - 対局の記録
+	fn write_record(&mut self,record:&GameRecord) -> Result<(),KifuWriteError> {
+		let s = FileArchiveKifuWriter::to_archive(record)?;
+
+		self.append(s)
+	}
+}
+/// `FileArchiveKifuWriter`で書き込まれたアーカイブから読み込んだ一局分の棋譜
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArchivedGame {
+	/// ヘッダの名前と値の組のリスト（記録された順）
+	pub headers:Vec<(String,String)>,
+	/// 開始時の局面のsfen文字列表現（`moves`を含まない）
+	pub initial_sfen:String,
+	/// 開始局面からの指し手のリスト
+	pub moves:Vec<Move>,
+}
+impl ArchivedGame {
+	/// 名前を指定してヘッダの値を取得
+	///
+	/// # Arguments
+	/// * `name` - ヘッダの名前
+	pub fn header(&self,name:&str) -> Option<&str> {
+		self.headers.iter().find(|&(k,_)| k == name).map(|(_,v)| v.as_str())
+	}
+}
+/// `FileArchiveKifuWriter`で書き込まれたアーカイブを読み込む
+///
+/// インデックスファイル（`<アーカイブのファイル>.idx`）が無いか、アーカイブの内容と一致しない場合は
+/// アーカイブを先頭から走査してインデックスを作り直す
+#[derive(Debug)]
+pub struct KifuArchiveReader {
+	reader:BufReader<fs::File>,
+	offsets:Vec<u64>,
+}
+impl KifuArchiveReader {
+	/// KifuArchiveReaderの生成
+	///
+	/// # Arguments
+	/// * `file` - アーカイブのファイル
+	pub fn open(file:String) -> Result<KifuArchiveReader,KifuReadError> {
+		let mut reader = BufReader::new(OpenOptions::new().read(true).open(&file)?);
+		let len = reader.get_ref().metadata()?.len();
+
+		let offsets = match KifuArchiveReader::read_index(&format!("{}.idx",file),len) {
+			Some(offsets) => offsets,
+			None => KifuArchiveReader::scan(&mut reader)?,
+		};
+
+		Ok(KifuArchiveReader {
+			reader,
+			offsets,
+		})
+	}
+
+	fn read_index(file:&str,len:u64) -> Option<Vec<u64>> {
+		let reader = BufReader::new(OpenOptions::new().read(true).open(file).ok()?);
+
+		let mut offsets = Vec::new();
+
+		for line in reader.lines() {
+			let offset = line.ok()?.trim().parse::<u64>().ok()?;
+
+			if offset >= len || offsets.last().is_some_and(|&o| o >= offset) {
+				return None;
+			}
+
+			offsets.push(offset);
+		}
+
+		if offsets.is_empty() && len > 0 {
+			None
+		} else {
+			Some(offsets)
+		}
+	}
+
+	fn scan(reader:&mut BufReader<fs::File>) -> Result<Vec<u64>,KifuReadError> {
+		reader.seek(SeekFrom::Start(0))?;
+
+		let mut offsets = Vec::new();
+		let mut offset = 0;
+		let mut in_header = false;
+		let mut line = String::new();
+
+		loop {
+			line.clear();
+
+			let n = reader.read_line(&mut line)?;
+
+			if n == 0 {
+				break;
+			}
+
+			if line.starts_with("[") {
+				if !in_header {
+					offsets.push(offset);
+				}
+				in_header = true;
+			} else {
+				in_header = false;
+			}
+
+			offset += n as u64;
+		}
+
+		Ok(offsets)
+	}
+
+	/// アーカイブに含まれる対局の数
+	pub fn len(&self) -> usize {
+		self.offsets.len()
+	}
+
+	/// アーカイブに対局が含まれていなければtrue
+	pub fn is_empty(&self) -> bool {
+		self.offsets.is_empty()
+	}
+
+	/// 指定した番号（0始まり）の対局を読み込む
+	///
+	/// # Arguments
+	/// * `n` - 対局の番号
+	pub fn get(&mut self,n:usize) -> Result<ArchivedGame,KifuReadError> {
+		let offset = *self.offsets.get(n).ok_or(KifuReadError::OutOfRange(n))?;
+
+		self.reader.seek(SeekFrom::Start(offset))?;
+
+		let mut headers = Vec::new();
+		let mut line = String::new();
+
+		loop {
+			line.clear();
+
+			if self.reader.read_line(&mut line)? == 0 {
+				return Err(KifuReadError::InvalidFormat(String::from("Unexpected end of file.")));
+			}
+
+			let l = line.trim_end();
+
+			if l.is_empty() {
+				break;
+			}
+
+			headers.push(parse_archive_header(l)?);
+		}
+
+		line.clear();
+		self.reader.read_line(&mut line)?;
+
+		let mut tokens = line.split_whitespace().collect::<Vec<&str>>();
+
+		match tokens.pop() {
+			Some("1-0") | Some("0-1") | Some("1/2-1/2") | Some("*") => (),
+			_ => {
+				return Err(KifuReadError::InvalidFormat(String::from("The result is missing from the move text.")));
+			}
+		}
+
+		let moves = tokens.into_iter().map(Move::try_from).collect::<Result<Vec<Move>,_>>()?;
+
+		let initial_sfen = headers.iter()
+								.find(|&(k,_)| k == "Position")
+								.map(|(_,v)| v.clone())
+								.ok_or(KifuReadError::InvalidFormat(String::from("The Position header is missing.")))?;
+
+		Ok(ArchivedGame {
+			headers,
+			initial_sfen,
+			moves,
+		})
+	}
+
+	/// アーカイブの先頭から順に対局を読み込むイテレータを取得
+	pub fn iter<'a>(&'a mut self) -> KifuArchiveIter<'a> {
+		KifuArchiveIter {
+			reader:self,
+			current:0,
+		}
+	}
+}
+/// `KifuArchiveReader`から対局を順に読み込むイテレータ
+#[derive(Debug)]
+pub struct KifuArchiveIter<'a> {
+	reader:&'a mut KifuArchiveReader,
+	current:usize,
+}
+impl<'a> Iterator for KifuArchiveIter<'a> {
+	type Item = Result<ArchivedGame,KifuReadError>;
+
+	fn next(&mut self) -> Option<Result<ArchivedGame,KifuReadError>> {
+		if self.current >= self.reader.len() {
+			None
+		} else {
+			let r = self.reader.get(self.current);
+			self.current += 1;
+			Some(r)
+		}
+	}
+}
+fn archive_result(s:&SelfMatchGameEndState) -> &'static str {
+	match *s {
+		SelfMatchGameEndState::Win(Teban::Sente) |
+			SelfMatchGameEndState::NyuGyokuWin(Teban::Sente) => "1-0",
+		SelfMatchGameEndState::Win(Teban::Gote) |
+			SelfMatchGameEndState::NyuGyokuWin(Teban::Gote) => "0-1",
+		SelfMatchGameEndState::Resign(Teban::Sente) |
+			SelfMatchGameEndState::NyuGyokuLose(Teban::Sente) |
+			SelfMatchGameEndState::Foul(Teban::Sente,_) |
+			SelfMatchGameEndState::Timeover(Teban::Sente) => "0-1",
+		SelfMatchGameEndState::Resign(Teban::Gote) |
+			SelfMatchGameEndState::NyuGyokuLose(Teban::Gote) |
+			SelfMatchGameEndState::Foul(Teban::Gote,_) |
+			SelfMatchGameEndState::Timeover(Teban::Gote) => "1-0",
+		SelfMatchGameEndState::Draw => "1/2-1/2",
+	}
+}
+fn archive_reason(s:&SelfMatchGameEndState) -> String {
+	match *s {
+		SelfMatchGameEndState::Win(_) => String::from("Mate"),
+		SelfMatchGameEndState::Resign(_) => String::from("Resign"),
+		SelfMatchGameEndState::NyuGyokuWin(_) => String::from("NyuGyokuWin"),
+		SelfMatchGameEndState::NyuGyokuLose(_) => String::from("NyuGyokuLose"),
+		SelfMatchGameEndState::Draw => String::from("Draw"),
+		SelfMatchGameEndState::Foul(_,k) => format!("{:?}",k),
+		SelfMatchGameEndState::Timeover(_) => String::from("Timeover"),
+	}
+}
+fn archive_time_control(l:&UsiGoTimeLimit) -> String {
+	match *l {
+		UsiGoTimeLimit::None => String::from("none"),
+		UsiGoTimeLimit::Infinite => String::from("infinite"),
+		UsiGoTimeLimit::Limit(t,b) => {
+			let mut s = Vec::new();
+
+			if let Some((bt,wt)) = t {
+				s.push(format!("btime {} wtime {}",bt,wt));
+			}
+
+			match b {
+				Some(UsiGoByoyomiOrInc::Byoyomi(b)) => s.push(format!("byoyomi {}",b)),
+				Some(UsiGoByoyomiOrInc::Inc(bi,wi)) => s.push(format!("binc {} winc {}",bi,wi)),
+				None => (),
+			}
+
+			s.join(" ")
+		}
+	}
+}
+/// 開始局面のsfen文字列からFNV-1aハッシュで開始局面の識別子を生成する（同じ開始局面の対局は同じ識別子になる）
+fn archive_opening_id(sfen:&str) -> String {
+	let mut h:u64 = 0xcbf2_9ce4_8422_2325;
+
+	for b in sfen.split_whitespace().collect::<Vec<&str>>().join(" ").bytes() {
+		h ^= b as u64;
+		h = h.wrapping_mul(0x0100_0000_01b3);
+	}
+
+	format!("{:016x}",h)
+}
+fn parse_archive_header(line:&str) -> Result<(String,String),KifuReadError> {
+	let invalid = || KifuReadError::InvalidFormat(format!("Invalid header line. ({})",line));
+
+	if !line.starts_with("[") || !line.ends_with("\"]") {
+		return Err(invalid());
+	}
+
+	let body = &line[1..line.len()-2];
+
+	let (name,value) = match body.find(" \"") {
+		Some(p) => (&body[..p],&body[p+2..]),
+		None => {
+			return Err(invalid());
+		}
+	};
+
+	let mut v = String::new();
+	let mut chars = value.chars();
+
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			v.push(chars.next().ok_or_else(invalid)?);
+		} else {
+			v.push(c);
+		}
+	}
+
+	Ok((String::from(name),v))
+}
+/// プレイヤーが最後に送信したinfoコマンドの評価値と読み筋
+#[derive(Debug)]
+struct LastInfo {
+	score:Option<UsiScore>,
+	pv:Option<Vec<Move>>,
+}
+impl LastInfo {
+	fn new() -> LastInfo {
+		LastInfo {
+			score:None,
+			pv:None,
+		}
+	}
+
+	fn clear(&mut self) {
+		self.score = None;
+		self.pv = None;
+	}
+
+	fn update(&mut self,commands:&Vec<UsiInfoSubCommand>) {
+		// multipvの二番目以降の候補手の情報は記録しない
+		if commands.iter().any(|c| match *c {
+			UsiInfoSubCommand::MultiPv(n) => n != 1,
+			_ => false,
+		}) {
+			return;
+		}
+
+		for c in commands {
+			match *c {
+				UsiInfoSubCommand::Score(ref s) => {
+					self.score = Some(s.clone());
+				},
+				UsiInfoSubCommand::Pv(ref pv) => {
+					self.pv = Some(pv.clone());
+				},
+				_ => (),
+			}
+		}
+	}
+}
+/// 送信されたinfoコマンドの評価値と読み筋を記録しつつ元の`InfoSender`へ委譲する`InfoSender`の実装
+#[derive(Clone)]
+struct LastInfoRecordingSender<S> where S: InfoSender {
+	sender:S,
+	last_info:Arc<Mutex<LastInfo>>,
+}
+impl<S> LastInfoRecordingSender<S> where S: InfoSender {
+	fn new(sender:S,last_info:Arc<Mutex<LastInfo>>) -> LastInfoRecordingSender<S> {
+		LastInfoRecordingSender {
+			sender,
+			last_info,
+		}
+	}
+
+	fn record(&self,commands:&Vec<UsiInfoSubCommand>) {
+		if let Ok(mut last_info) = self.last_info.lock() {
+			last_info.update(commands);
+		}
+	}
+}
+impl<S> InfoSender for LastInfoRecordingSender<S> where S: InfoSender {
+	fn send(&mut self,commands:Vec<UsiInfoSubCommand>) -> Result<(), InfoSendError> {
+		self.record(&commands);
+		self.sender.send(commands)
+	}
+
+	fn send_immediate(&mut self,commands:Vec<UsiInfoSubCommand>) -> Result<(), InfoSendError> {
+		self.record(&commands);
+		self.sender.send_immediate(commands)
+	}
+
+	fn flush(&mut self) -> Result<(), InfoSendError> {
+		self.sender.flush()
+	}
+}
+/// タイムアウトの種別
+#[derive(Debug)]
+enum TimeoutKind {
+	/// タイムアウト無し
+	Never,
+	/// 現在のターンのタイムアウト
+	Turn,
+	/// 自己対局機能の起動時に指定した終了時刻に達した
+	Uptime,
+}
+/// 自己対局機能の実装内でやり取りするメッセージオブジェクト
+#[derive(Debug)]
+pub enum SelfMatchMessage {
+	/// 準備完了
+	Ready,
+	/// ゲーム開始
+	GameStart,
+	/// プレイヤーの思考を開始する
+	StartThink(Teban,Banmen,MochigomaCollections,u32,Vec<AppliedMove>,Instant),
+	/// プレイヤーの思考を開始する（go ponder)
+	StartPonderThink(Teban,Banmen,MochigomaCollections,u32,Vec<AppliedMove>),
+	/// プレイヤーから指し手を返す
+	NotifyMove(BestMove),
+	/// ponderで予測した指し手と一致した
+	PonderHit,
+	/// ponderで予測した指し手と一致しない
+	PonderNG,
+	/// 対局終了
+	GameEnd(GameEndState),
+	/// 中断
+	Abort,
+	/// 自己対局終了
+	Quit,
+	/// エラー発生を通知
+	Error(usize),
+}
+/// 自己対局中のプレイヤーごとの先読み（ponder）の統計
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PonderStatistics {
+	/// 先読みを開始した回数
+	pub ponder_count:u32,
+	/// 先読みした相手の指し手が実際に指された回数
+	pub hit_count:u32,
+	/// 先読みが当たった時に、相手の手番中に既に先読みしていた時間の合計
+	pub time_saved:Duration,
+}
+impl PonderStatistics {
+	/// 先読みが当たった割合（先読みを一度も行っていない場合は0.0）
+	pub fn hit_rate(&self) -> f64 {
+		if self.ponder_count == 0 {
+			0.0
+		} else {
+			self.hit_count as f64 / self.ponder_count as f64
+		}
+	}
+}
+/// 自己対局の結果
+#[derive(Debug)]
+pub struct SelfMatchResult {
+	/// 実施した対局回数
+	pub game_count:u32,
+	/// 自己対局開始からの経過時間
+	pub elapsed:Duration,
+	/// 自己対局の開始時間
+	pub start_dt:DateTime<Local>,
+	/// 自己対局の終了時間
+	pub end_dt:DateTime<Local>,
+	/// 自己対局に用いたシード
+	pub seed:u64,
+	/// プレイヤーごとの先読みの統計（player1,player2の順）
+	pub ponder_statistics:[PonderStatistics; 2],
+}
+/// 自己対局の棋譜の記録形式
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KifuFormat {
+	/// sfen形式（`FileSfenKifuWriter::new`）
+	Sfen,
+	/// コメント行付きのsfen形式（`FileSfenKifuWriter::with_comments`）
+	SfenWithComments,
+	/// CSA形式（`FileCsaKifuWriter`）
+	Csa,
+	/// 複数の棋譜をまとめたアーカイブ形式（`FileArchiveKifuWriter`）
+	Archive,
+}
+impl<'a> TryFrom<&'a str> for KifuFormat {
+	type Error = TypeConvertError<String>;
+
+	fn try_from(s:&'a str) -> Result<KifuFormat,TypeConvertError<String>> {
+		match s {
+			"sfen" => Ok(KifuFormat::Sfen),
+			"sfen-comments" => Ok(KifuFormat::SfenWithComments),
+			"csa" => Ok(KifuFormat::Csa),
+			"archive" => Ok(KifuFormat::Archive),
+			_ => Err(TypeConvertError::SyntaxError(format!("The kifu format is invalid. ({})",s))),
+		}
+	}
+}
+/// 対局終了時に対局の記録（`GameRecord`）を書き込むためのコールバック関数
+pub type KifuWriterCallback = Box<dyn FnMut(&GameRecord) -> Result<(),KifuWriteError> + Send + 'static>;
+/// 自己対局の設定ファイルに記述されたプレイヤーごとの設定
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SelfMatchPlayerConfig {
+	/// 先読み（ponder）を行うか否か
+	pub ponder:bool,
+	/// プレイヤーに渡されるオプション（名前,値の文字列）
+	pub options:Vec<(String,String)>,
+}
+impl Default for SelfMatchPlayerConfig {
+	fn default() -> SelfMatchPlayerConfig {
+		SelfMatchPlayerConfig {
+			ponder:true,
+			options:Vec::new(),
+		}
+	}
+}
+/// 自己対局の設定ファイルの内容
+///
+/// 設定ファイルはINI形式（`ConfigFile`）で、以下のセクションと項目を記述できる（いずれも省略可能）。
+/// それ以外のセクションは無視される
+///
+/// * `[match]` - `games`（対局回数）,`uptime`（実行時間の上限・秒）,
+///   `time`,`sente_time`,`gote_time`（持ち時間・ミリ秒）,`byoyomi`（秒読み・ミリ秒）,
+///   `inc`,`sente_inc`,`gote_inc`（加算時間・ミリ秒）,`infinite`（`go infinite`で思考させる）,
+///   `kifu`（棋譜の記録先のパス）,`kifu_format`（`sfen`,`sfen-comments`,`csa`,`archive`のいずれか）,
+///   `log`（ログファイルのパス）,`seed`（自己対局のシード）
+/// * `[player1]`,`[player2]` - `ponder`（先読みを行うか否か）
+/// * `[player1.options]`,`[player2.options]` - プレイヤーに渡すオプション（`オプション名 = 値`）
+///
+/// # Examples
+/// ```
+/// use usiagent::event::{UsiGoTimeLimit, UsiGoByoyomiOrInc};
+/// use usiagent::selfmatch::SelfMatchConfig;
+///
+/// let config = SelfMatchConfig::read(&b"[match]
+/// games = 100
+/// time = 60000
+/// byoyomi = 1000
+///
+/// [player2]
+/// ponder = false
+///
+/// [player2.options]
+/// USI_Hash = 256
+/// "[..]).unwrap();
+///
+/// assert_eq!(config.number_of_games,Some(100));
+/// assert_eq!(config.time_limit,UsiGoTimeLimit::Limit(Some((60000,60000)),Some(UsiGoByoyomiOrInc::Byoyomi(1000))));
+/// assert!(!config.player2.ponder);
+/// assert_eq!(config.player2.options,vec![(String::from("USI_Hash"),String::from("256"))]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SelfMatchConfig {
+	/// player1の設定
+	pub player1:SelfMatchPlayerConfig,
+	/// player2の設定
+	pub player2:SelfMatchPlayerConfig,
+	/// 対局毎の制限時間
+	pub time_limit:UsiGoTimeLimit,
+	/// 対局の回数
+	pub number_of_games:Option<u32>,
+	/// 自己対局機能全体の実行時間制限
+	pub uptime:Option<Duration>,
+	/// 棋譜の記録先のパスと記録形式
+	pub kifu:Option<(String,KifuFormat)>,
+	/// ログファイルのパス
+	pub log:String,
+	/// 自己対局のシード（未指定の場合はランダムに決定される）
+	pub seed:Option<u64>,
+}
+impl Default for SelfMatchConfig {
+	fn default() -> SelfMatchConfig {
+		SelfMatchConfig {
+			player1:SelfMatchPlayerConfig::default(),
+			player2:SelfMatchPlayerConfig::default(),
+			time_limit:UsiGoTimeLimit::None,
+			number_of_games:None,
+			uptime:None,
+			kifu:None,
+			log:String::from("logs/log.txt"),
+			seed:None,
+		}
+	}
+}
+impl SelfMatchConfig {
+	const MATCH_KEYS:[&'static str; 14] = [
+		"games","uptime","time","sente_time","gote_time","byoyomi","inc","sente_inc","gote_inc",
+		"infinite","kifu","kifu_format","log","seed"
+	];
+
+	/// 自己対局の設定ファイルを読み込む
+	///
+	/// # Arguments
+	/// * `reader` - 読み込み元
+	pub fn read<R>(reader:R) -> Result<SelfMatchConfig,ConfigFileError> where R: BufRead {
+		SelfMatchConfig::try_from(&ConfigFile::read(reader)?)
+	}
+
+	/// ファイルから自己対局の設定を読み込む
+	///
+	/// # Arguments
+	/// * `file` - 読み込むファイルのパス
+	pub fn load(file:&str) -> Result<SelfMatchConfig,ConfigFileError> {
+		SelfMatchConfig::try_from(&ConfigFile::open(file)?)
+	}
+
+	fn player_config(config:&ConfigFile,name:&str) -> Result<SelfMatchPlayerConfig,ConfigFileError> {
+		for entry in config.entries(name) {
+			if entry.key != "ponder" {
+				return Err(ConfigFileError::UnknownKey(entry.line,entry.key.clone()));
+			}
+		}
+
+		Ok(SelfMatchPlayerConfig {
+			ponder:config.parse::<bool>(name,"ponder")?.unwrap_or(true),
+			options:config.entries(&format!("{}.options",name)).into_iter().map(|e| {
+				(e.key.clone(),e.value.clone())
+			}).collect(),
+		})
+	}
+
+	/// 設定に従って`SelfMatchEngine`を生成する（シードが指定されていない場合はランダムに決定される）
+	pub fn create_engine<E>(&self) -> SelfMatchEngine<E> where E: PlayerError {
+		let mut engine = match self.seed {
+			Some(seed) => SelfMatchEngine::with_seed(seed),
+			None => SelfMatchEngine::new(),
+		};
+
+		engine.set_ponder(self.player1.ponder,self.player2.ponder);
+
+		engine
+	}
+
+	/// 設定された記録先と記録形式で棋譜を書き込むコールバック関数を生成する（記録先が未指定の場合は`None`）
+	pub fn create_kifu_writer(&self) -> Result<Option<KifuWriterCallback>,KifuWriteError> {
+		let (file,format) = match self.kifu {
+			Some((ref file,format)) => (file.clone(),format),
+			None => {
+				return Ok(None);
+			}
+		};
+
+		Ok(Some(match format {
+			KifuFormat::Sfen => {
+				let mut writer = FileSfenKifuWriter::new(file)?;
+				Box::new(move |record:&GameRecord| writer.write_record(record))
+			},
+			KifuFormat::SfenWithComments => {
+				let mut writer = FileSfenKifuWriter::with_comments(file)?;
+				Box::new(move |record:&GameRecord| writer.write_record(record))
+			},
+			KifuFormat::Csa => {
+				let mut writer = FileCsaKifuWriter::new(file)?;
+				Box::new(move |record:&GameRecord| writer.write_record(record))
+			},
+			KifuFormat::Archive => {
+				let mut writer = FileArchiveKifuWriter::new(file)?;
+				Box::new(move |record:&GameRecord| writer.write_record(record))
+			}
+		}))
+	}
+}
+impl<'a> TryFrom<&'a ConfigFile> for SelfMatchConfig {
+	type Error = ConfigFileError;
+
+	fn try_from(config:&'a ConfigFile) -> Result<SelfMatchConfig,ConfigFileError> {
+		for entry in config.entries("match") {
+			if !SelfMatchConfig::MATCH_KEYS.contains(&entry.key.as_str()) {
+				return Err(ConfigFileError::UnknownKey(entry.line,entry.key.clone()));
+			}
+		}
+
+		let invalid_value = |key:&str| {
+			let e = config.entries("match").into_iter().rev().find(|e| e.key == key).cloned();
+			e.map(|e| ConfigFileError::InvalidValue(e.line,e.key,e.value))
+			 .unwrap_or_else(|| ConfigFileError::InvalidValue(0,key.to_string(),String::new()))
+		};
+
+		let time = config.parse::<u32>("match","time")?;
+		let sente_time = config.parse::<u32>("match","sente_time")?.or(time);
+		let gote_time = config.parse::<u32>("match","gote_time")?.or(time);
+		let byoyomi = config.parse::<u32>("match","byoyomi")?;
+		let inc = config.parse::<u32>("match","inc")?;
+		let sente_inc = config.parse::<u32>("match","sente_inc")?.or(inc);
+		let gote_inc = config.parse::<u32>("match","gote_inc")?.or(inc);
+
+		let time = match (sente_time,gote_time) {
+			(None,None) => None,
+			(s,g) => Some((s.unwrap_or(0),g.unwrap_or(0))),
+		};
+
+		let byoyomi_or_inc = match (byoyomi,sente_inc,gote_inc) {
+			(None,None,None) => None,
+			(Some(b),None,None) => Some(UsiGoByoyomiOrInc::Byoyomi(b)),
+			(None,s,g) => Some(UsiGoByoyomiOrInc::Inc(s.unwrap_or(0),g.unwrap_or(0))),
+			_ => {
+				return Err(invalid_value("byoyomi"));
+			}
+		};
+
+		let time_limit = if config.parse::<bool>("match","infinite")?.unwrap_or(false) {
+			UsiGoTimeLimit::Infinite
+		} else if time.is_none() && byoyomi_or_inc.is_none() {
+			UsiGoTimeLimit::None
+		} else {
+			UsiGoTimeLimit::Limit(time,byoyomi_or_inc)
+		};
+
+		let kifu_format = match config.get("match","kifu_format") {
+			Some(f) => KifuFormat::try_from(f).map_err(|_| invalid_value("kifu_format"))?,
+			None => KifuFormat::Sfen,
+		};
+
+		Ok(SelfMatchConfig {
+			player1:SelfMatchConfig::player_config(config,"player1")?,
+			player2:SelfMatchConfig::player_config(config,"player2")?,
+			time_limit,
+			number_of_games:config.parse::<u32>("match","games")?,
+			uptime:config.parse::<u64>("match","uptime")?.map(Duration::from_secs),
+			kifu:config.get("match","kifu").map(|f| (f.to_string(),kifu_format)),
+			log:config.get("match","log").map(|l| l.to_string()).unwrap_or_else(|| String::from("logs/log.txt")),
+			seed:config.parse::<u64>("match","seed")?,
+		})
 	}
 }
-/// タイムアウトの種別
-#[derive(Debug)]
-enum TimeoutKind {
-	/// タイムアウト無し
-	Never,
-	/// 現在のターンのタイムアウト
-	Turn,
-	/// 自己対局機能の起動時に指定した終了時刻に達した
-	Uptime,
-}
-/// 自己対局機能の実装内でやり取りするメッセージオブジェクト
-#[derive(Debug)]
-pub enum SelfMatchMessage {
-	/// 準備完了
-	Ready,
-	/// ゲーム開始
-	GameStart,
-	/// プレイヤーの思考を開始する
-	StartThink(Teban,Banmen,MochigomaCollections,u32,Vec<AppliedMove>,Instant),
-	/// プレイヤーの思考を開始する（go ponder)
-	StartPonderThink(Teban,Banmen,MochigomaCollections,u32,Vec<AppliedMove>),
-	/// プレイヤーから指し手を返す
-	NotifyMove(BestMove),
-	/// ponderで予測した指し手と一致した
-	PonderHit,
-	/// ponderで予測した指し手と一致しない
-	PonderNG,
-	/// 対局終了
-	GameEnd(GameEndState),
-	/// 中断
-	Abort,
-	/// 自己対局終了
-	Quit,
-	/// エラー発生を通知
-	Error(usize),
+/// 設定ファイルに記述されたオプションをプレイヤーのオプションの定義に基づいて変換する
+fn parse_player_options<T,E>(player:&mut T,options:&[(String,String)])
+	-> Result<Vec<(String,SysEventOption)>,SelfMatchRunningError<E>> where T: USIPlayer<E>, E: PlayerError {
+	let kinds = player.get_option_kinds()?;
+	let types = player.get_options()?;
+
+	options.iter().map(|(name,value)| {
+		parse_option_value(name,value,&kinds,&types).map(|v| (name.clone(),v)).map_err(|e| {
+			SelfMatchRunningError::Fail(e.to_string())
+		})
+	}).collect()
 }
-/// 自己対局の結果
-#[derive(Debug)]
-pub struct SelfMatchResult {
-	/// 実施した対局回数
-	pub game_count:u32,
-	/// 自己対局開始からの経過時間
-	pub elapsed:Duration,
-	/// 自己対局の開始時間
-	pub start_dt:DateTime<Local>,
-	/// 自己対局の終了時間
-	pub end_dt:DateTime<Local>,
+/// プレイヤーが`EnteringKingRule`オプションをサポートしていて値が未指定の場合、入玉宣言の規則をオプションに追加する
+fn with_nyugyoku_rule_option<T,E>(player:&mut T,mut options:Vec<(String,SysEventOption)>,rule:NyugyokuRule)
+	-> Result<Vec<(String,SysEventOption)>,E> where T: USIPlayer<E>, E: PlayerError {
+	if player.get_option_kinds()?.contains_key(NyugyokuRule::OPTION_NAME) &&
+		!options.iter().any(|(k,_)| k == NyugyokuRule::OPTION_NAME) {
+		options.push((String::from(NyugyokuRule::OPTION_NAME),SysEventOption::Str(rule.to_usi_option_value().to_string())));
+	}
+
+	Ok(options)
 }
 /// 自己対局エンジン
 #[derive(Debug)]
@@ -170,24 +1373,106 @@ pub struct SelfMatchEngine<E>
 	player_error_type:PhantomData<E>,
 	/// システムイベントキュー
 	pub system_event_queue:Arc<Mutex<SystemEventQueue>>,
+	seed:u64,
+	ponder:[bool; 2],
+	handicap:Option<Handicap>,
+	invalid_position_policy:InvalidPositionPolicy,
+	nyugyoku_rule:NyugyokuRule,
 }
 impl<E> SelfMatchEngine<E>
 	where E: PlayerError {
-	/// `SelfMatchEngine`の生成
+	/// `SelfMatchEngine`の生成（シードはランダムに決定される）
 	pub fn new() -> SelfMatchEngine<E> where E: PlayerError {
+		let mut rnd = prelude::thread_rng();
+
+		SelfMatchEngine::with_seed(rnd.gen())
+	}
+
+	/// シードを指定して`SelfMatchEngine`を生成
+	///
+	/// 局面のハッシュ計算はこのシードから初期化されるため、同じシードを指定すれば自己対局を再現できる
+	///
+	/// # Arguments
+	/// * `seed` - 自己対局に用いるシード
+	pub fn with_seed(seed:u64) -> SelfMatchEngine<E> where E: PlayerError {
 		SelfMatchEngine {
 			player_error_type:PhantomData::<E>,
 			system_event_queue:Arc::new(Mutex::new(EventQueue::new())),
+			seed,
+			ponder:[true,true],
+			handicap:None,
+			invalid_position_policy:InvalidPositionPolicy::Ignore,
+			nyugyoku_rule:NyugyokuRule::default(),
 		}
 	}
 
+	/// 駒落ちで対局を行うように設定する（`None`を指定すると平手）
+	///
+	/// `initial_position_creator`を指定せずに開始した場合、各対局は指定した駒落ちの初期局面から始まる。
+	/// 駒落ちの初期局面は上手（後手）から指し始めるため、`flip_players`がfalseの場合はplayer1が下手（先手）、
+	/// player2が上手（後手）となり、最初にplayer2が指す
+	///
+	/// # Arguments
+	/// * `handicap` - 駒落ちの種類
+	pub fn set_handicap(&mut self,handicap:Option<Handicap>) {
+		self.handicap = handicap;
+	}
+
+	/// プレイヤーごとに先読み（ponder）を行うかどうかを設定する（デフォルトはどちらも行う）
+	///
+	/// 先読みを行わないプレイヤーは、bestmoveでponderの指し手を返しても`think_ponder`が呼ばれない
+	///
+	/// # Arguments
+	/// * `player1` - player1に先読みを行わせるか否か
+	/// * `player2` - player2に先読みを行わせるか否か
+	pub fn set_ponder(&mut self,player1:bool,player2:bool) {
+		self.ponder = [player1,player2];
+	}
+
+	/// `initial_position_creator`が不正な局面を返した時の扱いを設定する（デフォルトは検証しない）
+	///
+	/// `InvalidPositionPolicy::Reject`を指定した場合、不正な局面が生成された時点で自己対局をエラー終了する
+	///
+	/// # Arguments
+	/// * `policy` - 不正な局面の扱い
+	pub fn set_invalid_position_policy(&mut self,policy:InvalidPositionPolicy) {
+		self.invalid_position_policy = policy;
+	}
+
+	/// 入玉宣言の規則を設定する（デフォルトは27点法）
+	///
+	/// プレイヤーが`EnteringKingRule`オプションをサポートしていて、かつ`player1_options`,`player2_options`で
+	/// 値が指定されていない場合は、ここで設定した規則がオプションとしてプレイヤーに渡される
+	///
+	/// # Arguments
+	/// * `rule` - 入玉宣言の規則
+	pub fn set_nyugyoku_rule(&mut self,rule:NyugyokuRule) {
+		self.nyugyoku_rule = rule;
+	}
+
+	/// 自己対局に用いるシードを取得
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	/// 自己対局のシードから決定的に初期化された乱数生成器を生成
+	///
+	/// `flip_players`や`initial_position_creator`に渡すクロージャ内でこの乱数生成器を使うことで、
+	/// 対局の手番の割り当てや初期局面の選択もシードから再現できるようになる
+	///
+	/// # Arguments
+	/// * `stream` - 用途ごとに異なる値を指定することで、互いに独立した乱数列を得るための識別子
+	pub fn create_rng(&self,stream:u64) -> XorShiftRng {
+		XorShiftRng::seed_from_u64(self.seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+	}
+
 	/// デフォルト設定で開始（ログファイルのパスlogs/log.txt,ログをファイルに記録）
 	///
 	/// # Arguments
 	/// * `on_init_event_dispatcher` - 自己対局時に通知されるSelfMatchEventのイベントディスパッチャーを初期化
 	/// * `flip_players` - 対局時の初期局面時のplayer1とplayer2の手番の割り当てを逆にする。(通常はplayer1が先手)
 	/// * `initial_position_creator` - 対局毎の初期局面を生成して返す関数
-	/// * `kifu_writer` - 対局終了時に棋譜を書き込むためのコールバック関数
+	/// * `kifu_writer` - 対局終了時に対局の記録（`GameRecord`）を書き込むためのコールバック関数
 	/// * `input_handler` - 標準入力から読みこんだ行が渡されるコールバック関数。システムイベントの発行などに使う（'quit'で終了など）
 	/// * `player1` - USIPlayerを実装したプレイヤーオブジェクト
 	/// * `player2` - USIPlayerを実装したプレイヤーオブジェクト
@@ -202,7 +1487,7 @@ impl<E> SelfMatchEngine<E>
 	pub fn start_default<T,S,P,I,F,RH,EH>(&mut self, on_init_event_dispatcher:I,
 						flip_players:F,
 						initial_position_creator:Option<Box<dyn FnMut() -> String + Send + 'static>>,
-						kifu_writer:Option<Box<dyn FnMut(&String,&Vec<Move>) -> Result<(),KifuWriteError>  +Send + 'static>>,
+						kifu_writer:Option<Box<dyn FnMut(&GameRecord) -> Result<(),KifuWriteError>  +Send + 'static>>,
 						input_handler:RH,
 						player1:T,
 						player2:T,
@@ -245,7 +1530,7 @@ impl<E> SelfMatchEngine<E>
 	/// * `on_init_event_dispatcher` - 自己対局時に通知されるSelfMatchEventのイベントディスパッチャーを初期化
 	/// * `flip_players` - 対局時の初期局面時のplayer1とplayer2の手番の割り当てを逆にする。(通常はplayer1が先手)
 	/// * `initial_position_creator` - 対局毎の初期局面を生成して返す関数
-	/// * `kifu_writer` - 対局終了時に棋譜を書き込むためのコールバック関数
+	/// * `kifu_writer` - 対局終了時に対局の記録（`GameRecord`）を書き込むためのコールバック関数
 	/// * `input_handler` - 標準入力から読みこんだ行が渡されるコールバック関数。システムイベントの発行などに使う（'quit'で終了など）
 	/// * `player1` - USIPlayerを実装したプレイヤーオブジェクト
 	/// * `player2` - USIPlayerを実装したプレイヤーオブジェクト
@@ -261,7 +1546,7 @@ impl<E> SelfMatchEngine<E>
 						on_init_event_dispatcher:I,
 						flip_players:F,
 						initial_position_creator:Option<Box<dyn FnMut() -> String + Send + 'static>>,
-						kifu_writer:Option<Box<dyn FnMut(&String,&Vec<Move>) -> Result<(),KifuWriteError>  +Send + 'static>>,
+						kifu_writer:Option<Box<dyn FnMut(&GameRecord) -> Result<(),KifuWriteError>  +Send + 'static>>,
 						input_handler:RH,
 						player1:T,
 						player2:T,
@@ -307,13 +1592,89 @@ impl<E> SelfMatchEngine<E>
 					logger, on_error)
 	}
 
+	/// 自己対局の設定（`SelfMatchConfig`）に従って開始
+	///
+	/// 先読みの有無、プレイヤーのオプション、制限時間、対局回数、実行時間制限、棋譜の記録先、ログファイルのパスは設定の値が使われる。
+	/// オプションの値はプレイヤーのオプションの定義に基づいて検証され、不正な値があった場合は開始せずにエラーを返す。
+	/// シードは`SelfMatchConfig::create_engine`で生成した場合にのみ反映される
+	///
+	/// # Arguments
+	/// * `config` - 自己対局の設定
+	/// * `on_init_event_dispatcher` - 自己対局時に通知されるSelfMatchEventのイベントディスパッチャーを初期化
+	/// * `flip_players` - 対局時の初期局面時のplayer1とplayer2の手番の割り当てを逆にする。(通常はplayer1が先手)
+	/// * `initial_position_creator` - 対局毎の初期局面を生成して返す関数
+	/// * `input_handler` - 標準入力から読みこんだ行が渡されるコールバック関数。システムイベントの発行などに使う（'quit'で終了など）
+	/// * `player1` - USIPlayerを実装したプレイヤーオブジェクト
+	/// * `player2` - USIPlayerを実装したプレイヤーオブジェクト
+	/// * `info_sender` - infoコマンドを送信するための機能を持つオブジェクト
+	/// * `pinfo_sender` - あらかじめスケジュールされた一定の間隔でinfoコマンドを送信するための機能を持つオブジェクト
+	/// * `on_error` - エラー発生時に呼ばれるコールバック関数。エラーオブジェクトへの参照とロガーが渡される。
+	pub fn start_with_config<T,S,P,I,F,RH,EH>(&mut self,config:&SelfMatchConfig,
+						on_init_event_dispatcher:I,
+						flip_players:F,
+						initial_position_creator:Option<Box<dyn FnMut() -> String + Send + 'static>>,
+						input_handler:RH,
+						mut player1:T,
+						mut player2:T,
+						info_sender:S,
+						pinfo_sender:P,
+						mut on_error:EH) -> Result<SelfMatchResult,SelfMatchRunningError<E>>
+		where T: USIPlayer<E> + fmt::Debug + Send + 'static,
+				F: FnMut() -> bool + Send + 'static,
+				RH: FnMut(String) -> Result<bool,SelfMatchRunningError<E>> + Send + 'static,
+				I: FnMut(&mut SelfMatchEventDispatcher<E,FileLogger>),
+				S: InfoSender,
+				P: PeriodicallyInfo + Clone + Send + 'static,
+				Arc<Mutex<FileLogger>>: Send + 'static,
+				EH: FnMut(Option<Arc<Mutex<OnErrorHandler<FileLogger>>>>,
+					&SelfMatchRunningError<E>) {
+		self.set_ponder(config.player1.ponder,config.player2.ponder);
+
+		let options = parse_player_options(&mut player1,&config.player1.options).and_then(|player1_options| {
+			parse_player_options(&mut player2,&config.player2.options).map(|player2_options| {
+				(player1_options,player2_options)
+			})
+		});
+
+		let (player1_options,player2_options) = match options {
+			Ok(options) => options,
+			Err(e) => {
+				on_error(None,&e);
+				return Err(e);
+			}
+		};
+
+		let kifu_writer = match config.create_kifu_writer() {
+			Ok(kifu_writer) => kifu_writer,
+			Err(e) => {
+				let e = SelfMatchRunningError::KifuWriteError(e);
+				on_error(None,&e);
+				return Err(e);
+			}
+		};
+
+		self.start_with_log_path(config.log.clone(),
+								on_init_event_dispatcher,
+								flip_players,
+								initial_position_creator,
+								kifu_writer, input_handler,
+								player1,player2,
+								player1_options, player2_options,
+								info_sender,
+								pinfo_sender,
+								config.time_limit,
+								config.uptime,
+								config.number_of_games,
+								on_error)
+	}
+
 	/// `Logger`,`USIInputReader`を指定して開始
 	///
 	/// # Arguments
 	/// * `on_init_event_dispatcher` - 自己対局時に通知されるSelfMatchEventのイベントディスパッチャーを初期化
 	/// * `flip_players` - 対局時の初期局面時のplayer1とplayer2の手番の割り当てを逆にする。(通常はplayer1が先手)
 	/// * `initial_position_creator` - 対局毎の初期局面を生成して返す関数
-	/// * `kifu_writer` - 対局終了時に棋譜を書き込むためのコールバック関数
+	/// * `kifu_writer` - 対局終了時に対局の記録（`GameRecord`）を書き込むためのコールバック関数
 	/// * `input_reader` - 入力を読み取るためのオブジェクト。実装によって標準入力以外から読み取るものを指定することも可能。
 	/// * `input_handler` - 標準入力から読みこんだ行が渡されるコールバック関数。システムイベントの発行などに使う（'quit'で終了など）
 	/// * `player1` - USIPlayerを実装したプレイヤーオブジェクト
@@ -330,7 +1691,7 @@ impl<E> SelfMatchEngine<E>
 	pub fn start<T,S,P,I,F,R,RH,L,EH>(&mut self, on_init_event_dispatcher:I,
 						flip_players:F,
 						initial_position_creator:Option<Box<dyn FnMut() -> String + Send + 'static>>,
-						kifu_writer:Option<Box<dyn FnMut(&String,&Vec<Move>) -> Result<(),KifuWriteError>  +Send + 'static>>,
+						kifu_writer:Option<Box<dyn FnMut(&GameRecord) -> Result<(),KifuWriteError>  +Send + 'static>>,
 						input_reader:R,
 						input_handler:RH,
 						player1:T,
@@ -381,7 +1742,7 @@ impl<E> SelfMatchEngine<E>
 	fn run<T,S,P,I,F,R,RH,L>(&mut self, mut on_init_event_dispatcher:I,
 						mut flip_players:F,
 						initial_position_creator:Option<Box<dyn FnMut() -> String + Send + 'static>>,
-						kifu_writer:Option<Box<dyn FnMut(&String,&Vec<Move>) -> Result<(),KifuWriteError> + Send + 'static>>,
+						kifu_writer:Option<Box<dyn FnMut(&GameRecord) -> Result<(),KifuWriteError> + Send + 'static>>,
 						mut input_reader:R,
 						mut input_handler:RH,
 						mut player1:T,
@@ -406,6 +1767,10 @@ impl<E> SelfMatchEngine<E>
 				Arc<Mutex<L>>: Send + 'static {
 		let start_time = Instant::now();
 		let start_dt = Local::now();
+		let seed = self.seed;
+		let ponder = self.ponder;
+		let invalid_position_policy = self.invalid_position_policy;
+		let nyugyoku_rule = self.nyugyoku_rule;
 
 		let mut self_match_event_dispatcher:SelfMatchEventDispatcher<E,L> = USIEventDispatcher::new(&on_error_handler_arc);
 
@@ -417,20 +1782,46 @@ impl<E> SelfMatchEngine<E>
 
 		let user_event_queue = [user_event_queue_arc[0].clone(),user_event_queue_arc[1].clone()];
 
+		let initial_position = match self.handicap {
+			Some(h) => h.to_sfen().map_err(|_| SelfMatchRunningError::InvalidState(String::from(
+				"Failed to create the initial position of the handicap game."
+			)))?,
+			None => String::from("startpos"),
+		};
+
 		let mut initial_position_creator:Box<dyn FnMut() -> String + Send + 'static> =
-			initial_position_creator.map_or(Box::new(|| String::from("startpos")), |f| {
+			initial_position_creator.map_or(Box::new(move || initial_position.clone()), |f| {
 				f
 			});
 
 		let on_error_handler = on_error_handler_arc.clone();
 
 		let mut kifu_writer = kifu_writer;
-		let mut kifu_writer = move |sfen:&String,m:&Vec<Move>| {
+		let mut kifu_writer = move |sfen:&String,
+									game_start_dt:DateTime<Local>,
+									sente_player:u32,
+									moves:Vec<MoveRecord>,
+									end_state:SelfMatchGameEndState| {
 			let _ = kifu_writer.as_mut().map(|w| {
-				let _= w(sfen,m).map_err(|e| on_error_handler.lock().map(|h| h.call(&e)));
+				let record = GameRecord {
+					initial_sfen:sfen.clone(),
+					sente_name:format!("{}(player{})",T::ID,sente_player),
+					gote_name:format!("{}(player{})",T::ID,sente_player % 2 + 1),
+					sente_player,
+					start_dt:game_start_dt,
+					end_dt:Local::now(),
+					end_state,
+					seed,
+					time_limit:game_time_limit,
+					moves
+				};
+
+				let _= w(&record).map_err(|e| on_error_handler.lock().map(|h| h.call(&e)));
 			});
 		};
 
+		let last_info_arc:[Arc<Mutex<LastInfo>>; 2] = [Arc::new(Mutex::new(LastInfo::new())),Arc::new(Mutex::new(LastInfo::new()))];
+
 		let quit_ready_arc = Arc::new(AtomicBool::new(false));
 		let on_error_handler = on_error_handler_arc.clone();
 
@@ -475,6 +1866,26 @@ impl<E> SelfMatchEngine<E>
 			});
 		}
 
+		let player1_options = match with_nyugyoku_rule_option(&mut player1,player1_options,nyugyoku_rule) {
+			Ok(options) => options,
+			Err(ref e) => {
+				let _ = on_error_handler.lock().map(|h| h.call(e));
+				return Err(SelfMatchRunningError::Fail(String::from(
+					"An error occurred while executing a self match. Please see the log for details ..."
+				)));
+			}
+		};
+
+		let player2_options = match with_nyugyoku_rule_option(&mut player2,player2_options,nyugyoku_rule) {
+			Ok(options) => options,
+			Err(ref e) => {
+				let _ = on_error_handler.lock().map(|h| h.call(e));
+				return Err(SelfMatchRunningError::Fail(String::from(
+					"An error occurred while executing a self match. Please see the log for details ..."
+				)));
+			}
+		};
+
 		for (k,v) in player1_options {
 			match player1.set_option(k,v) {
 				Ok(()) => (),
@@ -508,10 +1919,14 @@ impl<E> SelfMatchEngine<E>
 
 		let user_event_queue = user_event_queue_arc.clone();
 
+		let last_info = [last_info_arc[0].clone(),last_info_arc[1].clone()];
+
 		let bridge_h = thread::spawn(move || SandBox::immediate(|| {
 			let cs = [cs1.clone(),cs2.clone()];
 			let mut prev_move:Option<AppliedMove> = None;
 			let mut ponders:[Option<AppliedMove>; 2] = [None,None];
+			let mut ponder_start_time:[Instant; 2] = [Instant::now(),Instant::now()];
+			let mut ponder_statistics:[PonderStatistics; 2] = [PonderStatistics::default(),PonderStatistics::default()];
 
 			let quit_ready_inner = quit_ready.clone();
 
@@ -529,7 +1944,11 @@ impl<E> SelfMatchEngine<E>
 									_:[Sender<SelfMatchMessage>; 2],
 									sr:&Receiver<SelfMatchMessage>,
 									s:SelfMatchGameEndState| {
-				let mut message_state = GameEndState::Win;
+				let mut message_state = if s == SelfMatchGameEndState::Draw {
+					GameEndState::Draw
+				} else {
+					GameEndState::Win
+				};
 
 				let quit_notification = || {
 					quit_ready_inner.store(true,Ordering::Release);
@@ -565,11 +1984,15 @@ impl<E> SelfMatchEngine<E>
 							)));
 						}
 					}
-					message_state = GameEndState::Lose;
+					if message_state == GameEndState::Win {
+						message_state = GameEndState::Lose;
+					}
 				}
 				Ok(())
 			};
 
+			let hasher = KyokumenHash::with_seed(seed);
+
 			let mut game_count = 0;
 
 			'gameloop: while !quit_ready.load(Ordering::Acquire) &&
@@ -581,6 +2004,8 @@ impl<E> SelfMatchEngine<E>
 
 				game_count += 1;
 
+				let game_start_dt = Local::now();
+
 				let mut cs_index = if flip_players() {
 					1
 				} else {
@@ -600,10 +2025,65 @@ impl<E> SelfMatchEngine<E>
 					}
 				};
 
+				if invalid_position_policy != InvalidPositionPolicy::Ignore {
+					let mut invalid = false;
+
+					if let Err(errors) = Rule::validate_position(&banmen,teban,&mc) {
+						for e in &errors {
+							let _ = on_error_handler.lock().map(|h| h.call(e));
+						}
+
+						invalid = true;
+					}
+
+					if !invalid {
+						let mut state = State::new(banmen.clone());
+						let mut teban = teban;
+						let mut mc = mc.clone();
+
+						for m in &mvs {
+							match Rule::apply_valid_move(&state,teban,&mc,m.to_applied_move()) {
+								Ok((next,nmc,_)) => {
+									state = next;
+									mc = nmc;
+									teban = teban.opposite();
+								},
+								Err(ref e) => {
+									let _ = on_error_handler.lock().map(|h| h.call(e));
+									invalid = true;
+									break;
+								}
+							}
+						}
+
+						if !invalid {
+							if let Err(errors) = Rule::validate_position(state.get_banmen(),teban,&mc) {
+								for e in &errors {
+									let _ = on_error_handler.lock().map(|h| h.call(e));
+								}
+
+								invalid = true;
+							}
+						}
+					}
+
+					if invalid && invalid_position_policy == InvalidPositionPolicy::Reject {
+						return Err(SelfMatchRunningError::InvalidState(String::from(
+							"The initial position is invalid."
+						)));
+					}
+				}
+
 				if teban == Teban::Gote {
 					cs_index = (cs_index + 1) % 2;
 				}
 
+				let sente_player = if teban == Teban::Sente {
+					cs_index as u32 + 1
+				} else {
+					(cs_index as u32 + 1) % 2 + 1
+				};
+
 				let banmen_at_start = banmen.clone();
 				let mc_at_start = mc.clone();
 				let teban_at_start = teban.clone();
@@ -614,8 +2094,6 @@ impl<E> SelfMatchEngine<E>
 				let kyokumen_map:KyokumenMap<u64,u32> = KyokumenMap::new();
 				let oute_kyokumen_map:KyokumenMap<u64,u32> = KyokumenMap::new();
 
-				let hasher = KyokumenHash::new();
-
 				let (ms,mg) = match mc {
 					MochigomaCollections::Pair(ref ms, ref mg) => {
 						match teban {
@@ -630,6 +2108,10 @@ impl<E> SelfMatchEngine<E>
 
 				let (mhash, shash) = hasher.calc_initial_hash(&banmen,&ms,&mg);
 
+				let mut move_records = mvs.iter().map(|&m| {
+					MoveRecord::new(m,Duration::from_secs(0),None,None)
+				}).collect::<Vec<MoveRecord>>();
+
 				let mut mvs = mvs.into_iter().map(|m| m.to_applied_move()).collect::<Vec<AppliedMove>>();
 
 				let (mut teban,
@@ -681,6 +2163,9 @@ impl<E> SelfMatchEngine<E>
 								teban_at_start.clone(),banmen_at_start.clone(),mc_at_start.clone(),n,mvs.clone(),Instant::now()));
 						},
 						pm @ Some(_) if pm == prev_move => {
+							ponder_statistics[cs_index].hit_count += 1;
+							ponder_statistics[cs_index].time_saved += ponder_start_time[cs_index].elapsed();
+
 							match user_event_queue[cs_index].lock() {
 								Ok(mut user_event_queue) => {
 									user_event_queue.push(UserEvent::PonderHit(Instant::now()));
@@ -750,6 +2235,16 @@ impl<E> SelfMatchEngine<E>
 									);
 									current_time_limit = current_game_time_limit[cs_index].to_instant(teban,Instant::now());
 
+									let record = match last_info[cs_index].lock() {
+										Ok(mut last_info) => {
+											MoveRecord::new(m,think_start_time.elapsed(),last_info.score.take(),last_info.pv.take())
+										},
+										Err(ref e) => {
+											let _ = on_error_handler.lock().map(|h| h.call(e));
+											MoveRecord::new(m,think_start_time.elapsed(),None,None)
+										}
+									};
+
 									let m = m.to_applied_move();
 
 									match Rule::apply_valid_move(&state,teban,&mc,m) {
@@ -759,10 +2254,9 @@ impl<E> SelfMatchEngine<E>
 
 											if is_win {
 												mvs.push(m);
+												move_records.push(record);
 
-												kifu_writer(&sfen,&mvs.into_iter()
-																		.map(|m| m.to_move())
-																		.collect::<Vec<Move>>());
+												kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Win(teban));
 												on_gameend(
 													cs[cs_index].clone(),
 													cs[(cs_index+1) % 2].clone(),
@@ -776,9 +2270,8 @@ impl<E> SelfMatchEngine<E>
 											if Rule::in_check(teban.opposite(),&state) {
 												if Rule::in_check(teban.opposite(),&next) {
 													mvs.push(m);
-													kifu_writer(&sfen,&mvs.into_iter()
-																			.map(|m| m.to_move())
-																			.collect::<Vec<Move>>());
+													move_records.push(record);
+													kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Foul(teban,FoulKind::NotRespondedOute));
 													on_gameend(
 														cs[(cs_index+1) % 2].clone(),
 														cs[cs_index].clone(),
@@ -791,9 +2284,8 @@ impl<E> SelfMatchEngine<E>
 											} else {
 												if Rule::in_check(teban.opposite(),&next) {
 													mvs.push(m);
-													kifu_writer(&sfen,&mvs.into_iter()
-																			.map(|m| m.to_move())
-																			.collect::<Vec<Move>>());
+													move_records.push(record);
+													kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Foul(teban,FoulKind::Suicide));
 													on_gameend(
 														cs[(cs_index+1) % 2].clone(),
 														cs[cs_index].clone(),
@@ -806,6 +2298,7 @@ impl<E> SelfMatchEngine<E>
 											}
 
 											mvs.push(m);
+											move_records.push(record);
 
 											mhash = hasher.calc_main_hash(mhash,teban,&state.get_banmen(),&mc,m,&o);
 											shash = hasher.calc_sub_hash(shash,teban,&state.get_banmen(),&mc,m,&o);
@@ -814,9 +2307,7 @@ impl<E> SelfMatchEngine<E>
 											state = next;
 
 											if Rule::is_put_fu_and_mate(&state,teban,&mc,m) {
-												kifu_writer(&sfen,&mvs.into_iter()
-																				.map(|m| m.to_move())
-																				.collect::<Vec<Move>>());
+												kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Foul(teban,FoulKind::PutFuAndMate));
 												on_gameend(
 													cs[(cs_index+1) % 2].clone(),
 													cs[cs_index].clone(),
@@ -832,9 +2323,7 @@ impl<E> SelfMatchEngine<E>
 												teban,mhash,shash,
 												&oute_kyokumen_map
 											) {
-												kifu_writer(&sfen,&mvs.into_iter()
-																		.map(|m| m.to_move())
-																		.collect::<Vec<Move>>());
+												kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Foul(teban,FoulKind::SennichiteOu));
 												on_gameend(
 													cs[(cs_index+1) % 2].clone(),
 													cs[cs_index].clone(),
@@ -854,9 +2343,7 @@ impl<E> SelfMatchEngine<E>
 											if Rule::is_sennichite(
 												&state,teban,mhash,shash,&kyokumen_map
 											) {
-												kifu_writer(&sfen,&mvs.into_iter()
-																		.map(|m| m.to_move())
-																		.collect::<Vec<Move>>());
+												kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Foul(teban,FoulKind::Sennichite));
 												on_gameend(
 													cs[(cs_index+1) % 2].clone(),
 													cs[cs_index].clone(),
@@ -873,10 +2360,13 @@ impl<E> SelfMatchEngine<E>
 
 											teban = teban.opposite();
 
-											ponders[cs_index] = pm.map(|pm| pm.to_applied_move());
+											ponders[cs_index] = pm.filter(|_| ponder[cs_index]).map(|pm| pm.to_applied_move());
 
 											match pm {
-												Some(pm) => {
+												Some(pm) if ponder[cs_index] => {
+													ponder_statistics[cs_index].ponder_count += 1;
+													ponder_start_time[cs_index] = Instant::now();
+
 													match mvs.clone() {
 														mut mvs => {
 															mvs.push(pm.to_applied_move());
@@ -887,16 +2377,15 @@ impl<E> SelfMatchEngine<E>
 														}
 													}
 												},
-												None => (),
+												_ => (),
 											}
 
 											cs_index = (cs_index + 1) % 2;
 										},
 										Err(_) => {
 											mvs.push(m);
-											kifu_writer(&sfen,&mvs.into_iter()
-																	.map(|m| m.to_move())
-																	.collect::<Vec<Move>>());
+											move_records.push(record);
+											kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Foul(teban,FoulKind::InvalidMove));
 											on_gameend(
 												cs[(cs_index+1) % 2].clone(),
 												cs[cs_index].clone(),
@@ -910,9 +2399,7 @@ impl<E> SelfMatchEngine<E>
 									prev_move = Some(m)
 								},
 								SelfMatchMessage::NotifyMove(BestMove::Resign) => {
-									kifu_writer(&sfen,&mvs.into_iter()
-															.map(|m| m.to_move())
-															.collect::<Vec<Move>>());
+									kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Resign(teban));
 									on_gameend(
 										cs[(cs_index+1) % 2].clone(),
 										cs[cs_index].clone(),
@@ -938,30 +2425,39 @@ impl<E> SelfMatchEngine<E>
 									}
 									break;
 								},
-								SelfMatchMessage::NotifyMove(BestMove::Win) if Rule::is_nyugyoku_win(&state,teban,&mc,&current_time_limit)=> {
-									kifu_writer(&sfen,&mvs.into_iter()
-															.map(|m| m.to_move())
-															.collect::<Vec<Move>>());
-									on_gameend(
-										cs[cs_index].clone(),
-										cs[(cs_index+1) % 2].clone(),
-										[cs[0].clone(),cs[1].clone()],
-										&sr,
-										SelfMatchGameEndState::NyuGyokuWin(teban)
-									)?;
-									break;
-								},
 								SelfMatchMessage::NotifyMove(BestMove::Win) => {
-									kifu_writer(&sfen,&mvs.into_iter()
-															.map(|m| m.to_move())
-															.collect::<Vec<Move>>());
-									on_gameend(
-										cs[(cs_index+1) % 2].clone(),
-										cs[cs_index].clone(),
-										[cs[0].clone(),cs[1].clone()],
-										&sr,
-										SelfMatchGameEndState::NyuGyokuLose(teban)
-									)?;
+									match Rule::judge_nyugyoku(&state,teban,&mc,&current_time_limit,nyugyoku_rule) {
+										GameEndState::Win => {
+											kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::NyuGyokuWin(teban));
+											on_gameend(
+												cs[cs_index].clone(),
+												cs[(cs_index+1) % 2].clone(),
+												[cs[0].clone(),cs[1].clone()],
+												&sr,
+												SelfMatchGameEndState::NyuGyokuWin(teban)
+											)?;
+										},
+										GameEndState::Draw => {
+											kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Draw);
+											on_gameend(
+												cs[cs_index].clone(),
+												cs[(cs_index+1) % 2].clone(),
+												[cs[0].clone(),cs[1].clone()],
+												&sr,
+												SelfMatchGameEndState::Draw
+											)?;
+										},
+										GameEndState::Lose => {
+											kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::NyuGyokuLose(teban));
+											on_gameend(
+												cs[(cs_index+1) % 2].clone(),
+												cs[cs_index].clone(),
+												[cs[0].clone(),cs[1].clone()],
+												&sr,
+												SelfMatchGameEndState::NyuGyokuLose(teban)
+											)?;
+										}
+									}
 									break;
 								},
 								SelfMatchMessage::Error(n) => {
@@ -978,6 +2474,8 @@ impl<E> SelfMatchEngine<E>
 										elapsed: start_time.elapsed(),
 										start_dt:start_dt,
 										end_dt:Local::now(),
+										seed,
+										ponder_statistics,
 									});
 								},
 								_ => {
@@ -1001,7 +2499,7 @@ impl<E> SelfMatchEngine<E>
 
 									match timeout_kind {
 										TimeoutKind::Turn => {
-											kifu_writer(&sfen,&mvs.into_iter().map(|m| m.to_move()).collect::<Vec<Move>>());
+											kifu_writer(&sfen,game_start_dt,sente_player,move_records,SelfMatchGameEndState::Timeover(teban));
 											match sr.recv()? {
 												SelfMatchMessage::NotifyMove(_) => {
 													on_gameend(
@@ -1054,7 +2552,9 @@ impl<E> SelfMatchEngine<E>
 				game_count: game_count,
 				elapsed: start_time.elapsed(),
 				start_dt:start_dt,
-				end_dt:Local::now()
+				end_dt:Local::now(),
+				seed,
+				ponder_statistics,
 			})
 		}, on_error_handler.clone()).map_err(|e| {
 			match e {
@@ -1101,7 +2601,8 @@ impl<E> SelfMatchEngine<E>
 			let logger = logger_arc.clone();
 			let user_event_queue = [user_event_queue_arc[0].clone(),user_event_queue_arc[1].clone()];
 			let quit_ready = quit_ready_arc.clone();
-			let info_sender = info_sender.clone();
+			let last_info = last_info_arc[i].clone();
+			let info_sender = LastInfoRecordingSender::new(info_sender.clone(),last_info.clone());
 			let pinfo_sender = pinfo_sender.clone();
 			let limit = game_time_limit;
 
@@ -1121,6 +2622,15 @@ impl<E> SelfMatchEngine<E>
 							loop {
 								match cr.recv()? {
 									SelfMatchMessage::StartThink(t,b,mc,n,m,s) => {
+										match last_info.lock() {
+											Ok(mut last_info) => {
+												last_info.clear();
+											},
+											Err(ref e) => {
+												let _ = on_error_handler.lock().map(|h| h.call(e));
+											}
+										}
+
 										let (ms, mg) = match mc {
 											MochigomaCollections::Pair(ref ms, ref mg) => {
 												(ms.clone(),mg.clone())
@@ -1145,6 +2655,15 @@ impl<E> SelfMatchEngine<E>
 										}
 									},
 									SelfMatchMessage::StartPonderThink(t,b,mc,n,m) => {
+										match last_info.lock() {
+											Ok(mut last_info) => {
+												last_info.clear();
+											},
+											Err(ref e) => {
+												let _ = on_error_handler.lock().map(|h| h.call(e));
+											}
+										}
+
 										let (ms, mg) = match mc {
 											MochigomaCollections::Pair(ref ms, ref mg) => {
 												(ms.clone(),mg.clone())
@@ -1184,7 +2703,7 @@ impl<E> SelfMatchEngine<E>
 											}
 											_ => {
 												let _ = logger.lock().map(|mut logger| {
-													logger.logging(&format!("Invalid message."))
+													logger.warn(module_path!(),"Invalid message.")
 												}).map_err(|_| {
 													USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 													false
@@ -1222,7 +2741,7 @@ impl<E> SelfMatchEngine<E>
 									},
 									_ => {
 										let _ = logger.lock().map(|mut logger| {
-											logger.logging(&format!("Invalid message."))
+											logger.warn(module_path!(),"Invalid message.")
 										}).map_err(|_| {
 											USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 											false
@@ -1247,7 +2766,7 @@ impl<E> SelfMatchEngine<E>
 						},
 						_ => {
 							let _ = logger.lock().map(|mut logger| {
-								logger.logging(&format!("Invalid message."))
+								logger.warn(module_path!(),"Invalid message.")
 							}).map_err(|_| {
 								USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 								false
@@ -1341,7 +2860,7 @@ impl<E> SelfMatchEngine<E>
 		let result = bridge_h.join().map_err(|_| {
 			has_error = true;
 			let _ = logger.lock().map(|mut logger| {
-				logger.logging(&format!("Main thread join failed."))
+				logger.error(module_path!(),"Main thread join failed.")
 			}).map_err(|_| {
 				USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 				false
@@ -1357,7 +2876,7 @@ impl<E> SelfMatchEngine<E>
 			let _ = h.join().map_err(|_| {
 				has_error = true;
 				let _ = logger.lock().map(|mut logger| {
-					logger.logging(&format!("Sub thread join failed."))
+					logger.error(module_path!(),"Sub thread join failed.")
 				}).map_err(|_| {
 					USIStdErrorWriter::write("Logger's exclusive lock could not be secured").unwrap();
 					false
@@ -293,6 +293,123 @@ impl MochigomaCollections {
 		}
 	}
 }
+/// 局面の対称変換
+///
+/// 局面を構成する要素すべてに同じ変換を適用すると、指し手の合法性が保たれた局面が得られる
+pub trait Symmetry {
+	/// 盤面を180度回転させて先手と後手を入れ替える
+	fn flip(&self) -> Self;
+	/// 筋を左右反転させる（1筋と9筋を入れ替える）
+	fn mirror(&self) -> Self;
+}
+impl Symmetry for KomaKind {
+	fn flip(&self) -> KomaKind {
+		match *self {
+			KomaKind::SFu => KomaKind::GFu,
+			KomaKind::SKyou => KomaKind::GKyou,
+			KomaKind::SKei => KomaKind::GKei,
+			KomaKind::SGin => KomaKind::GGin,
+			KomaKind::SKin => KomaKind::GKin,
+			KomaKind::SKaku => KomaKind::GKaku,
+			KomaKind::SHisha => KomaKind::GHisha,
+			KomaKind::SOu => KomaKind::GOu,
+			KomaKind::SFuN => KomaKind::GFuN,
+			KomaKind::SKyouN => KomaKind::GKyouN,
+			KomaKind::SKeiN => KomaKind::GKeiN,
+			KomaKind::SGinN => KomaKind::GGinN,
+			KomaKind::SKakuN => KomaKind::GKakuN,
+			KomaKind::SHishaN => KomaKind::GHishaN,
+			KomaKind::GFu => KomaKind::SFu,
+			KomaKind::GKyou => KomaKind::SKyou,
+			KomaKind::GKei => KomaKind::SKei,
+			KomaKind::GGin => KomaKind::SGin,
+			KomaKind::GKin => KomaKind::SKin,
+			KomaKind::GKaku => KomaKind::SKaku,
+			KomaKind::GHisha => KomaKind::SHisha,
+			KomaKind::GOu => KomaKind::SOu,
+			KomaKind::GFuN => KomaKind::SFuN,
+			KomaKind::GKyouN => KomaKind::SKyouN,
+			KomaKind::GKeiN => KomaKind::SKeiN,
+			KomaKind::GGinN => KomaKind::SGinN,
+			KomaKind::GKakuN => KomaKind::SKakuN,
+			KomaKind::GHishaN => KomaKind::SHishaN,
+			KomaKind::Blank => KomaKind::Blank,
+		}
+	}
+
+	fn mirror(&self) -> KomaKind {
+		*self
+	}
+}
+impl Symmetry for Banmen {
+	fn flip(&self) -> Banmen {
+		let mut kinds = [[KomaKind::Blank; 9]; 9];
+
+		for (y,row) in self.0.iter().enumerate() {
+			for (x,k) in row.iter().enumerate() {
+				kinds[8 - y][8 - x] = k.flip();
+			}
+		}
+
+		Banmen(kinds)
+	}
+
+	fn mirror(&self) -> Banmen {
+		let mut kinds = self.0;
+
+		for row in kinds.iter_mut() {
+			row.reverse();
+		}
+
+		Banmen(kinds)
+	}
+}
+impl Symmetry for Teban {
+	fn flip(&self) -> Teban {
+		self.opposite()
+	}
+
+	fn mirror(&self) -> Teban {
+		*self
+	}
+}
+impl Symmetry for MochigomaCollections {
+	fn flip(&self) -> MochigomaCollections {
+		match *self {
+			MochigomaCollections::Empty => MochigomaCollections::Empty,
+			MochigomaCollections::Pair(ref ms, ref mg) => {
+				MochigomaCollections::Pair(mg.clone(),ms.clone())
+			}
+		}
+	}
+
+	fn mirror(&self) -> MochigomaCollections {
+		self.clone()
+	}
+}
+impl Symmetry for Move {
+	fn flip(&self) -> Move {
+		match *self {
+			Move::To(KomaSrcPosition(sx,sy),KomaDstToPosition(dx,dy,n)) => {
+				Move::To(KomaSrcPosition(10 - sx,10 - sy),KomaDstToPosition(10 - dx,10 - dy,n))
+			},
+			Move::Put(kind,KomaDstPutPosition(x,y)) => {
+				Move::Put(kind,KomaDstPutPosition(10 - x,10 - y))
+			}
+		}
+	}
+
+	fn mirror(&self) -> Move {
+		match *self {
+			Move::To(KomaSrcPosition(sx,sy),KomaDstToPosition(dx,dy,n)) => {
+				Move::To(KomaSrcPosition(10 - sx,sy),KomaDstToPosition(10 - dx,dy,n))
+			},
+			Move::Put(kind,KomaDstPutPosition(x,y)) => {
+				Move::Put(kind,KomaDstPutPosition(10 - x,y))
+			}
+		}
+	}
+}
 /// 獲った駒の種別
 #[derive(Clone, Copy, Eq, PartialOrd, PartialEq, Debug, Hash)]
 pub enum ObtainKind {
@@ -496,7 +613,8 @@ const MOCHIGOMA_START_INDEXES:[u8; MOCHIGOMA_KIND_MAX + 1] = [
 	34,
 	36
 ];
-const MOCHIGOMA_MAX_COUNT:[u8; MOCHIGOMA_KIND_MAX + 1] = [
+/// 持ち駒の種類ごとの駒の最大数（盤上の駒を含めた全体の枚数）
+pub const MOCHIGOMA_MAX_COUNT:[u8; MOCHIGOMA_KIND_MAX + 1] = [
 	18,
 	4,
 	4,
@@ -0,0 +1,477 @@
+//! GUIとエンジンの間のUSIプロトコルの通信の記録と再生
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use error::*;
+use event::{SystemEventKind, SystemEventQueue};
+use input::USIInputReader;
+use output::USIOutputWriter;
+use player::USIPlayer;
+use Logger;
+use UsiAgent;
+
+/// 通信の方向
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum TranscriptDirection {
+	/// GUIからエンジンへの入力
+	Input,
+	/// エンジンからGUIへの出力
+	Output,
+}
+impl fmt::Display for TranscriptDirection {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			TranscriptDirection::Input => write!(f,">"),
+			TranscriptDirection::Output => write!(f,"<"),
+		}
+	}
+}
+/// 通信の記録の一行
+///
+/// `経過時間(ミリ秒)\t方向(>は入力、<は出力)\t行の内容`の形式で記録される
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TranscriptEntry {
+	/// 記録開始からの経過時間（ミリ秒）
+	pub elapsed:u64,
+	/// 通信の方向
+	pub direction:TranscriptDirection,
+	/// 送受信された行
+	pub line:String,
+}
+impl fmt::Display for TranscriptEntry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f,"{}\t{}\t{}",self.elapsed,self.direction,self.line)
+	}
+}
+impl<'a> TryFrom<&'a str> for TranscriptEntry {
+	type Error = TypeConvertError<String>;
+
+	fn try_from(s:&'a str) -> Result<TranscriptEntry,TypeConvertError<String>> {
+		let mut fields = s.splitn(3,'\t');
+
+		match (fields.next(),fields.next(),fields.next()) {
+			(Some(elapsed),Some(direction),Some(line)) => {
+				let direction = match direction {
+					">" => TranscriptDirection::Input,
+					"<" => TranscriptDirection::Output,
+					_ => {
+						return Err(TypeConvertError::SyntaxError(format!("The direction of the transcript is invalid. ({})",direction)));
+					}
+				};
+
+				Ok(TranscriptEntry {
+					elapsed:elapsed.parse()?,
+					direction,
+					line:line.to_string()
+				})
+			},
+			_ => {
+				Err(TypeConvertError::SyntaxError(format!("The format of the transcript is invalid. ({})",s)))
+			}
+		}
+	}
+}
+/// 送受信された行をミリ秒単位の経過時間とともに書き込む
+pub struct TranscriptRecorder<W> where W: Write + Send + 'static {
+	writer:Arc<Mutex<W>>,
+	start:Instant,
+}
+impl<W> TranscriptRecorder<W> where W: Write + Send + 'static {
+	/// `TranscriptRecorder`の生成
+	///
+	/// # Arguments
+	/// * `writer` - 書き込み先
+	pub fn new(writer:W) -> TranscriptRecorder<W> {
+		TranscriptRecorder {
+			writer:Arc::new(Mutex::new(writer)),
+			start:Instant::now(),
+		}
+	}
+
+	/// 一行を記録する
+	///
+	/// # Arguments
+	/// * `direction` - 通信の方向
+	/// * `line` - 送受信された行
+	pub fn record(&self,direction:TranscriptDirection,line:&str) -> io::Result<()> {
+		let entry = TranscriptEntry {
+			elapsed:self.start.elapsed().as_millis() as u64,
+			direction,
+			line:line.to_string()
+		};
+
+		let mut writer = self.writer.lock().map_err(|_| {
+			io::Error::other("Could not get exclusive lock on transcript writer object.")
+		})?;
+
+		writeln!(writer,"{}",entry)?;
+		writer.flush()
+	}
+}
+impl TranscriptRecorder<BufWriter<File>> {
+	/// 書き込み先のファイルを作成して`TranscriptRecorder`を生成（ファイルが存在する場合は上書きする）
+	///
+	/// # Arguments
+	/// * `file` - 書き込み先のファイル
+	pub fn create(file:String) -> io::Result<TranscriptRecorder<BufWriter<File>>> {
+		Ok(TranscriptRecorder::new(BufWriter::new(File::create(file)?)))
+	}
+}
+impl<W> Clone for TranscriptRecorder<W> where W: Write + Send + 'static {
+	fn clone(&self) -> TranscriptRecorder<W> {
+		TranscriptRecorder {
+			writer:self.writer.clone(),
+			start:self.start,
+		}
+	}
+}
+impl<W> fmt::Debug for TranscriptRecorder<W> where W: Write + Send + 'static {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "TranscriptRecorder")
+	}
+}
+/// 読み取った行を記録する`USIInputReader`の実装
+pub struct TranscriptInputReader<R,W> where R: USIInputReader, W: Write + Send + 'static {
+	reader:R,
+	recorder:TranscriptRecorder<W>,
+}
+impl<R,W> TranscriptInputReader<R,W> where R: USIInputReader, W: Write + Send + 'static {
+	/// `TranscriptInputReader`の生成
+	///
+	/// # Arguments
+	/// * `reader` - 実際に入力を読み取るオブジェクト
+	/// * `recorder` - 記録先
+	pub fn new(reader:R,recorder:TranscriptRecorder<W>) -> TranscriptInputReader<R,W> {
+		TranscriptInputReader {
+			reader,
+			recorder
+		}
+	}
+}
+impl<R,W> USIInputReader for TranscriptInputReader<R,W> where R: USIInputReader, W: Write + Send + 'static {
+	fn read(&mut self) -> io::Result<Option<String>> {
+		let line = self.reader.read()?;
+
+		if let Some(ref line) = line {
+			self.recorder.record(TranscriptDirection::Input,line)?;
+		}
+
+		Ok(line)
+	}
+}
+/// 書き込んだ行を記録する`USIOutputWriter`の実装
+pub struct TranscriptOutputWriter<O,W> where O: USIOutputWriter, W: Write + Send + 'static {
+	writer:O,
+	recorder:TranscriptRecorder<W>,
+}
+impl<O,W> TranscriptOutputWriter<O,W> where O: USIOutputWriter, W: Write + Send + 'static {
+	/// `TranscriptOutputWriter`の生成
+	///
+	/// # Arguments
+	/// * `writer` - 実際に出力を行うオブジェクト
+	/// * `recorder` - 記録先
+	pub fn new(writer:O,recorder:TranscriptRecorder<W>) -> TranscriptOutputWriter<O,W> {
+		TranscriptOutputWriter {
+			writer,
+			recorder
+		}
+	}
+}
+impl<O,W> USIOutputWriter for TranscriptOutputWriter<O,W> where O: USIOutputWriter, W: Write + Send + 'static {
+	fn write(&self,lines:&Vec<String>) -> io::Result<usize> {
+		let size = self.writer.write(lines)?;
+
+		for line in lines {
+			self.recorder.record(TranscriptDirection::Output,line)?;
+		}
+
+		Ok(size)
+	}
+}
+impl<O,W> fmt::Debug for TranscriptOutputWriter<O,W> where O: USIOutputWriter, W: Write + Send + 'static {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "TranscriptOutputWriter")
+	}
+}
+/// 記録された通信の内容
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct Transcript {
+	/// 記録された行のリスト
+	pub entries:Vec<TranscriptEntry>,
+}
+impl Transcript {
+	/// 記録を読み込む（空行は無視する）
+	///
+	/// # Arguments
+	/// * `reader` - 読み込み元
+	pub fn read<R>(reader:R) -> Result<Transcript,TranscriptReadError> where R: BufRead {
+		let mut entries = Vec::new();
+
+		for (n,line) in reader.lines().enumerate() {
+			let line = line?;
+			let line = line.trim_end_matches('\r');
+
+			if line.is_empty() {
+				continue;
+			}
+
+			entries.push(TranscriptEntry::try_from(line).map_err(|_| {
+				TranscriptReadError::InvalidFormat(n + 1,line.to_string())
+			})?);
+		}
+
+		Ok(Transcript {
+			entries
+		})
+	}
+
+	/// ファイルから記録を読み込む
+	///
+	/// # Arguments
+	/// * `file` - 読み込むファイル
+	pub fn open(file:String) -> Result<Transcript,TranscriptReadError> {
+		Transcript::read(BufReader::new(File::open(file)?))
+	}
+
+	/// GUIからエンジンへの入力を経過時間とともに返す
+	pub fn inputs(&self) -> Vec<(u64,String)> {
+		self.entries.iter()
+					.filter(|e| e.direction == TranscriptDirection::Input)
+					.map(|e| (e.elapsed,e.line.clone()))
+					.collect()
+	}
+
+	/// エンジンからGUIへの出力を返す
+	pub fn outputs(&self) -> Vec<String> {
+		self.entries.iter()
+					.filter(|e| e.direction == TranscriptDirection::Output)
+					.map(|e| e.line.clone())
+					.collect()
+	}
+}
+/// あらかじめ与えられた行を順に返す`USIInputReader`の実装
+///
+/// 全ての行を返した後は`quit`を返す
+pub struct ScriptedInputReader {
+	lines:VecDeque<(u64,String)>,
+	honor_timing:bool,
+	start:Option<Instant>,
+}
+impl ScriptedInputReader {
+	/// `ScriptedInputReader`の生成
+	///
+	/// # Arguments
+	/// * `lines` - 読み取り開始からの経過時間（ミリ秒）と行のリスト
+	/// * `honor_timing` - trueの場合は各行の経過時間まで待ってから返す
+	pub fn new(lines:Vec<(u64,String)>,honor_timing:bool) -> ScriptedInputReader {
+		ScriptedInputReader {
+			lines:lines.into_iter().collect(),
+			honor_timing,
+			start:None,
+		}
+	}
+}
+impl USIInputReader for ScriptedInputReader {
+	fn read(&mut self) -> io::Result<Option<String>> {
+		let start = *self.start.get_or_insert_with(Instant::now);
+
+		match self.lines.pop_front() {
+			Some((elapsed,line)) => {
+				if self.honor_timing {
+					let at = Duration::from_millis(elapsed);
+					let now = start.elapsed();
+
+					if at > now {
+						thread::sleep(at - now);
+					}
+				}
+
+				Ok(Some(line))
+			},
+			None => Ok(Some(String::from("quit")))
+		}
+	}
+}
+struct ReplayOutputWriter {
+	lines:Arc<Mutex<Vec<String>>>,
+}
+impl USIOutputWriter for ReplayOutputWriter {
+	fn write(&self,lines:&Vec<String>) -> io::Result<usize> {
+		let mut output = self.lines.lock().map_err(|_| {
+			io::Error::other("Could not get exclusive lock on output object.")
+		})?;
+
+		output.extend(lines.iter().cloned());
+
+		Ok(lines.iter().map(|l| l.len() + 1).sum())
+	}
+}
+/// 記録された出力と再生時の出力の差分の一行
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum DiffLine {
+	/// 両方に含まれる行
+	Same(String),
+	/// 記録された出力にのみ含まれる行
+	Missing(String),
+	/// 再生時の出力にのみ含まれる行
+	Unexpected(String),
+}
+/// 再生の結果
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ReplayResult {
+	/// 記録された出力
+	pub expected:Vec<String>,
+	/// 再生時の出力
+	pub actual:Vec<String>,
+	/// 行単位の差分
+	pub diff:Vec<DiffLine>,
+}
+impl ReplayResult {
+	/// `ReplayResult`の生成
+	///
+	/// # Arguments
+	/// * `expected` - 記録された出力
+	/// * `actual` - 再生時の出力
+	pub fn new(expected:Vec<String>,actual:Vec<String>) -> ReplayResult {
+		let diff = diff_lines(&expected,&actual);
+
+		ReplayResult {
+			expected,
+			actual,
+			diff
+		}
+	}
+
+	/// 記録された出力と再生時の出力が一致しているか
+	pub fn is_identical(&self) -> bool {
+		self.diff.iter().all(|d| matches!(*d,DiffLine::Same(_)))
+	}
+
+	/// 差分を`  `(一致)`- `(記録のみ)`+ `(再生時のみ)を先頭に付けた行のリストに変換
+	pub fn report(&self) -> Vec<String> {
+		self.diff.iter().map(|d| match *d {
+			DiffLine::Same(ref l) => format!("  {}",l),
+			DiffLine::Missing(ref l) => format!("- {}",l),
+			DiffLine::Unexpected(ref l) => format!("+ {}",l),
+		}).collect()
+	}
+}
+fn diff_lines(expected:&[String],actual:&[String]) -> Vec<DiffLine> {
+	let n = expected.len();
+	let m = actual.len();
+
+	let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+
+	for i in (0..n).rev() {
+		for j in (0..m).rev() {
+			lcs[i][j] = if expected[i] == actual[j] {
+				lcs[i + 1][j + 1] + 1
+			} else {
+				lcs[i + 1][j].max(lcs[i][j + 1])
+			};
+		}
+	}
+
+	let mut diff = Vec::with_capacity(n.max(m));
+	let (mut i, mut j) = (0,0);
+
+	while i < n && j < m {
+		if expected[i] == actual[j] {
+			diff.push(DiffLine::Same(expected[i].clone()));
+			i += 1;
+			j += 1;
+		} else if lcs[i + 1][j] >= lcs[i][j + 1] {
+			diff.push(DiffLine::Missing(expected[i].clone()));
+			i += 1;
+		} else {
+			diff.push(DiffLine::Unexpected(actual[j].clone()));
+			j += 1;
+		}
+	}
+
+	diff.extend(expected[i..].iter().map(|l| DiffLine::Missing(l.clone())));
+	diff.extend(actual[j..].iter().map(|l| DiffLine::Unexpected(l.clone())));
+
+	diff
+}
+/// 記録された入力を`UsiAgent`に与えて、エンジンの出力を記録された出力と比較する
+#[derive(Clone, Default, Debug)]
+pub struct Replayer {
+	honor_timing:bool,
+	ignore_info:bool,
+}
+impl Replayer {
+	/// `Replayer`の生成
+	pub fn new() -> Replayer {
+		Replayer {
+			honor_timing:false,
+			ignore_info:false,
+		}
+	}
+
+	/// 記録された時間の間隔で入力を与えるかを設定（デフォルトはfalse）
+	///
+	/// # Arguments
+	/// * `honor_timing` - trueの場合は記録された時間の間隔で入力を与える
+	pub fn honor_timing(mut self,honor_timing:bool) -> Replayer {
+		self.honor_timing = honor_timing;
+		self
+	}
+
+	/// `info`で始まる行を比較の対象から外すかを設定（デフォルトはfalse）
+	///
+	/// # Arguments
+	/// * `ignore_info` - trueの場合は`info`コマンドの行を比較しない
+	pub fn ignore_info(mut self,ignore_info:bool) -> Replayer {
+		self.ignore_info = ignore_info;
+		self
+	}
+
+	/// 記録を再生する
+	///
+	/// # Arguments
+	/// * `agent` - 再生に使う`UsiAgent`
+	/// * `transcript` - 記録
+	/// * `logger` - ログを書き込むためのオブジェクト
+	pub fn replay<'a,T,E,L>(&self,agent:&'a UsiAgent<T,E>,transcript:&Transcript,logger:L)
+		-> Result<ReplayResult,USIAgentRunningError<'a,SystemEventQueue,E>>
+		where T: USIPlayer<E> + fmt::Debug + Send + 'static,
+			  E: PlayerError,
+			  L: Logger + fmt::Debug + Send + 'static,
+			  EventHandlerError<SystemEventKind, E>: From<E> {
+		let reader = ScriptedInputReader::new(transcript.inputs(),self.honor_timing);
+
+		let lines = Arc::new(Mutex::new(Vec::new()));
+
+		let writer = ReplayOutputWriter {
+			lines:lines.clone()
+		};
+
+		agent.start(reader,writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		})?;
+
+		let actual = match lines.lock() {
+			Ok(lines) => lines.clone(),
+			Err(e) => e.into_inner().clone()
+		};
+
+		let filter = |lines:Vec<String>| -> Vec<String> {
+			if self.ignore_info {
+				lines.into_iter().filter(|l| !l.starts_with("info")).collect()
+			} else {
+				lines
+			}
+		};
+
+		Ok(ReplayResult::new(filter(transcript.outputs()),filter(actual)))
+	}
+}
@@ -12,6 +12,7 @@ use usiagent::shogi::*;
 use usiagent::rule::BANMEN_START_POS;
 use usiagent::command::*;
 use usiagent::event::*;
+use usiagent::extension::ExtensionPosition;
 
 #[allow(unused_imports)]
 use usiagent::shogi::KomaKind::{
@@ -96,9 +97,9 @@ fn startup(s:&Sender<String>,r:&Receiver<String>,pmr:&Receiver<Result<ActionKind
 
 	assert_eq!(&*res,"option name OptionString2 type string");
 
-	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'option name USI_Hash type spin min 1 max 100' timed out.");
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'option name USI_Hash type spin min 1 max 1000' timed out.");
 
-	assert_eq!(&*res,"option name USI_Hash type spin min 1 max 100");
+	assert_eq!(&*res,"option name USI_Hash type spin min 1 max 1000");
 
 	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'option name USI_Ponder type check default false' timed out.");
 
@@ -5723,6 +5724,180 @@ fn test_info_send_commands_without_str() {
 	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
 }
 #[test]
+fn test_info_send_commands_throttled_flushed_before_bestmove() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,pnr) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let _ = thread::spawn(move || {
+		let player = MockPlayer::new(pms,pns,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player| {
+											let _ = player.sender.send(Ok(ActionKind::NewGame));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											Ok(())
+										}),
+										Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::Think));
+											Ok(BestMove::Move(Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false)),None))
+										}),
+										Box::new(|player,_,_,_,mut send_info_commands,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::Think));
+
+											let r = send_info_commands(vec![
+												UsiInfoSubCommand::Depth(1),
+												UsiInfoSubCommand::Pv(vec![
+													Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false))
+												])
+											]).and_then(|_| send_info_commands(vec![
+												UsiInfoSubCommand::Depth(2),
+												UsiInfoSubCommand::Pv(vec![
+													Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false)),
+													Move::To(KomaSrcPosition(9,3),KomaDstToPosition(9,4,false))
+												])
+											]));
+
+											// 探索スレッドがthinkから戻った後もInfoSenderを保持している状況を再現する
+											let _ = thread::spawn(move || {
+												thread::sleep(Duration::from_millis(500));
+												drop(send_info_commands);
+											});
+
+											if r.is_err() {
+												Err(CommonError::Fail(String::from("An error occurred when sending the info command.")))
+											} else {
+												Ok(BestMove::Resign)
+											}
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![Box::new(|player,s,_| {
+											match s {
+												&GameEndState::Lose => {
+													let _ = player.sender.send(Ok(ActionKind::GameOver));
+												},
+												_ => {
+													let _ = player.sender.send(Err(String::from("gameend state is invalid.")));
+												}
+											}
+
+											Ok(())
+										})])
+		);
+		let mut agent = UsiAgent::new(player);
+
+		agent.set_info_interval(Duration::from_secs(10));
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	startup(&s,&r,&pmr);
+
+	let _ = s.send(String::from("isready"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::TakeReady timed out.");
+
+	assert_eq!(res,Ok(ActionKind::TakeReady));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'readyok' timed out.");
+
+	assert_eq!(&*res,"readyok");
+
+	let _ = s.send(String::from("usinewgame"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::NewGame timed out.");
+
+	assert_eq!(res,Ok(ActionKind::NewGame));
+
+	let _ = s.send(String::from("position startpos"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let _ = s.send(String::from("go"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Think timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Think));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'bestmove 1g1f' timed out.");
+
+	assert_eq!(&*res,"bestmove 1g1f");
+
+	let _ = s.send(String::from("position startpos moves 1g1f"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let _ = s.send(String::from("go"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Think timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Think));
+
+	for _ in 0..2 {
+		let _ = pnr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive info send notify timed out.");
+	}
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'info depth 1 pv 1g1f' timed out.");
+
+	assert_eq!(&*res,"info depth 1 pv 1g1f");
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'info depth 2 pv 1g1f 9c9d' timed out.");
+
+	assert_eq!(&*res,"info depth 2 pv 1g1f 9c9d");
+
+	let res = r.recv_timeout(Duration::from_millis(300)).expect("attempt to receive 'bestmove resign' timed out.");
+
+	assert_eq!(&*res,"bestmove resign");
+
+	let _ = s.send(String::from("gameover lose"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::GameOver timed out.");
+
+	assert_eq!(res,Ok(ActionKind::GameOver));
+
+	let _ = s.send(String::from("quit"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
+#[test]
 fn test_info_send_commands_without_str_and_multipv() {
 	let (pms,pmr) = mpsc::channel();
 	let (pns,pnr) = mpsc::channel();
@@ -6510,3 +6685,274 @@ fn test_periodically_info_send_drop_sender() {
 
 	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
 }
+#[test]
+fn test_extension_commands() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let _ = thread::spawn(move || {
+		let player = MockPlayer::new(pms,pns,
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![])
+		);
+		let mut agent = UsiAgent::new(player);
+
+		agent.add_extension_command("echo", |args,position,writer| {
+			let teban = match position {
+				Some(&ExtensionPosition { teban: Teban::Sente, .. }) => "sente",
+				Some(&ExtensionPosition { teban: Teban::Gote, .. }) => "gote",
+				None => "none"
+			};
+			writer.write(&vec![format!("{} {}",teban,args.join(" "))])?;
+			Ok(())
+		});
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	startup(&s,&r,&pmr);
+
+	let _ = s.send(String::from("echo a  b"));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'none a b' timed out.");
+
+	assert_eq!(&*res,"none a b");
+
+	let _ = s.send(String::from("position startpos moves 7g7f"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let _ = s.send(String::from("echo c"));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'gote c' timed out.");
+
+	assert_eq!(&*res,"gote c");
+
+	let _ = s.send(String::from("moves"));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive legal moves timed out.");
+
+	assert_eq!(res.split(' ').count(),30);
+
+	let _ = s.send(String::from("perft 1"));
+
+	for _ in 0..30 {
+		r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of divide timed out.");
+	}
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of perft timed out.");
+
+	assert_eq!(&*res,"nodes: 30, captures: 0, promotions: 0, checks: 0, mates: 0");
+
+	let _ = s.send(String::from("quit"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
+#[test]
+fn test_extension_commands_run_outside_of_event_thread() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let _ = thread::spawn(move || {
+		let player = MockPlayer::new(pms,pns,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![])
+		);
+		let mut agent = UsiAgent::new(player);
+
+		agent.add_extension_command("slow", |_,_,writer| {
+			thread::sleep(Duration::from_millis(300));
+			writer.write(&vec![String::from("slow done")])?;
+			Ok(())
+		});
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	startup(&s,&r,&pmr);
+
+	let _ = s.send(String::from("slow"));
+	let _ = s.send(String::from("isready"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::TakeReady timed out.");
+
+	assert_eq!(res,Ok(ActionKind::TakeReady));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'readyok' timed out.");
+
+	assert_eq!(&*res,"readyok");
+
+	let res = r.recv_timeout(Duration::from_millis(500)).expect("attempt to receive 'slow done' timed out.");
+
+	assert_eq!(&*res,"slow done");
+
+	let _ = s.send(String::from("quit"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
+#[test]
+fn test_invalid_position_policy_reject() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let _ = thread::spawn(move || {
+		let player = MockPlayer::new(pms,pns,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player| {
+											let _ = player.sender.send(Ok(ActionKind::NewGame));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![])
+		);
+		let mut agent = UsiAgent::new(player);
+
+		agent.set_invalid_position_policy(InvalidPositionPolicy::Reject);
+
+		let _ = agent.start(input_reader,output_writer,logger,|_,_| {});
+
+		let _ = ts.send(());
+	});
+
+	startup(&s,&r,&pmr);
+
+	let _ = s.send(String::from("isready"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::TakeReady timed out.");
+
+	assert_eq!(res,Ok(ActionKind::TakeReady));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'readyok' timed out.");
+
+	assert_eq!(&*res,"readyok");
+
+	let _ = s.send(String::from("usinewgame"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::NewGame timed out.");
+
+	assert_eq!(res,Ok(ActionKind::NewGame));
+
+	let _ = s.send(String::from("position startpos moves 1g1f"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let _ = s.send(String::from("position startpos moves 1g1f 1c1d 1f1d"));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'info string position rejected' timed out.");
+
+	assert!(res.starts_with("info string position rejected: "),"unexpected output {}",res);
+
+	assert!(pmr.recv_timeout(Duration::from_millis(150)).is_err());
+
+	let _ = s.send(String::from("go"));
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'info string go refused' timed out.");
+
+	assert!(res.starts_with("info string go refused"),"unexpected output {}",res);
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive 'bestmove resign' timed out.");
+
+	assert_eq!(&*res,"bestmove resign");
+
+	let _ = s.send(String::from("quit"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
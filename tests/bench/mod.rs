@@ -0,0 +1,273 @@
+use std::convert::TryFrom;
+use std::sync::{mpsc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use usiagent::UsiAgent;
+use usiagent::bench::*;
+use usiagent::error::BenchError;
+use usiagent::extension::ExtensionPosition;
+use usiagent::protocol::PositionParser;
+use usiagent::rule::*;
+use usiagent::shogi::*;
+
+use common::*;
+
+type OnPosition = Box<dyn FnMut(&mut MockPlayer,Teban,Banmen,Mochigoma,Mochigoma,u32,Vec<Move>)
+									-> Result<(),CommonError> + Send + 'static>;
+type OnNewGame = Box<dyn FnMut(&mut MockPlayer) -> Result<(),CommonError> + Send + 'static>;
+
+impl BenchPlayer<CommonError> for MockPlayer {
+	fn bench_search(&mut self,limit:&BenchLimit,stop:&AtomicBool) -> Result<u64,CommonError> {
+		let kyokumen = self.kyokumen.as_ref().ok_or(CommonError::Fail(String::from("The position is not set.")))?;
+
+		match *limit {
+			BenchLimit::Depth(depth) => {
+				Ok(Rule::perft(&kyokumen.state,kyokumen.teban,&kyokumen.mc,depth as usize).nodes as u64)
+			},
+			BenchLimit::Nodes(nodes) => Ok(nodes),
+			BenchLimit::Time(_) => {
+				while !stop.load(Ordering::Acquire) {
+					thread::sleep(Duration::from_millis(10));
+				}
+
+				Ok(0)
+			}
+		}
+	}
+}
+fn on_position() -> OnPosition {
+	Box::new(|player,t,banmen,ms,mg,n,m| {
+		let p = ExtensionPosition::new(t,banmen,MochigomaCollections::Pair(ms,mg),n,&m).unwrap();
+
+		player.kyokumen = Some(Kyokumen {
+			teban:p.teban,
+			mc:p.mc,
+			state:p.state
+		});
+		Ok(())
+	})
+}
+fn on_newgame() -> OnNewGame {
+	Box::new(|_| Ok(()))
+}
+fn create_bench_player(count:usize) -> MockPlayer {
+	let (pms,_) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+
+	let mut player = create_player(pms,pns);
+
+	player.on_newgame = ConsumedIterator::new((0..count).map(|_| on_newgame()).collect());
+	player.on_position = ConsumedIterator::new((0..count * BENCH_POSITIONS.len()).map(|_| on_position()).collect());
+
+	player
+}
+#[test]
+fn test_bench_positions_are_valid() {
+	let position_parser = PositionParser::new();
+
+	for p in BENCH_POSITIONS.iter() {
+		let (teban,banmen,mc,_,mvs) = position_parser.parse(&p.split(' ').collect::<Vec<&str>>()).unwrap().extract();
+
+		assert_eq!(Rule::validate_position(&banmen,teban,&mc),Ok(()),"{}",p);
+
+		let mut state = State::new(banmen);
+		let mut mc = mc;
+		let mut teban = teban;
+
+		for m in mvs {
+			assert!(Rule::legal_moves_all(teban,&state,&mc).into_iter().any(|l| l.to_move() == m),"{}",p);
+
+			let (next,nmc,_) = Rule::apply_move_none_check(&state,teban,&mc,m.to_applied_move());
+
+			state = next;
+			mc = nmc;
+			teban = teban.opposite();
+		}
+
+		assert_eq!(Rule::validate_position(state.get_banmen(),teban,&mc),Ok(()),"{}",p);
+		assert!(Rule::perft(&state,teban,&mc,1).nodes > 0,"{}",p);
+	}
+}
+#[test]
+fn test_bench_run() {
+	let player = Mutex::new(create_bench_player(2));
+	let stop = AtomicBool::new(false);
+
+	let r = Bench::new().limit(BenchLimit::Depth(1)).run(&player,&stop).unwrap();
+
+	assert_eq!(r.nodes_per_position.len(),BENCH_POSITIONS.len());
+	assert_eq!(r.nodes_per_position[0],30);
+	assert_eq!(r.nodes,r.nodes_per_position.iter().sum::<u64>());
+
+	let report = r.report();
+
+	assert_eq!(report.len(),BENCH_POSITIONS.len() + 4);
+	assert_eq!(report[0],"Position: 1/8 nodes 30");
+	assert_eq!(report[report.len() - 2],format!("Nodes searched  : {}",r.nodes));
+
+	let again = Bench::new().limit(BenchLimit::Depth(1)).run(&player,&stop).unwrap();
+
+	assert_eq!(again.nodes,r.nodes);
+	assert_eq!(again.nodes_per_position,r.nodes_per_position);
+}
+#[test]
+fn test_bench_run_with_positions() {
+	let player = Mutex::new(create_bench_player(2));
+	let stop = AtomicBool::new(false);
+
+	let r = Bench::new().positions(vec![String::from("startpos")])
+						.limit(BenchLimit::Depth(2))
+						.run(&player,&stop).unwrap();
+
+	assert_eq!(r.nodes_per_position,vec![900]);
+	assert_eq!(r.nodes,900);
+
+	assert!(match Bench::new().positions(vec![String::from("sfen")]).run(&player,&stop) {
+		Err(BenchError::InvalidPosition(_)) => true,
+		_ => false
+	});
+}
+#[test]
+fn test_bench_limit_try_from() {
+	let args = |s:&str| s.split(' ').map(|s| s.to_string()).collect::<Vec<String>>();
+
+	assert_eq!(BenchLimit::try_from(&args("depth 5")[..]),Ok(BenchLimit::Depth(5)));
+	assert_eq!(BenchLimit::try_from(&args("nodes 10000")[..]),Ok(BenchLimit::Nodes(10000)));
+	assert_eq!(BenchLimit::try_from(&args("movetime 1000")[..]),Ok(BenchLimit::Time(1000)));
+	assert!(BenchLimit::try_from(&args("depth")[..]).is_err());
+	assert!(BenchLimit::try_from(&args("depth a")[..]).is_err());
+	assert!(BenchLimit::try_from(&args("mate 5")[..]).is_err());
+}
+#[test]
+fn test_bench_command() {
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let (ts,tr) = mpsc::channel();
+
+	let _ = thread::spawn(move || {
+		let mut agent = UsiAgent::new(create_bench_player(2));
+
+		agent.add_bench_command(Bench::new().positions(vec![String::from("startpos")]).limit(BenchLimit::Depth(1)));
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	let _ = s.send(String::from("bench"));
+
+	let res = r.recv_timeout(Duration::from_millis(500)).expect("attempt to receive the result of bench timed out.");
+
+	assert_eq!(&*res,"info string Position: 1/1 nodes 30");
+
+	let _ = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+	let _ = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+
+	assert_eq!(&*res,"info string Nodes searched  : 30");
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+
+	assert!(res.starts_with("info string Nodes/second    : "));
+
+	let _ = s.send(String::from("bench depth 2"));
+
+	let res = r.recv_timeout(Duration::from_millis(500)).expect("attempt to receive the result of bench timed out.");
+
+	assert_eq!(&*res,"info string Position: 1/1 nodes 900");
+
+	for _ in 0..4 {
+		let _ = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+	}
+
+	let _ = s.send(String::from("quit"));
+
+	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
+#[test]
+fn test_bench_run_stopped() {
+	let player = Mutex::new(create_bench_player(1));
+	let stop = AtomicBool::new(true);
+
+	let r = Bench::new().limit(BenchLimit::Depth(1)).run(&player,&stop).unwrap();
+
+	assert!(r.stopped);
+	assert!(r.nodes_per_position.is_empty());
+	assert_eq!(r.report().last().map(|l| &**l),Some("The benchmark was stopped."));
+}
+#[test]
+fn test_bench_command_stop() {
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let (ts,tr) = mpsc::channel();
+
+	let _ = thread::spawn(move || {
+		let mut agent = UsiAgent::new(create_bench_player(1));
+
+		agent.add_bench_command(Bench::new().positions(vec![String::from("startpos"),String::from("startpos")])
+											.limit(BenchLimit::Time(1000)));
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	let _ = s.send(String::from("bench"));
+
+	thread::sleep(Duration::from_millis(100));
+
+	let _ = s.send(String::from("stop"));
+
+	let res = r.recv_timeout(Duration::from_millis(500)).expect("attempt to receive the result of bench timed out.");
+
+	assert_eq!(&*res,"info string Position: 1/1 nodes 0");
+
+	for _ in 0..4 {
+		let _ = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+	}
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the result of bench timed out.");
+
+	assert_eq!(&*res,"info string The benchmark was stopped.");
+
+	let _ = s.send(String::from("quit"));
+
+	let _ = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
\ No newline at end of file
@@ -383,6 +383,15 @@ impl MockPlayer {
 		)
 	}
 }
+pub fn create_player(sender:Sender<Result<ActionKind,String>>,info_send_notifier:Sender<()>) -> MockPlayer {
+	MockPlayer::new(sender,info_send_notifier,
+					ConsumedIterator::new(vec![]),
+					ConsumedIterator::new(vec![]),
+					ConsumedIterator::new(vec![]),
+					ConsumedIterator::new(vec![]),
+					ConsumedIterator::new(vec![]),
+					ConsumedIterator::new(vec![]))
+}
 impl fmt::Debug for MockPlayer {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "MockPlayer")
@@ -410,7 +419,7 @@ impl USIPlayer<CommonError> for MockPlayer {
 
 	fn get_options(&mut self) -> Result<BTreeMap<String,UsiOptType>,CommonError> {
 		let mut options:BTreeMap<String,UsiOptType> = BTreeMap::new();
-		options.insert(String::from("USI_Hash"),UsiOptType::Spin(1,100,None));
+		options.insert(String::from("USI_Hash"),UsiOptType::Spin(1,1000,None));
 		options.insert(String::from("USI_Ponder"),UsiOptType::Check(Some(false)));
 		options.insert(String::from("OptionButton"),UsiOptType::Button);
 		options.insert(String::from("OptionCheck"),UsiOptType::Check(None));
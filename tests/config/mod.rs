@@ -0,0 +1,242 @@
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use usiagent::UsiAgent;
+use usiagent::config::*;
+use usiagent::error::*;
+use usiagent::event::*;
+use usiagent::player::USIPeriodicallyInfo;
+use usiagent::selfmatch::*;
+
+use common::*;
+
+#[test]
+fn test_config_file_read() {
+	let config = ConfigFile::read(&b"# comment
+Threads = 4
+
+[options]
+; comment
+USI_Hash = 256
+BookFile = \"book/book 1.db\"
+ClearHash
+USI_Hash = 512
+
+[match]
+games=10
+"[..]).unwrap();
+
+	assert_eq!(config.section_names(),vec!["","options","match"]);
+	assert_eq!(config.get("","Threads"),Some("4"));
+	assert_eq!(config.get("options","USI_Hash"),Some("512"));
+	assert_eq!(config.get("options","BookFile"),Some("book/book 1.db"));
+	assert_eq!(config.get("options","ClearHash"),Some(""));
+	assert_eq!(config.get("match","Threads"),None);
+	assert_eq!(config.parse::<u32>("match","games").unwrap(),Some(10));
+	assert_eq!(config.entries("options").iter().map(|e| e.line).collect::<Vec<usize>>(),vec![6,7,8,9]);
+
+	assert!(matches!(ConfigFile::read(&b"[options\nUSI_Hash = 1\n"[..]),Err(ConfigFileError::InvalidFormat(1,_))));
+	assert!(matches!(ConfigFile::read(&b"\n= 1\n"[..]),Err(ConfigFileError::InvalidFormat(2,_))));
+	assert!(matches!(config.parse::<bool>("match","games"),Err(ConfigFileError::InvalidValue(12,_,_))));
+}
+#[test]
+fn test_selfmatch_config_read() {
+	let config = SelfMatchConfig::read(&b"[match]
+games = 100
+uptime = 3600
+sente_time = 60000
+gote_time = 30000
+inc = 1000
+gote_inc = 2000
+kifu = logs/kifu.csa
+kifu_format = csa
+log = logs/selfmatch.txt
+seed = 12345
+
+[player1]
+ponder = false
+
+[player1.options]
+USI_Hash = 256
+OptionButton
+
+[player2.options]
+OptionCombo = cccc
+"[..]).unwrap();
+
+	assert_eq!(config.number_of_games,Some(100));
+	assert_eq!(config.uptime,Some(Duration::from_secs(3600)));
+	assert_eq!(config.time_limit,UsiGoTimeLimit::Limit(Some((60000,30000)),Some(UsiGoByoyomiOrInc::Inc(1000,2000))));
+	assert_eq!(config.kifu,Some((String::from("logs/kifu.csa"),KifuFormat::Csa)));
+	assert_eq!(config.log,"logs/selfmatch.txt");
+	assert_eq!(config.seed,Some(12345));
+	assert!(!config.player1.ponder);
+	assert!(config.player2.ponder);
+	assert_eq!(config.player1.options,vec![
+		(String::from("USI_Hash"),String::from("256")),
+		(String::from("OptionButton"),String::new())
+	]);
+	assert_eq!(config.player2.options,vec![(String::from("OptionCombo"),String::from("cccc"))]);
+
+	let engine:SelfMatchEngine<CommonError> = config.create_engine();
+
+	assert_eq!(engine.seed(),12345);
+
+	let config = SelfMatchConfig::read(&b"[match]\ninfinite = true\n"[..]).unwrap();
+
+	assert_eq!(config.time_limit,UsiGoTimeLimit::Infinite);
+	assert_eq!(config.kifu,None);
+	assert_eq!(config.log,"logs/log.txt");
+	assert_eq!(SelfMatchConfig::read(&b""[..]).unwrap(),SelfMatchConfig::default());
+}
+#[test]
+fn test_selfmatch_config_read_invalid() {
+	assert!(matches!(SelfMatchConfig::read(&b"[match]\ngame = 1\n"[..]),
+		Err(ConfigFileError::UnknownKey(2,ref k)) if k == "game"));
+	assert!(matches!(SelfMatchConfig::read(&b"[player2]\nponder = yes\n"[..]),
+		Err(ConfigFileError::InvalidValue(2,_,_))));
+	assert!(matches!(SelfMatchConfig::read(&b"[player1]\nUSI_Hash = 1\n"[..]),
+		Err(ConfigFileError::UnknownKey(2,_))));
+	assert!(matches!(SelfMatchConfig::read(&b"[match]\nbyoyomi = 1000\ninc = 1000\n"[..]),
+		Err(ConfigFileError::InvalidValue(2,ref k,_)) if k == "byoyomi"));
+	assert!(matches!(SelfMatchConfig::read(&b"[match]\nkifu = kifu.txt\nkifu_format = kif\n"[..]),
+		Err(ConfigFileError::InvalidValue(3,ref k,_)) if k == "kifu_format"));
+}
+#[test]
+fn test_selfmatch_config_kifu_writer() {
+	let dir = env::temp_dir().join("usiagent-test-config");
+	let _ = fs::create_dir_all(&dir);
+	let file = dir.join("kifu.csa");
+	let _ = fs::remove_file(&file);
+
+	let mut config = SelfMatchConfig::default();
+
+	assert!(config.create_kifu_writer().unwrap().is_none());
+
+	config.kifu = Some((file.to_string_lossy().to_string(),KifuFormat::Csa));
+
+	assert!(config.create_kifu_writer().unwrap().is_some());
+	assert!(file.exists());
+}
+#[test]
+fn test_selfmatch_start_with_config_invalid_option() {
+	let (pms1,_) = mpsc::channel();
+	let (pns1,_) = mpsc::channel();
+	let (pms2,_) = mpsc::channel();
+	let (pns2,_) = mpsc::channel();
+	let (is,_) = mpsc::channel();
+	let (os,_) = mpsc::channel();
+
+	let config = SelfMatchConfig::read(&b"[player2.options]\nOptionSpin = 100\n"[..]).unwrap();
+
+	let mut engine = config.create_engine();
+
+	let r = engine.start_with_config(&config,
+		|_| {},
+		|| false,
+		None,
+		|_| Ok(false),
+		create_player(pms1,pns1),
+		create_player(pms2,pns2),
+		MockInfoSender::new(is),
+		USIPeriodicallyInfo::new(Arc::new(Mutex::new(MockOutputWriter::new(os))),false),
+		|_,_| {}
+	);
+
+	assert!(matches!(r,Err(SelfMatchRunningError::Fail(ref s)) if s.contains("OptionSpin")));
+}
+#[test]
+fn test_agent_default_options() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ls,lr) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let logger = MockLogger::new(ls);
+
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let _ = thread::spawn(move || {
+		let mut player = create_player(pms,pns);
+
+		player.options_it = ConsumedIterator::new(vec![
+			(String::from("OptionSpin"),SysEventOption::Num(25)),
+			(String::from("OptionCombo"),SysEventOption::Str(String::from("cccc"))),
+			(String::from("OptionSpin"),SysEventOption::Num(30)),
+		]);
+
+		let mut agent = UsiAgent::new(player);
+
+		agent.read_default_options(&b"[options]
+OptionSpin = 25
+OptionCombo = cccc
+USI_Hash = 10000
+"[..]).unwrap();
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	for _ in 0..2 {
+		let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::SetOption timed out.");
+
+		assert_eq!(res,Ok(ActionKind::SetOption));
+	}
+
+	let res = lr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the log message timed out.");
+
+	assert!(res.contains("USI_Hash"));
+
+	let _ = s.send(String::from("usi"));
+
+	let mut options = Vec::new();
+
+	loop {
+		let res = r.recv_timeout(Duration::from_millis(350)).expect("attempt to receive the response of usi timed out.");
+
+		if &*res == "usiok" {
+			break;
+		}
+
+		options.push(res);
+	}
+
+	assert!(options.contains(&String::from("option name OptionSpin type spin default 25 min 5 max 50")));
+	assert!(options.contains(&String::from("option name OptionCombo type combo default cccc var bbbb var cccc")));
+	assert!(options.contains(&String::from("option name USI_Hash type spin min 1 max 1000")));
+
+	let _ = s.send(String::from("setoption name OptionSpin value 30"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::SetOption timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetOption));
+
+	let _ = s.send(String::from("quit"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
@@ -0,0 +1,137 @@
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use usiagent::error::ExtensionCommandError;
+use usiagent::extension::*;
+use usiagent::protocol::PositionParser;
+use usiagent::shogi::*;
+
+use common::*;
+
+fn position(sfen:&str) -> ExtensionPosition {
+	let (teban, banmen, mc, n, mvs) = PositionParser::new().parse(&sfen.split(' ').collect::<Vec<&str>>()).unwrap().extract();
+
+	let mc = match mc {
+		MochigomaCollections::Empty => MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()),
+		mc => mc
+	};
+
+	ExtensionPosition::new(teban,banmen,mc,n,&mvs).unwrap()
+}
+fn output() -> (MockOutputWriter,Receiver<String>) {
+	let (s,r) = mpsc::channel();
+
+	(MockOutputWriter::new(s),r)
+}
+fn lines(r:&Receiver<String>) -> Vec<String> {
+	let mut lines = Vec::new();
+
+	while let Ok(l) = r.recv_timeout(Duration::from_millis(10)) {
+		lines.push(l);
+	}
+
+	lines
+}
+#[test]
+fn test_extension_position_apply_moves() {
+	let p = position("startpos moves 7g7f 3c3d");
+
+	assert_eq!(p.teban,Teban::Sente);
+	assert_eq!(p.n,3);
+	assert_eq!(p.to_sfen().unwrap(),"sfen lnsgkgsnl/1r5b1/pppppp1pp/6p2/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL b - 3");
+}
+#[test]
+fn test_extension_position_illegal_move() {
+	let (teban, banmen, mc, n, mvs) = PositionParser::new().parse(&["startpos","moves","7g7f","3c3d","7f7d"]).unwrap().extract();
+
+	assert!(matches!(ExtensionPosition::new(teban,banmen,mc,n,&mvs),Err(ExtensionCommandError::InvalidPosition(_))));
+}
+#[test]
+fn test_builtin_display() {
+	let commands = ExtensionCommands::with_builtin();
+	let (writer,r) = output();
+
+	commands.execute("d",&[],Some(&position("startpos moves 7g7f")),&writer).unwrap();
+
+	let lines = lines(&r);
+
+	assert_eq!(lines.len(),12);
+	assert_eq!(lines[0],"  9  8  7  6  5  4  3  2  1");
+	assert_eq!(lines[1],"  l  n  s  g  k  g  s  n  l  a");
+	assert_eq!(lines[7],"  P  P  .  P  P  P  P  P  P  g");
+	assert_eq!(lines[10],"teban: gote");
+	assert_eq!(lines[11],"sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/2P6/PP1PPPPPP/1B5R1/LNSGKGSNL w - 2");
+}
+#[test]
+fn test_builtin_perft() {
+	let commands = ExtensionCommands::with_builtin();
+	let (writer,r) = output();
+
+	commands.execute("perft",&[String::from("2")],Some(&position("startpos")),&writer).unwrap();
+
+	let lines = lines(&r);
+
+	assert_eq!(lines.len(),31);
+	assert!(lines[..30].iter().all(|l| l.ends_with(": 30")));
+	assert!(lines.contains(&String::from("7g7f: 30")));
+	assert_eq!(lines[30],"nodes: 900, captures: 0, promotions: 0, checks: 0, mates: 0");
+
+	assert!(match commands.execute("perft",&[],Some(&position("startpos")),&writer) {
+		Err(ExtensionCommandError::InvalidArgument(_)) => true,
+		_ => false
+	});
+	assert!(match commands.execute("perft",&[String::from("a")],Some(&position("startpos")),&writer) {
+		Err(ExtensionCommandError::InvalidArgument(_)) => true,
+		_ => false
+	});
+}
+#[test]
+fn test_builtin_moves() {
+	let commands = ExtensionCommands::with_builtin();
+	let (writer,r) = output();
+
+	commands.execute("moves",&[],Some(&position("startpos")),&writer).unwrap();
+
+	let lines = lines(&r);
+
+	assert_eq!(lines.len(),1);
+
+	let mvs = lines[0].split(' ').collect::<Vec<&str>>();
+
+	assert_eq!(mvs.len(),30);
+	assert!(mvs.contains(&"7g7f"));
+	assert!(mvs.contains(&"5i4h"));
+}
+#[test]
+fn test_extension_commands_error() {
+	let commands = ExtensionCommands::with_builtin();
+	let (writer,_) = output();
+
+	assert!(match commands.execute("d",&[],None,&writer) {
+		Err(ExtensionCommandError::PositionNotSet) => true,
+		_ => false
+	});
+	assert!(match commands.execute("eval",&[],None,&writer) {
+		Err(ExtensionCommandError::UnknownCommand(ref name)) if name == "eval" => true,
+		_ => false
+	});
+}
+#[test]
+fn test_extension_commands_add() {
+	let mut commands = ExtensionCommands::new();
+	let (writer,r) = output();
+
+	assert!(commands.names().is_empty());
+
+	commands.add("echo",|args,_,writer| {
+		writer.write(&vec![args.join(" ")])?;
+		Ok(())
+	});
+
+	assert_eq!(commands.names().into_iter().collect::<Vec<String>>(),vec![String::from("echo")]);
+
+	commands.execute("echo",&[String::from("a"),String::from("b")],None,&writer).unwrap();
+
+	assert_eq!(lines(&r),vec![String::from("a b")]);
+}
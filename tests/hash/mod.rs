@@ -1102,3 +1102,30 @@ fn test_kyokumenmap_remove() {
         }
     }
 }
+#[test]
+fn test_kyokumenhash_with_seed_same_seed() {
+    let mut ms = Mochigoma::new();
+    ms.insert(MochigomaKind::Fu,2);
+    let mut mg = Mochigoma::new();
+    mg.insert(MochigomaKind::Kaku,1);
+
+    for seed in [0,1,12345,u64::MAX] {
+        let h1 = KyokumenHash::<u64>::with_seed(seed);
+        let h2 = KyokumenHash::<u64>::with_seed(seed);
+
+        assert_eq!(h1.calc_initial_hash(&BANMEN_START_POS,&ms,&mg),h2.calc_initial_hash(&BANMEN_START_POS,&ms,&mg));
+
+        let h1 = KyokumenHash::<u128>::with_seed(seed);
+        let h2 = KyokumenHash::<u128>::with_seed(seed);
+
+        assert_eq!(h1.calc_initial_hash(&BANMEN_START_POS,&ms,&mg),h2.calc_initial_hash(&BANMEN_START_POS,&ms,&mg));
+    }
+}
+#[test]
+fn test_kyokumenhash_with_seed_different_seed() {
+    let h1 = KyokumenHash::<u64>::with_seed(1);
+    let h2 = KyokumenHash::<u64>::with_seed(2);
+
+    assert_ne!(h1.calc_initial_hash(&BANMEN_START_POS,&Mochigoma::new(),&Mochigoma::new()),
+               h2.calc_initial_hash(&BANMEN_START_POS,&Mochigoma::new(),&Mochigoma::new()));
+}
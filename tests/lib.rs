@@ -2,6 +2,9 @@ extern crate usiagent;
 extern crate crossbeam_channel;
 extern crate rand_xorshift;
 extern crate rand;
+extern crate chrono;
+#[cfg(feature = "log-bridge")]
+extern crate log;
 
 mod common;
 mod rule;
@@ -12,3 +15,12 @@ mod protocol;
 mod event;
 mod player;
 mod hash;
+mod extension;
+mod bench;
+mod transcript;
+mod logger;
+mod net;
+mod options;
+mod config;
+#[cfg(feature = "log-bridge")]
+mod logbridge;
@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use log;
+use log::{Log, LevelFilter, Record};
+
+use usiagent::logger::*;
+use usiagent::logbridge::*;
+
+#[derive(Debug)]
+struct RecordingLogger {
+	sender:Sender<(LogLevel,String,String)>,
+}
+impl Logger for RecordingLogger {
+	fn logging(&mut self, msg:&String) -> bool {
+		self.log(LogLevel::Error,"",msg)
+	}
+
+	fn log(&mut self, level:LogLevel, module:&str, msg:&str) -> bool {
+		let _ = self.sender.send((level,module.to_string(),msg.to_string()));
+		true
+	}
+}
+#[test]
+fn test_log_level_conversion() {
+	for &level in LogLevel::ALL.iter() {
+		let l:log::Level = level.into();
+
+		assert_eq!(LogLevel::from(l),level);
+	}
+
+	assert_eq!(log::Level::from(LogLevel::Warn),log::Level::Warn);
+}
+#[test]
+fn test_usi_log_bridge_level() {
+	let (s,r) = mpsc::channel();
+
+	let logger = Arc::new(Mutex::new(RecordingLogger { sender:s }));
+
+	let bridge = UsiLogBridge::new(logger).level(LevelFilter::Info);
+
+	bridge.log(&Record::builder()
+							.level(log::Level::Warn)
+							.target("engine::search")
+							.args(format_args!("depth {}",3))
+							.build());
+
+	bridge.log(&Record::builder()
+							.level(log::Level::Debug)
+							.target("engine::search")
+							.args(format_args!("hidden"))
+							.build());
+
+	let res = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the log timed out.");
+
+	assert_eq!(res,(LogLevel::Warn,String::from("engine::search"),String::from("depth 3")));
+	assert!(r.try_recv().is_err());
+}
+#[test]
+fn test_shared_logger() {
+	let (s,r) = mpsc::channel();
+
+	let logger = Arc::new(Mutex::new(RecordingLogger { sender:s }));
+
+	let mut shared = SharedLogger::new(logger.clone());
+
+	assert!(shared.logging(&String::from("from agent")));
+
+	UsiLogBridge::new(logger).init().unwrap();
+
+	log::info!(target: "engine", "from engine");
+
+	let mut facade = LogFacadeLogger::new();
+
+	assert!(facade.debug("usiagent::selfmatch","through facade"));
+	assert!(facade.logging(&String::from("error through facade")));
+
+	assert_eq!(r.recv_timeout(Duration::from_millis(150)).unwrap(),
+				(LogLevel::Error,String::new(),String::from("from agent")));
+	assert_eq!(r.recv_timeout(Duration::from_millis(150)).unwrap(),
+				(LogLevel::Info,String::from("engine"),String::from("from engine")));
+	assert_eq!(r.recv_timeout(Duration::from_millis(150)).unwrap(),
+				(LogLevel::Debug,String::from("usiagent::selfmatch"),String::from("through facade")));
+	assert_eq!(r.recv_timeout(Duration::from_millis(150)).unwrap(),
+				(LogLevel::Error,String::from("usiagent"),String::from("error through facade")));
+}
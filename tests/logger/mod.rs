@@ -0,0 +1,279 @@
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use usiagent::{OnErrorHandler, UsiAgent};
+use usiagent::command::UsiOptType;
+use usiagent::error::ShogiError;
+use usiagent::logger::*;
+
+use common::*;
+
+fn log_path(name:&str) -> PathBuf {
+	let dir = env::temp_dir().join("usiagent-test-logger");
+	let _ = fs::create_dir_all(&dir);
+	let path = dir.join(name);
+
+	let _ = fs::remove_file(&path);
+
+	for i in 1..10 {
+		let _ = fs::remove_file(format!("{}.{}",path.display(),i));
+	}
+
+	path
+}
+#[derive(Debug)]
+struct LevelLogger {
+	sender:Sender<LogLevel>,
+}
+impl Logger for LevelLogger {
+	fn logging(&mut self, _:&String) -> bool {
+		true
+	}
+
+	fn set_level(&mut self, level:LogLevel) {
+		let _ = self.sender.send(level);
+	}
+}
+#[derive(Debug)]
+struct RecordLogger {
+	sender:Sender<(LogLevel,String,String)>,
+}
+impl Logger for RecordLogger {
+	fn logging(&mut self, _:&String) -> bool {
+		true
+	}
+
+	fn log(&mut self, level:LogLevel, module:&str, msg:&str) -> bool {
+		let _ = self.sender.send((level,module.to_string(),msg.to_string()));
+		true
+	}
+}
+#[test]
+fn test_log_level_try_from() {
+	assert_eq!(LogLevel::try_from("error").unwrap(),LogLevel::Error);
+	assert_eq!(LogLevel::try_from("Warn").unwrap(),LogLevel::Warn);
+	assert_eq!(LogLevel::try_from("INFO").unwrap(),LogLevel::Info);
+	assert_eq!(LogLevel::try_from("debug").unwrap(),LogLevel::Debug);
+	assert_eq!(LogLevel::try_from("trace").unwrap(),LogLevel::Trace);
+	assert!(LogLevel::try_from("verbose").is_err());
+	assert!(LogLevel::Error < LogLevel::Trace);
+	assert_eq!(&*LogLevel::Warn.to_string(),"WARN");
+}
+#[test]
+fn test_log_level_usi_option() {
+	assert_eq!(LogLevel::usi_option(LogLevel::Info),UsiOptType::Combo(Some(String::from("info")),vec![
+		String::from("error"),
+		String::from("warn"),
+		String::from("info"),
+		String::from("debug"),
+		String::from("trace"),
+	]));
+}
+#[test]
+fn test_log_filter() {
+	let filter = LogFilter::try_from("warn,usiagent::selfmatch=debug,usiagent::selfmatch::kifu=off").unwrap();
+
+	assert!(filter.enabled(LogLevel::Error,""));
+	assert!(filter.enabled(LogLevel::Warn,"usiagent"));
+	assert!(!filter.enabled(LogLevel::Info,"usiagent::rule"));
+	assert!(filter.enabled(LogLevel::Debug,"usiagent::selfmatch"));
+	assert!(filter.enabled(LogLevel::Debug,"usiagent::selfmatch::player"));
+	assert!(!filter.enabled(LogLevel::Trace,"usiagent::selfmatch"));
+	assert!(!filter.enabled(LogLevel::Error,"usiagent::selfmatch::kifu"));
+	assert!(filter.enabled(LogLevel::Warn,"usiagent::selfmatchx"));
+	assert!(!filter.enabled(LogLevel::Debug,"usiagent::selfmatchx"));
+
+	assert!(LogFilter::try_from("info,usiagent=loud").is_err());
+
+	let filter = LogFilter::default();
+
+	assert!(filter.enabled(LogLevel::Info,"usiagent"));
+	assert!(!filter.enabled(LogLevel::Debug,"usiagent"));
+}
+#[test]
+fn test_file_logger_level() {
+	let path = log_path("level.txt");
+
+	{
+		let mut logger = FileLogger::new(path.display().to_string()).unwrap()
+										.filter(LogFilter::try_from("warn,usiagent::agent=trace").unwrap());
+
+		assert!(logger.info("usiagent","hidden"));
+		assert!(logger.warn("usiagent","shown warn"));
+		assert!(logger.trace("usiagent::agent","shown trace"));
+		assert!(logger.logging(&String::from("shown error")));
+
+		logger.set_level(LogLevel::Info);
+
+		assert!(logger.info("usiagent","shown info"));
+	}
+
+	let content = fs::read_to_string(&path).unwrap();
+
+	assert!(!content.contains("hidden"));
+	assert!(content.contains("WARN usiagent\n  shown warn\n"));
+	assert!(content.contains("TRACE usiagent::agent\n  shown trace\n"));
+	assert!(content.contains("ERROR\n  shown error\n"));
+	assert!(content.contains("INFO usiagent\n  shown info\n"));
+}
+#[test]
+fn test_file_logger_legacy_text_layout() {
+	let path = log_path("legacy.txt");
+
+	{
+		let mut logger = FileLogger::new(path.display().to_string()).unwrap();
+
+		assert!(logger.logging(&String::from("legacy message")));
+		assert!(logger.error("usiagent","error message"));
+		assert!(logger.debug("usiagent","hidden"));
+	}
+
+	let content = fs::read_to_string(&path).unwrap();
+	let lines = content.lines().collect::<Vec<&str>>();
+
+	assert_eq!(lines.len(),4);
+	assert_eq!(lines[0].len(),"2000-01-01 00:00:00".len());
+	assert_eq!(lines[1],"  legacy message");
+	assert_eq!(lines[2].len(),"2000-01-01 00:00:00".len());
+	assert_eq!(lines[3],"  error message");
+}
+#[test]
+fn test_on_error_handler_level_and_module() {
+	let (s,r) = mpsc::channel();
+	let handler = OnErrorHandler::new(Arc::new(Mutex::new(RecordLogger { sender:s })));
+
+	handler.call(&ShogiError::InvalidState(String::from("invalid state")));
+
+	let (level,module,msg) = r.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the log message timed out.");
+
+	assert_eq!(level,LogLevel::Error);
+	assert_eq!(module,"usiagent");
+	assert_eq!(msg,"invalid state");
+}
+#[test]
+fn test_file_logger_json_lines() {
+	let path = log_path("json.txt");
+
+	{
+		let mut logger = FileLogger::new(path.display().to_string()).unwrap().format(LogFormat::JsonLines);
+
+		assert!(logger.error("usiagent","line1\n\"quoted\"\t\\"));
+	}
+
+	let content = fs::read_to_string(&path).unwrap();
+	let lines = content.lines().collect::<Vec<&str>>();
+
+	assert_eq!(lines.len(),1);
+	assert!(lines[0].starts_with("{\"time\":\""));
+	assert!(lines[0].ends_with(
+		"\"level\":\"ERROR\",\"module\":\"usiagent\",\"message\":\"line1\\n\\\"quoted\\\"\\t\\\\\"}"
+	));
+}
+#[test]
+fn test_file_logger_rotation_size() {
+	let path = log_path("rotation.txt");
+	let file = path.display().to_string();
+
+	{
+		let mut logger = FileLogger::new(file.clone()).unwrap()
+										.format(LogFormat::JsonLines)
+										.rotation(LogRotation::Size(100),2);
+
+		for i in 0..4 {
+			assert!(logger.error("usiagent",&format!("message {} {}",i,"x".repeat(30))));
+		}
+	}
+
+	assert!(fs::read_to_string(&file).unwrap().contains("message 3"));
+	assert!(fs::read_to_string(format!("{}.1",file)).unwrap().contains("message 2"));
+	assert!(fs::read_to_string(format!("{}.2",file)).unwrap().contains("message 1"));
+	assert!(fs::metadata(format!("{}.3",file)).is_err());
+}
+#[test]
+fn test_agent_log_level_option() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ls,lr) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let logger = LevelLogger { sender:ls };
+
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,r) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let _ = thread::spawn(move || {
+		let player = MockPlayer::new(pms,pns,
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![])
+		);
+		let mut agent = UsiAgent::new(player);
+
+		agent.enable_log_level_option(LogLevel::Warn);
+
+		let _ = agent.start(input_reader,output_writer,logger,|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(());
+	});
+
+	let res = lr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the default log level timed out.");
+
+	assert_eq!(res,LogLevel::Warn);
+
+	let _ = s.send(String::from("usi"));
+
+	let mut options = Vec::new();
+
+	loop {
+		let res = r.recv_timeout(Duration::from_millis(350)).expect("attempt to receive the response of usi timed out.");
+
+		if &*res == "usiok" {
+			break;
+		}
+
+		options.push(res);
+	}
+
+	assert!(options.contains(
+		&String::from("option name LogLevel type combo default warn var error var warn var info var debug var trace")
+	));
+
+	let _ = s.send(String::from("setoption name LogLevel value debug"));
+
+	let res = lr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive the log level timed out.");
+
+	assert_eq!(res,LogLevel::Debug);
+
+	let _ = s.send(String::from("quit"));
+
+	let res = pmr.recv_timeout(Duration::from_millis(150)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+}
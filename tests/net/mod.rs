@@ -0,0 +1,261 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use usiagent::UsiAgent;
+use usiagent::input::USIInputReader;
+use usiagent::output::USIOutputWriter;
+use usiagent::net::*;
+
+use common::*;
+
+#[derive(Debug)]
+struct ErrorStream(io::ErrorKind);
+impl Read for ErrorStream {
+	fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+		Err(io::Error::new(self.0,"read error"))
+	}
+}
+fn usi_session<S>(stream:S) -> Vec<String> where S: USIConnection {
+	let mut writer = stream.try_clone_connection().unwrap();
+	let mut reader = BufReader::new(stream);
+
+	writer.write_all(b"usi\n").unwrap();
+
+	let mut lines = Vec::new();
+
+	loop {
+		let mut line = String::new();
+
+		assert!(reader.read_line(&mut line).unwrap() > 0);
+
+		let line = line.trim_end().to_string();
+
+		if line == "usiok" {
+			break;
+		}
+
+		lines.push(line);
+	}
+
+	writer.write_all(b"quit\n").unwrap();
+
+	let mut line = String::new();
+
+	assert_eq!(reader.read_line(&mut line).unwrap(),0);
+
+	lines
+}
+#[test]
+fn test_stream_input_reader() {
+	let mut reader = USIStreamInputReader::new(&b"usi\r\nisready\n\nquit"[..]);
+
+	assert_eq!(reader.read().unwrap(),Some(String::from("usi")));
+	assert_eq!(reader.read().unwrap(),Some(String::from("isready")));
+	assert_eq!(reader.read().unwrap(),Some(String::new()));
+	assert_eq!(reader.read().unwrap(),Some(String::from("quit")));
+	assert_eq!(reader.read().unwrap(),None);
+}
+#[test]
+fn test_tcp_reader_writer() {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let client = TcpStream::connect(addr).unwrap();
+	let server = listener.accept_connection().unwrap();
+
+	let writer = USITcpOutputWriter::new(client.try_clone_connection().unwrap());
+
+	assert_eq!(writer.write(&vec![String::from("usi"),String::from("isready")]).unwrap(),12);
+
+	client.shutdown_connection().unwrap();
+
+	let mut reader = USITcpInputReader::new(server);
+
+	assert_eq!(reader.read().unwrap(),Some(String::from("usi")));
+	assert_eq!(reader.read().unwrap(),Some(String::from("isready")));
+	assert_eq!(reader.read().unwrap(),None);
+}
+#[test]
+fn test_stream_reader_disconnected() {
+	for &kind in [io::ErrorKind::ConnectionReset,
+				  io::ErrorKind::ConnectionAborted,
+				  io::ErrorKind::BrokenPipe,
+				  io::ErrorKind::UnexpectedEof].iter() {
+		let mut reader = USIStreamInputReader::new(ErrorStream(kind));
+
+		assert_eq!(reader.read().unwrap(),None);
+	}
+
+	let mut reader = USIStreamInputReader::new(ErrorStream(io::ErrorKind::Other));
+
+	assert_eq!(reader.read().map_err(|e| e.kind()),Err(io::ErrorKind::Other));
+}
+#[test]
+fn test_stream_reader_invalid_data() {
+	let mut reader = USIStreamInputReader::new(&b"\xff\xfe\nusi\n"[..]);
+
+	assert_eq!(reader.read().map_err(|e| e.kind()),Err(io::ErrorKind::InvalidData));
+	assert_eq!(reader.read().unwrap(),Some(String::from("usi")));
+	assert_eq!(reader.read().unwrap(),None);
+}
+#[test]
+fn test_invalid_data_keeps_reading() {
+	let (pms,_) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ws,wr) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let _ = thread::spawn(move || {
+		let agent = UsiAgent::new(create_player(pms,pns));
+
+		let r = agent.start(USIStreamInputReader::new(&b"\xff\xfe\nusi\nquit\n"[..]),
+							MockOutputWriter::new(ws),StdErrorLogger::new(),|_,_| ());
+
+		let _ = ts.send(r.is_ok());
+	});
+
+	let res = wr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive id name timed out.");
+
+	assert_eq!(&*res,"id name mockplayer");
+
+	let res = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+
+	assert!(res);
+}
+#[test]
+fn test_disconnected_ends_session() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let _ = thread::spawn(move || {
+		let agent = UsiAgent::new(create_player(pms,pns));
+
+		let r = agent.start(USIStreamInputReader::new(ErrorStream(io::ErrorKind::ConnectionReset)),
+							USIStreamOutputWriter::new(io::sink()),StdErrorLogger::new(),|_,_| ());
+
+		let _ = ts.send(r.is_ok());
+	});
+
+	let res = pmr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let res = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+
+	assert!(res);
+}
+#[test]
+fn test_serve_tcp() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (cs,cr) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let addr = listener.local_addr().unwrap();
+
+	let _ = thread::spawn(move || {
+		let agent = UsiAgent::new(create_player(pms.clone(),pns.clone()));
+
+		let r = agent.serve(&listener,Some(3),|| {
+			let _ = cs.send(());
+			create_player(pms.clone(),pns.clone())
+		},StdErrorLogger::new(),|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(r.is_ok());
+	});
+
+	let lines = usi_session(TcpStream::connect(addr).unwrap());
+
+	assert_eq!(&*lines[0],"id name mockplayer");
+	assert_eq!(&*lines[1],"id author j6k1");
+
+	let res = pmr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	assert!(cr.try_recv().is_err());
+
+	{
+		let mut stream = TcpStream::connect(addr).unwrap();
+
+		stream.write_all(b"usi\n").unwrap();
+
+		let mut reader = BufReader::new(stream.try_clone().unwrap());
+		let mut line = String::new();
+
+		reader.read_line(&mut line).unwrap();
+
+		assert_eq!(line.trim_end(),"id name mockplayer");
+	}
+
+	cr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive the creation of the player timed out.");
+
+	let res = pmr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	assert_eq!(usi_session(TcpStream::connect(addr).unwrap()),lines);
+
+	cr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive the creation of the player timed out.");
+
+	let res = pmr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let res = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+
+	assert!(res);
+}
+#[cfg(unix)]
+#[test]
+fn test_serve_unix() {
+	let (pms,pmr) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let dir = env::temp_dir().join("usiagent-test-net");
+	let _ = fs::create_dir_all(&dir);
+	let path = dir.join("usiagent.sock");
+	let _ = fs::remove_file(&path);
+
+	let listener = UnixListener::bind(&path).unwrap();
+
+	let _ = thread::spawn(move || {
+		let agent = UsiAgent::new(create_player(pms.clone(),pns.clone()));
+
+		let r = agent.serve(&listener,Some(1),|| create_player(pms.clone(),pns.clone()),StdErrorLogger::new(),|h,e| {
+			if let Some(h) = h {
+				let _ = h.lock().map(|h| h.call(e));
+			}
+		});
+
+		let _ = ts.send(r.is_ok());
+	});
+
+	let lines = usi_session(UnixStream::connect(&path).unwrap());
+
+	assert_eq!(&*lines[0],"id name mockplayer");
+
+	let res = pmr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let res = tr.recv_timeout(Duration::from_millis(300)).expect("attempt to receive on quited timed out.");
+
+	assert!(res);
+
+	let _ = fs::remove_file(&path);
+}
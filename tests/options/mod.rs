@@ -0,0 +1,151 @@
+use std::env;
+use std::fs;
+
+use usiagent::command::UsiOptType;
+use usiagent::error::{OptionRegistryError, SetOptionParseError};
+use usiagent::event::{SysEventOption, SysEventOptionKind};
+use usiagent::options::OptionRegistry;
+use usiagent::selfmatch::SelfMatchConfig;
+
+#[derive(Debug, Default)]
+struct Settings {
+	hash:i64,
+	ponder:bool,
+	style:String,
+	book:String,
+	cleared:u32,
+}
+fn create_registry() -> OptionRegistry<Settings> {
+	OptionRegistry::<Settings>::new()
+		.spin("USI_Hash",1,100,Some(16),|s,v| s.hash = v)
+		.check("USI_Ponder",Some(false),|s,v| s.ponder = v)
+		.combo("Style",Some("normal"),&["normal","aggressive"],|s,v| s.style = v)
+		.filename("Book File",None,|s,v| s.book = v)
+		.button("ClearHash",|s| s.cleared += 1)
+}
+#[test]
+fn test_option_maps() {
+	let registry = create_registry();
+
+	let kinds = registry.option_kinds();
+
+	assert_eq!(kinds.len(),5);
+	assert!(matches!(kinds["USI_Hash"],SysEventOptionKind::Num));
+	assert!(matches!(kinds["USI_Ponder"],SysEventOptionKind::Bool));
+	assert!(matches!(kinds["Style"],SysEventOptionKind::Str));
+	assert!(matches!(kinds["Book File"],SysEventOptionKind::Str));
+	assert!(matches!(kinds["ClearHash"],SysEventOptionKind::Exist));
+
+	let options = registry.options();
+
+	assert_eq!(options["USI_Hash"],UsiOptType::Spin(1,100,Some(16)));
+	assert_eq!(options["USI_Ponder"],UsiOptType::Check(Some(false)));
+	assert_eq!(options["Style"],UsiOptType::Combo(Some(String::from("normal")),
+													vec![String::from("normal"),String::from("aggressive")]));
+	assert_eq!(options["Book File"],UsiOptType::FileName(None));
+	assert_eq!(options["ClearHash"],UsiOptType::Button);
+}
+#[test]
+fn test_option_apply() {
+	let registry = create_registry();
+	let mut settings = Settings::default();
+
+	registry.apply_defaults(&mut settings).unwrap();
+
+	assert_eq!(settings.hash,16);
+	assert!(!settings.ponder);
+	assert_eq!(&*settings.style,"normal");
+	assert_eq!(&*settings.book,"");
+
+	registry.apply(&mut settings,"USI_Hash",SysEventOption::Num(64)).unwrap();
+	registry.apply(&mut settings,"USI_Ponder",SysEventOption::Bool(true)).unwrap();
+	registry.apply(&mut settings,"Style",SysEventOption::Str(String::from("aggressive"))).unwrap();
+	registry.apply(&mut settings,"Book File",SysEventOption::Str(String::from("book.bin"))).unwrap();
+	registry.apply(&mut settings,"ClearHash",SysEventOption::Exist).unwrap();
+
+	assert_eq!(settings.hash,64);
+	assert!(settings.ponder);
+	assert_eq!(&*settings.style,"aggressive");
+	assert_eq!(&*settings.book,"book.bin");
+	assert_eq!(settings.cleared,1);
+
+	assert!(matches!(registry.apply(&mut settings,"USI_Hash",SysEventOption::Num(101)),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::OutOfRange(ref name,101,1,100))) if name == "USI_Hash"
+	));
+	assert!(matches!(registry.apply(&mut settings,"Style",SysEventOption::Str(String::from("defensive"))),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::NotInCombo(_,ref v))) if v == "defensive"
+	));
+	assert!(matches!(registry.apply(&mut settings,"USI_Ponder",SysEventOption::Num(1)),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::InvalidValue(_,_)))
+	));
+	assert!(matches!(registry.apply(&mut settings,"Unknown",SysEventOption::Exist),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::UnknownOption(ref name))) if name == "Unknown"
+	));
+
+	assert_eq!(settings.hash,64);
+	assert_eq!(&*settings.style,"aggressive");
+}
+#[test]
+fn test_option_read_write() {
+	let registry = create_registry();
+
+	let options = registry.read_options(&b"# player1\nUSI_Hash = 32\n\nUSI_Ponder=true\nBook File = books/a b.bin\nClearHash\n"[..]).unwrap();
+
+	assert_eq!(options,vec![
+		(String::from("USI_Hash"),SysEventOption::Num(32)),
+		(String::from("USI_Ponder"),SysEventOption::Bool(true)),
+		(String::from("Book File"),SysEventOption::Str(String::from("books/a b.bin"))),
+		(String::from("ClearHash"),SysEventOption::Exist),
+	]);
+
+	let mut buf = Vec::new();
+
+	registry.write_options(&mut buf,&options).unwrap();
+
+	assert_eq!(String::from_utf8(buf.clone()).unwrap(),"USI_Hash = 32\nUSI_Ponder = true\nBook File = \"books/a b.bin\"\nClearHash\n");
+	assert_eq!(registry.read_options(&buf[..]).unwrap(),options);
+
+	let options = vec![(String::from("Book File"),SysEventOption::Str(String::from(" \"a\" ")))];
+	let mut buf = Vec::new();
+
+	registry.write_options(&mut buf,&options).unwrap();
+
+	assert_eq!(registry.read_options(&buf[..]).unwrap(),options);
+
+	let config = SelfMatchConfig::read(&[&b"[player1.options]\n"[..],&buf[..]].concat()[..]).unwrap();
+
+	assert_eq!(config.player1.options,vec![(String::from("Book File"),String::from(" \"a\" "))]);
+
+	let options = registry.read_options(&b"; comment\n[options]\nBook File = \"books/b.bin\"\n[other]\nUSI_Hash = 1000\n"[..]).unwrap();
+
+	assert_eq!(options,vec![(String::from("Book File"),SysEventOption::Str(String::from("books/b.bin")))]);
+
+	assert!(matches!(registry.read_options(&b"USI_Hash = 1000\n"[..]),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::OutOfRange(_,1000,1,100)))
+	));
+	assert!(matches!(registry.read_options(&b"USI_Hash = 10\n= 1\n"[..]),
+		Err(OptionRegistryError::InvalidFormat(2,_))
+	));
+	assert!(matches!(registry.read_options(&b"USI_Ponder = yes\n"[..]),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::InvalidValue(_,ref v))) if v == "yes"
+	));
+	assert!(matches!(registry.read_options(&b"USI_Hash =\n"[..]),
+		Err(OptionRegistryError::OptionError(SetOptionParseError::MissingValue(ref name))) if name == "USI_Hash"
+	));
+}
+#[test]
+fn test_option_save_load() {
+	let registry = create_registry();
+
+	let dir = env::temp_dir().join("usiagent-test-options");
+	let _ = fs::create_dir_all(&dir);
+	let path = dir.join("player1.txt").display().to_string();
+
+	let options = registry.defaults();
+
+	registry.save_options(&path,&options).unwrap();
+
+	assert_eq!(registry.load_options(&path).unwrap(),options);
+
+	let _ = fs::remove_file(&path);
+}
@@ -41,7 +41,7 @@ use usiagent::shogi::KomaKind::{
 };
 
 use common::*;
-use usiagent::command::UsiInfoSubCommand;
+use usiagent::command::{UsiInfoSubCommand, UsiScore, UsiScoreMate};
 
 #[test]
 fn test_apply_moves() {
@@ -173,4 +173,125 @@ fn test_send_immediate() {
 	}
 
 	assert!(found);
-}
\ No newline at end of file
+}
+fn recv_commands(r:&mpsc::Receiver<UsiInfoMessage>) -> Vec<Vec<UsiInfoSubCommand>> {
+	let mut commands = Vec::new();
+
+	while let Ok(m) = r.try_recv() {
+		if let UsiInfoMessage::Commands(c) = m {
+			commands.push(c);
+		}
+	}
+
+	commands
+}
+#[test]
+fn test_throttled_info_sender_coalesce() {
+	let (s,r) = mpsc::channel();
+
+	let mut info_sender = ThrottledInfoSender::new(MockInfoSender::new(s),Duration::from_secs(3600));
+
+	let m = Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false));
+
+	info_sender.send(vec![UsiInfoSubCommand::Depth(1)]).unwrap();
+
+	assert_eq!(recv_commands(&r),vec![vec![UsiInfoSubCommand::Depth(1)]]);
+
+	info_sender.send(vec![
+		UsiInfoSubCommand::Depth(2),
+		UsiInfoSubCommand::SelDepth(4),
+		UsiInfoSubCommand::Score(UsiScore::Cp(10)),
+		UsiInfoSubCommand::Pv(vec![m])
+	]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Nodes(100),UsiInfoSubCommand::CurrMove(m)]).unwrap();
+
+	assert_eq!(recv_commands(&r),Vec::<Vec<UsiInfoSubCommand>>::new());
+
+	info_sender.flush().unwrap();
+
+	assert_eq!(recv_commands(&r),vec![vec![
+		UsiInfoSubCommand::Depth(2),
+		UsiInfoSubCommand::SelDepth(4),
+		UsiInfoSubCommand::Nodes(100),
+		UsiInfoSubCommand::Score(UsiScore::Cp(10)),
+		UsiInfoSubCommand::CurrMove(m),
+		UsiInfoSubCommand::Pv(vec![m])
+	]]);
+
+	info_sender.send(vec![UsiInfoSubCommand::Nodes(200),UsiInfoSubCommand::CurrMove(m)]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Depth(3),UsiInfoSubCommand::Score(UsiScore::Cp(20))]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Nodes(300)]).unwrap();
+
+	info_sender.flush().unwrap();
+
+	assert_eq!(recv_commands(&r),vec![vec![
+		UsiInfoSubCommand::Depth(3),
+		UsiInfoSubCommand::Nodes(300),
+		UsiInfoSubCommand::Score(UsiScore::Cp(20))
+	]]);
+}
+#[test]
+fn test_throttled_info_sender_multipv_and_string() {
+	let (s,r) = mpsc::channel();
+
+	let mut info_sender = ThrottledInfoSender::new(MockInfoSender::new(s),Duration::from_secs(3600));
+
+	let m1 = Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false));
+	let m2 = Move::To(KomaSrcPosition(2,7),KomaDstToPosition(2,6,false));
+
+	info_sender.send(vec![UsiInfoSubCommand::Str(String::from("start"))]).unwrap();
+
+	assert_eq!(recv_commands(&r).len(),1);
+
+	info_sender.send(vec![UsiInfoSubCommand::MultiPv(2),UsiInfoSubCommand::Pv(vec![m2])]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::MultiPv(1),UsiInfoSubCommand::Pv(vec![m2])]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Str(String::from("a"))]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::MultiPv(1),UsiInfoSubCommand::Pv(vec![m1])]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Str(String::from("b"))]).unwrap();
+
+	info_sender.send_immediate(vec![UsiInfoSubCommand::Depth(5)]).unwrap();
+
+	assert_eq!(recv_commands(&r),vec![
+		vec![UsiInfoSubCommand::Str(String::from("a"))],
+		vec![UsiInfoSubCommand::Str(String::from("b"))],
+		vec![UsiInfoSubCommand::Depth(5)],
+		vec![UsiInfoSubCommand::MultiPv(2),UsiInfoSubCommand::Pv(vec![m2])]
+	]);
+}
+#[test]
+fn test_throttled_info_sender_pass_through() {
+	let (s,r) = mpsc::channel();
+
+	let mut info_sender = ThrottledInfoSender::new(MockInfoSender::new(s.clone()),Duration::from_secs(3600));
+
+	info_sender.send(vec![UsiInfoSubCommand::Depth(1)]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Depth(2)]).unwrap();
+
+	assert_eq!(recv_commands(&r).len(),1);
+
+	info_sender.send(vec![UsiInfoSubCommand::Depth(3),UsiInfoSubCommand::Score(UsiScore::Mate(UsiScoreMate::Num(5)))]).unwrap();
+
+	assert_eq!(recv_commands(&r),vec![vec![
+		UsiInfoSubCommand::Depth(3),
+		UsiInfoSubCommand::Score(UsiScore::Mate(UsiScoreMate::Num(5)))
+	]]);
+
+	info_sender.send(vec![UsiInfoSubCommand::Depth(4)]).unwrap();
+
+	let cloned = info_sender.clone();
+
+	drop(info_sender);
+
+	assert_eq!(recv_commands(&r).len(),0);
+
+	drop(cloned);
+
+	assert_eq!(recv_commands(&r),vec![vec![UsiInfoSubCommand::Depth(4)]]);
+
+	let mut info_sender = ThrottledInfoSender::new(MockInfoSender::new(s),Duration::from_millis(0));
+
+	info_sender.send(vec![UsiInfoSubCommand::Depth(1)]).unwrap();
+	info_sender.send(vec![UsiInfoSubCommand::Depth(2)]).unwrap();
+
+	assert_eq!(recv_commands(&r).len(),2);
+}
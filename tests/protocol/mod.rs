@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
 use usiagent::shogi::*;
@@ -1455,4 +1456,70 @@ fn test_position_parse_result_extract() {
 	for (i,r) in input_and_expected.into_iter() {
 		assert_eq!(i.extract(),r);
 	}
-}
\ No newline at end of file
+}
+fn create_option_maps() -> (BTreeMap<String,SysEventOptionKind>,BTreeMap<String,UsiOptType>) {
+	let mut kinds = BTreeMap::new();
+	let mut types = BTreeMap::new();
+
+	kinds.insert(String::from("BookFile"),SysEventOptionKind::Str);
+	types.insert(String::from("BookFile"),UsiOptType::FileName(None));
+	kinds.insert(String::from("Book Move Limit"),SysEventOptionKind::Num);
+	types.insert(String::from("Book Move Limit"),UsiOptType::Spin(0,100,Some(16)));
+	kinds.insert(String::from("Style"),SysEventOptionKind::Str);
+	types.insert(String::from("Style"),UsiOptType::Combo(None,vec![String::from("Normal"),String::from("Very Aggressive")]));
+	kinds.insert(String::from("USI_Ponder"),SysEventOptionKind::Bool);
+	types.insert(String::from("USI_Ponder"),UsiOptType::Check(None));
+	kinds.insert(String::from("Clear Hash"),SysEventOptionKind::Exist);
+	types.insert(String::from("Clear Hash"),UsiOptType::Button);
+
+	(kinds,types)
+}
+#[test]
+fn test_setoption_parser() {
+	let (kinds,types) = create_option_maps();
+	let parser = SetOptionParser::new();
+
+	assert_eq!(parser.parse(" name BookFile value C:\\My Books\\book.db",&kinds,&types),
+				Ok((String::from("BookFile"),SysEventOption::Str(String::from("C:\\My Books\\book.db")))));
+	assert_eq!(parser.parse("  name   Book Move Limit   value  32",&kinds,&types),
+				Ok((String::from("Book Move Limit"),SysEventOption::Num(32))));
+	assert_eq!(parser.parse(" name Style value Very Aggressive",&kinds,&types),
+				Ok((String::from("Style"),SysEventOption::Str(String::from("Very Aggressive")))));
+	assert_eq!(parser.parse(" name USI_Ponder value true",&kinds,&types),
+				Ok((String::from("USI_Ponder"),SysEventOption::Bool(true))));
+	assert_eq!(parser.parse(" name Clear Hash",&kinds,&types),
+				Ok((String::from("Clear Hash"),SysEventOption::Exist)));
+	assert_eq!(parser.parse(" name BookFile value book value.db",&kinds,&BTreeMap::new()),
+				Ok((String::from("BookFile"),SysEventOption::Str(String::from("book value.db")))));
+}
+#[test]
+fn test_setoption_parser_error() {
+	let (kinds,types) = create_option_maps();
+	let parser = SetOptionParser::new();
+
+	assert_eq!(parser.parse(" BookFile value book.db",&kinds,&types),
+				Err(SetOptionParseError::InvalidFormat(String::from("BookFile value book.db"))));
+	assert_eq!(parser.parse(" name value book.db",&kinds,&types),
+				Err(SetOptionParseError::InvalidFormat(String::from("name value book.db"))));
+	assert_eq!(parser.parse(" name Unknown value 1",&kinds,&types),
+				Err(SetOptionParseError::UnknownOption(String::from("Unknown"))));
+	assert_eq!(parser.parse(" name BookFile value ",&kinds,&types),
+				Err(SetOptionParseError::MissingValue(String::from("BookFile"))));
+	assert_eq!(parser.parse(" name BookFile",&kinds,&types),
+				Err(SetOptionParseError::MissingValue(String::from("BookFile"))));
+	assert_eq!(parser.parse(" name Book Move Limit value 101",&kinds,&types),
+				Err(SetOptionParseError::OutOfRange(String::from("Book Move Limit"),101,0,100)));
+	assert_eq!(parser.parse(" name Book Move Limit value -1",&kinds,&types),
+				Err(SetOptionParseError::OutOfRange(String::from("Book Move Limit"),-1,0,100)));
+	assert_eq!(parser.parse(" name Book Move Limit value ten",&kinds,&types),
+				Err(SetOptionParseError::InvalidValue(String::from("Book Move Limit"),String::from("ten"))));
+	assert_eq!(parser.parse(" name Style value Aggressive",&kinds,&types),
+				Err(SetOptionParseError::NotInCombo(String::from("Style"),String::from("Aggressive"))));
+	assert_eq!(parser.parse(" name USI_Ponder value yes",&kinds,&types),
+				Err(SetOptionParseError::InvalidValue(String::from("USI_Ponder"),String::from("yes"))));
+	assert_eq!(parser.parse(" name Clear Hash value 1",&kinds,&types),
+				Err(SetOptionParseError::InvalidValue(String::from("Clear Hash"),String::from("1"))));
+
+	assert_eq!(parser.parse(" name Book Move Limit value 101",&kinds,&BTreeMap::new()),
+				Ok((String::from("Book Move Limit"),SysEventOption::Num(101))));
+}
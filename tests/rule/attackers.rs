@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use usiagent::shogi::*;
+use usiagent::shogi::MochigomaCollections;
+use usiagent::rule::Rule;
+use usiagent::rule::State;
+
+use super::*;
+
+fn square(file:u32,rank:u32) -> rule::Square {
+	((9 - file) * 9 + rank - 1) as rule::Square
+}
+fn create_state(pieces:&[(u32,u32,KomaKind)]) -> State {
+	let mut banmen = Banmen([[Blank; 9]; 9]);
+
+	for &(file,rank,k) in pieces {
+		banmen.0[rank as usize - 1][9 - file as usize] = k;
+	}
+
+	State::new(banmen)
+}
+#[test]
+fn test_gen_attack_bits_equal_to_legal_moves() {
+	let kinds = [
+		SFu, SKyou, SKei, SGin, SKin, SKaku, SHisha, SOu, SFuN, SKyouN, SKeiN, SGinN, SKakuN, SHishaN,
+		GFu, GKyou, GKei, GGin, GKin, GKaku, GHisha, GOu, GFuN, GKyouN, GKeiN, GGinN, GKakuN, GHishaN
+	];
+
+	for &kind in kinds.iter() {
+		let teban = if kind < GFu {
+			Teban::Sente
+		} else {
+			Teban::Gote
+		};
+
+		let blocker = if teban == Teban::Sente {
+			GKin
+		} else {
+			SKin
+		};
+
+		for &(file,rank) in [(5,5),(1,3),(9,7),(2,2),(8,8)].iter() {
+			let state = create_state(&[(file,rank,kind),(5,2,blocker),(3,5,blocker),(5,8,blocker),(7,3,blocker)]);
+
+			let occupied = Rule::occupied_board(&state);
+			let from = square(file,rank);
+
+			let attacks = Rule::gen_attack_bits(kind,from as u32,occupied).iter().collect::<HashSet<rule::Square>>();
+
+			let mvs = Rule::legal_moves_all(teban,&state,&MochigomaCollections::Empty).into_iter().filter_map(|m| {
+				match m.to_move() {
+					Move::To(KomaSrcPosition(sx,sy),KomaDstToPosition(dx,dy,_)) if sx == file && sy == rank => {
+						Some(square(dx,dy))
+					},
+					_ => None
+				}
+			}).collect::<HashSet<rule::Square>>();
+
+			assert_eq!(attacks,mvs,"kind = {:?}, position = {:?}",kind,(file,rank));
+			assert_eq!(Rule::mobility(&state,from).iter().collect::<HashSet<rule::Square>>(),mvs);
+		}
+	}
+}
+#[test]
+fn test_gen_attack_bits_include_self_piece() {
+	let state = create_state(&[(5,5,SHisha),(5,3,SFu),(3,5,GFu)]);
+
+	let attacks = Rule::gen_attack_bits(SHisha,square(5,5) as u32,Rule::occupied_board(&state));
+
+	assert!(attacks & (1 << (square(5,3) + 1)) != 0);
+	assert!(attacks & (1 << (square(5,2) + 1)) == 0);
+	assert!(attacks & (1 << (square(3,5) + 1)) != 0);
+	assert!(attacks & (1 << (square(2,5) + 1)) == 0);
+
+	let mobility = Rule::mobility(&state,square(5,5));
+
+	assert!(mobility & (1 << (square(5,3) + 1)) == 0);
+	assert!(mobility & (1 << (square(5,4) + 1)) != 0);
+	assert!(mobility & (1 << (square(3,5) + 1)) != 0);
+}
+#[test]
+fn test_attackers_to() {
+	let state = create_state(&[(5,3,GFu),(4,2,GKin),(6,1,GKaku),(5,7,SKyou),(5,9,SHisha),(3,5,SKaku),(4,5,SKei)]);
+	let to = square(5,3);
+
+	let occupied = Rule::occupied_board(&state);
+
+	assert_eq!(Rule::attackers_to(&state,Teban::Sente,to,occupied).iter().collect::<HashSet<rule::Square>>(),
+			   [square(5,7),square(3,5),square(4,5)].iter().cloned().collect::<HashSet<rule::Square>>());
+	assert_eq!(Rule::attackers_to(&state,Teban::Gote,to,occupied).iter().collect::<HashSet<rule::Square>>(),
+			   [square(4,2)].iter().cloned().collect::<HashSet<rule::Square>>());
+
+	let occupied = occupied ^ (1 << (square(5,7) + 1));
+
+	assert_eq!(Rule::attackers_to(&state,Teban::Sente,to,occupied).iter().collect::<HashSet<rule::Square>>(),
+			   [square(5,9),square(3,5),square(4,5)].iter().cloned().collect::<HashSet<rule::Square>>());
+}
+#[test]
+fn test_mobility_start_pos() {
+	let state = State::new(BANMEN_START_POS.clone());
+
+	assert_eq!(Rule::mobility(&state,square(2,8)).bitcount(),6);
+	assert_eq!(Rule::mobility(&state,square(8,8)).bitcount(),0);
+	assert_eq!(Rule::mobility(&state,square(8,2)).bitcount(),6);
+	assert_eq!(Rule::mobility(&state,square(5,5)).bitcount(),0);
+}
@@ -0,0 +1,117 @@
+use std::convert::TryFrom;
+
+use usiagent::rule::{AppliedMove, LegalMove, Move16, Move32};
+use usiagent::shogi::*;
+
+use super::{create_positions, generate_legal_moves};
+
+#[test]
+fn test_move16_layout() {
+	let m = Move16::from(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)));
+
+	assert_eq!(u16::from(m),24 << 7 | 23);
+	assert_eq!(m.src(),Some(24));
+	assert_eq!(m.dst(),23);
+	assert!(!m.is_put());
+	assert!(!m.is_nari());
+	assert_eq!(m.kind(),None);
+
+	let m = Move16::from(Move::To(KomaSrcPosition(8,8),KomaDstToPosition(2,2,true)));
+
+	assert_eq!(u16::from(m),1 << 15 | 16 << 7 | 64);
+
+	let m = Move16::from(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(5,5)));
+
+	assert_eq!(u16::from(m),1 << 14 | 4 << 7 | 40);
+	assert_eq!(m.src(),None);
+	assert_eq!(m.dst(),40);
+	assert!(m.is_put());
+	assert_eq!(m.kind(),Some(MochigomaKind::Kin));
+}
+#[test]
+fn test_move16_round_trip() {
+	for (teban,state,mc) in create_positions() {
+		for m in generate_legal_moves(teban,&state,&mc) {
+			let m16 = Move16::from(m);
+
+			assert_eq!(Move16::try_from(u16::from(m16)).unwrap(),m16);
+			assert_eq!(m16.to_legal_move(&state),m);
+			assert_eq!(m16.to_applied_move(),m.to_applied_move());
+			assert_eq!(m16.to_move(),m.to_move());
+			assert_eq!(Move16::from(m.to_applied_move()),m16);
+			assert_eq!(Move16::from(m.to_move()),m16);
+			assert_ne!(u16::from(m16),0);
+		}
+	}
+}
+#[test]
+fn test_move32_round_trip() {
+	for (teban,state,mc) in create_positions() {
+		for m in generate_legal_moves(teban,&state,&mc) {
+			let m32 = Move32::new(&state,teban,Move16::from(m));
+
+			assert_eq!(Move32::try_from(u32::from(m32)).unwrap(),m32);
+			assert_eq!(m32.move16(),Move16::from(m));
+			assert_eq!(LegalMove::from(m32),m);
+			assert_eq!(AppliedMove::from(m32),m.to_applied_move());
+			assert_eq!(m32.to_move(),m.to_move());
+
+			match m {
+				LegalMove::To(mt) => {
+					let (sx,sy) = (mt.src() / 9,mt.src() % 9);
+
+					assert_eq!(m32.moved(),state.get_banmen().0[sy as usize][sx as usize]);
+					assert_eq!(m32.captured().is_some(),mt.obtained().is_some());
+				},
+				LegalMove::Put(mp) => {
+					let expected = match teban {
+						Teban::Sente => KomaKind::SFu as u32 + mp.kind() as u32,
+						Teban::Gote => KomaKind::GFu as u32 + mp.kind() as u32,
+					};
+
+					assert_eq!(m32.moved() as u32,expected);
+					assert_eq!(m32.captured(),None);
+				}
+			}
+		}
+	}
+}
+#[test]
+fn test_move16_try_from_invalid() {
+	// 移動先が盤面の範囲外
+	assert!(Move16::try_from(24 << 7 | 81).is_err());
+	// 移動元が盤面の範囲外
+	assert!(Move16::try_from(100 << 7 | 23).is_err());
+	// 移動元と移動先が同じ
+	assert!(Move16::try_from(23 << 7 | 23).is_err());
+	// 置く駒の種類が不正
+	assert!(Move16::try_from(1 << 14 | 7 << 7 | 40).is_err());
+	// 成る持ち駒を置く手
+	assert!(Move16::try_from(1 << 15 | 1 << 14 | 4 << 7 | 40).is_err());
+	// 指し手無しを表す値
+	assert!(Move16::try_from(0).is_err());
+}
+#[test]
+fn test_move32_try_from_invalid() {
+	let m16 = u16::from(Move16::from(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)))) as u32;
+	let blank = KomaKind::Blank as u32;
+
+	assert!(Move32::try_from(blank << 21 | (KomaKind::SFu as u32) << 16 | m16).is_ok());
+	// Move16として不正
+	assert!(Move32::try_from(blank << 21 | (KomaKind::SFu as u32) << 16 | 24 << 7 | 81).is_err());
+	// 動かした駒の種類が不正
+	assert!(Move32::try_from(blank << 21 | blank << 16 | m16).is_err());
+	assert!(Move32::try_from(blank << 21 | 31 << 16 | m16).is_err());
+	// 獲った駒の種類が不正
+	assert!(Move32::try_from(31 << 21 | (KomaKind::SFu as u32) << 16 | m16).is_err());
+	// 使われていない上位のビットが立っている
+	assert!(Move32::try_from(1 << 26 | blank << 21 | (KomaKind::SFu as u32) << 16 | m16).is_err());
+
+	let put = u16::from(Move16::from(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(5,5)))) as u32;
+
+	assert!(Move32::try_from(blank << 21 | (KomaKind::GKin as u32) << 16 | put).is_ok());
+	// 置いた駒と持ち駒の種類が一致しない
+	assert!(Move32::try_from(blank << 21 | (KomaKind::SGin as u32) << 16 | put).is_err());
+	// 駒を置く手で駒を獲っている
+	assert!(Move32::try_from((KomaKind::GFu as u32) << 21 | (KomaKind::SKin as u32) << 16 | put).is_err());
+}
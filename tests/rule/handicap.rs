@@ -0,0 +1,51 @@
+use usiagent::shogi::*;
+use usiagent::protocol::*;
+use usiagent::rule::{BANMEN_START_POS, HANDICAPS, Handicap};
+
+#[test]
+fn test_handicap_to_sfen() {
+	let expected = vec![
+		(Handicap::KyouOchi,"sfen lnsgkgsn1/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::MigiKyouOchi,"sfen 1nsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::KakuOchi,"sfen lnsgkgsnl/1r7/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::HishaOchi,"sfen lnsgkgsnl/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::HiKyouOchi,"sfen lnsgkgsn1/7b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::NimaiOchi,"sfen lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::SanmaiOchi,"sfen lnsgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::YonmaiOchi,"sfen 1nsgkgsn1/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::GomaiOchi,"sfen 1nsgkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::RokumaiOchi,"sfen 2sgkgs2/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::NanamaiOchi,"sfen 2sgkg3/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::HachimaiOchi,"sfen 3gkg3/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+		(Handicap::JumaiOchi,"sfen 4k4/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+	];
+
+	assert_eq!(expected.len(),HANDICAPS.len());
+
+	for (h,sfen) in expected {
+		assert_eq!(h.to_sfen().unwrap(),String::from(sfen));
+	}
+}
+#[test]
+fn test_handicap_from_banmen() {
+	for &h in HANDICAPS.iter() {
+		assert_eq!(Handicap::from_banmen(&h.banmen()),Some(h));
+	}
+
+	assert_eq!(Handicap::from_banmen(&BANMEN_START_POS),None);
+}
+#[test]
+fn test_handicap_parse_sfen() {
+	for &h in HANDICAPS.iter() {
+		let sfen = h.to_sfen().unwrap();
+
+		let (teban, banmen, mc, n, mvs) = PositionParser::new().parse(&sfen.split(" ").collect::<Vec<&str>>()).unwrap().extract();
+
+		assert_eq!(teban,Teban::Gote);
+		assert_eq!(teban,h.teban());
+		assert_eq!(Handicap::from_banmen(&banmen),Some(h));
+		assert_eq!(mc,MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()));
+		assert_eq!(n,1);
+		assert!(mvs.is_empty());
+	}
+}
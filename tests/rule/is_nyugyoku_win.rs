@@ -1,6 +1,10 @@
 use usiagent::shogi::*;
 use usiagent::shogi::MochigomaCollections;
-use usiagent::rule::Rule;
+use std::convert::TryFrom;
+use usiagent::command::UsiOptType;
+use usiagent::event::GameEndState;
+use usiagent::protocol::PositionParser;
+use usiagent::rule::{NyugyokuPoint, NyugyokuRule, Rule};
 use usiagent::rule::State;
 
 use super::*;
@@ -615,3 +619,76 @@ fn test_is_nyugyoku_win_lose_gote() {
 	assert!(!Rule::is_nyugyoku_win(&state,Teban::Gote,&mc,&None));
 	assert!(!Rule::is_nyugyoku_win(&state,Teban::Gote,&mc,&Some(Instant::now())));
 }
+fn parse_position(sfen:&str) -> (Teban,State,MochigomaCollections) {
+	let (teban, banmen, mc, _, _) = PositionParser::new().parse(&sfen.split(' ').collect::<Vec<&str>>()).unwrap().extract();
+
+	(teban,State::new(banmen),mc)
+}
+#[test]
+fn test_nyugyoku_point() {
+	let (_,state,mc) = parse_position("sfen +P+P+P+P+P+P+P+P+P/4K4/R8/9/9/9/9/9/4k4 b B4G4S 1");
+
+	assert_eq!(Rule::nyugyoku_point(&state,Teban::Sente,&mc),NyugyokuPoint { ou_entered:true, count:10, point:27 });
+	assert_eq!(Rule::nyugyoku_point(&state,Teban::Gote,&mc),NyugyokuPoint { ou_entered:true, count:0, point:0 });
+
+	let (_,state,mc) = parse_position("startpos");
+
+	assert_eq!(Rule::nyugyoku_point(&state,Teban::Sente,&mc),NyugyokuPoint { ou_entered:false, count:0, point:0 });
+}
+#[test]
+fn test_judge_nyugyoku_by_point() {
+	let (_,state,mc) = parse_position("sfen +P+P+P+P+P+P+P+P+P/4K4/R8/9/9/9/9/9/4k4 b B4G4S 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::Csa27),GameEndState::Lose);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::Csa24),GameEndState::Draw);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::NoEnteringKing),GameEndState::Lose);
+	assert!(!Rule::is_nyugyoku_win(&state,Teban::Sente,&mc,&None));
+
+	let (_,state,mc) = parse_position("sfen +P+P+P+P+P+P+P+P+P/4K4/R8/9/9/9/9/9/4k4 b B4G4SN 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::Csa27),GameEndState::Win);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::Csa24),GameEndState::Draw);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::TryRule),GameEndState::Lose);
+	assert!(Rule::is_nyugyoku_win(&state,Teban::Sente,&mc,&None));
+
+	let (_,state,mc) = parse_position("sfen +P+P+P+P+P+P+P+P+P/4K4/R8/9/9/9/9/9/4k4 b B4G4S4N 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::Csa24),GameEndState::Win);
+
+	let (_,state,mc) = parse_position("sfen 4K4/9/9/9/9/9/r8/4k4/+p+p+p+p+p+p+p+p+p w b4g4s 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Gote,&mc,&None,NyugyokuRule::Csa27),GameEndState::Win);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Gote,&mc,&None,NyugyokuRule::Csa24),GameEndState::Draw);
+}
+#[test]
+fn test_judge_nyugyoku_try_rule() {
+	let (_,state,mc) = parse_position("sfen 9/4K4/9/9/9/9/9/9/4k4 b - 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::TryRule),GameEndState::Win);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::Csa27),GameEndState::Lose);
+
+	let (_,state,mc) = parse_position("sfen 5g3/4K4/9/9/9/9/9/9/4k4 b - 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::TryRule),GameEndState::Lose);
+
+	let (_,state,mc) = parse_position("sfen 4K4/9/9/9/9/9/9/4k4/9 w - 1");
+
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Gote,&mc,&None,NyugyokuRule::TryRule),GameEndState::Win);
+	assert_eq!(Rule::judge_nyugyoku(&state,Teban::Sente,&mc,&None,NyugyokuRule::TryRule),GameEndState::Lose);
+}
+#[test]
+fn test_nyugyoku_rule_usi_option() {
+	for &rule in &[NyugyokuRule::NoEnteringKing,NyugyokuRule::Csa24,NyugyokuRule::Csa27,NyugyokuRule::TryRule] {
+		assert_eq!(NyugyokuRule::try_from(rule.to_usi_option_value()).unwrap(),rule);
+	}
+
+	assert!(NyugyokuRule::try_from("CSARule26").is_err());
+	assert_eq!(NyugyokuRule::default(),NyugyokuRule::Csa27);
+	assert_eq!(NyugyokuRule::OPTION_NAME,"EnteringKingRule");
+	assert_eq!(NyugyokuRule::usi_option(NyugyokuRule::TryRule),UsiOptType::Combo(Some(String::from("TryRule")),vec![
+		String::from("NoEnteringKing"),
+		String::from("CSARule24"),
+		String::from("CSARule27"),
+		String::from("TryRule")
+	]));
+}
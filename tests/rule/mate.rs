@@ -0,0 +1,129 @@
+use usiagent::shogi::*;
+use usiagent::shogi::MochigomaCollections;
+use usiagent::rule::Rule;
+use usiagent::rule::State;
+
+use super::*;
+
+fn create_state(pieces:&[(u32,u32,KomaKind)]) -> State {
+	let mut banmen = Banmen([[Blank; 9]; 9]);
+
+	for &(file,rank,k) in pieces {
+		banmen.0[rank as usize - 1][9 - file as usize] = k;
+	}
+
+	State::new(banmen)
+}
+fn create_mc(hand:&[(MochigomaKind,usize)]) -> MochigomaCollections {
+	let mut ms = Mochigoma::new();
+
+	for &(k,n) in hand {
+		ms.insert(k,n);
+	}
+
+	MochigomaCollections::Pair(ms,Mochigoma::new())
+}
+#[test]
+fn test_mate_1ply_put() {
+	let state = create_state(&[(1,1,GOu),(1,3,SFu),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Kin,1)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc).map(|m| m.to_move()),
+		Some(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(1,2))));
+}
+#[test]
+fn test_mate_1ply_gote() {
+	let state = create_state(&[(9,9,SOu),(9,7,GFu),(5,1,GOu)]);
+	let mut mg = Mochigoma::new();
+
+	mg.insert(MochigomaKind::Kin,1);
+
+	let mc = MochigomaCollections::Pair(Mochigoma::new(),mg);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Gote,&mc).map(|m| m.to_move()),
+		Some(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(9,8))));
+}
+#[test]
+fn test_mate_1ply_exclude_put_fu_and_mate() {
+	let state = create_state(&[(1,1,GOu),(3,2,SKin),(1,9,SKyou),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Fu,1)]);
+
+	assert!(Rule::is_put_fu_and_mate(
+		&Rule::apply_move_none_check(&state,Teban::Sente,&mc,
+			Move::Put(MochigomaKind::Fu,KomaDstPutPosition(1,2)).to_applied_move()).0,
+		Teban::Sente,&mc,Move::Put(MochigomaKind::Fu,KomaDstPutPosition(1,2)).to_applied_move()));
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc),None);
+}
+#[test]
+fn test_mate_1ply_move_fu() {
+	let state = create_state(&[(1,1,GOu),(3,2,SKin),(1,3,SFu),(1,9,SKyou),(5,9,SOu)]);
+	let mc = create_mc(&[]);
+
+	match Rule::mate_1ply(&state,Teban::Sente,&mc).map(|m| m.to_move()) {
+		Some(Move::To(KomaSrcPosition(1,3),KomaDstToPosition(1,2,_))) => (),
+		m => panic!("unexpected result {:?}",m)
+	}
+}
+#[test]
+fn test_mate_1ply_put_hisha() {
+	let state = create_state(&[(1,1,GOu),(2,1,GKei),(1,3,SFu),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Hisha,1)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc).map(|m| m.to_move()),
+		Some(Move::Put(MochigomaKind::Hisha,KomaDstPutPosition(1,2))));
+}
+#[test]
+fn test_mate_1ply_pinned_defender() {
+	let state = create_state(&[(1,1,GOu),(2,2,GKin),(1,3,SFu),(4,4,SKaku),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Kin,1)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc).map(|m| m.to_move()),
+		Some(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(1,2))));
+
+	let state = create_state(&[(1,1,GOu),(2,2,GKin),(1,3,SFu),(5,9,SOu)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc),None);
+}
+#[test]
+fn test_mate_1ply_escape_to_attacked_square() {
+	let state = create_state(&[(1,1,GOu),(9,2,SHisha),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Gin,1)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc).map(|m| m.to_move()),
+		Some(Move::Put(MochigomaKind::Gin,KomaDstPutPosition(1,2))));
+
+	let state = create_state(&[(1,1,GOu),(9,3,SHisha),(5,9,SOu)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc),None);
+}
+#[test]
+fn test_mate_1ply_none() {
+	let state = State::new(BANMEN_START_POS.clone());
+	let mc = MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new());
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc),None);
+	assert_eq!(Rule::mate_3ply(&state,Teban::Sente,&mc),None);
+
+	let state = create_state(&[(1,1,GOu),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Kin,2)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc),None);
+	assert_eq!(Rule::mate_3ply(&state,Teban::Sente,&mc),None);
+}
+#[test]
+fn test_mate_3ply() {
+	let state = create_state(&[(1,1,GOu),(3,3,SHisha),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Kin,1)]);
+
+	assert_eq!(Rule::mate_1ply(&state,Teban::Sente,&mc),None);
+	assert_eq!(Rule::mate_3ply(&state,Teban::Sente,&mc).map(|m| m.to_move()),
+		Some(Move::To(KomaSrcPosition(3,3),KomaDstToPosition(1,3,true))));
+}
+#[test]
+fn test_mate_3ply_prefer_1ply() {
+	let state = create_state(&[(1,1,GOu),(1,3,SFu),(5,9,SOu)]);
+	let mc = create_mc(&[(MochigomaKind::Kin,1)]);
+
+	assert_eq!(Rule::mate_3ply(&state,Teban::Sente,&mc).map(|m| m.to_move()),
+		Some(Move::Put(MochigomaKind::Kin,KomaDstPutPosition(1,2))));
+}
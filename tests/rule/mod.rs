@@ -16,6 +16,14 @@ mod control;
 mod is_possible_nari;
 mod position;
 mod perft;
+mod handicap;
+mod mate;
+mod attackers;
+#[cfg(feature = "sliding-attack-table")]
+mod sliding;
+mod validate_position;
+mod compact_move;
+mod symmetry;
 
 use std::cmp;
 use std::collections::HashMap;
@@ -30,6 +38,8 @@ use usiagent::error::*;
 use usiagent::rule;
 use usiagent::rule::{BANMEN_START_POS, SquareToPoint};
 use usiagent::hash::*;
+use usiagent::math::Prng;
+use usiagent::movepick::RandomPicker;
 
 #[allow(unused)]
 use usiagent::shogi::KomaKind::{
@@ -2625,3 +2635,43 @@ fn check_sennichite_by_oute(banmen:&Banmen,teban:&Teban,mhash:u64,shash:u64,
 
 	true
 }
+/// sfen形式の局面（指し手を含む）を解析し、指し手を適用した後の手番、盤面の状態、持ち駒を返す
+pub fn create_position(sfen:&str) -> (Teban,rule::State,usiagent::shogi::MochigomaCollections) {
+	let (teban, banmen, mc, _, mvs) = PositionParser::new().parse(&sfen.split(' ').collect::<Vec<&str>>()).unwrap().extract();
+
+	let mut state = rule::State::new(banmen);
+	let mut mc = mc;
+	let mut teban = teban;
+
+	for m in mvs {
+		let (next,nmc,_) = rule::Rule::apply_move_none_check(&state,teban,&mc,m.to_applied_move());
+
+		state = next;
+		mc = nmc;
+		teban = teban.opposite();
+	}
+
+	(teban,state,mc)
+}
+/// 合法手の生成や指し手の変換を検証するテストで共通に使う局面の一覧を返す
+pub fn create_positions() -> Vec<(Teban,rule::State,usiagent::shogi::MochigomaCollections)> {
+	vec![
+		"startpos",
+		"sfen l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1",
+		"sfen l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL b RGgsn5p 1",
+		"sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f 3c3d 8h2b+",
+		"sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f 3c3d 8h2b+ 3a2b",
+	].into_iter().map(create_position).collect()
+}
+/// 手番側の合法手を、王手がかかっている場合は王手を回避する手に限定して生成して返す
+pub fn generate_legal_moves(teban:Teban,state:&rule::State,mc:&usiagent::shogi::MochigomaCollections) -> Vec<rule::LegalMove> {
+	let mut buffer = RandomPicker::new(Prng::new(0));
+
+	if rule::Rule::in_check(teban.opposite(),state) {
+		rule::Rule::generate_moves::<rule::EvasionsAll>(teban,state,mc,&mut buffer).unwrap();
+	} else {
+		rule::Rule::generate_moves::<rule::NonEvasionsAll>(teban,state,mc,&mut buffer).unwrap();
+	}
+
+	buffer.into()
+}
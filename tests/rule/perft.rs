@@ -2,14 +2,19 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::ops::{Add, AddAssign};
 use std::path::Path;
+use std::sync::Arc;
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
+use usiagent::hash::KyokumenHash;
 use usiagent::math::Prng;
 use usiagent::movepick::{MovePicker, RandomPicker};
 use usiagent::protocol::{PositionParser};
-use usiagent::rule::{EvasionsAll, LegalMove, NonEvasionsAll, Rule};
+use usiagent::rule::{EvasionsAll, LegalMove, NonEvasionsAll, Perft, Rule, BANMEN_START_POS};
 use usiagent::rule::State;
-use usiagent::shogi::{MochigomaCollections, Teban};
+use usiagent::rule;
+use usiagent::shogi::{Mochigoma, MochigomaCollections, Teban};
+
+use super::create_position;
 
 #[derive(Default,Debug,PartialEq,Eq)]
 struct PerftResult {
@@ -55,6 +60,17 @@ impl From<String> for PerftResult {
         }
     }
 }
+impl From<rule::PerftResult> for PerftResult {
+    fn from(value: rule::PerftResult) -> Self {
+        PerftResult {
+            nodes: value.nodes,
+            captures: value.captures,
+            promotions: value.promotions,
+            checks: value.checks,
+            mates: value.mates
+        }
+    }
+}
 trait PerftSolver {
     fn perft(&self,teban:Teban,state:&State,mc:&MochigomaCollections,m:Option<LegalMove>,depth:usize) -> PerftResult;
 }
@@ -222,4 +238,75 @@ fn test_perft_once() {
 
         assert_eq!(expected, result);
     }
-}
\ No newline at end of file
+}
+fn perft_test_positions() -> Vec<(Teban,State,MochigomaCollections,usize)> {
+    vec![
+        ("startpos",3),
+        ("sfen l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1",2),
+        ("sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f 3c3d 8h2b+",2),
+    ].into_iter().map(|(sfen,depth)| {
+        let (teban,state,mc) = create_position(sfen);
+
+        (teban,state,mc,depth)
+    }).collect()
+}
+#[test]
+fn test_rule_perft_startpos() {
+    let state = State::new(BANMEN_START_POS.clone());
+    let mc = MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new());
+
+    assert_eq!(Rule::perft(&state,Teban::Sente,&mc,0).nodes,1);
+    assert_eq!(Rule::perft(&state,Teban::Sente,&mc,1).nodes,30);
+    assert_eq!(Rule::perft(&state,Teban::Sente,&mc,2).nodes,900);
+    assert_eq!(Rule::perft(&state,Teban::Sente,&mc,3).nodes,25470);
+}
+#[test]
+fn test_rule_perft_equal_to_solver() {
+    for (teban,state,mc,depth) in perft_test_positions() {
+        for depth in 1..=depth {
+            assert_eq!(PerftResult::from(Rule::perft(&state,teban,&mc,depth)),
+                       PerftSolverByEvasions.perft(teban,&state,&mc,None,depth));
+        }
+    }
+}
+#[test]
+fn test_rule_divide() {
+    for (teban,state,mc,depth) in perft_test_positions() {
+        let divide = Rule::divide(&state,teban,&mc,depth);
+
+        assert_eq!(divide.len(),Rule::perft(&state,teban,&mc,1).nodes);
+
+        let total = divide.iter().fold(rule::PerftResult::default(),|acc,&(_,r)| acc + r);
+
+        assert_eq!(total,Rule::perft(&state,teban,&mc,depth));
+
+        for (m,r) in divide {
+            let (next,nmc,_) = Rule::apply_move_none_check(&state,teban,&mc,m.to_applied_move());
+
+            assert_eq!(r.nodes,Rule::perft(&next,teban.opposite(),&nmc,depth - 1).nodes);
+        }
+    }
+
+    let state = State::new(BANMEN_START_POS.clone());
+    let mc = MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new());
+
+    assert!(Rule::divide(&state,Teban::Sente,&mc,0).is_empty());
+}
+#[test]
+fn test_perft_with_threads_and_cache() {
+    let hasher = Arc::new(KyokumenHash::with_seed(1));
+
+    for (teban,state,mc,depth) in perft_test_positions() {
+        let expected = Rule::perft(&state,teban,&mc,depth);
+
+        assert_eq!(Perft::new().threads(4).perft(&state,teban,&mc,depth),expected);
+        assert_eq!(Perft::new().cache(hasher.clone(),1 << 16).perft(&state,teban,&mc,depth),expected);
+        assert_eq!(Perft::new().threads(3).cache(hasher.clone(),1 << 16).perft(&state,teban,&mc,depth),expected);
+        assert_eq!(Perft::new().threads(2).cache(hasher.clone(),0).perft(&state,teban,&mc,depth),expected);
+
+        let single = Rule::divide(&state,teban,&mc,depth);
+        let multi = Perft::new().threads(4).cache(hasher.clone(),1 << 16).divide(&state,teban,&mc,depth);
+
+        assert_eq!(single,multi);
+    }
+}
@@ -1,6 +1,10 @@
+use std::sync::Arc;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use usiagent::hash::KyokumenHash;
 use usiagent::rule::*;
-use usiagent::shogi::{Banmen, Teban};
-use usiagent::shogi::KomaKind::{Blank, SOu, GOu};
+use usiagent::shogi::{Banmen, Teban, KomaKind, KomaSrcPosition, KomaDstToPosition, KomaDstPutPosition, Mochigoma, MochigomaCollections, MochigomaKind, Move};
+use usiagent::shogi::KomaKind::{Blank, SOu, GOu, SFu, GFu, SKin, GKin, SHisha};
 
 #[test]
 fn test_sente_ou_square() {
@@ -30,3 +34,187 @@ fn test_gote_ou_square() {
         assert_eq!(Rule::ou_square(Teban::Gote,&State::new(banmen)),x * 9 + y);
     }
 }
+#[test]
+fn test_position_hash_incremental_update() {
+    let mut rnd = XorShiftRng::seed_from_u64(1234);
+
+    for _ in 0..10 {
+        let mut position = Position::new(State::new(BANMEN_START_POS.clone()),
+                                         Teban::Sente,
+                                         MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()),
+                                         Arc::new(KyokumenHash::with_seed(1)));
+
+        let initial_hash = position.hash();
+
+        assert_eq!(initial_hash,position.calc_hash());
+
+        for _ in 0..100 {
+            let mvs = Rule::legal_moves_all(position.teban(),position.get_state(),position.get_mc());
+
+            if mvs.is_empty() {
+                break;
+            }
+
+            let m = mvs[rnd.gen_range(0..mvs.len())];
+
+            position.apply_move_none_check(m.to_applied_move());
+
+            assert_eq!(position.hash(),position.calc_hash());
+        }
+
+        while let Some(_) = position.undo_move() {
+            assert_eq!(position.hash(),position.calc_hash());
+        }
+
+        assert_eq!(position.ply(),0);
+        assert_eq!(position.teban(),Teban::Sente);
+        assert_eq!(position.hash(),initial_hash);
+    }
+}
+#[test]
+fn test_position_hash_transposition_and_teban() {
+    let hasher = Arc::new(KyokumenHash::with_seed(1));
+
+    let create = || {
+        Position::new(State::new(BANMEN_START_POS.clone()),
+                      Teban::Sente,
+                      MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()),
+                      hasher.clone())
+    };
+
+    let m1 = Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false));
+    let m2 = Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false));
+    let m3 = Move::To(KomaSrcPosition(2,7),KomaDstToPosition(2,6,false));
+    let m4 = Move::To(KomaSrcPosition(8,3),KomaDstToPosition(8,4,false));
+
+    let mut p1 = create();
+    let mut p2 = create();
+
+    for &m in &[m1,m2,m3,m4] {
+        p1.apply_valid_move(m.to_applied_move()).unwrap();
+    }
+
+    for &m in &[m3,m4,m1,m2] {
+        p2.apply_valid_move(m.to_applied_move()).unwrap();
+    }
+
+    assert_eq!(p1.hash(),p2.hash());
+
+    let sente = create();
+    let gote = Position::new(State::new(BANMEN_START_POS.clone()),
+                             Teban::Gote,
+                             MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()),
+                             hasher.clone());
+
+    assert_eq!(sente.board_hash(),gote.board_hash());
+    assert_ne!(sente.hash(),gote.hash());
+
+    let mut p = create();
+
+    assert!(p.apply_valid_move(m2.to_applied_move()).is_err());
+    assert_eq!(p.ply(),0);
+}
+fn create_position(pieces:&[(u32,u32,KomaKind)],teban:Teban,ms:Mochigoma,mg:Mochigoma) -> Position {
+    let mut banmen = Banmen([[Blank; 9]; 9]);
+
+    for &(file,rank,k) in pieces {
+        banmen.0[rank as usize - 1][9 - file as usize] = k;
+    }
+
+    Position::new(State::new(banmen),teban,MochigomaCollections::Pair(ms,mg),Arc::new(KyokumenHash::with_seed(1)))
+}
+fn apply_moves(position:&mut Position,mvs:&[Move]) {
+    for &m in mvs {
+        position.apply_valid_move(m.to_applied_move()).unwrap();
+    }
+}
+#[test]
+fn test_position_repetition_state_draw() {
+    let mut position = Position::new(State::new(BANMEN_START_POS.clone()),
+                                     Teban::Sente,
+                                     MochigomaCollections::Pair(Mochigoma::new(),Mochigoma::new()),
+                                     Arc::new(KyokumenHash::with_seed(1)));
+
+    apply_moves(&mut position,&[
+        Move::To(KomaSrcPosition(2,8),KomaDstToPosition(3,8,false)),
+        Move::To(KomaSrcPosition(8,2),KomaDstToPosition(7,2,false)),
+        Move::To(KomaSrcPosition(3,8),KomaDstToPosition(2,8,false)),
+    ]);
+
+    assert_eq!(position.repetition_state(16),RepetitionState::None);
+
+    apply_moves(&mut position,&[
+        Move::To(KomaSrcPosition(7,2),KomaDstToPosition(8,2,false)),
+    ]);
+
+    assert_eq!(position.repetition_state(16),RepetitionState::Draw);
+    assert_eq!(position.repetition_state(3),RepetitionState::None);
+
+    position.undo_move();
+
+    assert_eq!(position.repetition_state(16),RepetitionState::None);
+}
+#[test]
+fn test_position_repetition_state_perpetual_check() {
+    let mut position = create_position(&[(2,3,SHisha),(1,1,GOu),(5,9,SOu)],Teban::Sente,Mochigoma::new(),Mochigoma::new());
+
+    apply_moves(&mut position,&[
+        Move::To(KomaSrcPosition(2,3),KomaDstToPosition(1,3,false)),
+        Move::To(KomaSrcPosition(1,1),KomaDstToPosition(2,1,false)),
+        Move::To(KomaSrcPosition(1,3),KomaDstToPosition(2,3,false)),
+        Move::To(KomaSrcPosition(2,1),KomaDstToPosition(1,1,false)),
+    ]);
+
+    assert_eq!(position.repetition_state(16),RepetitionState::Lose);
+
+    apply_moves(&mut position,&[
+        Move::To(KomaSrcPosition(2,3),KomaDstToPosition(1,3,false)),
+    ]);
+
+    assert_eq!(position.repetition_state(16),RepetitionState::Win);
+}
+#[test]
+fn test_position_repetition_state_superior() {
+    let mut mg = Mochigoma::new();
+
+    mg.insert(MochigomaKind::Fu,1);
+
+    let mut position = create_position(&[(5,6,SKin),(5,5,GFu),(1,1,GOu),(9,9,SOu)],Teban::Sente,Mochigoma::new(),mg);
+
+    apply_moves(&mut position,&[
+        Move::To(KomaSrcPosition(5,6),KomaDstToPosition(5,5,false)),
+        Move::To(KomaSrcPosition(1,1),KomaDstToPosition(2,1,false)),
+        Move::To(KomaSrcPosition(5,5),KomaDstToPosition(5,6,false)),
+        Move::To(KomaSrcPosition(2,1),KomaDstToPosition(1,1,false)),
+        Move::To(KomaSrcPosition(9,9),KomaDstToPosition(8,9,false)),
+        Move::Put(MochigomaKind::Fu,KomaDstPutPosition(5,5)),
+        Move::To(KomaSrcPosition(8,9),KomaDstToPosition(8,8,false)),
+        Move::To(KomaSrcPosition(1,1),KomaDstToPosition(2,1,false)),
+        Move::To(KomaSrcPosition(8,8),KomaDstToPosition(9,9,false)),
+        Move::To(KomaSrcPosition(2,1),KomaDstToPosition(1,1,false)),
+    ]);
+
+    assert_eq!(position.repetition_state(16),RepetitionState::Superior);
+    assert_eq!(position.repetition_state(8),RepetitionState::None);
+}
+#[test]
+fn test_position_repetition_state_inferior() {
+    let mut ms = Mochigoma::new();
+
+    ms.insert(MochigomaKind::Fu,1);
+
+    let mut position = create_position(&[(5,5,SFu),(5,4,GKin),(1,1,GOu),(9,9,SOu)],Teban::Sente,ms,Mochigoma::new());
+
+    apply_moves(&mut position,&[
+        Move::To(KomaSrcPosition(9,9),KomaDstToPosition(8,9,false)),
+        Move::To(KomaSrcPosition(5,4),KomaDstToPosition(5,5,false)),
+        Move::To(KomaSrcPosition(8,9),KomaDstToPosition(8,8,false)),
+        Move::To(KomaSrcPosition(5,5),KomaDstToPosition(5,4,false)),
+        Move::Put(MochigomaKind::Fu,KomaDstPutPosition(5,5)),
+        Move::To(KomaSrcPosition(1,1),KomaDstToPosition(2,1,false)),
+        Move::To(KomaSrcPosition(8,8),KomaDstToPosition(9,9,false)),
+        Move::To(KomaSrcPosition(2,1),KomaDstToPosition(1,1,false)),
+    ]);
+
+    assert_eq!(position.repetition_state(16),RepetitionState::Inferior);
+}
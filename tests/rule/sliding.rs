@@ -0,0 +1,33 @@
+use usiagent::bitboard::{BitBoard, SlidingDirection};
+
+#[test]
+fn test_sliding_attacks_empty_board() {
+	let expected = [
+		(SlidingDirection::Bottom,40,4),
+		(SlidingDirection::Right,40,4),
+		(SlidingDirection::RightTop,40,4),
+		(SlidingDirection::RightBottom,40,4),
+		(SlidingDirection::Bottom,8,0),
+		(SlidingDirection::Right,80,0),
+		(SlidingDirection::RightTop,0,0),
+		(SlidingDirection::RightBottom,0,8),
+		(SlidingDirection::RightTop,8,8),
+	];
+
+	for &(d,from,count) in expected.iter() {
+		assert_eq!(BitBoard::sliding_attacks_by_table(d,from,BitBoard::default()).bitcount(),count,
+				   "direction = {:?}, from = {}",d,from);
+	}
+}
+#[test]
+fn test_sliding_attacks_blocked() {
+	// 5五の駒から見て右下方向の3マス先(2八)に駒がある場合
+	let from = 4 * 9 + 4;
+	let blocker = 7 * 9 + 7;
+
+	let occupied = BitBoard::from(1 << (blocker + 1));
+
+	let attacks = BitBoard::sliding_attacks_by_table(SlidingDirection::RightBottom,from,occupied);
+
+	assert_eq!(attacks.iter().collect::<Vec<_>>(),vec![5 * 9 + 5,6 * 9 + 6,7 * 9 + 7]);
+}
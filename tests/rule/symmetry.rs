@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+use usiagent::rule::{Rule, State, BANMEN_START_POS};
+use usiagent::shogi::*;
+
+use super::{create_positions, generate_legal_moves};
+
+fn legal_moves(teban:Teban,state:&State,mc:&MochigomaCollections) -> HashSet<Move> {
+	generate_legal_moves(teban,state,mc).into_iter().map(|m| m.to_move()).collect()
+}
+#[test]
+fn test_flip_startpos() {
+	assert_eq!(BANMEN_START_POS.flip(),BANMEN_START_POS.clone());
+	assert_ne!(BANMEN_START_POS.mirror(),BANMEN_START_POS.clone());
+	assert_eq!(Teban::Sente.flip(),Teban::Gote);
+	assert_eq!(Teban::Sente.mirror(),Teban::Sente);
+	assert_eq!(KomaKind::SKakuN.flip(),KomaKind::GKakuN);
+	assert_eq!(KomaKind::Blank.flip(),KomaKind::Blank);
+	assert_eq!(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)).flip(),
+				Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false)));
+	assert_eq!(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)).mirror(),
+				Move::To(KomaSrcPosition(3,7),KomaDstToPosition(3,6,false)));
+	assert_eq!(Move::Put(MochigomaKind::Fu,KomaDstPutPosition(1,2)).flip(),
+				Move::Put(MochigomaKind::Fu,KomaDstPutPosition(9,8)));
+}
+#[test]
+fn test_symmetry_round_trip() {
+	for (teban,state,mc) in create_positions() {
+		assert_eq!(teban.flip().flip(),teban);
+		assert_eq!(mc.flip().flip(),mc);
+		assert_eq!(state.flip().flip().get_banmen(),state.get_banmen());
+		assert_eq!(state.mirror().mirror().get_banmen(),state.get_banmen());
+		assert_eq!(state.flip().mirror().get_banmen(),state.mirror().flip().get_banmen());
+	}
+}
+#[test]
+fn test_symmetry_preserves_legal_moves() {
+	for (teban,state,mc) in create_positions() {
+		let mvs = legal_moves(teban,&state,&mc);
+
+		let flipped = legal_moves(teban.flip(),&state.flip(),&mc.flip());
+
+		assert_eq!(mvs.iter().map(|m| m.flip()).collect::<HashSet<Move>>(),flipped);
+
+		let mirrored = legal_moves(teban.mirror(),&state.mirror(),&mc.mirror());
+
+		assert_eq!(mvs.iter().map(|m| m.mirror()).collect::<HashSet<Move>>(),mirrored);
+
+		assert_eq!(Rule::in_check(teban.opposite(),&state),Rule::in_check(teban,&state.flip()));
+		assert_eq!(Rule::in_check(teban.opposite(),&state),Rule::in_check(teban.opposite(),&state.mirror()));
+	}
+}
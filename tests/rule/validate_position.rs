@@ -0,0 +1,75 @@
+use usiagent::error::PositionValidationError;
+use usiagent::protocol::PositionParser;
+use usiagent::rule::Rule;
+use usiagent::shogi::*;
+
+fn validate(sfen:&str) -> Result<(),Vec<PositionValidationError>> {
+	let (teban, banmen, mc, _, _) = PositionParser::new().parse(&sfen.split(' ').collect::<Vec<&str>>()).unwrap().extract();
+
+	Rule::validate_position(&banmen,teban,&mc)
+}
+#[test]
+fn test_validate_position_valid() {
+	assert_eq!(validate("startpos"),Ok(()));
+	assert_eq!(validate("sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),Ok(()));
+	assert_eq!(validate("sfen l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1"),Ok(()));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/9/4K4 b - 1"),Ok(()));
+}
+#[test]
+fn test_validate_position_nifu() {
+	assert_eq!(validate("sfen 4k4/9/9/4p4/4p4/9/9/9/4K4 b - 1"),
+				Err(vec![PositionValidationError::Nifu(Teban::Gote,5)]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/8P/8P/9/4K4 b - 1"),
+				Err(vec![PositionValidationError::Nifu(Teban::Sente,1)]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/8P/8+P/9/4K4 b - 1"),Ok(()));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/8P/8p/9/4K4 b - 1"),Ok(()));
+}
+#[test]
+fn test_validate_position_dead_piece() {
+	assert_eq!(validate("sfen P3k3L/N8/9/9/9/9/9/9/4K4 b - 1"),
+				Err(vec![
+					PositionValidationError::DeadPiece(KomaKind::SFu,9,1),
+					PositionValidationError::DeadPiece(KomaKind::SKyou,1,1),
+					PositionValidationError::DeadPiece(KomaKind::SKei,9,2)
+				]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/n8/p3K3l b - 1"),
+				Err(vec![
+					PositionValidationError::DeadPiece(KomaKind::GKei,9,8),
+					PositionValidationError::DeadPiece(KomaKind::GFu,9,9),
+					PositionValidationError::DeadPiece(KomaKind::GKyou,1,9)
+				]));
+	assert_eq!(validate("sfen +P3k3+L/+N8/N8/9/9/9/9/9/4K4 b - 1"),Ok(()));
+	assert_eq!(validate("sfen 4k4/N8/9/9/9/9/9/8n/4K4 b - 1"),
+				Err(vec![
+					PositionValidationError::DeadPiece(KomaKind::SKei,9,2),
+					PositionValidationError::DeadPiece(KomaKind::GKei,1,8)
+				]));
+}
+#[test]
+fn test_validate_position_ou_count() {
+	assert_eq!(validate("sfen 9/9/9/9/9/9/9/9/4K4 b - 1"),
+				Err(vec![PositionValidationError::InvalidOuCount(Teban::Gote,0)]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/9/3KK4 b - 1"),
+				Err(vec![PositionValidationError::InvalidOuCount(Teban::Sente,2)]));
+}
+#[test]
+fn test_validate_position_too_many_pieces() {
+	assert_eq!(validate("sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b Bp 1"),
+				Err(vec![
+					PositionValidationError::TooManyPieces(MochigomaKind::Fu,19),
+					PositionValidationError::TooManyPieces(MochigomaKind::Kaku,3),
+					PositionValidationError::TooManyPiecesTotal(42)
+				]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/9/GG2K4 b 3G 1"),
+				Err(vec![PositionValidationError::TooManyPieces(MochigomaKind::Kin,5)]));
+}
+#[test]
+fn test_validate_position_opponent_in_check() {
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/9/4K3R b - 1"),Ok(()));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/9/4K3R w - 1"),Ok(()));
+	assert_eq!(validate("sfen 4k3R/9/9/9/9/9/9/9/4K4 b - 1"),
+				Err(vec![PositionValidationError::OpponentInCheck(Teban::Gote)]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/4g4/4K4 w - 1"),
+				Err(vec![PositionValidationError::OpponentInCheck(Teban::Sente)]));
+	assert_eq!(validate("sfen 4k4/9/9/9/9/9/9/4g4/4K4 b - 1"),Ok(()));
+}
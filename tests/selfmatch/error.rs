@@ -7,6 +7,7 @@ use usiagent::shogi::*;
 use usiagent::command::*;
 use usiagent::event::*;
 use usiagent::error::*;
+use usiagent::rule::InvalidPositionPolicy;
 
 use super::*;
 
@@ -3632,3 +3633,124 @@ fn test_error_gameover_player2() {
 
 	let _ = s.send(String::from(""));
 }
+#[test]
+fn test_error_invalid_opening_move_rejected() {
+	let (pms1,pmr1) = mpsc::channel();
+	let (pns1,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let (pms2,pmr2) = mpsc::channel();
+	let (pns2,_) = mpsc::channel();
+
+	let pmr = [pmr1,pmr2];
+
+	let (ls,lr) = mpsc::channel();
+
+	let logger = MockLogger::new(ls);
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,_) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let output_writer =  Arc::new(Mutex::new(output_writer));
+
+	let _ = thread::spawn(move || {
+		let player1 = MockPlayer::new(pms1,pns1,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player| {
+											let _ = player.sender.send(Ok(ActionKind::NewGame));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![])
+		);
+
+		let player2 = MockPlayer::new(pms2,pns2,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player| {
+											let _ = player.sender.send(Ok(ActionKind::NewGame));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![])
+		);
+
+		let (is,_) = mpsc::channel();
+
+		let info_sender = MockInfoSender::new(is);
+
+		let mut engine = SelfMatchEngine::new();
+
+		engine.set_invalid_position_policy(InvalidPositionPolicy::Reject);
+
+		let input_read_handler = create_input_read_handler(&engine.system_event_queue);
+
+		let r = engine.start(|_| {},
+			|| false,
+			// 二手目で後手が先手の歩を動かす不正な手
+			Some(Box::new(|| String::from("startpos moves 7g7f 7f7e"))),
+			None, input_reader, input_read_handler,
+			player1,player2,
+			create_options(), create_options(),
+			info_sender,
+			USIPeriodicallyInfo::new(output_writer,false),
+			UsiGoTimeLimit::None,
+			None,None,
+			logger, |h,e| {
+				if let Some(h) = h {
+					let _ = h.lock().map(|h| h.call(e));
+				}
+			}
+		);
+
+		let _ = ts.send(r.is_err());
+	});
+
+	startup(&pmr);
+
+	for r in pmr.iter() {
+		let res = r.recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::TakeReady timed out.");
+
+		assert_eq!(res,Ok(ActionKind::TakeReady));
+	}
+
+	for r in pmr.iter() {
+		let res = r.recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::NewGame timed out.");
+
+		assert_eq!(res,Ok(ActionKind::NewGame));
+	}
+
+	let res = tr.recv_timeout(Duration::from_millis(500)).expect("attempt to receive on quited timed out.");
+
+	assert!(res);
+
+	for m in vec![
+		"This is not legal move.",
+		"invalid state. (The initial position is invalid.)"
+	].into_iter().map(|m| m.to_string()) {
+		let res = lr.recv_timeout(Duration::from_millis(60)).expect("attempt to receive log message timed out.");
+
+		assert_eq!(res,m);
+	}
+
+	let _ = s.send(String::from(""));
+}
@@ -1,7 +1,11 @@
 use std::thread;
+use std::fs;
+use std::env;
+use std::process;
 use std::time::Duration;
 
 use std::sync::mpsc;
+use chrono::prelude::*;
 use usiagent::selfmatch::*;
 use usiagent::shogi::*;
 use usiagent::command::*;
@@ -542,7 +546,7 @@ fn test_kifuwrite_7times() {
 			},
 			|| false,
 			Some(Box::new(move || it.next().unwrap())),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -904,3 +908,323 @@ fn test_kifuwrite_7times() {
 
 	let _ = s.send(String::from(""));
 }
+#[test]
+fn test_sfen_kifuwriter_seed_only_in_comments() {
+	let file = env::temp_dir().join(format!("usiagent_test_sfen_{}.txt",process::id()));
+	let file = file.to_str().unwrap().to_string();
+
+	let _ = fs::remove_file(&file);
+
+	let now = Local::now();
+
+	let record = GameRecord {
+		initial_sfen:String::from("startpos"),
+		sente_name:String::from("sente"),
+		gote_name:String::from("gote"),
+		sente_player:1,
+		start_dt:now,
+		end_dt:now,
+		end_state:SelfMatchGameEndState::Draw,
+		seed:12345,
+		time_limit:UsiGoTimeLimit::None,
+		moves:vec![
+			MoveRecord::new(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),Duration::from_secs(0),None,None),
+		]
+	};
+
+	{
+		let mut writer = FileSfenKifuWriter::new(file.clone()).unwrap();
+
+		writer.write_record(&record).unwrap();
+		writer.write_record(&record).unwrap();
+	}
+
+	assert_eq!(fs::read_to_string(&file).unwrap(),"startpos moves 7g7f\nstartpos moves 7g7f\n");
+
+	let _ = fs::remove_file(&file);
+
+	{
+		let mut writer = FileSfenKifuWriter::with_comments(file.clone()).unwrap();
+
+		writer.write_record(&record).unwrap();
+	}
+
+	let sfen = fs::read_to_string(&file).unwrap();
+	let lines = sfen.lines().collect::<Vec<&str>>();
+
+	assert_eq!(lines.len(),2);
+	assert!(lines[0].starts_with("# sente=sente gote=gote sente_player=1 "));
+	assert!(lines[0].ends_with(" result=Draw seed=12345"));
+	assert_eq!(lines[1],"startpos moves 7g7f");
+
+	let _ = fs::remove_file(&file);
+}
+#[test]
+fn test_csa_kifuwriter_to_csa() {
+	let start_dt = Local.with_ymd_and_hms(2020,1,2,3,4,5).unwrap();
+	let end_dt = Local.with_ymd_and_hms(2020,1,2,3,14,15).unwrap();
+
+	let record = GameRecord {
+		initial_sfen:String::from("startpos"),
+		sente_name:String::from("sente"),
+		gote_name:String::from("gote"),
+		sente_player:1,
+		start_dt,
+		end_dt,
+		end_state:SelfMatchGameEndState::Resign(Teban::Sente),
+		seed:12345,
+		time_limit:UsiGoTimeLimit::None,
+		moves:vec![
+			MoveRecord::new(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),
+							Duration::from_secs(3),
+							Some(UsiScore::Cp(120)),
+							Some(vec![
+								Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),
+								Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false))
+							])),
+			MoveRecord::new(Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false)),
+							Duration::from_secs(5),
+							Some(UsiScore::Cp(-30)),
+							None),
+			MoveRecord::new(Move::To(KomaSrcPosition(8,8),KomaDstToPosition(2,2,true)),
+							Duration::from_secs(1),
+							None,
+							None),
+		]
+	};
+
+	assert_eq!(record.to_moves(),vec![
+		Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),
+		Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false)),
+		Move::To(KomaSrcPosition(8,8),KomaDstToPosition(2,2,true))
+	]);
+
+	assert_eq!(FileCsaKifuWriter::to_csa(&record).unwrap(),vec![
+		"V2.2",
+		"N+sente",
+		"N-gote",
+		"$START_TIME:2020/01/02 03:04:05",
+		"$END_TIME:2020/01/02 03:14:15",
+		"'seed=12345",
+		"PI",
+		"+",
+		"+7776FU",
+		"T3",
+		"'** 120 +7776FU -3334FU",
+		"-3334FU",
+		"T5",
+		"'** 30",
+		"+8822UM",
+		"T1",
+		"%TORYO"
+	].join("\n"));
+}
+#[test]
+fn test_csa_kifuwriter_to_csa_end_states() {
+	let now = Local::now();
+
+	for (end_state,expected) in vec![
+		(SelfMatchGameEndState::Win(Teban::Sente),"%TSUMI"),
+		(SelfMatchGameEndState::Win(Teban::Gote),"%TSUMI"),
+		(SelfMatchGameEndState::Resign(Teban::Sente),"%TORYO"),
+		(SelfMatchGameEndState::Resign(Teban::Gote),"%TORYO"),
+		(SelfMatchGameEndState::NyuGyokuWin(Teban::Sente),"%KACHI"),
+		(SelfMatchGameEndState::NyuGyokuLose(Teban::Sente),"%+ILLEGAL_ACTION"),
+		(SelfMatchGameEndState::NyuGyokuLose(Teban::Gote),"%-ILLEGAL_ACTION"),
+		(SelfMatchGameEndState::Draw,"%HIKIWAKE"),
+		(SelfMatchGameEndState::Timeover(Teban::Gote),"%TIME_UP"),
+		(SelfMatchGameEndState::Foul(Teban::Sente,FoulKind::Sennichite),"%SENNICHITE"),
+		(SelfMatchGameEndState::Foul(Teban::Gote,FoulKind::SennichiteOu),"%OUTE_SENNICHITE"),
+		(SelfMatchGameEndState::Foul(Teban::Sente,FoulKind::InvalidMove),"%+ILLEGAL_ACTION"),
+		(SelfMatchGameEndState::Foul(Teban::Gote,FoulKind::PutFuAndMate),"%-ILLEGAL_ACTION"),
+		(SelfMatchGameEndState::Foul(Teban::Gote,FoulKind::NotRespondedOute),"%-ILLEGAL_ACTION"),
+		(SelfMatchGameEndState::Foul(Teban::Sente,FoulKind::Suicide),"%+ILLEGAL_ACTION"),
+	] {
+		let record = GameRecord {
+			initial_sfen:String::from("startpos"),
+			sente_name:String::from("sente"),
+			gote_name:String::from("gote"),
+			sente_player:1,
+			start_dt:now,
+			end_dt:now,
+			end_state,
+			seed:0,
+			time_limit:UsiGoTimeLimit::None,
+			moves:vec![]
+		};
+
+		let csa = FileCsaKifuWriter::to_csa(&record).unwrap();
+
+		assert_eq!(csa.lines().last(),Some(expected),"{:?}",end_state);
+	}
+}
+#[test]
+fn test_csa_kifuwriter_to_csa_invalid_move() {
+	let now = Local::now();
+
+	let record = GameRecord {
+		initial_sfen:String::from("startpos"),
+		sente_name:String::from("sente"),
+		gote_name:String::from("gote"),
+		sente_player:2,
+		start_dt:now,
+		end_dt:now,
+		end_state:SelfMatchGameEndState::Draw,
+		seed:0,
+		time_limit:UsiGoTimeLimit::None,
+		moves:vec![
+			MoveRecord::new(Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false)),
+							Duration::from_secs(0),
+							None,
+							None),
+		]
+	};
+
+	match FileCsaKifuWriter::to_csa(&record) {
+		Err(KifuWriteError::InvalidState(_)) => (),
+		r => panic!("unexpected result {:?}",r),
+	}
+}
+#[test]
+fn test_csa_kifuwriter_write_without_result() {
+	let file = env::temp_dir().join(format!("usiagent_test_csa_{}.csa",process::id()));
+	let file = file.to_str().unwrap().to_string();
+
+	let _ = fs::remove_file(&file);
+
+	{
+		let mut writer = FileCsaKifuWriter::new(file.clone()).unwrap();
+
+		writer.write(&String::from("startpos"),&vec![
+			Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false))
+		]).unwrap();
+	}
+
+	let csa = fs::read_to_string(&file).unwrap();
+
+	assert!(csa.contains("+7776FU"));
+	assert!(!csa.lines().any(|l| l.starts_with('%')));
+
+	let _ = fs::remove_file(&file);
+}
+#[test]
+fn test_archive_kifuwriter_write_and_read() {
+	let file = env::temp_dir().join(format!("usiagent_test_archive_{}.kifu",process::id()));
+	let file = file.to_str().unwrap().to_string();
+	let index_file = format!("{}.idx",file);
+
+	let _ = fs::remove_file(&file);
+	let _ = fs::remove_file(&index_file);
+
+	let now = Local::now();
+
+	let records = vec![
+		GameRecord {
+			initial_sfen:String::from("startpos"),
+			sente_name:String::from("player\"a\""),
+			gote_name:String::from("player_b"),
+			sente_player:1,
+			start_dt:now,
+			end_dt:now,
+			end_state:SelfMatchGameEndState::Resign(Teban::Gote),
+			seed:1,
+			time_limit:UsiGoTimeLimit::Limit(Some((1000,2000)),Some(UsiGoByoyomiOrInc::Byoyomi(100))),
+			moves:vec![
+				MoveRecord::new(Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),Duration::from_secs(0),None,None),
+				MoveRecord::new(Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false)),Duration::from_secs(0),None,None),
+			]
+		},
+		GameRecord {
+			initial_sfen:String::from("sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1"),
+			sente_name:String::from("player_b"),
+			gote_name:String::from("player_a"),
+			sente_player:2,
+			start_dt:now,
+			end_dt:now,
+			end_state:SelfMatchGameEndState::Foul(Teban::Gote,FoulKind::Sennichite),
+			seed:1,
+			time_limit:UsiGoTimeLimit::Infinite,
+			moves:vec![]
+		}
+	];
+
+	{
+		let mut writer = FileArchiveKifuWriter::new(file.clone()).unwrap();
+
+		for r in &records {
+			writer.write_record(r).unwrap();
+		}
+	}
+
+	let check = |reader:&mut KifuArchiveReader| {
+		assert_eq!(reader.len(),2);
+
+		let game = reader.get(1).unwrap();
+
+		assert_eq!(game.initial_sfen,records[1].initial_sfen);
+		assert_eq!(game.moves,vec![]);
+		assert_eq!(game.header("Result"),Some("1-0"));
+		assert_eq!(game.header("Reason"),Some("Sennichite"));
+		assert_eq!(game.header("SentePlayer"),Some("2"));
+		assert_eq!(game.header("TimeControl"),Some("infinite"));
+
+		let games = reader.iter().collect::<Result<Vec<ArchivedGame>,KifuReadError>>().unwrap();
+
+		assert_eq!(games.len(),2);
+		assert_eq!(games[0].initial_sfen,String::from("startpos"));
+		assert_eq!(games[0].moves,records[0].to_moves());
+		assert_eq!(games[0].header("Sente"),Some("player\"a\""));
+		assert_eq!(games[0].header("Result"),Some("1-0"));
+		assert_eq!(games[0].header("Reason"),Some("Resign"));
+		assert_eq!(games[0].header("TimeControl"),Some("btime 1000 wtime 2000 byoyomi 100"));
+		assert_ne!(games[0].header("Opening"),games[1].header("Opening"));
+
+		match reader.get(2) {
+			Err(KifuReadError::OutOfRange(2)) => (),
+			r => panic!("unexpected result {:?}",r),
+		}
+	};
+
+	check(&mut KifuArchiveReader::open(file.clone()).unwrap());
+
+	fs::remove_file(&index_file).unwrap();
+
+	check(&mut KifuArchiveReader::open(file.clone()).unwrap());
+
+	let _ = fs::remove_file(&file);
+}
+#[test]
+fn test_archive_kifuwriter_write_unknown_result() {
+	let file = env::temp_dir().join(format!("usiagent_test_archive_unknown_{}.kifu",process::id()));
+	let file = file.to_str().unwrap().to_string();
+	let index_file = format!("{}.idx",file);
+
+	let _ = fs::remove_file(&file);
+	let _ = fs::remove_file(&index_file);
+
+	{
+		let mut writer = FileArchiveKifuWriter::new(file.clone()).unwrap();
+
+		writer.write(&String::from("startpos moves 7g7f"),&vec![
+			Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),
+			Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false))
+		]).unwrap();
+	}
+
+	let game = KifuArchiveReader::open(file.clone()).unwrap().get(0).unwrap();
+
+	assert_eq!(game.initial_sfen,String::from("startpos"));
+	assert_eq!(game.header("Position"),Some("startpos"));
+	assert_eq!(game.header("Result"),Some("*"));
+	assert_eq!(game.moves,vec![
+		Move::To(KomaSrcPosition(7,7),KomaDstToPosition(7,6,false)),
+		Move::To(KomaSrcPosition(3,3),KomaDstToPosition(3,4,false))
+	]);
+
+	let archive = fs::read_to_string(&file).unwrap();
+
+	assert_eq!(archive.matches("7g7f").count(),1);
+
+	let _ = fs::remove_file(&file);
+	let _ = fs::remove_file(&index_file);
+}
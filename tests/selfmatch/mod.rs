@@ -324,7 +324,7 @@ fn test_resign_1times() {
 			},
 			|| false,
 			None,
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -585,7 +585,7 @@ fn test_invalidmove_1times() {
 			},
 			|| false,
 			None,
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -850,7 +850,7 @@ fn test_invalidmove_by_from_blank_1times() {
 			},
 			|| false,
 			None,
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -1107,7 +1107,7 @@ fn test_invalidmove_by_no_responded_oute_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("startpos moves 7g7f 3c3d"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -1346,7 +1346,7 @@ fn test_invalidmove_by_suicide_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("startpos moves 5i5h 3c3d 7g7f 2b7g"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -1576,7 +1576,7 @@ fn test_win_move_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen lnsgkgsnl/1r2G2b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGK1SNL b - 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -1803,7 +1803,7 @@ fn test_win_invalidmove_put_fu_and_mate_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen 3nkn3/3s1s3/9/4L4/9/9/1PPPPPPPP/1B5R1/LNSGK1SNL b Prb2g2sl9p 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -2030,7 +2030,7 @@ fn test_win_invalidmove_sennichite_by_oute_once_move_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen 4k4/9/5R3/9/9/9/PPPPPPPPP/1B52/LNSGKGSNL b rb2g2s2n2l9p 1 moves 4c5c 5a4a 5c4c 4a5a"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -2263,7 +2263,7 @@ fn test_win_validmove_not_sennichite_by_oute_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen 4k4/9/5R3/9/9/9/PPPPPPPPP/1B52/LNSGKGSNL b rb2g2s2n2l9p 1 moves 4c5c 5a4a 5c6c 4a5a"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -2498,7 +2498,7 @@ fn test_win_invalid_move_sennichite_once_move_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("startpos moves 4i4h 6a6b 4h4i 6b6a 4i4h 6a6b 4h4i 6b6a 4i4h 6a6b 4h4i 6b6a"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -2755,7 +2755,7 @@ fn test_win_invalid_move_sennichite_by_oute_once_move_1times_with_empty_kyokumen
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen 4k4/9/5R3/9/9/9/PPPPPPPPP/1B52/LNSGKGSNL b rb2g2s2n2l9p 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -3124,7 +3124,7 @@ fn test_win_invalid_move_sennichite_1times_with_empty_kyokumen_map() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("startpos"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -3513,7 +3513,7 @@ fn test_game_time_limit_1times() {
 			},
 			|| false,
 			None,
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -6233,7 +6233,7 @@ fn test_nyugyoku_win_win_sente_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen ln5n+P/1+R+B1K3+P/+P+P+P+P+P2+P1/ln6P/9/pp5NL/2+p1+p+p+p+p+p/2+b1k3+p/+pN5NL b R2G2S2g2s 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -6502,7 +6502,7 @@ fn test_nyugyoku_win_win_gote_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen ln5n+P/2+B1K3+P/+P+P+P+P+P2+P1/ln6P/9/pp5NL/4+p+p+p+p+p/+p3k1+b+r1/+pN5NL b 2G2Sr2g2s 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -6775,7 +6775,7 @@ fn test_nyugyoku_win_lose_sente_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen ln5n+P/1+R+B1K3+P/+P+P+P+P+P2+P1/ln6P/9/pp5NL/2+p1+p+p+p+p+p/2+b1k3+p/+pN5NL b R2G2S2g2s 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -7044,7 +7044,7 @@ fn test_nyugyoku_win_lose_gote_1times() {
 			},
 			|| false,
 			Some(Box::new(|| String::from("sfen ln5n+P/2+B1K3+P/+P+P+P+P+P2+P1/ln6P/9/pp5NL/4+p+p+p+p+p/+p3k1+b+r1/+pN5NL b 2G2Sr2g2s 1"))),
-			Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+			Some(Box::new(move |record| kifuwriter.write_record(record))),
 			input_reader, input_read_handler,
 			player1,player2,
 			create_options(), create_options(),
@@ -7142,3 +7142,301 @@ fn test_nyugyoku_win_lose_gote_1times() {
 
 	let _ = s.send(String::from(""));
 }
+#[test]
+fn test_selfmatch_engine_with_seed() {
+	use rand::Rng;
+
+	let engine:SelfMatchEngine<CommonError> = SelfMatchEngine::with_seed(12345);
+
+	assert_eq!(engine.seed(),12345);
+
+	let mut r1 = engine.create_rng(0);
+	let mut r2 = SelfMatchEngine::<CommonError>::with_seed(12345).create_rng(0);
+	let mut r3 = engine.create_rng(1);
+
+	let v1 = (0..16).map(|_| r1.gen::<u64>()).collect::<Vec<u64>>();
+	let v2 = (0..16).map(|_| r2.gen::<u64>()).collect::<Vec<u64>>();
+	let v3 = (0..16).map(|_| r3.gen::<u64>()).collect::<Vec<u64>>();
+
+	assert_eq!(v1,v2);
+	assert_ne!(v1,v3);
+}
+#[test]
+fn test_ponder_statistics_ponderhit() {
+	let statistics = run_ponderhit_1times(true);
+
+	assert_eq!(statistics[0].ponder_count,1);
+	assert_eq!(statistics[0].hit_count,1);
+	assert_eq!(statistics[0].hit_rate(),1.0);
+	assert_eq!(statistics[1],PonderStatistics::default());
+	assert_eq!(statistics[1].hit_rate(),0.0);
+}
+#[test]
+fn test_ponder_statistics_ponder_disabled() {
+	let statistics = run_ponderhit_1times(false);
+
+	assert_eq!(statistics[0],PonderStatistics::default());
+	assert_eq!(statistics[1],PonderStatistics::default());
+}
+fn run_ponderhit_1times(ponder:bool) -> [PonderStatistics; 2] {
+	let (pms1,pmr1) = mpsc::channel();
+	let (pns1,_) = mpsc::channel();
+	let (ts,tr) = mpsc::channel();
+
+	let (pms2,pmr2) = mpsc::channel();
+	let (pns2,_) = mpsc::channel();
+
+	let pmr = [pmr1,pmr2];
+
+	let logger = StdErrorLogger::new();
+	let (input_reader,s) = {
+		let (s,r) = mpsc::channel();
+
+		let input_reader = MockInputReader::new(r);
+		(input_reader,s)
+	};
+
+	let (output_writer,_) = {
+		let (s,r) = mpsc::channel();
+
+		let output_writer = MockOutputWriter::new(s);
+		(output_writer,r)
+	};
+
+	let output_writer =  Arc::new(Mutex::new(output_writer));
+
+	let (es,er) = mpsc::channel();
+
+	let _ = thread::spawn(move || {
+		let player1 = MockPlayer::new(pms1,pns1,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player| {
+											let _ = player.sender.send(Ok(ActionKind::NewGame));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											Ok(())
+										}),
+										Box::new(|player,_,_,_,_,_,mvs| {
+											if mvs == vec![Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false)),
+															Move::To(KomaSrcPosition(9,3),KomaDstToPosition(9,4,false))] {
+												let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											} else {
+												let _ = player.sender.send(Err(String::from("mvs is invalid.")));
+											}
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::Think));
+											Ok(BestMove::Move(Move::To(KomaSrcPosition(1,7),KomaDstToPosition(1,6,false)),
+																Some(Move::To(KomaSrcPosition(9,3),KomaDstToPosition(9,4,false)))))
+										}),
+										Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::Think));
+											Ok(BestMove::Resign)
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![Box::new(|player,s,_| {
+											match s {
+												&GameEndState::Lose => {
+													let _ = player.sender.send(Ok(ActionKind::GameOver));
+												},
+												_ => {
+													let _ = player.sender.send(Err(String::from("gameend state is invalid.")));
+												}
+											}
+
+											Ok(())
+										})])
+		);
+
+		let player2 = MockPlayer::new(pms2,pns2,
+										ConsumedIterator::new(vec![Box::new(|player,_| {
+											let _ = player.sender.send(Ok(ActionKind::TakeReady));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player| {
+											let _ = player.sender.send(Ok(ActionKind::NewGame));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::SetPosition));
+											Ok(())
+										})]),
+										ConsumedIterator::new(vec![Box::new(|player,_,_,_,_,_,_| {
+											let _ = player.sender.send(Ok(ActionKind::Think));
+											Ok(BestMove::Move(Move::To(KomaSrcPosition(9,3),KomaDstToPosition(9,4,false)),None))
+										})]),
+										ConsumedIterator::new(vec![]),
+										ConsumedIterator::new(vec![Box::new(|player,s,_| {
+											match s {
+												&GameEndState::Win => {
+													let _ = player.sender.send(Ok(ActionKind::GameOver));
+												},
+												_ => {
+													let _ = player.sender.send(Err(String::from("gameend state is invalid.")));
+												}
+											}
+
+											Ok(())
+										})])
+		);
+
+		let (is,_) = mpsc::channel();
+
+		let info_sender = MockInfoSender::new(is);
+
+		let mut engine = SelfMatchEngine::new();
+
+		engine.set_ponder(ponder,true);
+
+		let input_read_handler = create_input_read_handler(&engine.system_event_queue);
+
+		let r = engine.start(|self_match_event_dispatcher| {
+			let hes = es.clone();
+
+			self_match_event_dispatcher
+				.add_handler(SelfMatchEventKind::GameStart, move |_,e| {
+					match e {
+						&SelfMatchEvent::GameStart(n,t,_) => {
+							if t == Teban::Sente && n == 1 {
+								let _ = hes.send(Ok(EventState::GameStart));
+							} else {
+								let _ = hes.send(Err(String::from("GameStart event is invalid.")));
+							}
+							Ok(())
+						},
+						e => Err(EventHandlerError::InvalidState(e.event_kind())),
+					}
+				});
+			let hes = es.clone();
+
+			self_match_event_dispatcher
+				.add_handler(SelfMatchEventKind::Moved, move |_,e| {
+					match e {
+						&SelfMatchEvent::Moved(_,_) => {
+							let _ = hes.send(Ok(EventState::Moved));
+							Ok(())
+						},
+						e => Err(EventHandlerError::InvalidState(e.event_kind())),
+					}
+				});
+			let hes = es.clone();
+
+			self_match_event_dispatcher
+				.add_handler(SelfMatchEventKind::GameEnd, move |_,e| {
+					match e {
+						&SelfMatchEvent::GameEnd(SelfMatchGameEndState::Resign(t)) => {
+							if t == Teban::Sente {
+								let _ = hes.send(Ok(EventState::GameEnd));
+							} else {
+								let _ = hes.send(Err(String::from("GameEnd event is invalid.")));
+							}
+						},
+						_ => {
+							let _ = hes.send(Err(String::from("GameEnd event is invalid.")));
+						}
+					}
+					Ok(())
+				});
+			let hes = es.clone();
+			self_match_event_dispatcher
+				.add_handler(SelfMatchEventKind::Abort, move |_,e| {
+					match e {
+						&SelfMatchEvent::Abort => {
+							let _ = hes.send(Err(String::from("GameEnd event is invalid.")));
+							Ok(())
+						},
+						e => Err(EventHandlerError::InvalidState(e.event_kind())),
+					}
+				});
+			},
+			|| false,
+			None,
+			None, input_reader, input_read_handler,
+			player1,player2,
+			create_options(), create_options(),
+			info_sender,
+			USIPeriodicallyInfo::new(output_writer,false),
+			UsiGoTimeLimit::None,
+			None,Some(1),
+			logger, |h,e| {
+				if let Some(h) = h {
+					let _ = h.lock().map(|h| h.call(e));
+				}
+			}
+		);
+
+		let _ = ts.send(r.map(|r| r.ponder_statistics).map_err(|e| format!("{}",e)));
+	});
+
+	startup(&pmr);
+
+	gamestart_process(&pmr);
+
+	let res = er.recv_timeout(Duration::from_millis(300)).expect("attempt to receive EventState::GameStart timed out.");
+
+	assert_eq!(res,Ok(EventState::GameStart));
+
+	let res = pmr[0].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let res = pmr[0].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::Think timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Think));
+
+	let res = er.recv_timeout(Duration::from_millis(60)).expect("attempt to receive EventState::Moved timed out.");
+
+	assert_eq!(res,Ok(EventState::Moved));
+
+	let res = pmr[0].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let res = pmr[0].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::Think timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Think));
+
+	let res = pmr[1].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::SetPosition timed out.");
+
+	assert_eq!(res,Ok(ActionKind::SetPosition));
+
+	let res = pmr[1].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::Think timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Think));
+
+	let res = er.recv_timeout(Duration::from_millis(60)).expect("attempt to receive EventState::Moved timed out.");
+
+	assert_eq!(res,Ok(EventState::Moved));
+
+	let res = pmr[0].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::GameOver timed out.");
+
+	assert_eq!(res,Ok(ActionKind::GameOver));
+
+	let res = pmr[1].recv_timeout(Duration::from_millis(60)).expect("attempt to receive ActionKind::GameOver timed out.");
+
+	assert_eq!(res,Ok(ActionKind::GameOver));
+
+	let res = er.recv_timeout(Duration::from_millis(60)).expect("attempt to receive EventState::GameEnd timed out.");
+
+	assert_eq!(res,Ok(EventState::GameEnd));
+
+	let res = pmr[0].recv_timeout(Duration::from_millis(500)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let res = pmr[1].recv_timeout(Duration::from_millis(500)).expect("attempt to receive ActionKind::Quit timed out.");
+
+	assert_eq!(res,Ok(ActionKind::Quit));
+
+	let r = tr.recv_timeout(Duration::from_millis(180)).expect("attempt to receive on quited timed out.");
+
+	let _ = s.send(String::from(""));
+
+	r.expect("self match failed.")
+}
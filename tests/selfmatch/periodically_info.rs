@@ -195,7 +195,7 @@ fn test_periodically_info_send() {
 		},
 							 || false,
 							 None,
-							 Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+							 Some(Box::new(move |record| kifuwriter.write_record(record))),
 							 input_reader, input_read_handler,
 							 player1,player2,
 							 create_options(), create_options(),
@@ -474,7 +474,7 @@ fn test_periodically_info_send_drop_sender() {
 		},
 							 || false,
 							 None,
-							 Some(Box::new(move |sfen,mvs| kifuwriter.write(sfen,mvs))),
+							 Some(Box::new(move |record| kifuwriter.write_record(record))),
 							 input_reader, input_read_handler,
 							 player1,player2,
 							 create_options(), create_options(),
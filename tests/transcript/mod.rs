@@ -0,0 +1,166 @@
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use usiagent::UsiAgent;
+use usiagent::error::TranscriptReadError;
+use usiagent::input::USIInputReader;
+use usiagent::output::USIOutputWriter;
+use usiagent::transcript::*;
+
+use common::*;
+
+#[derive(Clone)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+#[test]
+fn test_transcript_entry() {
+	let entry = TranscriptEntry {
+		elapsed:120,
+		direction:TranscriptDirection::Input,
+		line:String::from("position startpos moves 7g7f")
+	};
+
+	assert_eq!(entry.to_string(),"120\t>\tposition startpos moves 7g7f");
+	assert_eq!(TranscriptEntry::try_from("120\t>\tposition startpos moves 7g7f"),Ok(entry));
+	assert_eq!(TranscriptEntry::try_from("5\t<\t"),Ok(TranscriptEntry {
+		elapsed:5,
+		direction:TranscriptDirection::Output,
+		line:String::new()
+	}));
+	assert!(TranscriptEntry::try_from("5\t=\tusi").is_err());
+	assert!(TranscriptEntry::try_from("a\t>\tusi").is_err());
+	assert!(TranscriptEntry::try_from("usi").is_err());
+}
+#[test]
+fn test_transcript_recorder() {
+	let buffer = SharedBuffer(Arc::new(Mutex::new(Vec::new())));
+	let recorder = TranscriptRecorder::new(buffer.clone());
+
+	let (s,r) = mpsc::channel();
+	let mut reader = TranscriptInputReader::new(MockInputReader::new(r),recorder.clone());
+
+	let (os,or) = mpsc::channel();
+	let writer = TranscriptOutputWriter::new(MockOutputWriter::new(os),recorder);
+
+	let _ = s.send(String::from("usi"));
+
+	assert_eq!(reader.read().unwrap(),Some(String::from("usi")));
+
+	writer.write(&vec![String::from("id name mockplayer"),String::from("usiok")]).unwrap();
+
+	assert_eq!(or.recv().unwrap(),"id name mockplayer");
+	assert_eq!(or.recv().unwrap(),"usiok");
+
+	let transcript = Transcript::read(&buffer.0.lock().unwrap()[..]).unwrap();
+
+	assert_eq!(transcript.entries.iter().map(|e| (e.direction,e.line.as_str())).collect::<Vec<(TranscriptDirection,&str)>>(),vec![
+		(TranscriptDirection::Input,"usi"),
+		(TranscriptDirection::Output,"id name mockplayer"),
+		(TranscriptDirection::Output,"usiok")
+	]);
+	assert!(transcript.entries.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+	assert_eq!(transcript.inputs().into_iter().map(|(_,l)| l).collect::<Vec<String>>(),vec![String::from("usi")]);
+	assert_eq!(transcript.outputs(),vec![String::from("id name mockplayer"),String::from("usiok")]);
+}
+#[test]
+fn test_transcript_read_invalid_format() {
+	match Transcript::read(&b"0\t>\tusi\r\n\r\n10\tusiok\n"[..]) {
+		Err(TranscriptReadError::InvalidFormat(n,ref l)) => {
+			assert_eq!(n,3);
+			assert_eq!(l,"10\tusiok");
+		},
+		r => panic!("unexpected result. {:?}",r)
+	}
+}
+#[test]
+fn test_scripted_input_reader() {
+	let mut reader = ScriptedInputReader::new(vec![(0,String::from("usi")),(60,String::from("isready"))],true);
+
+	let start = Instant::now();
+
+	assert_eq!(reader.read().unwrap(),Some(String::from("usi")));
+	assert_eq!(reader.read().unwrap(),Some(String::from("isready")));
+	assert!(start.elapsed() >= Duration::from_millis(60));
+	assert_eq!(reader.read().unwrap(),Some(String::from("quit")));
+
+	let mut reader = ScriptedInputReader::new(vec![(10000,String::from("usi"))],false);
+
+	let start = Instant::now();
+
+	assert_eq!(reader.read().unwrap(),Some(String::from("usi")));
+	assert!(start.elapsed() < Duration::from_millis(10000));
+}
+#[test]
+fn test_replay_result_diff() {
+	let lines = |s:&str| s.split(' ').map(|l| l.to_string()).collect::<Vec<String>>();
+
+	let r = ReplayResult::new(lines("a b c"),lines("a x c d"));
+
+	assert!(!r.is_identical());
+	assert_eq!(r.diff,vec![
+		DiffLine::Same(String::from("a")),
+		DiffLine::Missing(String::from("b")),
+		DiffLine::Unexpected(String::from("x")),
+		DiffLine::Same(String::from("c")),
+		DiffLine::Unexpected(String::from("d"))
+	]);
+	assert_eq!(r.report(),vec!["  a","- b","+ x","  c","+ d"]);
+
+	assert!(ReplayResult::new(lines("a b"),lines("a b")).is_identical());
+}
+#[test]
+fn test_replay() {
+	let input = |l:&str| TranscriptEntry {
+		elapsed:0,
+		direction:TranscriptDirection::Input,
+		line:l.to_string()
+	};
+
+	let transcript = Transcript {
+		entries:vec![input("usi"),input("quit")]
+	};
+
+	let (pms,_) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+
+	let agent = UsiAgent::new(create_player(pms,pns));
+
+	let r = Replayer::new().replay(&agent,&transcript,StdErrorLogger::new()).unwrap();
+
+	assert!(!r.is_identical());
+	assert_eq!(r.actual.first().map(|l| l.as_str()),Some("id name mockplayer"));
+	assert_eq!(r.actual.last().map(|l| l.as_str()),Some("usiok"));
+
+	let mut entries = vec![input("usi")];
+
+	entries.extend(r.actual.iter().map(|l| TranscriptEntry {
+		elapsed:0,
+		direction:TranscriptDirection::Output,
+		line:l.clone()
+	}));
+	entries.push(input("quit"));
+
+	let transcript = Transcript {
+		entries
+	};
+
+	let (pms,_) = mpsc::channel();
+	let (pns,_) = mpsc::channel();
+
+	let agent = UsiAgent::new(create_player(pms,pns));
+
+	let r = Replayer::new().replay(&agent,&transcript,StdErrorLogger::new()).unwrap();
+
+	assert!(r.is_identical(),"{:?}",r.report());
+}